@@ -3,4 +3,4 @@ import type { BackendHost } from "./BackendHost";
 import type { SgBackendProtocol } from "./SgBackendProtocol";
 import type { SgRouteFilter } from "./SgRouteFilter";
 
-export interface SgBackendRef { host: BackendHost, port: number, timeout_ms: number | null, protocol: SgBackendProtocol | null, weight: number, filters: Array<SgRouteFilter>, }
\ No newline at end of file
+export interface SgBackendRef { host: BackendHost, port: number, timeout_ms: number | null, connect_timeout_ms: number | null, response_header_timeout_ms: number | null, idle_stream_timeout_ms: number | null, deadline_ms: number | null, protocol: SgBackendProtocol | null, weight: number, version: string | null, filters: Array<SgRouteFilter>, }
\ No newline at end of file
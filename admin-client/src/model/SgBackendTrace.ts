@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgPluginTrace } from "./SgPluginTrace";
+
+export interface SgBackendTrace { host: string, port: number, weight: number, version: string | null, plugins: Array<SgPluginTrace>, }
\ No newline at end of file
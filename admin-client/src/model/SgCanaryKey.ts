@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SgCanaryKey = { "kind": "Header", name: string, } | { "kind": "Cookie", name: string, } | { "kind": "Query", name: string, };
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SgExplainRequest { gateway: string, method: string, host: string, path: string, query: string | null, headers: Record<string, string>, client_ip: string | null, sni: string | null, listener: string | null, }
\ No newline at end of file
@@ -1,6 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgErrorPage } from "./SgErrorPage";
+import type { SgGatewayFallback } from "./SgGatewayFallback";
 import type { SgListener } from "./SgListener";
 import type { SgParameters } from "./SgParameters";
 import type { SgRouteFilter } from "./SgRouteFilter";
 
-export interface SgGateway { name: string, parameters: SgParameters, listeners: Array<SgListener>, filters: Array<SgRouteFilter>, }
\ No newline at end of file
+export interface SgGateway { name: string, parameters: SgParameters, listeners: Array<SgListener>, filters: Array<SgRouteFilter>, fallback: SgGatewayFallback | null, error_pages: Array<SgErrorPage>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgBackendRef } from "./SgBackendRef";
+
+export type SgGatewayFallback = { "kind": "static", status: number, content_type: string | null, body: string, } | { "kind": "redirect", location: string, status: number | null, } | { "kind": "backend", backend: SgBackendRef, };
\ No newline at end of file
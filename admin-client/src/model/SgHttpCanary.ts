@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgCanaryCookie } from "./SgCanaryCookie";
+import type { SgCanaryKey } from "./SgCanaryKey";
+
+export interface SgHttpCanary { version: string, percentage: number, key: SgCanaryKey | null, override_header: string | null, sticky_cookie: SgCanaryCookie | null, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type SgHttpHeaderMatch = { "kind": "exact", name: string, value: string, } | { "kind": "regular", name: string, re: string, };
\ No newline at end of file
+export type SgHttpHeaderMatch = { "kind": "exact", name: string, value: string, } | { "kind": "regular", name: string, re: string, } | { "kind": "present", name: string, } | { "kind": "absent", name: string, } | { "kind": "not_exact", name: string, value: string, } | { "kind": "not_regular", name: string, re: string, };
\ No newline at end of file
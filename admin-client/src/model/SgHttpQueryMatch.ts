@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type SgHttpQueryMatch = { "kind": "exact", "value": { key: string, value: string, } } | { "kind": "regular", "value": { key: string, re: string, } };
\ No newline at end of file
+export type SgHttpQueryMatch = { "kind": "exact", "value": { key: string, value: string, } } | { "kind": "regular", "value": { key: string, re: string, } } | { "kind": "present", "value": { key: string, } } | { "kind": "absent", "value": { key: string, } } | { "kind": "not_exact", "value": { key: string, value: string, } } | { "kind": "not_regular", "value": { key: string, re: string, } };
\ No newline at end of file
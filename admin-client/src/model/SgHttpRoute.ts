@@ -2,4 +2,4 @@
 import type { SgHttpRouteRule } from "./SgHttpRouteRule";
 import type { SgRouteFilter } from "./SgRouteFilter";
 
-export interface SgHttpRoute { gateway_name: string, hostnames: Array<string> | null, filters: Array<SgRouteFilter>, rules: Array<SgHttpRouteRule>, priority: number, }
\ No newline at end of file
+export interface SgHttpRoute { gateway_name: string, hostnames: Array<string> | null, section_names: Array<string> | null, filters: Array<SgRouteFilter>, rules: Array<SgHttpRouteRule>, priority: number, }
\ No newline at end of file
@@ -1,7 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgHttpCookieMatch } from "./SgHttpCookieMatch";
 import type { SgHttpHeaderMatch } from "./SgHttpHeaderMatch";
 import type { SgHttpMethodMatch } from "./SgHttpMethodMatch";
 import type { SgHttpPathMatch } from "./SgHttpPathMatch";
 import type { SgHttpQueryMatch } from "./SgHttpQueryMatch";
 
-export interface SgHttpRouteMatch { path: SgHttpPathMatch | null, header: Array<SgHttpHeaderMatch> | null, query: Array<SgHttpQueryMatch> | null, method: Array<SgHttpMethodMatch> | null, }
\ No newline at end of file
+export interface SgHttpRouteMatch { path: SgHttpPathMatch | null, header: Array<SgHttpHeaderMatch> | null, query: Array<SgHttpQueryMatch> | null, method: Array<SgHttpMethodMatch> | null, cookie: Array<SgHttpCookieMatch> | null, client_ip: Array<string> | null, sni: Array<string> | null, expr: string | null, case_sensitive: boolean | null, }
\ No newline at end of file
@@ -1,6 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { SgBackendRef } from "./SgBackendRef";
+import type { SgHttpCanary } from "./SgHttpCanary";
 import type { SgHttpRouteMatch } from "./SgHttpRouteMatch";
 import type { SgRouteFilter } from "./SgRouteFilter";
 
-export interface SgHttpRouteRule { matches: Array<SgHttpRouteMatch> | null, filters: Array<SgRouteFilter>, backends: Array<SgBackendRef>, timeout_ms: number | null, }
\ No newline at end of file
+export interface SgHttpRouteRule { matches: Array<SgHttpRouteMatch> | null, filters: Array<SgRouteFilter>, backends: Array<SgBackendRef>, timeout_ms: number | null, connect_timeout_ms: number | null, response_header_timeout_ms: number | null, idle_stream_timeout_ms: number | null, deadline_ms: number | null, canary: SgHttpCanary | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SgListenerLimits { max_connections: number | null, max_connections_per_ip: number | null, header_read_timeout_ms: number | null, idle_timeout_ms: number | null, max_header_size: number | null, max_headers: number | null, http2_max_concurrent_streams: number | null, }
\ No newline at end of file
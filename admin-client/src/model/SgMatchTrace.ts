@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgPredicateTrace } from "./SgPredicateTrace";
+
+export interface SgMatchTrace { index: number, predicates: Array<SgPredicateTrace>, matched: boolean, }
\ No newline at end of file
@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgPathNormalization } from "./SgPathNormalization";
 
-export interface SgParameters { redis_url: string | null, log_level: string | null, lang: string | null, ignore_tls_verification: boolean | null, }
\ No newline at end of file
+export interface SgParameters { redis_url: string | null, log_level: string | null, lang: string | null, ignore_tls_verification: boolean | null, path_normalization: Array<SgPathNormalization> | null, }
\ No newline at end of file
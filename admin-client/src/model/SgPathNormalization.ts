@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SgPathNormalization = "decode_unreserved" | "merge_slashes" | "remove_dot_segments";
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgRouteTrace } from "./SgRouteTrace";
+import type { SgSelectedRule } from "./SgSelectedRule";
+
+export interface SgRouteExplain { path: string, routes: Array<SgRouteTrace>, selected: SgSelectedRule | null, }
\ No newline at end of file
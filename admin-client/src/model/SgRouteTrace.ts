@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgRuleTrace } from "./SgRuleTrace";
+
+export interface SgRouteTrace { name: string, priority: number, rules: Array<SgRuleTrace>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgMatchTrace } from "./SgMatchTrace";
+
+export interface SgRuleTrace { index: number, matches: Array<SgMatchTrace>, matched: boolean, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SgBackendTrace } from "./SgBackendTrace";
+import type { SgPluginTrace } from "./SgPluginTrace";
+
+export interface SgSelectedRule { route: string, rule: number, match: number | null, plugins: Array<SgPluginTrace>, backends: Array<SgBackendTrace>, }
\ No newline at end of file
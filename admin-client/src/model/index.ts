@@ -5,7 +5,15 @@ export * from './ConfigItem';
 export * from './K8sServiceData';
 export * from './SgBackendProtocol';
 export * from './SgBackendRef';
+export * from './SgBackendTrace';
+export * from './SgCanaryCookie';
+export * from './SgCanaryKey';
+export * from './SgErrorPage';
+export * from './SgExplainRequest';
 export * from './SgGateway';
+export * from './SgGatewayFallback';
+export * from './SgHttpCanary';
+export * from './SgHttpCookieMatch';
 export * from './SgHttpHeaderMatch';
 export * from './SgHttpMethodMatch';
 export * from './SgHttpPathMatch';
@@ -14,8 +22,17 @@ export * from './SgHttpRoute';
 export * from './SgHttpRouteMatch';
 export * from './SgHttpRouteRule';
 export * from './SgListener';
+export * from './SgListenerLimits';
+export * from './SgMatchTrace';
 export * from './SgParameters';
+export * from './SgPathNormalization';
+export * from './SgPluginTrace';
+export * from './SgPredicateTrace';
 export * from './SgProtocolConfig';
+export * from './SgRouteExplain';
 export * from './SgRouteFilter';
+export * from './SgRouteTrace';
+export * from './SgRuleTrace';
+export * from './SgSelectedRule';
 export * from './SgTlsConfig';
 export * from './SgTlsMode';
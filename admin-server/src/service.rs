@@ -4,6 +4,7 @@ use spacegate_config::service::*;
 use crate::{mw, state::AppState};
 
 pub mod config;
+pub mod explain;
 pub mod plugin;
 pub fn router<B>(state: AppState<B>) -> Router<()>
 where
@@ -15,5 +16,6 @@ where
             config::router::<B>().layer(middleware::from_fn_with_state(state.clone(), mw::version_control::version_control)),
         )
         .nest("/plugin", plugin::router::<B>())
+        .nest("/explain", explain::router::<B>())
         .with_state(state)
 }
@@ -0,0 +1,41 @@
+use axum::{extract::State, routing::post, Json, Router};
+use serde::Deserialize;
+use spacegate_config::{
+    model::{SgExplainRequest, SgRouteExplain},
+    service::*,
+    BoxError, Config,
+};
+
+use crate::{
+    error::InternalError,
+    state::{self, AppState, PluginCode},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ExplainBody {
+    /// Config to explain against, the stored config is used if absent, so a draft config can be checked before it's saved.
+    #[serde(default)]
+    pub config: Option<Config>,
+    #[serde(flatten)]
+    pub request: SgExplainRequest,
+}
+
+/// Plugins are considered registered if their schemas are loaded.
+async fn post_explain<B: Retrieve>(
+    State(AppState { backend, plugin_schemas, .. }): State<AppState<B>>,
+    Json(body): Json<ExplainBody>,
+) -> Result<Json<SgRouteExplain>, InternalError<BoxError>> {
+    let config = match body.config {
+        Some(config) => config,
+        None => backend.retrieve_config().await.map_err(InternalError)?,
+    };
+    let registered = |code: &str| plugin_schemas.contains_key(&PluginCode::plugin(code));
+    spacegate_config::explain::explain(&config, &body.request, registered).map(Json).map_err(InternalError)
+}
+
+pub fn router<B>() -> axum::Router<state::AppState<B>>
+where
+    B: Retrieve + Send + Sync + 'static,
+{
+    Router::new().route("/", post(post_explain::<B>))
+}
@@ -3,6 +3,11 @@ pub const GATEWAY_ANNOTATION_REDIS_URL: &str = "redis_url";
 pub const GATEWAY_ANNOTATION_LOG_LEVEL: &str = "log_level";
 pub const GATEWAY_ANNOTATION_LANGUAGE: &str = "lang";
 pub const GATEWAY_ANNOTATION_IGNORE_TLS_VERIFICATION: &str = "ignore_tls_verification";
+pub const GATEWAY_ANNOTATION_PATH_NORMALIZATION: &str = "path_normalization";
+/// Fallback of the gateway in json.
+pub const GATEWAY_ANNOTATION_FALLBACK: &str = "fallback";
+/// Error pages of the gateway in json.
+pub const GATEWAY_ANNOTATION_ERROR_PAGES: &str = "error_pages";
 
 pub const DEFAULT_NAMESPACE: &str = "default";
 pub const ANNOTATION_RESOURCE_PRIORITY: &str = "priority";
@@ -0,0 +1,139 @@
+//! Conversions of config models into the kernel types, shared by the gateway and the route explain.
+
+use regex::Regex;
+use spacegate_kernel::layers::{
+    gateway::path_normalizer::SgPathNormalizer,
+    http_route::{builder::SgHttpRouteRuleLayerBuilder, match_request as kernel, path_template::SgPathTemplate, predicate::SgMatchExpr},
+};
+
+use crate::{
+    model::{self as config, SgPathNormalization},
+    BoxError,
+};
+
+/// Set the matches of a rule, a rule without matches matches all requests.
+pub fn convert_rule_matches(builder: SgHttpRouteRuleLayerBuilder, matches: Option<Vec<config::SgHttpRouteMatch>>) -> Result<SgHttpRouteRuleLayerBuilder, BoxError> {
+    Ok(match matches {
+        Some(matches) => builder.matches(matches.into_iter().map(convert_config_to_kernel).collect::<Result<Vec<_>, _>>()?),
+        None => builder.match_all(),
+    })
+}
+
+pub fn convert_path_normalization(steps: &[SgPathNormalization]) -> SgPathNormalizer {
+    SgPathNormalizer {
+        decode_unreserved: steps.contains(&SgPathNormalization::DecodeUnreserved),
+        merge_slashes: steps.contains(&SgPathNormalization::MergeSlashes),
+        remove_dot_segments: steps.contains(&SgPathNormalization::RemoveDotSegments),
+    }
+}
+
+/// convert [`config::SgHttpRouteMatch`] into [`kernel::SgHttpRouteMatch`]
+pub fn convert_config_to_kernel(config_match: config::SgHttpRouteMatch) -> Result<kernel::SgHttpRouteMatch, BoxError> {
+    let case_insensitive = !config_match.case_sensitive.unwrap_or(true);
+    Ok(kernel::SgHttpRouteMatch {
+        path: match config_match.path {
+            Some(config::SgHttpPathMatch::Exact(path)) => Some(kernel::SgHttpPathMatch::Exact(path)),
+            Some(config::SgHttpPathMatch::Prefix(path)) => Some(kernel::SgHttpPathMatch::Prefix(path)),
+            // the flag is put into the pattern, so the route index builds the regex set of the same case sensitivity
+            Some(config::SgHttpPathMatch::Regular(path)) if case_insensitive => Some(kernel::SgHttpPathMatch::Regular(Regex::new(&format!("(?i){path}"))?)),
+            Some(config::SgHttpPathMatch::Regular(path)) => Some(kernel::SgHttpPathMatch::Regular(Regex::new(&path)?)),
+            Some(config::SgHttpPathMatch::Template(path)) => Some(kernel::SgHttpPathMatch::Template(SgPathTemplate::new(&path)?)),
+            None => None,
+        },
+        header: match config_match.header {
+            Some(headers) => Some(
+                headers
+                    .into_iter()
+                    .map(|header| match header {
+                        config::SgHttpHeaderMatch::Exact { name, value } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::Exact(value.clone()),
+                        }),
+                        config::SgHttpHeaderMatch::Regular { name, re } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::Regular(Regex::new(&re)?),
+                        }),
+                        config::SgHttpHeaderMatch::Present { name } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::Present,
+                        }),
+                        config::SgHttpHeaderMatch::Absent { name } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::Absent,
+                        }),
+                        config::SgHttpHeaderMatch::NotExact { name, value } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::NotExact(value),
+                        }),
+                        config::SgHttpHeaderMatch::NotRegular { name, re } => Ok(kernel::SgHttpHeaderMatch {
+                            name,
+                            policy: kernel::SgHttpHeaderMatchPolicy::NotRegular(Regex::new(&re)?),
+                        }),
+                    })
+                    .collect::<Result<Vec<_>, BoxError>>()?,
+            ),
+            None => None,
+        },
+        query: match config_match.query {
+            Some(queries) => Some(
+                queries
+                    .into_iter()
+                    .map(|query| match query {
+                        config::SgHttpQueryMatch::Exact { key, value } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::Exact(value.clone()),
+                        }),
+                        config::SgHttpQueryMatch::Regular { key, re } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::Regular(Regex::new(&re)?),
+                        }),
+                        config::SgHttpQueryMatch::Present { key } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::Present,
+                        }),
+                        config::SgHttpQueryMatch::Absent { key } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::Absent,
+                        }),
+                        config::SgHttpQueryMatch::NotExact { key, value } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::NotExact(value),
+                        }),
+                        config::SgHttpQueryMatch::NotRegular { key, re } => Ok(kernel::SgHttpQueryMatch {
+                            name: key,
+                            policy: kernel::SgHttpQueryMatchPolicy::NotRegular(Regex::new(&re)?),
+                        }),
+                    })
+                    .collect::<Result<Vec<_>, BoxError>>()?,
+            ),
+            None => None,
+        },
+        method: config_match.method.map(|method| method.into_iter().map(|x| kernel::SgHttpMethodMatch(x.0)).collect()),
+        cookie: match config_match.cookie {
+            Some(cookies) => Some(
+                cookies
+                    .into_iter()
+                    .map(|cookie| {
+                        let (name, policy) = match cookie {
+                            config::SgHttpCookieMatch::Exact { name, value } => (name, kernel::SgHttpCookieMatchPolicy::Exact(value)),
+                            config::SgHttpCookieMatch::Regular { name, re } => (name, kernel::SgHttpCookieMatchPolicy::Regular(Regex::new(&re)?)),
+                            config::SgHttpCookieMatch::Present { name } => (name, kernel::SgHttpCookieMatchPolicy::Present),
+                            config::SgHttpCookieMatch::Absent { name } => (name, kernel::SgHttpCookieMatchPolicy::Absent),
+                            config::SgHttpCookieMatch::NotExact { name, value } => (name, kernel::SgHttpCookieMatchPolicy::NotExact(value)),
+                            config::SgHttpCookieMatch::NotRegular { name, re } => (name, kernel::SgHttpCookieMatchPolicy::NotRegular(Regex::new(&re)?)),
+                        };
+                        Ok(kernel::SgHttpCookieMatch { name, policy })
+                    })
+                    .collect::<Result<Vec<_>, BoxError>>()?,
+            ),
+            None => None,
+        },
+        client_ip: match config_match.client_ip {
+            Some(cidrs) => Some(cidrs.iter().map(|cidr| cidr.parse::<kernel::SgHttpClientIpMatch>()).collect::<Result<Vec<_>, BoxError>>()?),
+            None => None,
+        },
+        sni: config_match.sni.map(|sni| sni.into_iter().map(kernel::SgHttpSniMatch).collect()),
+        expr: config_match.expr.as_deref().map(SgMatchExpr::new).transpose()?,
+        case_insensitive,
+    })
+}
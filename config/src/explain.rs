@@ -0,0 +1,160 @@
+//! # Route Explain
+//!
+//! Explain how a gateway would route a request, by the same router the gateway uses, without starting the gateway or sending anything to backends.
+
+use std::net::{IpAddr, SocketAddr};
+
+use hyper::{header::HOST, Request};
+use spacegate_kernel::{
+    extension::{ListenerHostname, ListenerName, PeerAddr, TlsServerName},
+    layers::{
+        gateway::SgGatewayRouter,
+        http_route::{SgHttpRoute, SgHttpRouteRuleLayer},
+    },
+    SgBody,
+};
+
+use crate::{
+    convert::{convert_path_normalization, convert_rule_matches},
+    model::{SgBackendTrace, SgExplainRequest, SgMatchTrace, SgPluginTrace, SgPredicateTrace, SgRouteExplain, SgRouteFilter, SgRouteTrace, SgRuleTrace, SgSelectedRule},
+    BoxError, Config,
+};
+
+fn trace_plugins(scope: &str, filters: &[SgRouteFilter], registered: &impl Fn(&str) -> bool) -> Vec<SgPluginTrace> {
+    filters
+        .iter()
+        .map(|filter| SgPluginTrace {
+            scope: scope.to_string(),
+            code: filter.code.clone(),
+            name: filter.name.clone(),
+            registered: registered(&filter.code),
+        })
+        .collect()
+}
+
+/// Explain how the gateway `request.gateway` of `config` would route the request, `registered` tells whether a plugin code is known to the gateway.
+///
+/// Routes are considered as the gateway does, i.e. by priority and then by name, and the path is normalized as the gateway does.
+pub fn explain(config: &Config, request: &SgExplainRequest, registered: impl Fn(&str) -> bool) -> Result<SgRouteExplain, BoxError> {
+    let item = config.gateways.get(&request.gateway).ok_or_else(|| format!("gateway {} not found", request.gateway))?;
+    let routes = item.routes.iter().collect::<Vec<_>>();
+    let kernel_routes = routes
+        .iter()
+        .map(|(_, route)| {
+            let rules =
+                route.rules.iter().map(|rule| convert_rule_matches(SgHttpRouteRuleLayer::builder(), rule.matches.clone())?.build()).collect::<Result<Vec<_>, BoxError>>()?;
+            SgHttpRoute::builder()
+                .hostnames(route.hostnames.clone().unwrap_or_default())
+                .listeners(route.section_names.clone().unwrap_or_default())
+                .rules(rules)
+                .priority(route.priority)
+                .build()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let router = SgGatewayRouter::new(&kernel_routes);
+
+    let normalizer = convert_path_normalization(item.gateway.parameters.path_normalization.as_deref().unwrap_or_default());
+    let path = normalizer.normalize(&request.path).into_owned();
+    let uri = match &request.query {
+        Some(query) => format!("{path}?{query}"),
+        None => path.clone(),
+    };
+    let mut builder = Request::builder().method(request.method.as_str()).uri(uri).header(HOST, &request.host);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    let mut req = builder.body(SgBody::empty())?;
+    if let Some(client_ip) = &request.client_ip {
+        let ip: IpAddr = client_ip.parse()?;
+        req.extensions_mut().insert(PeerAddr(SocketAddr::new(ip, 0)));
+    }
+    if let Some(sni) = &request.sni {
+        req.extensions_mut().insert(TlsServerName(sni.as_str().into()));
+    }
+    if let Some(name) = &request.listener {
+        let listener = item.gateway.listeners.iter().find(|listener| &listener.name == name).ok_or_else(|| format!("listener {name} not found"))?;
+        req.extensions_mut().insert(ListenerName(name.as_str().into()));
+        if let Some(hostname) = listener.hostname.as_deref().filter(|hostname| *hostname != "*") {
+            req.extensions_mut().insert(ListenerHostname(hostname.into()));
+        }
+    }
+
+    let (candidates, selected) = router.explain(&req);
+    let mut traces: Vec<SgRouteTrace> = Vec::new();
+    for candidate in candidates {
+        let (name, route) = routes[candidate.route];
+        if traces.last().map(|trace| &trace.name) != Some(name) {
+            traces.push(SgRouteTrace {
+                name: name.clone(),
+                priority: route.priority,
+                rules: Vec::new(),
+            });
+        }
+        let Some(route_trace) = traces.last_mut() else {
+            continue;
+        };
+        if route_trace.rules.last().map(|rule| rule.index) != Some(candidate.rule) {
+            route_trace.rules.push(SgRuleTrace {
+                index: candidate.rule,
+                matches: Vec::new(),
+                matched: false,
+            });
+        }
+        let Some(rule_trace) = route_trace.rules.last_mut() else {
+            continue;
+        };
+        rule_trace.matched |= candidate.matched;
+        if let Some(index) = candidate.match_index {
+            rule_trace.matches.push(SgMatchTrace {
+                index,
+                predicates: candidate
+                    .predicates
+                    .into_iter()
+                    .map(|predicate| SgPredicateTrace {
+                        kind: predicate.kind.to_string(),
+                        predicate: predicate.predicate,
+                        passed: predicate.passed,
+                    })
+                    .collect(),
+                matched: candidate.matched,
+            });
+        }
+    }
+
+    let selected = selected.map(|(route_index, rule_index)| {
+        let (name, route) = routes[route_index];
+        let rule = &route.rules[rule_index];
+        let r#match = traces
+            .iter()
+            .filter(|trace| &trace.name == name)
+            .flat_map(|trace| trace.rules.iter())
+            .find(|trace| trace.index == rule_index)
+            .and_then(|trace| trace.matches.iter().find(|m| m.matched))
+            .map(|m| m.index);
+        let plugins = [
+            trace_plugins("gateway", &item.gateway.filters, &registered),
+            trace_plugins("route", &route.filters, &registered),
+            trace_plugins("rule", &rule.filters, &registered),
+        ]
+        .concat();
+        let backends = rule
+            .backends
+            .iter()
+            .map(|backend| SgBackendTrace {
+                host: backend.get_host(),
+                port: backend.port,
+                weight: backend.weight,
+                version: backend.version.clone(),
+                plugins: trace_plugins("backend", &backend.filters, &registered),
+            })
+            .collect();
+        SgSelectedRule {
+            route: name.clone(),
+            rule: rule_index,
+            r#match,
+            plugins,
+            backends,
+        }
+    });
+    Ok(SgRouteExplain { path, routes: traces, selected })
+}
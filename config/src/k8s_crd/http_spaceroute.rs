@@ -2,7 +2,10 @@ use k8s_gateway_api::{BackendObjectReference, CommonRouteSpec, Hostname, HttpRou
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use std::collections::BTreeMap;
 
-use crate::constants::{self, DEFAULT_NAMESPACE};
+use crate::{
+    constants::{self, DEFAULT_NAMESPACE},
+    model::{http_route::SgHttpCanary, SgHttpCookieMatch, SgHttpHeaderMatch, SgHttpQueryMatch},
+};
 
 #[derive(Clone, Debug, Default, kube::CustomResource, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 #[kube(
@@ -121,7 +124,7 @@ pub struct HttpRouteRule {
     ///
     /// When no rules matching a request have been successfully attached to the
     /// parent a request is coming from, a HTTP 404 status code MUST be returned.
-    pub matches: Option<Vec<HttpRouteMatch>>,
+    pub matches: Option<Vec<HttpSpacerouteMatch>>,
 
     /// Filters define the filters that are applied to requests that match this
     /// rule.
@@ -172,6 +175,61 @@ pub struct HttpRouteRule {
     pub backend_refs: Option<Vec<HttpBackendRef>>,
 
     pub timeout_ms: Option<u32>,
+
+    pub connect_timeout_ms: Option<u32>,
+
+    pub response_header_timeout_ms: Option<u32>,
+
+    pub idle_stream_timeout_ms: Option<u32>,
+
+    pub deadline_ms: Option<u32>,
+
+    pub canary: Option<SgHttpCanary>,
+}
+
+/// HTTPRouteMatch with predicates the gateway api can't express.
+///
+/// All of them are ANDed with the standard ones.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpSpacerouteMatch {
+    #[serde(flatten)]
+    pub inner: HttpRouteMatch,
+
+    /// Header matchers besides `Exact` and `RegularExpression`, like presence, absence and negation.
+    pub header_predicates: Option<Vec<SgHttpHeaderMatch>>,
+
+    /// Query parameter matchers besides `Exact` and `RegularExpression`, like presence, absence and negation.
+    pub query_param_predicates: Option<Vec<SgHttpQueryMatch>>,
+
+    pub cookies: Option<Vec<SgHttpCookieMatch>>,
+
+    /// Networks of the client, the match is satisfied if the client is in any of them.
+    pub client_ips: Option<Vec<String>>,
+
+    /// TLS server names, the match is satisfied if the client indicated any of them.
+    pub snis: Option<Vec<String>>,
+
+    /// A compound condition over request attributes in a subset of CEL.
+    pub expr: Option<String>,
+
+    /// Whether the path is matched with case sensitivity, `true` by default.
+    pub case_sensitive: Option<bool>,
+}
+
+impl From<HttpRouteMatch> for HttpSpacerouteMatch {
+    fn from(inner: HttpRouteMatch) -> Self {
+        HttpSpacerouteMatch {
+            inner,
+            header_predicates: None,
+            query_param_predicates: None,
+            cookies: None,
+            client_ips: None,
+            snis: None,
+            expr: None,
+            case_sensitive: None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
@@ -225,6 +283,16 @@ pub struct BackendRef {
 
     pub timeout_ms: Option<u32>,
 
+    pub connect_timeout_ms: Option<u32>,
+
+    pub response_header_timeout_ms: Option<u32>,
+
+    pub idle_stream_timeout_ms: Option<u32>,
+
+    pub deadline_ms: Option<u32>,
+
+    pub version: Option<String>,
+
     /// BackendObjectReference references a Kubernetes object.
     #[serde(flatten)]
     pub inner: BackendObjectReference,
@@ -249,7 +317,7 @@ impl From<HttpRoute> for HttpSpaceroute {
                     rules
                         .into_iter()
                         .map(|rule| HttpRouteRule {
-                            matches: rule.matches,
+                            matches: rule.matches.map(|matches| matches.into_iter().map(HttpSpacerouteMatch::from).collect()),
                             filters: rule.filters,
                             backend_refs: rule.backend_refs.map(|backend_refs| {
                                 backend_refs
@@ -258,6 +326,11 @@ impl From<HttpRoute> for HttpSpaceroute {
                                         backend_ref: http_backend_ref.backend_ref.map(|backend_ref| BackendRef {
                                             weight: backend_ref.weight,
                                             timeout_ms: None,
+                                            connect_timeout_ms: None,
+                                            response_header_timeout_ms: None,
+                                            idle_stream_timeout_ms: None,
+                                            deadline_ms: None,
+                                            version: None,
                                             inner: BackendObjectReference {
                                                 group: backend_ref.inner.group,
                                                 kind: backend_ref.inner.kind,
@@ -271,6 +344,11 @@ impl From<HttpRoute> for HttpSpaceroute {
                                     .collect()
                             }),
                             timeout_ms: None,
+                            connect_timeout_ms: None,
+                            response_header_timeout_ms: None,
+                            idle_stream_timeout_ms: None,
+                            deadline_ms: None,
+                            canary: None,
                         })
                         .collect()
                 }),
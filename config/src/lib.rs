@@ -3,6 +3,10 @@ use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 pub mod constants;
+#[cfg(feature = "kernel")]
+pub mod convert;
+#[cfg(feature = "kernel")]
+pub mod explain;
 #[cfg(feature = "k8s")]
 pub mod k8s_crd;
 #[cfg(feature = "service")]
@@ -1,3 +1,6 @@
+pub mod explain;
+pub use explain::*;
+
 pub mod filter;
 pub use filter::*;
 
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A synthetic request to explain how a gateway would route it, nothing is sent to backends.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[serde(default)]
+pub struct SgExplainRequest {
+    /// Name of the gateway receiving the request.
+    pub gateway: String,
+    pub method: String,
+    /// Host header of the request, a port may be included.
+    pub host: String,
+    pub path: String,
+    /// Query string of the request, without the leading `?`.
+    pub query: Option<String>,
+    /// Headers of the request, cookies are given by the `cookie` header.
+    pub headers: BTreeMap<String, String>,
+    /// IP address of the client, used by client IP matches.
+    pub client_ip: Option<String>,
+    /// TLS server name indicated by the client, a plain connection has none.
+    pub sni: Option<String>,
+    /// Name of the listener accepting the request, the request is from all listeners if absent.
+    pub listener: Option<String>,
+}
+
+impl Default for SgExplainRequest {
+    fn default() -> Self {
+        Self {
+            gateway: Default::default(),
+            method: "GET".to_string(),
+            host: Default::default(),
+            path: "/".to_string(),
+            query: Default::default(),
+            headers: Default::default(),
+            client_ip: Default::default(),
+            sni: Default::default(),
+            listener: Default::default(),
+        }
+    }
+}
+
+/// How a request would be routed by a gateway.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgRouteExplain {
+    /// Path used for routing, after the path normalization of the gateway.
+    pub path: String,
+    /// Routes serving the host of the request, in the order they are tried.
+    pub routes: Vec<SgRouteTrace>,
+    /// The rule handling the request, `None` if the request falls back to the not found response.
+    pub selected: Option<SgSelectedRule>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgRouteTrace {
+    pub name: String,
+    pub priority: i16,
+    pub rules: Vec<SgRuleTrace>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgRuleTrace {
+    /// Index of the rule in the route.
+    pub index: usize,
+    /// Matches of the rule, empty if the rule matches all requests.
+    pub matches: Vec<SgMatchTrace>,
+    pub matched: bool,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgMatchTrace {
+    /// Index of the match in the rule.
+    pub index: usize,
+    pub predicates: Vec<SgPredicateTrace>,
+    pub matched: bool,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgPredicateTrace {
+    /// Kind of the predicate, e.g. `path`, `header` or `expr`.
+    pub kind: String,
+    /// The predicate in a human readable form, e.g. `Prefix /api`.
+    pub predicate: String,
+    pub passed: bool,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgSelectedRule {
+    /// Name of the route.
+    pub route: String,
+    /// Index of the rule in the route.
+    pub rule: usize,
+    /// Index of the match in the rule, `None` if the rule matches all requests.
+    pub r#match: Option<usize>,
+    /// Plugins the request passes through before reaching a backend, from the outermost.
+    pub plugins: Vec<SgPluginTrace>,
+    /// Backends the request may be forwarded to.
+    pub backends: Vec<SgBackendTrace>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgPluginTrace {
+    /// Level of the plugin, one of `gateway`, `route`, `rule` and `backend`.
+    pub scope: String,
+    pub code: String,
+    pub name: Option<String>,
+    /// Whether the plugin code is registered, a plugin of an unknown code is skipped by the gateway.
+    pub registered: bool,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub struct SgBackendTrace {
+    pub host: String,
+    pub port: u16,
+    pub weight: u16,
+    pub version: Option<String>,
+    /// Plugins of the backend, applied after the plugins of the selected rule.
+    pub plugins: Vec<SgPluginTrace>,
+}
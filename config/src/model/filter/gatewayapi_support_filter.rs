@@ -70,3 +70,44 @@ pub enum SgHttpPathModifierType {
     #[default]
     ReplacePrefixMatch,
 }
+
+pub const SG_FILTER_MIRROR_CODE: &str = "mirror";
+
+/// MirrorFilter defines a filter that sends a copy of the request to shadow backends, responses of shadow backends are ignored.
+///
+/// https://gateway-api.sigs.k8s.io/api-types/httproute/#filters-optional
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SgFilterMirror {
+    /// Backends define the shadow backends where a copy of the request should be sent.
+    pub backends: Vec<SgMirrorBackend>,
+    /// Percentage of requests to be mirrored, from 0 to 100.
+    pub percentage: f64,
+    /// Whether to mirror the request body.
+    pub mirror_body: bool,
+    /// Bodies larger than this, or with unknown size, are not mirrored.
+    pub max_body_size: usize,
+    /// Timeout for a mirrored request.
+    pub timeout_ms: u64,
+}
+
+impl Default for SgFilterMirror {
+    fn default() -> Self {
+        Self {
+            backends: Vec::new(),
+            percentage: 100.0,
+            mirror_body: false,
+            max_body_size: 0x10000,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SgMirrorBackend {
+    pub host: String,
+    pub port: Option<u16>,
+    /// `http` or `https`, default to `http`.
+    pub protocol: Option<String>,
+}
@@ -2,7 +2,7 @@ use std::{fmt::Display, net::IpAddr};
 
 use serde::{Deserialize, Serialize};
 
-use super::filter::SgRouteFilter;
+use super::{filter::SgRouteFilter, http_route::SgBackendRef};
 
 /// Gateway represents an instance of a service-traffic handling infrastructure
 /// by binding Listeners to a set of IP addresses.
@@ -20,6 +20,43 @@ pub struct SgGateway {
     pub listeners: Vec<SgListener>,
     /// Filters define the filters that are applied to requests that match this gateway.
     pub filters: Vec<SgRouteFilter>,
+    /// What the gateway responds when no route matches a request, `404 Not Found` by default.
+    pub fallback: Option<SgGatewayFallback>,
+    /// Pages replacing errors generated by the gateway itself, e.g. no route matched, timeouts, bad gateway or plugin errors.
+    pub error_pages: Vec<SgErrorPage>,
+}
+
+/// Response of a gateway when no route matches a request.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub enum SgGatewayFallback {
+    /// Respond a static response.
+    Static {
+        status: u16,
+        content_type: Option<String>,
+        #[serde(default)]
+        body: String,
+    },
+    /// Redirect to the location, with `302 Found` by default.
+    Redirect { location: String, status: Option<u16> },
+    /// Forward to a default backend.
+    Backend { backend: SgBackendRef },
+}
+
+/// Page of errors of some status codes.
+///
+/// The template may refer to `${status}`, `${reason}`, `${message}` and `${request_id}`,
+/// pages of the same status are picked by the `Accept` header of the request.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[serde(default)]
+pub struct SgErrorPage {
+    /// A status code like `404`, a class like `5xx`, or an inclusive range like `500-504`.
+    pub status: String,
+    /// Content type of the page, e.g. `text/html; charset=utf-8` or `application/json`.
+    pub content_type: String,
+    pub template: String,
 }
 
 /// Gateway parameter configuration.
@@ -35,6 +72,44 @@ pub struct SgParameters {
     pub lang: Option<String>,
     /// Ignore backend tls verification
     pub ignore_tls_verification: Option<bool>,
+    /// Steps of path normalization before routing, paths are left as is by default.
+    pub path_normalization: Option<Vec<SgPathNormalization>>,
+}
+
+/// A step of path normalization, steps are applied in the order of the variants whatever order they are given.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+pub enum SgPathNormalization {
+    /// Decode percent-encoded unreserved characters, e.g. `%7E` to `~`, and uppercase hex digits of other percent-encodings.
+    DecodeUnreserved,
+    /// Merge consecutive slashes into one, e.g. `/a//b` to `/a/b`.
+    MergeSlashes,
+    /// Remove `.` and `..` segments, e.g. `/a/./c/../b` to `/a/b`.
+    RemoveDotSegments,
+}
+
+impl Display for SgPathNormalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SgPathNormalization::DecodeUnreserved => write!(f, "decode_unreserved"),
+            SgPathNormalization::MergeSlashes => write!(f, "merge_slashes"),
+            SgPathNormalization::RemoveDotSegments => write!(f, "remove_dot_segments"),
+        }
+    }
+}
+
+impl std::str::FromStr for SgPathNormalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "decode_unreserved" => Ok(SgPathNormalization::DecodeUnreserved),
+            "merge_slashes" => Ok(SgPathNormalization::MergeSlashes),
+            "remove_dot_segments" => Ok(SgPathNormalization::RemoveDotSegments),
+            other => Err(format!("unknown path normalization {other}")),
+        }
+    }
 }
 
 /// Listener embodies the concept of a logical endpoint where a Gateway accepts network connections.
@@ -52,6 +127,29 @@ pub struct SgListener {
     pub protocol: SgProtocolConfig,
     /// `HostName` is used to define the host on which the listener accepts requests.
     pub hostname: Option<String>,
+    /// Connection level limits of this listener.
+    pub limits: SgListenerLimits,
+}
+
+/// Connection level limits of a listener, unset means no limit or the default value.
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[serde(default)]
+pub struct SgListenerLimits {
+    /// Maximum number of concurrent connections.
+    pub max_connections: Option<u32>,
+    /// Maximum number of concurrent connections from the same source ip.
+    pub max_connections_per_ip: Option<u32>,
+    /// Timeout in milliseconds for a client to send the entire request header.
+    pub header_read_timeout_ms: Option<u32>,
+    /// Connections without any read or write for this duration in milliseconds will be closed.
+    pub idle_timeout_ms: Option<u32>,
+    /// Maximum size of request header in bytes.
+    pub max_header_size: Option<u32>,
+    /// Maximum number of request headers.
+    pub max_headers: Option<u32>,
+    /// Maximum number of concurrent streams of a http2 connection.
+    pub http2_max_concurrent_streams: Option<u32>,
 }
 
 #[non_exhaustive]
@@ -14,6 +14,8 @@ pub struct SgHttpRoute {
     pub gateway_name: String,
     /// Hostnames defines a set of hostname that should match against the HTTP Host header to select a HTTPRoute to process the request.
     pub hostnames: Option<Vec<String>>,
+    /// Names of listeners of the gateway the route attaches to, i.e. `sectionName` of parent refs, all listeners if absent.
+    pub section_names: Option<Vec<String>>,
     /// Filters define the filters that are applied to requests that match this hostnames.
     pub filters: Vec<SgRouteFilter>,
     /// Rules are a list of HTTP matchers, filters and actions.
@@ -27,6 +29,7 @@ impl Default for SgHttpRoute {
         Self {
             gateway_name: Default::default(),
             hostnames: Default::default(),
+            section_names: Default::default(),
             filters: Default::default(),
             rules: Default::default(),
             priority: 1,
@@ -45,8 +48,67 @@ pub struct SgHttpRouteRule {
     pub filters: Vec<SgRouteFilter>,
     /// BackendRefs defines the backend(s) where matching requests should be sent.
     pub backends: Vec<SgBackendRef>,
-    /// Timeout define the timeout for requests that match this rule.
+    /// Timeout define the timeout for requests that match this rule to get the response, streaming the response body is not limited.
     pub timeout_ms: Option<u32>,
+    /// Default timeout of connecting to backends of this rule.
+    pub connect_timeout_ms: Option<u32>,
+    /// Default timeout of waiting for the response header from backends of this rule.
+    pub response_header_timeout_ms: Option<u32>,
+    /// Default max interval between two chunks of the response body from backends of this rule.
+    pub idle_stream_timeout_ms: Option<u32>,
+    /// Default overall deadline of the exchange with backends of this rule, including streaming the response body.
+    pub deadline_ms: Option<u32>,
+    /// Canary pins users to either canary or stable backends deterministically, instead of picking backends randomly by weight per request.
+    pub canary: Option<SgHttpCanary>,
+}
+
+/// Canary routing of a rule.
+///
+/// Backends whose version is the canary version are canary backends, the other ones are stable backends.
+/// A request goes to the version forced by the override header, or the side recorded by the sticky cookie,
+/// or else the side decided by the hash of the user identifier, or randomly by the percentage without identifier.
+/// Backends are picked by weight within a side.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
+pub struct SgHttpCanary {
+    /// Version of canary backends, see [`SgBackendRef::version`].
+    pub version: String,
+    /// Percentage of users pinned to canary backends, from 0 to 100.
+    pub percentage: u8,
+    /// Where the user identifier is read from.
+    #[serde(default)]
+    pub key: Option<SgCanaryKey>,
+    /// Header whose value forces a version, e.g. `x-canary-version: v2`.
+    #[serde(default)]
+    pub override_header: Option<String>,
+    /// Cookie set by the gateway to keep users on the picked side.
+    #[serde(default)]
+    pub sticky_cookie: Option<SgCanaryCookie>,
+}
+
+/// Where the user identifier of canary routing is read from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum SgCanaryKey {
+    Header { name: String },
+    Cookie { name: String },
+    Query { name: String },
+}
+
+/// Sticky cookie of canary routing, its value is `<version>:canary` or `<version>:stable`.
+///
+/// A cookie issued for another canary version is ignored, so is a cookie pinning to canary while the percentage is 0.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
+pub struct SgCanaryCookie {
+    pub name: String,
+    /// Unset means a session cookie.
+    #[serde(default)]
+    pub max_age_secs: Option<u32>,
 }
 
 /// BackendRef defines how a HTTPRoute should forward an HTTP request.
@@ -58,8 +120,16 @@ pub struct SgBackendRef {
     pub host: BackendHost,
     /// Port specifies the destination port number to use for this resource.
     pub port: u16,
-    /// Timeout specifies the timeout for requests forwarded to the referenced backend.
+    /// Timeout specifies the timeout for requests forwarded to the referenced backend to get the response, streaming the response body is not limited.
     pub timeout_ms: Option<u32>,
+    /// Timeout of connecting to the referenced backend, overrides the one of the rule.
+    pub connect_timeout_ms: Option<u32>,
+    /// Timeout of waiting for the response header from the referenced backend, overrides the one of the rule.
+    pub response_header_timeout_ms: Option<u32>,
+    /// Max interval between two chunks of the response body from the referenced backend, overrides the one of the rule.
+    pub idle_stream_timeout_ms: Option<u32>,
+    /// Overall deadline of the exchange with the referenced backend, including streaming the response body, overrides the one of the rule.
+    pub deadline_ms: Option<u32>,
     // Protocol specifies the protocol used to talk to the referenced backend.
     pub protocol: Option<SgBackendProtocol>,
     /// Weight specifies the proportion of requests forwarded to the referenced backend.
@@ -67,6 +137,8 @@ pub struct SgBackendRef {
     /// For non-zero values, there may be some epsilon from the exact proportion defined here depending on the precision an implementation supports.
     /// Weight is not a percentage and the sum of weights does not need to equal 100.
     pub weight: u16,
+    /// Version label of the backend, used by canary routing of the rule.
+    pub version: Option<String>,
     /// Filters define the filters that are applied to backend that match this hostnames.
     pub filters: Vec<SgRouteFilter>,
 }
@@ -77,8 +149,13 @@ impl Default for SgBackendRef {
             host: Default::default(),
             port: 80,
             timeout_ms: Default::default(),
+            connect_timeout_ms: Default::default(),
+            response_header_timeout_ms: Default::default(),
+            idle_stream_timeout_ms: Default::default(),
+            deadline_ms: Default::default(),
             protocol: Default::default(),
             weight: 1,
+            version: Default::default(),
             filters: Default::default(),
         }
     }
@@ -1,17 +1,19 @@
 use std::collections::HashMap;
 
-use k8s_gateway_api::{HttpHeader, HttpPathModifier, HttpRequestHeaderFilter, HttpRequestRedirectFilter, HttpRouteFilter, HttpUrlRewriteFilter};
+use k8s_gateway_api::{
+    BackendObjectReference, HttpHeader, HttpPathModifier, HttpRequestHeaderFilter, HttpRequestMirrorFilter, HttpRequestRedirectFilter, HttpRouteFilter, HttpUrlRewriteFilter,
+};
 
 use crate::{
     constants,
     k8s_crd::sg_filter::{K8sSgFilterSpecFilter, K8sSgFilterSpecTargetRef},
     model::{
         gatewayapi_support_filter::{
-            SgFilterHeaderModifier, SgFilterHeaderModifierKind, SgFilterRedirect, SgFilterRewrite, SgHttpPathModifier, SgHttpPathModifierType, SG_FILTER_HEADER_MODIFIER_CODE,
-            SG_FILTER_REDIRECT_CODE, SG_FILTER_REWRITE_CODE,
+            SgFilterHeaderModifier, SgFilterHeaderModifierKind, SgFilterMirror, SgFilterRedirect, SgFilterRewrite, SgHttpPathModifier, SgHttpPathModifierType, SgMirrorBackend,
+            SG_FILTER_HEADER_MODIFIER_CODE, SG_FILTER_MIRROR_CODE, SG_FILTER_REDIRECT_CODE, SG_FILTER_REWRITE_CODE,
         },
         helper_filter::SgSingeFilter,
-        SgRouteFilter,
+        K8sServiceData, SgRouteFilter,
     },
     BoxResult,
 };
@@ -23,7 +25,11 @@ impl SgRouteFilter {
     /// the k8s object of `SGFilter`, whereas `to_http_route_filter` is used to convert to `HttpRouteFilter`,
     /// a filter defined in the Gateway API.
     pub fn to_singe_filter(self, target: K8sSgFilterSpecTargetRef) -> Option<SgSingeFilter> {
-        if self.code == SG_FILTER_HEADER_MODIFIER_CODE || self.code == SG_FILTER_REDIRECT_CODE || self.code == SG_FILTER_REWRITE_CODE {
+        if self.code == SG_FILTER_HEADER_MODIFIER_CODE
+            || self.code == SG_FILTER_REDIRECT_CODE
+            || self.code == SG_FILTER_REWRITE_CODE
+            || (self.code == SG_FILTER_MIRROR_CODE && SgFilterMirror::to_request_mirror_filter(&self.spec).is_some())
+        {
             None
         } else {
             Some(SgSingeFilter {
@@ -86,6 +92,8 @@ impl SgRouteFilter {
             } else {
                 None
             }
+        } else if self.code == SG_FILTER_MIRROR_CODE {
+            SgFilterMirror::to_request_mirror_filter(&self.spec).map(|request_mirror| HttpRouteFilter::RequestMirror { request_mirror })
         } else {
             None
         }
@@ -159,13 +167,91 @@ impl SgRouteFilter {
                     }),
                 })?,
             },
-            k8s_gateway_api::HttpRouteFilter::RequestMirror { .. } => return Err("[SG.Common] HttpRoute [spec.rules.filters.type=RequestMirror] not supported yet".into()),
+            k8s_gateway_api::HttpRouteFilter::RequestMirror { request_mirror } => SgRouteFilter {
+                code: SG_FILTER_MIRROR_CODE.to_string(),
+                name: None,
+                spec: serde_json::to_value(SgFilterMirror::from_request_mirror_filter(request_mirror))?,
+            },
             k8s_gateway_api::HttpRouteFilter::ExtensionRef { .. } => return Err("[SG.Common] HttpRoute [spec.rules.filters.type=ExtensionRef] not supported yet".into()),
         };
         Ok(sg_filter)
     }
 }
 
+impl SgFilterMirror {
+    /// Gateway API `RequestMirror` filter mirrors every request with its body to a single backend,
+    /// mirror filters with other options can only be represented by `SGFilter`.
+    fn to_request_mirror_filter(spec: &serde_json::Value) -> Option<HttpRequestMirrorFilter> {
+        let mirror = serde_json::from_value::<SgFilterMirror>(spec.clone()).ok()?;
+        let default = SgFilterMirror::default();
+        if mirror.backends.len() != 1
+            || mirror.percentage < 100.0
+            || !mirror.mirror_body
+            || mirror.max_body_size != default.max_body_size
+            || mirror.timeout_ms != default.timeout_ms
+        {
+            return None;
+        }
+        let backend = mirror.backends.into_iter().next()?;
+        let backend_ref = match backend.protocol.as_deref() {
+            Some("https") => BackendObjectReference {
+                group: None,
+                kind: Some(constants::BANCKEND_KIND_EXTERNAL_HTTPS.to_string()),
+                name: backend.host,
+                namespace: None,
+                port: backend.port,
+            },
+            Some(_) => BackendObjectReference {
+                group: None,
+                kind: Some(constants::BANCKEND_KIND_EXTERNAL_HTTP.to_string()),
+                name: backend.host,
+                namespace: None,
+                port: backend.port,
+            },
+            None => {
+                let (name, namespace) = match backend.host.split_once('.') {
+                    Some((name, namespace)) => (name.to_string(), Some(namespace.to_string())),
+                    None => (backend.host, None),
+                };
+                BackendObjectReference {
+                    group: None,
+                    kind: None,
+                    name,
+                    namespace,
+                    port: backend.port,
+                }
+            }
+        };
+        Some(HttpRequestMirrorFilter { backend_ref })
+    }
+
+    fn from_request_mirror_filter(request_mirror: HttpRequestMirrorFilter) -> SgFilterMirror {
+        let backend_ref = request_mirror.backend_ref;
+        let (host, protocol) = match backend_ref.kind.as_deref() {
+            None | Some(constants::BANCKEND_KIND_SERVICE) => (
+                K8sServiceData {
+                    name: backend_ref.name,
+                    namespace: backend_ref.namespace,
+                }
+                .to_string(),
+                None,
+            ),
+            Some(constants::BANCKEND_KIND_EXTERNAL_HTTP) => (backend_ref.name, Some("http".to_string())),
+            Some(constants::BANCKEND_KIND_EXTERNAL_HTTPS) => (backend_ref.name, Some("https".to_string())),
+            Some(_) => (backend_ref.name, None),
+        };
+        SgFilterMirror {
+            backends: vec![SgMirrorBackend {
+                host,
+                port: backend_ref.port,
+                protocol,
+            }],
+            mirror_body: true,
+            ..Default::default()
+        }
+    }
+}
+
 impl SgHttpPathModifier {
     pub fn to_http_path_modifier(self) -> HttpPathModifier {
         match self.kind {
@@ -8,16 +8,25 @@ use kube::api::ObjectMeta;
 use crate::{
     constants,
     k8s_crd::sg_filter::{K8sSgFilterSpecFilter, K8sSgFilterSpecTargetRef, SgFilterTargetKind},
-    model::{helper_filter::SgSingeFilter, SgGateway, SgParameters},
+    model::{helper_filter::SgSingeFilter, SgErrorPage, SgGateway, SgGatewayFallback, SgParameters, SgPathNormalization},
 };
 
 impl SgGateway {
     pub fn to_kube_gateway(self, namespace: &str) -> (Gateway, Option<Secret>, Vec<SgSingeFilter>) {
         let mut secret = None;
 
+        let mut annotations = self.parameters.into_kube_gateway();
+        if let Some(fallback) = self.fallback.and_then(|fallback| serde_json::to_string(&fallback).ok()) {
+            annotations.insert(crate::constants::GATEWAY_ANNOTATION_FALLBACK.to_string(), fallback);
+        }
+        if !self.error_pages.is_empty() {
+            if let Ok(error_pages) = serde_json::to_string(&self.error_pages) {
+                annotations.insert(crate::constants::GATEWAY_ANNOTATION_ERROR_PAGES.to_string(), error_pages);
+            }
+        }
         let gateway = Gateway {
             metadata: ObjectMeta {
-                annotations: Some(self.parameters.into_kube_gateway()),
+                annotations: Some(annotations),
                 labels: None,
                 name: Some(self.name.clone()),
                 owner_references: None,
@@ -114,6 +123,12 @@ impl SgParameters {
                 ignore_tls_verification.to_string(),
             );
         }
+        if let Some(path_normalization) = self.path_normalization {
+            ann.insert(
+                crate::constants::GATEWAY_ANNOTATION_PATH_NORMALIZATION.to_string(),
+                path_normalization.iter().map(|step| step.to_string()).collect::<Vec<_>>().join(","),
+            );
+        }
         ann
     }
 
@@ -125,6 +140,12 @@ impl SgParameters {
                 log_level: gateway_annotations.get(crate::constants::GATEWAY_ANNOTATION_LOG_LEVEL).map(|v| v.to_string()),
                 lang: gateway_annotations.get(crate::constants::GATEWAY_ANNOTATION_LANGUAGE).map(|v| v.to_string()),
                 ignore_tls_verification: gateway_annotations.get(crate::constants::GATEWAY_ANNOTATION_IGNORE_TLS_VERIFICATION).and_then(|v| v.parse::<bool>().ok()),
+                path_normalization: gateway_annotations.get(crate::constants::GATEWAY_ANNOTATION_PATH_NORMALIZATION).map(|v| {
+                    v.split(',')
+                        .filter(|step| !step.trim().is_empty())
+                        .filter_map(|step| step.parse::<SgPathNormalization>().map_err(|e| tracing::warn!("[SG.Common] {e}")).ok())
+                        .collect()
+                }),
             }
         } else {
             SgParameters {
@@ -132,7 +153,24 @@ impl SgParameters {
                 log_level: None,
                 lang: None,
                 ignore_tls_verification: None,
+                path_normalization: None,
             }
         }
     }
 }
+
+impl SgGatewayFallback {
+    pub(crate) fn from_kube_gateway(gateway: &Gateway) -> Option<Self> {
+        let fallback = gateway.metadata.annotations.as_ref()?.get(crate::constants::GATEWAY_ANNOTATION_FALLBACK)?;
+        serde_json::from_str(fallback).map_err(|e| tracing::warn!("[SG.Common] invalid gateway fallback: {e}")).ok()
+    }
+}
+
+impl SgErrorPage {
+    pub(crate) fn from_kube_gateway(gateway: &Gateway) -> Vec<Self> {
+        let Some(error_pages) = gateway.metadata.annotations.as_ref().and_then(|annotations| annotations.get(crate::constants::GATEWAY_ANNOTATION_ERROR_PAGES)) else {
+            return Vec::new();
+        };
+        serde_json::from_str(error_pages).map_err(|e| tracing::warn!("[SG.Common] invalid gateway error pages: {e}")).unwrap_or_default()
+    }
+}
@@ -8,7 +8,7 @@ use kube::api::ObjectMeta;
 use crate::{
     constants,
     k8s_crd::{
-        http_spaceroute::{self, BackendRef, HttpBackendRef, HttpRouteRule, HttpSpaceroute, HttpSpacerouteSpec},
+        http_spaceroute::{self, BackendRef, HttpBackendRef, HttpRouteRule, HttpSpaceroute, HttpSpacerouteMatch, HttpSpacerouteSpec},
         sg_filter::K8sSgFilterSpecTargetRef,
     },
     model::{
@@ -96,14 +96,22 @@ impl SgHttpRoute {
             },
             spec: HttpSpacerouteSpec {
                 inner: CommonRouteSpec {
-                    parent_refs: Some(vec![ParentReference {
-                        group: None,
-                        kind: Some("Gateway".to_string()),
-                        namespace: Some(namespace.to_string()),
-                        name: self.gateway_name.clone(),
-                        section_name: None,
-                        port: None,
-                    }]),
+                    parent_refs: Some(
+                        match self.section_names {
+                            Some(section_names) => section_names.into_iter().map(Some).collect::<Vec<_>>(),
+                            None => vec![None],
+                        }
+                        .into_iter()
+                        .map(|section_name| ParentReference {
+                            group: None,
+                            kind: Some("Gateway".to_string()),
+                            namespace: Some(namespace.to_string()),
+                            name: self.gateway_name.clone(),
+                            section_name,
+                            port: None,
+                        })
+                        .collect(),
+                    ),
                 },
                 hostnames: self.hostnames,
                 rules: Some(self.rules.into_iter().map(|r| r.into_kube_httproute()).collect::<Vec<_>>()),
@@ -123,6 +131,11 @@ impl SgHttpRouteRule {
             filters: Some(self.filters.into_iter().filter_map(|f| f.to_http_route_filter()).collect::<Vec<_>>()),
             backend_refs: Some(self.backends.into_iter().map(|b| b.into_kube_httproute()).collect::<Vec<_>>()),
             timeout_ms: self.timeout_ms,
+            connect_timeout_ms: self.connect_timeout_ms,
+            response_header_timeout_ms: self.response_header_timeout_ms,
+            idle_stream_timeout_ms: self.idle_stream_timeout_ms,
+            deadline_ms: self.deadline_ms,
+            canary: self.canary,
         }
     }
 
@@ -136,39 +149,94 @@ impl SgHttpRouteRule {
                 .transpose()?
                 .unwrap_or_default(),
             timeout_ms: rule.timeout_ms,
+            connect_timeout_ms: rule.connect_timeout_ms,
+            response_header_timeout_ms: rule.response_header_timeout_ms,
+            idle_stream_timeout_ms: rule.idle_stream_timeout_ms,
+            deadline_ms: rule.deadline_ms,
+            canary: rule.canary,
         })
     }
 }
 
 impl SgHttpRouteMatch {
-    pub(crate) fn into_kube_httproute(self) -> Vec<HttpRouteMatch> {
-        if let Some(method_vec) = self.method {
-            method_vec
-                .into_iter()
-                .map(|m| HttpRouteMatch {
-                    path: self.path.clone().map(|p| p.into_kube_httproute()),
-                    headers: self.header.clone().map(|h_vec| h_vec.into_iter().map(|h| h.into_kube_httproute()).collect::<Vec<_>>()),
-                    query_params: self.query.clone().map(|q_vec| q_vec.into_iter().map(|q| q.into_kube_httproute()).collect::<Vec<_>>()),
-                    method: Some(m.0),
-                })
-                .collect::<Vec<_>>()
-        } else {
-            vec![HttpRouteMatch {
-                path: self.path.map(|p| p.into_kube_httproute()),
-                headers: self.header.map(|h_vec| h_vec.into_iter().map(|h| h.into_kube_httproute()).collect::<Vec<_>>()),
-                query_params: self.query.map(|q_vec| q_vec.into_iter().map(|q| q.into_kube_httproute()).collect::<Vec<_>>()),
-                method: None,
-            }]
+    pub(crate) fn into_kube_httproute(self) -> Vec<HttpSpacerouteMatch> {
+        // gateway api only has exact and regex matches, others go to the extension fields
+        let (headers, header_predicates) = split_predicates(self.header, SgHttpHeaderMatch::into_kube_httproute);
+        let (query_params, query_param_predicates) = split_predicates(self.query, SgHttpQueryMatch::into_kube_httproute);
+        let route_match = |method: Option<String>| HttpSpacerouteMatch {
+            inner: HttpRouteMatch {
+                path: self.path.clone().map(|p| p.into_kube_httproute()),
+                headers: headers.clone(),
+                query_params: query_params.clone(),
+                method,
+            },
+            header_predicates: header_predicates.clone(),
+            query_param_predicates: query_param_predicates.clone(),
+            cookies: self.cookie.clone(),
+            client_ips: self.client_ip.clone(),
+            snis: self.sni.clone(),
+            expr: self.expr.clone(),
+            case_sensitive: self.case_sensitive,
+        };
+        match &self.method {
+            Some(method_vec) => method_vec.iter().map(|m| route_match(Some(m.0.clone()))).collect::<Vec<_>>(),
+            None => vec![route_match(None)],
         }
     }
 
-    pub(crate) fn from_kube_httproute(route_match: HttpRouteMatch) -> SgHttpRouteMatch {
+    pub(crate) fn from_kube_httproute(route_match: HttpSpacerouteMatch) -> SgHttpRouteMatch {
+        let HttpSpacerouteMatch {
+            inner: route_match,
+            header_predicates,
+            query_param_predicates,
+            cookies,
+            client_ips,
+            snis,
+            expr,
+            case_sensitive,
+        } = route_match;
         SgHttpRouteMatch {
             method: route_match.method.map(|m_vec| vec![http_route::SgHttpMethodMatch(m_vec)]),
             path: route_match.path.map(SgHttpPathMatch::from_kube_httproute),
-            header: route_match.headers.map(|h_vec| h_vec.into_iter().map(SgHttpHeaderMatch::from_kube_httproute).collect::<Vec<_>>()),
-            query: route_match.query_params.map(|q_vec| q_vec.into_iter().map(SgHttpQueryMatch::from_kube_httproute).collect::<Vec<_>>()),
+            header: merge_predicates(
+                route_match.headers.map(|h_vec| h_vec.into_iter().map(SgHttpHeaderMatch::from_kube_httproute).collect::<Vec<_>>()),
+                header_predicates,
+            ),
+            query: merge_predicates(
+                route_match.query_params.map(|q_vec| q_vec.into_iter().map(SgHttpQueryMatch::from_kube_httproute).collect::<Vec<_>>()),
+                query_param_predicates,
+            ),
+            cookie: cookies,
+            client_ip: client_ips,
+            sni: snis,
+            expr,
+            case_sensitive,
+        }
+    }
+}
+
+/// Split matches into ones the gateway api can express, and the rest.
+fn split_predicates<T, K>(matches: Option<Vec<T>>, into_kube: impl Fn(T) -> Result<K, T>) -> (Option<Vec<K>>, Option<Vec<T>>) {
+    let Some(matches) = matches else {
+        return (None, None);
+    };
+    let (mut kube, mut predicates) = (Vec::new(), Vec::new());
+    for m in matches {
+        match into_kube(m) {
+            Ok(k) => kube.push(k),
+            Err(m) => predicates.push(m),
+        }
+    }
+    ((!kube.is_empty()).then_some(kube), (!predicates.is_empty()).then_some(predicates))
+}
+
+fn merge_predicates<T>(matches: Option<Vec<T>>, predicates: Option<Vec<T>>) -> Option<Vec<T>> {
+    match (matches, predicates) {
+        (Some(mut matches), Some(predicates)) => {
+            matches.extend(predicates);
+            Some(matches)
         }
+        (matches, predicates) => matches.or(predicates),
     }
 }
 
@@ -178,9 +246,35 @@ impl SgHttpPathMatch {
             SgHttpPathMatch::Exact(value) => HttpPathMatch::Exact { value },
             SgHttpPathMatch::Prefix(value) => HttpPathMatch::PathPrefix { value },
             SgHttpPathMatch::Regular(value) => HttpPathMatch::RegularExpression { value },
+            // gateway api has no template match, the equivalent regex captures parameters by named groups
+            SgHttpPathMatch::Template(template) => HttpPathMatch::RegularExpression {
+                value: Self::template_to_regex(&template),
+            },
         }
     }
 
+    /// Convert a path template like `/users/{id}/orders/{order_id:[0-9]+}` or `/static/{*rest}` to a regex.
+    pub(crate) fn template_to_regex(template: &str) -> String {
+        let mut regex = String::from("^");
+        for segment in template.split('/').filter(|segment| !segment.is_empty()) {
+            match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(param) => match param.split_once(':') {
+                    Some((name, pattern)) => regex.push_str(&format!("/(?P<{name}>{pattern})")),
+                    None => match param.strip_prefix('*') {
+                        Some(name) => regex.push_str(&format!("(?:/(?P<{name}>.*))?")),
+                        None => regex.push_str(&format!("/(?P<{param}>[^/]+)")),
+                    },
+                },
+                None => {
+                    regex.push('/');
+                    regex.push_str(&regex::escape(segment));
+                }
+            }
+        }
+        regex.push_str("/?$");
+        regex
+    }
+
     pub(crate) fn from_kube_httproute(path_match: HttpPathMatch) -> SgHttpPathMatch {
         match path_match {
             HttpPathMatch::Exact { value } => SgHttpPathMatch::Exact(value),
@@ -191,10 +285,12 @@ impl SgHttpPathMatch {
 }
 
 impl SgHttpHeaderMatch {
-    pub(crate) fn into_kube_httproute(self) -> HttpHeaderMatch {
+    /// The match itself is returned if the gateway api can't express it.
+    pub(crate) fn into_kube_httproute(self) -> Result<HttpHeaderMatch, Self> {
         match self {
-            SgHttpHeaderMatch::Exact { name, value } => HttpHeaderMatch::Exact { name, value },
-            SgHttpHeaderMatch::Regular { name, re: value } => HttpHeaderMatch::RegularExpression { name, value },
+            SgHttpHeaderMatch::Exact { name, value } => Ok(HttpHeaderMatch::Exact { name, value }),
+            SgHttpHeaderMatch::Regular { name, re: value } => Ok(HttpHeaderMatch::RegularExpression { name, value }),
+            predicate => Err(predicate),
         }
     }
 
@@ -207,10 +303,12 @@ impl SgHttpHeaderMatch {
 }
 
 impl SgHttpQueryMatch {
-    pub(crate) fn into_kube_httproute(self) -> HttpQueryParamMatch {
+    /// The match itself is returned if the gateway api can't express it.
+    pub(crate) fn into_kube_httproute(self) -> Result<HttpQueryParamMatch, Self> {
         match self {
-            SgHttpQueryMatch::Exact { key: name, value } => HttpQueryParamMatch::Exact { name, value },
-            SgHttpQueryMatch::Regular { key: name, re: value } => HttpQueryParamMatch::RegularExpression { name, value },
+            SgHttpQueryMatch::Exact { key: name, value } => Ok(HttpQueryParamMatch::Exact { name, value }),
+            SgHttpQueryMatch::Regular { key: name, re: value } => Ok(HttpQueryParamMatch::RegularExpression { name, value }),
+            predicate => Err(predicate),
         }
     }
 
@@ -250,6 +348,11 @@ impl SgBackendRef {
             backend_ref: Some(BackendRef {
                 weight: Some(self.weight),
                 timeout_ms: self.timeout_ms,
+                connect_timeout_ms: self.connect_timeout_ms,
+                response_header_timeout_ms: self.response_header_timeout_ms,
+                idle_stream_timeout_ms: self.idle_stream_timeout_ms,
+                deadline_ms: self.deadline_ms,
+                version: self.version,
                 inner: backend_inner_ref,
             }),
             filters: Some(self.filters.into_iter().filter_map(|f| f.to_http_route_filter()).collect()),
@@ -286,8 +389,13 @@ impl SgBackendRef {
                     host: backend_host,
                     port: backend.inner.port.unwrap_or(80),
                     timeout_ms: backend.timeout_ms,
+                    connect_timeout_ms: backend.connect_timeout_ms,
+                    response_header_timeout_ms: backend.response_header_timeout_ms,
+                    idle_stream_timeout_ms: backend.idle_stream_timeout_ms,
+                    deadline_ms: backend.deadline_ms,
                     protocol,
                     weight: backend.weight.unwrap_or(1),
+                    version: backend.version,
                     filters: http_backend
                         .filters
                         .map(|f_vec| f_vec.into_iter().map(SgRouteFilter::from_http_route_filter).collect::<BoxResult<Vec<SgRouteFilter>>>())
@@ -298,3 +406,20 @@ impl SgBackendRef {
             .transpose()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::model::SgHttpPathMatch;
+
+    #[test]
+    fn test_template_to_regex() {
+        let regex = regex::Regex::new(&SgHttpPathMatch::template_to_regex("/users/{id}/orders/{order_id:[0-9]+}")).expect("invalid regex");
+        let captures = regex.captures("/users/alice/orders/42").expect("not matched");
+        assert_eq!((&captures["id"], &captures["order_id"]), ("alice", "42"));
+        assert!(!regex.is_match("/users/alice/orders/x"));
+        let regex = regex::Regex::new(&SgHttpPathMatch::template_to_regex("/static.v1/{*rest}")).expect("invalid regex");
+        assert_eq!(&regex.captures("/static.v1/js/app.js").expect("not matched")["rest"], "js/app.js");
+        assert!(regex.is_match("/static.v1"));
+        assert!(!regex.is_match("/staticxv1/app.js"));
+    }
+}
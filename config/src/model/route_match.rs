@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 /// PathMatchType specifies the semantics of how HTTP paths should be compared.
+///
+/// Paths are matched with case sensitivity, unless `case_sensitive` of the route match is `false`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "kind", content = "value", rename_all = "PascalCase")]
 #[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
@@ -10,27 +12,68 @@ pub enum SgHttpPathMatch {
     /// A path element refers to the list of labels in the path split by the / separator. When specified, a trailing / is ignored.
     Prefix(String),
     /// Matches if the URL path matches the given regular expression with case sensitivity.
+    /// Named groups are captured as path parameters.
     Regular(String),
+    /// Matches if the URL path matches the given template, e.g. `/users/{id}/orders/{order_id:[0-9]+}` or `/static/{*rest}`.
+    /// Parameters are captured, and can be referred by `${path.<name>}` in plugins like `rewrite`, `redirect` and `header_modifier`.
+    Template(String),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 #[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
 pub enum SgHttpHeaderMatch {
     /// Matches the HTTP header exactly and with case sensitivity.
     Exact { name: String, value: String },
     /// Matches if the Http header matches the given regular expression with case sensitivity.
     Regular { name: String, re: String },
+    /// Matches if the HTTP header is present, whatever its value is.
+    Present { name: String },
+    /// Matches if the HTTP header is absent.
+    Absent { name: String },
+    /// Matches if the HTTP header is absent or not equal to the value.
+    NotExact { name: String, value: String },
+    /// Matches if the HTTP header is absent or doesn't match the given regular expression.
+    NotRegular { name: String, re: String },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(tag = "kind", content = "value", rename_all = "snake_case")]
 #[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
 pub enum SgHttpQueryMatch {
     /// Matches the HTTP query parameter exactly and with case sensitivity.
     Exact { key: String, value: String },
     /// Matches if the Http query parameter matches the given regular expression with case sensitivity.
     Regular { key: String, re: String },
+    /// Matches if the HTTP query parameter is present, even without a value.
+    Present { key: String },
+    /// Matches if the HTTP query parameter is absent.
+    Absent { key: String },
+    /// Matches if the HTTP query parameter is absent or not equal to the value.
+    NotExact { key: String, value: String },
+    /// Matches if the HTTP query parameter is absent or doesn't match the given regular expression.
+    NotRegular { key: String, re: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[cfg_attr(feature = "typegen", derive(ts_rs::TS), ts(export, export_to = "../admin-client/src/model/"))]
+#[cfg_attr(feature = "k8s", derive(schemars::JsonSchema))]
+pub enum SgHttpCookieMatch {
+    /// Matches the cookie exactly and with case sensitivity.
+    Exact { name: String, value: String },
+    /// Matches if the cookie matches the given regular expression with case sensitivity.
+    Regular { name: String, re: String },
+    /// Matches if the cookie is present, whatever its value is.
+    Present { name: String },
+    /// Matches if the cookie is absent.
+    Absent { name: String },
+    /// Matches if the cookie is absent or not equal to the value.
+    NotExact { name: String, value: String },
+    /// Matches if the cookie is absent or doesn't match the given regular expression.
+    NotRegular { name: String, re: String },
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -56,4 +99,17 @@ pub struct SgHttpRouteMatch {
     /// Method specifies HTTP method matcher.
     /// When specified, this route will be matched only if the request has the specified method.
     pub method: Option<Vec<SgHttpMethodMatch>>,
+    /// Cookie specifies HTTP cookie matchers, cookie names are case sensitive.
+    pub cookie: Option<Vec<SgHttpCookieMatch>>,
+    /// ClientIp specifies networks of the client like `10.0.0.0/8` or plain addresses.
+    /// The route is matched if the client is in any of them.
+    pub client_ip: Option<Vec<String>>,
+    /// Sni specifies TLS server names like `api.example.com` or `*.example.com`.
+    /// The route is matched if the client indicated any of them, so plain connections never match.
+    pub sni: Option<Vec<String>>,
+    /// Expr specifies a compound condition over request attributes in a subset of CEL, e.g.
+    /// `method == "GET" && (headers["x-canary"] == "on" || !client_ip.inCidr("10.0.0.0/8"))`.
+    pub expr: Option<String>,
+    /// Whether the path is matched with case sensitivity, `true` by default as the Gateway API specifies.
+    pub case_sensitive: Option<bool>,
 }
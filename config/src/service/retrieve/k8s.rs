@@ -1,5 +1,5 @@
 use futures_util::future::join_all;
-use gateway::{SgListener, SgParameters, SgProtocolConfig, SgTlsConfig};
+use gateway::{SgErrorPage, SgGatewayFallback, SgListener, SgParameters, SgProtocolConfig, SgTlsConfig};
 use http_route::SgHttpRouteRule;
 use k8s_gateway_api::{Gateway, HttpRoute, Listener};
 use k8s_openapi::api::core::v1::Secret;
@@ -141,6 +141,8 @@ impl K8s {
             parameters: SgParameters::from_kube_gateway(&gateway_obj),
             listeners: self.retrieve_config_item_listeners(&gateway_obj.spec.listeners).await?,
             filters,
+            fallback: SgGatewayFallback::from_kube_gateway(&gateway_obj),
+            error_pages: SgErrorPage::from_kube_gateway(&gateway_obj),
         };
         Ok(result)
     }
@@ -160,9 +162,13 @@ impl K8s {
                 namespace: httpspace_route.namespace(),
             })
             .await?;
+        let gateway_name = gateway_refs.first().map(|x| x.name.clone()).unwrap_or_default();
+        // a parent ref without section name attaches the route to all listeners
+        let section_names = gateway_refs.iter().filter(|x| x.name == gateway_name).map(|x| x.section_name.clone()).collect::<Option<Vec<_>>>();
         Ok(SgHttpRoute {
-            gateway_name: gateway_refs.first().map(|x| x.name.clone()).unwrap_or_default(),
+            gateway_name,
             hostnames: httpspace_route.spec.hostnames.clone(),
+            section_names,
             filters,
             rules: httpspace_route
                 .spec
@@ -275,6 +281,7 @@ impl K8s {
                             _ => return Err("Unsupported protocol".into()),
                         },
                         hostname: listener.hostname.clone(),
+                        limits: Default::default(),
                     };
                     Ok(sg_listener)
                 })
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use serde_json::json;
+use spacegate_config::{
+    explain::explain,
+    model::{
+        BackendHost, SgBackendRef, SgExplainRequest, SgGateway, SgHttpHeaderMatch, SgHttpPathMatch, SgHttpRoute, SgHttpRouteMatch, SgHttpRouteRule, SgListener, SgRouteFilter,
+    },
+    Config, ConfigItem,
+};
+
+/// Plugin codes known to the gateway under test.
+fn registered(code: &str) -> bool {
+    code == "header-modifier"
+}
+
+fn backend(host: &str, weight: u16) -> SgBackendRef {
+    SgBackendRef {
+        host: BackendHost::Host { host: host.to_string() },
+        weight,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_explain() {
+    let api = SgHttpRoute {
+        gateway_name: "gateway".to_string(),
+        priority: 10,
+        filters: vec![SgRouteFilter {
+            code: "unknown-plugin".to_string(),
+            name: None,
+            spec: json!({}),
+        }],
+        rules: vec![
+            SgHttpRouteRule {
+                matches: Some(vec![SgHttpRouteMatch {
+                    path: Some(SgHttpPathMatch::Prefix("/api".to_string())),
+                    header: Some(vec![SgHttpHeaderMatch::Exact {
+                        name: "x-canary".to_string(),
+                        value: "on".to_string(),
+                    }]),
+                    ..Default::default()
+                }]),
+                backends: vec![backend("canary", 1)],
+                ..Default::default()
+            },
+            SgHttpRouteRule {
+                matches: Some(vec![SgHttpRouteMatch {
+                    path: Some(SgHttpPathMatch::Prefix("/other".to_string())),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            },
+            SgHttpRouteRule {
+                matches: Some(vec![
+                    SgHttpRouteMatch {
+                        path: Some(SgHttpPathMatch::Exact("/api/none".to_string())),
+                        ..Default::default()
+                    },
+                    SgHttpRouteMatch {
+                        path: Some(SgHttpPathMatch::Prefix("/api".to_string())),
+                        ..Default::default()
+                    },
+                ]),
+                backends: vec![backend("stable-a", 3), backend("stable-b", 1)],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let fallback = SgHttpRoute {
+        gateway_name: "gateway".to_string(),
+        rules: vec![SgHttpRouteRule::default()],
+        ..Default::default()
+    };
+    let item = ConfigItem {
+        gateway: SgGateway {
+            name: "gateway".to_string(),
+            ..Default::default()
+        },
+        routes: BTreeMap::from([("api".to_string(), api), ("fallback".to_string(), fallback)]),
+    };
+    let config = Config {
+        gateways: BTreeMap::from([("gateway".to_string(), item)]),
+    };
+    let request = SgExplainRequest {
+        gateway: "gateway".to_string(),
+        host: "example.com".to_string(),
+        path: "/api/users".to_string(),
+        ..Default::default()
+    };
+    let explained = explain(&config, &request, registered).expect("fail to explain");
+    let routes = explained.routes.iter().map(|route| (route.name.as_str(), route.rules.len())).collect::<Vec<_>>();
+    assert_eq!(routes, [("api", 3), ("fallback", 1)]);
+    let canary = &explained.routes[0].rules[0];
+    assert!(!canary.matched);
+    let predicates = canary.matches[0].predicates.iter().map(|p| (p.kind.as_str(), p.passed)).collect::<Vec<_>>();
+    assert_eq!(predicates, [("path", true), ("header", false)]);
+
+    let selected = explained.selected.expect("no rule selected");
+    assert_eq!((selected.route.as_str(), selected.rule, selected.r#match), ("api", 2, Some(1)));
+    assert_eq!(selected.plugins.len(), 1);
+    assert!(!selected.plugins[0].registered);
+    let backends = selected.backends.iter().map(|b| (b.host.as_str(), b.weight)).collect::<Vec<_>>();
+    assert_eq!(backends, [("stable-a", 3), ("stable-b", 1)]);
+
+    assert!(explain(&config, &SgExplainRequest::default(), registered).is_err());
+}
+
+#[test]
+fn test_explain_listener() {
+    let internal = SgHttpRoute {
+        gateway_name: "gateway".to_string(),
+        section_names: Some(vec!["internal".to_string()]),
+        priority: 10,
+        rules: vec![SgHttpRouteRule::default()],
+        ..Default::default()
+    };
+    let public = SgHttpRoute {
+        gateway_name: "gateway".to_string(),
+        rules: vec![SgHttpRouteRule::default()],
+        ..Default::default()
+    };
+    let listener = |name: &str, hostname: Option<&str>| SgListener {
+        name: name.to_string(),
+        hostname: hostname.map(str::to_string),
+        ..Default::default()
+    };
+    let item = ConfigItem {
+        gateway: SgGateway {
+            name: "gateway".to_string(),
+            listeners: vec![listener("public", Some("*.example.com")), listener("internal", None)],
+            ..Default::default()
+        },
+        routes: BTreeMap::from([("internal".to_string(), internal), ("public".to_string(), public)]),
+    };
+    let config = Config {
+        gateways: BTreeMap::from([("gateway".to_string(), item)]),
+    };
+    let selected = |listener: &str, host: &str| {
+        let request = SgExplainRequest {
+            gateway: "gateway".to_string(),
+            host: host.to_string(),
+            listener: Some(listener.to_string()),
+            ..Default::default()
+        };
+        explain(&config, &request, registered).expect("fail to explain").selected.map(|selected| selected.route)
+    };
+    assert_eq!(selected("internal", "admin.local").as_deref(), Some("internal"));
+    assert_eq!(selected("public", "api.example.com").as_deref(), Some("public"));
+    // hosts out of the listener hostname match no route
+    assert_eq!(selected("public", "admin.local"), None);
+}
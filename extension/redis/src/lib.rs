@@ -105,3 +105,9 @@ pub fn global_repo() -> &'static RedisClientRepo {
 pub trait AsRedisKey {
     fn as_redis_key(&self, prefix: impl AsRef<str>) -> String;
 }
+
+impl AsRedisKey for str {
+    fn as_redis_key(&self, prefix: impl AsRef<str>) -> String {
+        format!("{}:{}", prefix.as_ref(), self)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    convert::Infallible,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hyper::{header::HeaderValue, service::Service, Request, Response};
+use spacegate_kernel::{
+    extension::GatewayName,
+    helper_layers::{
+        function::FnLayer,
+        map_request::{add_extension::add_extension, MapRequestLayer},
+    },
+    layers::{
+        gateway::SgGatewayLayer,
+        http_route::{
+            match_request::{SgHttpPathMatch, SgHttpRouteMatch},
+            SgHttpBackendLayer, SgHttpRoute, SgHttpRouteRuleLayer,
+        },
+    },
+    BoxHyperService, Layer, SgBody, SgBoxLayer,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Counts allocations, so the cost per request can be reported along with the time.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Stands for the http client, so only the gateway itself is measured.
+async fn backend(_req: Request<SgBody>) -> Result<Response<SgBody>, Infallible> {
+    Ok(Response::new(SgBody::empty()))
+}
+
+/// A plugin awaiting the inner service, as most plugins do.
+fn fn_plugin() -> SgBoxLayer {
+    SgBoxLayer::new(FnLayer::new_closure(|req, inner| async move {
+        let mut resp = inner.call(req).await;
+        resp.headers_mut().insert("x-bench", HeaderValue::from_static("1"));
+        resp
+    }))
+}
+
+/// A plugin mapping the request only.
+fn map_plugin() -> SgBoxLayer {
+    SgBoxLayer::new(MapRequestLayer::new(add_extension(GatewayName::new("bench"), false)))
+}
+
+/// `size` routes with a rule and a backend each, with plugins on the gateway, the routes, the rules and the backends.
+fn gateway(size: usize) -> BoxHyperService {
+    let routes = (0..size).map(|i| {
+        let backend = SgHttpBackendLayer::builder().host(format!("service{i}")).port(80).plugin(map_plugin()).build().expect("invalid backend");
+        let rule = SgHttpRouteRuleLayer::builder()
+            .matches([SgHttpRouteMatch {
+                path: Some(SgHttpPathMatch::Prefix(format!("/service{i}/api"))),
+                ..Default::default()
+            }])
+            .plugin(map_plugin())
+            .backend(backend)
+            .build()
+            .expect("invalid rule");
+        SgHttpRoute::builder().rule(rule).plugin(fn_plugin()).build().expect("invalid route")
+    });
+    let layer = SgGatewayLayer::builder("bench", CancellationToken::new()).http_routers(routes).http_plugin(fn_plugin()).http_plugin(map_plugin()).build();
+    BoxHyperService::new(layer.layer(hyper::service::service_fn(backend)))
+}
+
+fn request(size: usize) -> Request<SgBody> {
+    Request::builder().uri(format!("/service{}/api/users/1", size - 1)).header("host", "example.com").body(SgBody::empty()).expect("invalid request")
+}
+
+fn bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("fail to build runtime");
+    let mut group = c.benchmark_group("gateway");
+    group.throughput(Throughput::Elements(1));
+    for size in [1, 100] {
+        let service = gateway(size);
+        let resp = runtime.block_on(service.call(request(size))).expect("infallible");
+        assert_eq!(resp.headers().get("x-bench"), Some(&HeaderValue::from_static("1")));
+
+        const ROUNDS: usize = 1000;
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..ROUNDS {
+            black_box(runtime.block_on(service.call(request(size))).expect("infallible"));
+        }
+        let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / ROUNDS as f64;
+        println!("gateway/{size}: {allocations:.1} allocations per request");
+
+        group.bench_with_input(BenchmarkId::new("request", size), &size, |b, &size| {
+            b.iter(|| black_box(runtime.block_on(service.call(request(size))).expect("infallible")))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
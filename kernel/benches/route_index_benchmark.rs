@@ -0,0 +1,65 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use hyper::Request;
+use regex::Regex;
+use spacegate_kernel::{
+    helper_layers::route::Router,
+    layers::{
+        gateway::SgGatewayRouter,
+        http_route::{
+            match_request::{MatchRequest, SgHttpPathMatch, SgHttpRouteMatch},
+            SgHttpRoute, SgHttpRouteRuleLayer,
+        },
+    },
+    SgBody,
+};
+
+fn path_match(path: SgHttpPathMatch) -> SgHttpRouteMatch {
+    SgHttpRouteMatch {
+        path: Some(path),
+        ..Default::default()
+    }
+}
+
+/// `size` routes with a prefix and an exact rule each, and a few regex routes.
+fn routes(size: usize) -> Vec<SgHttpRoute> {
+    (0..size)
+        .map(|i| {
+            let mut rules = vec![
+                SgHttpRouteRuleLayer::builder().matches([path_match(SgHttpPathMatch::Exact(format!("/service{i}/health")))]).build().expect("invalid rule"),
+                SgHttpRouteRuleLayer::builder().matches([path_match(SgHttpPathMatch::Prefix(format!("/service{i}/api")))]).build().expect("invalid rule"),
+            ];
+            if i % (size / 10).max(1) == 0 {
+                let regex = Regex::new(&format!("^/regex{i}/[0-9]+$")).expect("invalid regex");
+                rules.push(SgHttpRouteRuleLayer::builder().matches([path_match(SgHttpPathMatch::Regular(regex))]).build().expect("invalid rule"));
+            }
+            SgHttpRoute::builder().rules(rules).build().expect("invalid route")
+        })
+        .collect()
+}
+
+/// Try every match in order, as routing did before the index.
+fn linear_route(router: &SgGatewayRouter, req: &Request<SgBody>) -> Option<(usize, usize)> {
+    router.routers.iter().enumerate().find_map(|(route, router)| {
+        let rule = router.rules.iter().position(|matches| match matches {
+            Some(matches) => matches.iter().any(|m| m.match_request(req)),
+            None => true,
+        })?;
+        Some((route, rule))
+    })
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("route");
+    for size in [10, 100, 1000, 10000] {
+        let router = SgGatewayRouter::new(&routes(size));
+        let uri = format!("/service{}/api/users/1", size - 1);
+        let mut req = Request::builder().uri(&uri).header("host", "example.com").body(SgBody::empty()).expect("invalid request");
+        assert_eq!(router.route(&mut req), Some((size - 1, 1)));
+        group.bench_with_input(BenchmarkId::new("index", size), &size, |b, _| b.iter(|| black_box(router.route(&mut req))));
+        group.bench_with_input(BenchmarkId::new("linear", size), &size, |b, _| b.iter(|| black_box(linear_route(&router, &req))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
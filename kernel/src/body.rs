@@ -5,6 +5,10 @@ use hyper::body::{Body, Bytes};
 use crate::utils::never;
 
 // pub mod dump;
+pub mod limit;
+pub mod timeout;
+use limit::{BodyLimitExceeded, LimitedBody};
+use timeout::TimeoutBody;
 
 /// # Clone
 /// Always clone after ensure the body is dumped
@@ -69,6 +73,41 @@ impl SgBody {
             dump: Some(bytes),
         })
     }
+    /// Dump the body, but fail with [`BodyLimitExceeded`] if it's larger than `limit` bytes.
+    ///
+    /// Prefer this to [`SgBody::dump`] when the body comes from a client or an upstream.
+    pub async fn dump_with_limit(self, limit: usize) -> Result<Self, BoxError> {
+        if let Some(dump) = &self.dump {
+            if dump.len() > limit {
+                return Err(BodyLimitExceeded { limit }.into());
+            }
+            return Ok(self);
+        }
+        if self.body.size_hint().lower() > limit as u64 {
+            return Err(BodyLimitExceeded { limit }.into());
+        }
+        self.limited(limit).dump().await
+    }
+    /// Limit the body size, polling the body will fail with [`BodyLimitExceeded`] once `limit` bytes is exceeded.
+    pub fn limited(self, limit: usize) -> Self {
+        let dump = self.dump.filter(|dump| dump.len() <= limit);
+        Self {
+            body: BoxBody::new(LimitedBody::new(self.body, limit)),
+            dump,
+        }
+    }
+    /// Fail the body if there is no data for `idle`, or the `deadline` is reached, see [`TimeoutBody`].
+    ///
+    /// A dumped body is returned as is.
+    pub fn with_timeout(self, idle: Option<std::time::Duration>, deadline: Option<tokio::time::Instant>) -> Self {
+        if self.is_dumped() || (idle.is_none() && deadline.is_none()) {
+            return self;
+        }
+        Self {
+            body: BoxBody::new(TimeoutBody::new(self.body, idle, deadline)),
+            dump: None,
+        }
+    }
     pub fn dump_clone(&self) -> Option<Self> {
         self.dump.as_ref().map(|bytes| Self {
             body: BoxBody::new(Full::new(bytes.clone()).map_err(never)),
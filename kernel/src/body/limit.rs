@@ -0,0 +1,96 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use hyper::body::{Body, Bytes, Frame, SizeHint};
+
+use crate::BoxError;
+
+/// Error returned when a body is larger than the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimitExceeded {
+    pub limit: usize,
+}
+
+impl std::fmt::Display for BodyLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "body size exceeds the limit of {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for BodyLimitExceeded {}
+
+pin_project_lite::pin_project! {
+    /// A body which counts bytes while streaming, and fails with [`BodyLimitExceeded`] once the limit is exceeded.
+    pub struct LimitedBody<B> {
+        #[pin]
+        inner: B,
+        limit: usize,
+        remaining: usize,
+        exceeded: Option<Arc<AtomicBool>>,
+    }
+}
+
+impl<B> LimitedBody<B> {
+    pub fn new(inner: B, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            remaining: limit,
+            exceeded: None,
+        }
+    }
+    /// The flag will be set once the limit is exceeded.
+    pub fn with_exceeded_flag(mut self, exceeded: Arc<AtomicBool>) -> Self {
+        self.exceeded = Some(exceeded);
+        self
+    }
+}
+
+impl<B> Body for LimitedBody<B>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        match std::task::ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    if data.len() > *this.remaining {
+                        *this.remaining = 0;
+                        if let Some(exceeded) = this.exceeded {
+                            exceeded.store(true, Ordering::Release);
+                        }
+                        return Poll::Ready(Some(Err(BodyLimitExceeded { limit: *this.limit }.into())));
+                    }
+                    *this.remaining -= data.len();
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Some(Err(e)) => Poll::Ready(Some(Err(e.into()))),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let mut hint = self.inner.size_hint();
+        let remaining = self.remaining as u64;
+        if hint.lower() <= remaining && !matches!(hint.upper(), Some(upper) if upper <= remaining) {
+            hint.set_upper(remaining);
+        }
+        hint
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::body::{Body, Bytes, Frame, SizeHint};
+use tokio::time::{Instant, Sleep};
+
+use crate::BoxError;
+
+/// Error returned when a body is not finished in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyTimeout {
+    /// No data received within the idle timeout.
+    Idle(Duration),
+    /// The overall deadline is reached.
+    Deadline,
+}
+
+impl std::fmt::Display for BodyTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyTimeout::Idle(idle) => write!(f, "no body data received in {idle:?}"),
+            BodyTimeout::Deadline => write!(f, "body is not finished before deadline"),
+        }
+    }
+}
+
+impl std::error::Error for BodyTimeout {}
+
+pin_project_lite::pin_project! {
+    /// A body which fails with [`BodyTimeout`] if there is no data for a while, or the deadline is reached.
+    ///
+    /// The idle timer only runs while the inner body is pending, time the body is not polled by the consumer doesn't count.
+    pub struct TimeoutBody<B> {
+        #[pin]
+        inner: B,
+        idle: Option<Duration>,
+        #[pin]
+        idle_sleep: Option<Sleep>,
+        idle_armed: bool,
+        #[pin]
+        deadline: Option<Sleep>,
+    }
+}
+
+impl<B> TimeoutBody<B> {
+    pub fn new(inner: B, idle: Option<Duration>, deadline: Option<Instant>) -> Self {
+        Self {
+            inner,
+            idle,
+            idle_sleep: idle.map(tokio::time::sleep),
+            idle_armed: false,
+            deadline: deadline.map(tokio::time::sleep_until),
+        }
+    }
+}
+
+impl<B> Body for TimeoutBody<B>
+where
+    B: Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        if let Some(deadline) = this.deadline.as_mut().as_pin_mut() {
+            if deadline.poll(cx).is_ready() {
+                return Poll::Ready(Some(Err(BodyTimeout::Deadline.into())));
+            }
+        }
+        match this.inner.poll_frame(cx) {
+            Poll::Ready(frame) => {
+                *this.idle_armed = false;
+                Poll::Ready(frame.map(|frame| frame.map_err(Into::into)))
+            }
+            Poll::Pending => {
+                if let (Some(idle), Some(mut idle_sleep)) = (this.idle, this.idle_sleep.as_mut().as_pin_mut()) {
+                    if !*this.idle_armed {
+                        idle_sleep.as_mut().reset(Instant::now() + *idle);
+                        *this.idle_armed = true;
+                    }
+                    if idle_sleep.poll(cx).is_ready() {
+                        return Poll::Ready(Some(Err(BodyTimeout::Idle(*idle).into())));
+                    }
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper::body::{Bytes, Frame};
+
+    use super::BodyTimeout;
+    use crate::{BoxError, SgBody};
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let chunks = futures_util::stream::iter([Ok::<_, BoxError>(Frame::data(Bytes::from("hello")))]).chain(futures_util::stream::pending());
+        let mut body = SgBody::new_boxed_error(StreamBody::new(chunks)).with_timeout(Some(Duration::from_millis(50)), None);
+        let frame = body.frame().await.expect("no frame").expect("first chunk should not time out");
+        assert_eq!(frame.into_data().expect("not data"), "hello");
+        let err = body.frame().await.expect("no frame").expect_err("should time out");
+        assert_eq!(err.downcast_ref::<BodyTimeout>(), Some(&BodyTimeout::Idle(Duration::from_millis(50))));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_not_polled() {
+        // the second chunk comes soon after it's polled, the time the consumer takes doesn't count
+        let late = futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, BoxError>(Frame::data(Bytes::from("world")))
+        });
+        let chunks = futures_util::stream::iter([Ok::<_, BoxError>(Frame::data(Bytes::from("hello")))]).chain(late);
+        let mut body = SgBody::new_boxed_error(StreamBody::new(chunks)).with_timeout(Some(Duration::from_millis(50)), None);
+        body.frame().await.expect("no frame").expect("first chunk should not time out");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let frame = body.frame().await.expect("no frame").expect("second chunk should not time out");
+        assert_eq!(frame.into_data().expect("not data"), "world");
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(50);
+        let body = SgBody::new_boxed_error(StreamBody::new(futures_util::stream::pending::<Result<Frame<Bytes>, BoxError>>())).with_timeout(None, Some(deadline));
+        let err = body.collect().await.expect_err("should time out");
+        assert_eq!(err.downcast_ref::<BodyTimeout>(), Some(&BodyTimeout::Deadline));
+    }
+}
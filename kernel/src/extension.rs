@@ -8,8 +8,18 @@ mod peer_addr;
 pub use peer_addr::*;
 mod backend_host;
 pub use backend_host::*;
+mod backend_timeouts;
+pub use backend_timeouts::*;
 mod enter_time;
 pub use enter_time::*;
+mod consumer;
+pub use consumer::*;
+mod tls_server_name;
+pub use tls_server_name::*;
+mod gateway_error;
+pub use gateway_error::*;
+mod listener;
+pub use listener::*;
 
 /// FromBackend is a marker type to indicate that the response is from backend.
 #[derive(Debug, Clone, Copy)]
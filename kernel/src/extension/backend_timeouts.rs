@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+/// Timeouts of forwarding a request to backend, they are read by the backend service.
+///
+/// Unlike [`TimeoutLayer`](crate::helper_layers::timeout::TimeoutLayer), which limits the time to response only,
+/// the `deadline` here also applies to streaming the response body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackendTimeouts {
+    /// Timeout of establishing a connection to backend.
+    pub connect: Option<Duration>,
+    /// Timeout of waiting for response header after the request is sent.
+    pub response_header: Option<Duration>,
+    /// Max interval between two chunks of response body.
+    pub idle_stream: Option<Duration>,
+    /// Overall deadline of the exchange with backend, from connecting to the end of response body.
+    pub deadline: Option<Duration>,
+}
+
+impl BackendTimeouts {
+    pub fn is_empty(&self) -> bool {
+        self.connect.is_none() && self.response_header.is_none() && self.idle_stream.is_none() && self.deadline.is_none()
+    }
+    /// Merge two timeouts, values set in `other` take precedence.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            connect: other.connect.or(self.connect),
+            response_header: other.response_header.or(self.response_header),
+            idle_stream: other.idle_stream.or(self.idle_stream),
+            deadline: other.deadline.or(self.deadline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::BackendTimeouts;
+
+    #[test]
+    fn test_merge() {
+        let rule = BackendTimeouts {
+            connect: Some(Duration::from_secs(1)),
+            response_header: Some(Duration::from_secs(2)),
+            idle_stream: None,
+            deadline: Some(Duration::from_secs(5)),
+        };
+        let backend = BackendTimeouts {
+            connect: Some(Duration::from_secs(3)),
+            response_header: None,
+            idle_stream: Some(Duration::from_secs(4)),
+            deadline: None,
+        };
+        let merged = rule.merge(backend);
+        assert_eq!(merged.connect, Some(Duration::from_secs(3)));
+        assert_eq!(merged.response_header, Some(Duration::from_secs(2)));
+        assert_eq!(merged.idle_stream, Some(Duration::from_secs(4)));
+        assert_eq!(merged.deadline, Some(Duration::from_secs(5)));
+        assert_eq!(rule.merge(BackendTimeouts::default()), rule);
+        assert!(BackendTimeouts::default().is_empty());
+    }
+}
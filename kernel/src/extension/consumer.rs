@@ -0,0 +1,28 @@
+use std::{ops::Deref, sync::Arc};
+
+/// The consumer identified by an authentication plugin.
+///
+/// Downstream layers, e.g. rate limiting and logging, can use it to tell consumers apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Consumer(pub Arc<str>);
+
+impl Consumer {
+    pub fn new(name: impl Into<Arc<str>>) -> Self {
+        Self(name.into())
+    }
+}
+
+impl Deref for Consumer {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "ext-redis")]
+impl spacegate_ext_redis::AsRedisKey for Consumer {
+    fn as_redis_key(&self, prefix: impl AsRef<str>) -> String {
+        format!("{}:{}", prefix.as_ref(), self.0)
+    }
+}
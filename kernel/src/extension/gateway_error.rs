@@ -0,0 +1,9 @@
+use crate::helper_layers::response_error::SgProblem;
+
+/// Marks a response generated by the gateway for an error, e.g. no rule matched, timeouts, bad gateway or plugin errors.
+///
+/// Responses of backends, and pages made by plugins on purpose, don't carry it, so error pages leave them as they are.
+#[derive(Debug, Clone)]
+pub struct GatewayError {
+    pub problem: SgProblem,
+}
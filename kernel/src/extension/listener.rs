@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+/// Name of the listener which accepted the request, routes may attach to some listeners of the gateway only.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerName(pub Arc<str>);
+
+impl ListenerName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Hostname of the listener which accepted the request, requests of other hosts match no route.
+///
+/// It's an exact hostname, or a wildcard like `*.example.com` which matches any subdomain of `example.com`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListenerHostname(pub Arc<str>);
+
+impl ListenerHostname {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// If the host, with or without port, is one of the listener.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = match host.strip_prefix('[') {
+            Some(ipv6) => ipv6.split_once(']').map(|(ipv6, _)| ipv6).unwrap_or(ipv6),
+            None => host.rsplit_once(':').map(|(host, _)| host).unwrap_or(host),
+        };
+        let host = host.trim_end_matches('.');
+        match self.0.strip_prefix("*.") {
+            Some(domain) => {
+                host.len() > domain.len() + 1 && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain) && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+            }
+            None => host.eq_ignore_ascii_case(&self.0),
+        }
+    }
+}
+
+#[test]
+fn test_listener_hostname() {
+    let hostname = ListenerHostname("*.example.com".into());
+    assert!(hostname.matches("api.example.com"));
+    assert!(hostname.matches("a.b.Example.com:8443"));
+    assert!(!hostname.matches("example.com"));
+    assert!(!hostname.matches("badexample.com"));
+    let hostname = ListenerHostname("internal.local".into());
+    assert!(hostname.matches("internal.local:80"));
+    assert!(!hostname.matches("api.internal.local"));
+    assert!(!ListenerHostname("::1".into()).matches("[::2]:80"));
+}
@@ -1,5 +1,5 @@
 use crate::{helper_layers::route::Router, layers::http_route::match_request::SgHttpRouteMatch};
-use std::{ops::Deref, sync::Arc};
+use std::{borrow::Cow, ops::Deref, sync::Arc};
 
 #[derive(Debug, Clone)]
 pub struct Matched<R: Router> {
@@ -7,8 +7,18 @@ pub struct Matched<R: Router> {
     pub index: R::Index,
 }
 
+/// The matched route match, and parameters captured from the request path.
 #[derive(Debug, Clone)]
-pub struct MatchedSgRouter(pub Arc<SgHttpRouteMatch>);
+pub struct MatchedSgRouter(pub Arc<SgHttpRouteMatch>, pub PathCaptures);
+
+impl MatchedSgRouter {
+    pub fn new(r#match: Arc<SgHttpRouteMatch>) -> Self {
+        Self(r#match, PathCaptures::default())
+    }
+    pub fn captures(&self) -> &PathCaptures {
+        &self.1
+    }
+}
 
 impl Deref for MatchedSgRouter {
     type Target = SgHttpRouteMatch;
@@ -17,3 +27,61 @@ impl Deref for MatchedSgRouter {
         self.0.as_ref()
     }
 }
+
+/// Parameters captured by a path template, or named groups of a path regex.
+#[derive(Debug, Clone, Default)]
+pub struct PathCaptures(Vec<(Arc<str>, String)>);
+
+const PLACEHOLDER_START: &str = "${path.";
+
+impl PathCaptures {
+    pub fn push(&mut self, name: Arc<str>, value: impl Into<String>) {
+        self.0.push((name, value.into()))
+    }
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find_map(|(n, v)| (&**n == name).then_some(v.as_str()))
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(n, v)| (&**n, v.as_str()))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Whether the value has `${path.<name>}` placeholders.
+    pub fn has_placeholder(value: &str) -> bool {
+        value.contains(PLACEHOLDER_START)
+    }
+    /// Replace `${path.<name>}` placeholders by captured values, unknown names are replaced by empty strings.
+    pub fn substitute<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if !Self::has_placeholder(value) {
+            return Cow::Borrowed(value);
+        }
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let name = &rest[start + PLACEHOLDER_START.len()..start + len];
+            match self.get(name) {
+                Some(captured) => result.push_str(captured),
+                None => tracing::debug!("[Sg.PathCaptures] no path parameter {name}"),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        result.push_str(rest);
+        Cow::Owned(result)
+    }
+}
+
+#[test]
+fn test_substitute() {
+    let mut captures = PathCaptures::default();
+    captures.push("id".into(), "42");
+    captures.push("rest".into(), "a/b");
+    assert_eq!(captures.substitute("/v2/users/${path.id}/${path.rest}"), "/v2/users/42/a/b");
+    assert_eq!(captures.substitute("${path.unknown}-${path.id}"), "-42");
+    assert_eq!(captures.substitute("/plain"), "/plain");
+    assert_eq!(captures.substitute("/broken/${path.id"), "/broken/${path.id");
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+/// The server name (SNI) sent by the client in the TLS handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TlsServerName(pub Arc<str>);
+
+impl TlsServerName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
@@ -1,5 +1,6 @@
 pub mod async_filter;
 pub mod bidirection_filter;
+pub mod body_limit;
 pub mod check;
 pub mod filter;
 pub mod function;
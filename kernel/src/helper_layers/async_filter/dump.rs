@@ -1,12 +1,15 @@
 use std::pin::Pin;
 
 use futures_util::Future;
-use hyper::{Request, Response};
+use hyper::{Request, Response, StatusCode};
 
-use crate::{SgBody, SgResponseExt};
+use crate::{body::limit::BodyLimitExceeded, SgBody, SgResponseExt};
 
 use super::AsyncFilter;
 
+/// Request bodies larger than this are rejected with `413 Payload Too Large`.
+pub const DUMP_LIMIT: usize = 1 << 20;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Dump;
 
@@ -15,7 +18,10 @@ impl AsyncFilter for Dump {
     fn filter(&self, req: Request<SgBody>) -> Self::Future {
         let (part, body) = req.into_parts();
         Box::pin(async move {
-            let body = body.dump().await.map_err(|e| Response::<SgBody>::bad_gateway(e.as_ref()))?;
+            let body = body.dump_with_limit(DUMP_LIMIT).await.map_err(|e| match e.downcast_ref::<BodyLimitExceeded>() {
+                Some(exceeded) => Response::<SgBody>::with_gateway_error(StatusCode::PAYLOAD_TOO_LARGE, exceeded.to_string()),
+                None => Response::<SgBody>::bad_gateway(e.as_ref()),
+            })?;
             Ok(Request::from_parts(part, body))
         })
     }
@@ -0,0 +1,113 @@
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use futures_util::future::BoxFuture;
+use hyper::{
+    header::{HeaderMap, CONTENT_LENGTH},
+    Request, Response, StatusCode,
+};
+use tower_layer::Layer;
+
+use crate::{
+    body::limit::{BodyLimitExceeded, LimitedBody},
+    SgBody, SgResponseExt,
+};
+
+/// Limit the size of request and response body.
+///
+/// - A request with a larger `Content-Length` will be rejected with `413 Payload Too Large` before calling inner service,
+///   and a streaming request body will be counted while reading, once exceeded, `413` is returned as well.
+/// - A response with a larger `Content-Length` will be replaced with `502 Bad Gateway`, and a streaming response body will be aborted once exceeded.
+#[derive(Debug, Clone, Default)]
+pub struct BodyLimitLayer {
+    pub request: Option<usize>,
+    pub response: Option<usize>,
+}
+
+impl BodyLimitLayer {
+    pub fn new(request: Option<usize>, response: Option<usize>) -> Self {
+        Self { request, response }
+    }
+}
+
+impl<S> Layer<S> for BodyLimitLayer {
+    type Service = BodyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyLimit {
+            inner,
+            request: self.request,
+            response: self.response,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BodyLimit<S> {
+    inner: S,
+    request: Option<usize>,
+    response: Option<usize>,
+}
+
+/// Get content length from headers, none if it's absent or invalid.
+pub fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+impl<S> hyper::service::Service<Request<SgBody>> for BodyLimit<S>
+where
+    S: hyper::service::Service<Request<SgBody>, Response = Response<SgBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<SgBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, req: Request<SgBody>) -> Self::Future {
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let req = match self.request {
+            Some(limit) => {
+                if content_length(req.headers()).is_some_and(|length| length > limit as u64) {
+                    return Box::pin(std::future::ready(Ok(Response::with_gateway_error(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        BodyLimitExceeded { limit }.to_string(),
+                    ))));
+                }
+                req.map(|body| {
+                    let dump = body.dump.clone().filter(|dump| dump.len() <= limit);
+                    SgBody {
+                        body: http_body_util::combinators::BoxBody::new(LimitedBody::new(body, limit).with_exceeded_flag(exceeded.clone())),
+                        dump,
+                    }
+                })
+            }
+            None => req,
+        };
+        let response_limit = self.response;
+        let request_limit = self.request.unwrap_or_default();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let resp = fut.await?;
+            if exceeded.load(Ordering::Acquire) {
+                tracing::debug!("[Sg.BodyLimit] request body exceeds the limit");
+                return Ok(Response::with_gateway_error(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    BodyLimitExceeded { limit: request_limit }.to_string(),
+                ));
+            }
+            let Some(limit) = response_limit else {
+                return Ok(resp);
+            };
+            if content_length(resp.headers()).is_some_and(|length| length > limit as u64) {
+                tracing::debug!("[Sg.BodyLimit] response body exceeds the limit");
+                return Ok(Response::bad_gateway(BodyLimitExceeded { limit }));
+            }
+            Ok(resp.map(|body| body.limited(limit)))
+        })
+    }
+}
@@ -4,7 +4,7 @@ pub mod redis;
 use std::{convert::Infallible, future::ready, marker::PhantomData};
 
 use futures_util::{future::BoxFuture, Future};
-use hyper::{Request, Response, StatusCode};
+use hyper::{http::Extensions, Request, Response, StatusCode};
 use tower_layer::Layer;
 
 use crate::{Marker, SgBody, SgResponseExt};
@@ -16,6 +16,11 @@ where
     fn check(&self, _marker: &M) -> impl Future<Output = bool> + Send {
         ready(true)
     }
+    /// Check the marker, and return the extensions to attach to the request if it passes,
+    /// e.g. the identity of an authenticated consumer.
+    fn check_and_attach(&self, marker: &M) -> impl Future<Output = Option<Extensions>> + Send {
+        async move { self.check(marker).await.then(Extensions::new) }
+    }
     fn on_forbidden(&self, _marker: M) -> Response<SgBody> {
         Response::with_code_message(StatusCode::FORBIDDEN, "forbidden")
     }
@@ -115,7 +120,9 @@ where
         Box::pin(async move {
             let checker = &cloned.check;
             if let Some(marker) = marker {
-                if checker.check(&marker).await {
+                if let Some(extensions) = checker.check_and_attach(&marker).await {
+                    let mut request = request;
+                    request.extensions_mut().extend(extensions);
                     let resp = cloned.service.call(checker.on_pass(request)).await.expect("infallible");
                     Ok(checker.on_response(marker, resp))
                 } else {
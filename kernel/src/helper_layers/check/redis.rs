@@ -3,7 +3,7 @@ use std::sync::Arc;
 use futures_util::future::BoxFuture;
 use hyper::{body::Bytes, Response, StatusCode};
 use spacegate_ext_redis::{
-    redis::{self, Script, ToRedisArgs},
+    redis::{self, AsyncCommands, FromRedisValue, RedisResult, Script, ToRedisArgs},
     AsRedisKey, Connection, RedisClient,
 };
 
@@ -116,6 +116,18 @@ impl RedisResponseScript {
     }
 }
 
+impl RedisCheck {
+    /// Get the value stored for `marker`, under the same key the check scripts use.
+    pub async fn get<M, V>(&self, marker: &M) -> RedisResult<Option<V>>
+    where
+        M: AsRedisKey + ?Sized,
+        V: FromRedisValue,
+    {
+        let key = marker.as_redis_key(&self.key_prefix);
+        self.client.get_conn().await.get(key).await
+    }
+}
+
 impl<M> Check<M> for RedisCheck
 where
     M: AsRedisKey + redis::ToRedisArgs + Marker + Send + Sync + 'static,
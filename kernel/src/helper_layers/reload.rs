@@ -1,7 +1,12 @@
+//! # Reload
+//!
+//! Swap the inner service at runtime, requests in flight keep the service they started with.
+//!
+//! The service is held by an [`ArcSwap`], so calling it doesn't wait for, or block, a reload.
+
 use std::sync::{Arc, OnceLock};
 
-use futures_util::future::BoxFuture;
-use tokio::sync::RwLock;
+use arc_swap::ArcSwap;
 use tower_layer::Layer;
 
 #[derive(Default, Debug, Clone)]
@@ -9,30 +14,24 @@ pub struct ReloadLayer<S> {
     pub reloader: Reloader<S>,
 }
 
-impl<S> Layer<S> for ReloadLayer<S>
-where
-    S: Send,
-{
+impl<S> Layer<S> for ReloadLayer<S> {
     type Service = Reload<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        let inner = Arc::new(RwLock::new(inner));
+        let inner = Arc::new(ArcSwap::from_pointee(inner));
         self.reloader.setup(inner.clone());
         Reload { service: inner }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Reload<S>
-where
-    S: Send,
-{
-    pub(crate) service: Arc<RwLock<S>>,
+pub struct Reload<S> {
+    pub(crate) service: Arc<ArcSwap<S>>,
 }
 
 #[derive(Debug)]
 pub struct Reloader<S> {
-    pub service: Arc<OnceLock<Arc<RwLock<S>>>>,
+    pub service: Arc<OnceLock<Arc<ArcSwap<S>>>>,
 }
 
 impl<S> Default for Reloader<S> {
@@ -48,19 +47,22 @@ impl<S> Clone for Reloader<S> {
 }
 
 impl<S> Reloader<S> {
-    pub fn setup(&self, service: Arc<RwLock<S>>) {
+    pub fn setup(&self, service: Arc<ArcSwap<S>>) {
         if self.service.set(service).is_err() {
             tracing::warn!("reloader already settled");
         }
     }
-    pub async fn reload(&self, service: S) {
-        if let Some(wg) = self.service.get() {
-            let mut wg = wg.write().await;
-            *wg = service;
+    pub fn reload(&self, service: S) {
+        if let Some(current) = self.service.get() {
+            current.store(Arc::new(service));
         } else {
             tracing::warn!("reloader not initialized");
         }
     }
+    /// The service currently in use, `None` if the reloader is not set up yet.
+    pub fn current(&self) -> Option<Arc<S>> {
+        self.service.get().map(|current| current.load_full())
+    }
     pub fn into_layer(self) -> ReloadLayer<S> {
         ReloadLayer { reloader: self }
     }
@@ -68,23 +70,38 @@ impl<S> Reloader<S> {
 
 impl<Request, S> hyper::service::Service<Request> for Reload<S>
 where
-    Request: Send + Sync + 'static,
-    S: hyper::service::Service<Request> + Send + Sync + 'static,
-    <S as hyper::service::Service<Request>>::Future: std::marker::Send,
+    S: hyper::service::Service<Request>,
 {
     type Response = S::Response;
 
     type Error = S::Error;
 
-    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+    type Future = S::Future;
 
     fn call(&self, req: Request) -> Self::Future {
-        let service = self.service.clone();
-        Box::pin(async move {
-            let rg = service.read_owned().await;
-            let fut = rg.call(req);
-            drop(rg);
-            fut.await
-        })
+        self.service.load().call(req)
+    }
+}
+
+#[tokio::test]
+async fn test_reload() {
+    use crate::{service::BoxHyperService, SgBody};
+    use hyper::{service::Service, Request, Response};
+    use std::convert::Infallible;
+
+    fn service(message: &'static str) -> BoxHyperService {
+        BoxHyperService::new(hyper::service::service_fn(move |_: Request<SgBody>| async move {
+            Ok::<_, Infallible>(Response::new(SgBody::full(message)))
+        }))
     }
+    let reloader = Reloader::<BoxHyperService>::default();
+    let reload = reloader.clone().into_layer().layer(service("before"));
+    let body = |resp: Response<SgBody>| async move { resp.into_body().dump().await.expect("fail to dump").get_dumped().expect("not dumped").clone() };
+    assert_eq!(body(reload.call(Request::new(SgBody::empty())).await.expect("infallible")).await, "before");
+    // a request in flight keeps the service it started with
+    let in_flight = reload.call(Request::new(SgBody::empty()));
+    reloader.reload(service("after"));
+    assert_eq!(body(in_flight.await.expect("infallible")).await, "before");
+    assert_eq!(body(reload.call(Request::new(SgBody::empty())).await.expect("infallible")).await, "after");
+    assert!(reloader.current().is_some());
 }
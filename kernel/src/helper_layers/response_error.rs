@@ -1,10 +1,136 @@
-use std::{convert::Infallible, future::Future, marker, sync::Arc, task::ready};
+use std::{borrow::Cow, convert::Infallible, fmt::Write, future::Future, marker, sync::Arc, task::ready};
 
-use hyper::{Request, Response};
+use hyper::{Request, Response, StatusCode};
 use pin_project_lite::pin_project;
 use tower_layer::Layer;
 
 use crate::SgBody;
+
+/// Media type of [`SgProblem`] in json.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A structured error, see [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807).
+///
+/// It's rendered as `application/problem+json`, or as text which is the detail.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SgProblem {
+    /// URI of the problem type, `about:blank` if absent.
+    pub r#type: Option<String>,
+    /// Summary of the problem type, the reason phrase of the status if absent.
+    pub title: Option<String>,
+    pub status: u16,
+    pub detail: Option<String>,
+    /// URI of the occurrence, i.e. the path of the request.
+    pub instance: Option<String>,
+    /// Code of the plugin which raised the error.
+    pub plugin: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl SgProblem {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status: status.as_u16(),
+            ..Default::default()
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn plugin(mut self, plugin: impl Into<String>) -> Self {
+        self.plugin = Some(plugin.into());
+        self
+    }
+
+    /// The title, or the reason phrase of the status localized by `lang`.
+    pub fn title_in(&self, lang: Option<&str>) -> Cow<'_, str> {
+        if let Some(title) = &self.title {
+            return Cow::Borrowed(title);
+        }
+        let status = StatusCode::from_u16(self.status).ok();
+        let reason = status.and_then(|status| lang.and_then(|lang| localized_reason(status, lang)).or(status.canonical_reason()));
+        Cow::Borrowed(reason.unwrap_or_default())
+    }
+
+    pub fn to_text(&self, lang: Option<&str>) -> String {
+        match &self.detail {
+            Some(detail) => detail.clone(),
+            None => self.title_in(lang).into_owned(),
+        }
+    }
+
+    pub fn to_json(&self, lang: Option<&str>) -> String {
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            r#"{{"type":"{}","title":"{}","status":{}"#,
+            escape_json(self.r#type.as_deref().unwrap_or("about:blank")),
+            escape_json(&self.title_in(lang)),
+            self.status
+        );
+        for (name, value) in [
+            ("detail", &self.detail),
+            ("instance", &self.instance),
+            ("plugin", &self.plugin),
+            ("request_id", &self.request_id),
+        ] {
+            if let Some(value) = value {
+                let _ = write!(json, r#","{name}":"{}""#, escape_json(value));
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+pub(crate) fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reason phrase of the status in the language, only the primary subtag of `lang` is considered, e.g. `zh` of `zh-CN`.
+///
+/// `None` if the language or the status is not supported.
+pub fn localized_reason(status: StatusCode, lang: &str) -> Option<&'static str> {
+    let primary = lang.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    match primary.as_str() {
+        "en" => status.canonical_reason(),
+        "zh" => Some(match status.as_u16() {
+            400 => "错误的请求",
+            401 => "未授权",
+            403 => "禁止访问",
+            404 => "未找到",
+            405 => "方法不允许",
+            408 => "请求超时",
+            413 => "请求体过大",
+            414 => "请求 URI 过长",
+            429 => "请求过多",
+            500 => "服务器内部错误",
+            501 => "未实现",
+            502 => "错误的网关",
+            503 => "服务不可用",
+            504 => "网关超时",
+            _ => return None,
+        }),
+        _ => None,
+    }
+}
 #[derive(Debug, Clone)]
 pub struct ResponseErrorLayer<F> {
     formatter: Arc<F>,
@@ -35,6 +161,10 @@ impl ErrorFormatter for DefaultErrorFormatter {
 
 pub trait ErrorFormatter {
     fn format(&self, err: impl std::error::Error) -> String;
+    /// Structured form of the error, the detail is the formatted message by default.
+    fn problem(&self, status: StatusCode, err: impl std::error::Error) -> SgProblem {
+        SgProblem::new(status).detail(self.format(err))
+    }
 }
 
 impl<S, F> Layer<S> for ResponseErrorLayer<F>
@@ -116,3 +246,20 @@ where
         }
     }
 }
+
+#[test]
+fn test_problem() {
+    let problem = SgProblem {
+        instance: Some("/api".to_string()),
+        request_id: Some("req-1".to_string()),
+        ..SgProblem::new(StatusCode::GATEWAY_TIMEOUT).detail("timeout \"30s\"").plugin("limit")
+    };
+    assert_eq!(
+        problem.to_json(None),
+        r#"{"type":"about:blank","title":"Gateway Timeout","status":504,"detail":"timeout \"30s\"","instance":"/api","plugin":"limit","request_id":"req-1"}"#
+    );
+    assert_eq!(problem.title_in(Some("zh-CN")), "网关超时");
+    assert_eq!(problem.title_in(Some("fr")), "Gateway Timeout");
+    assert_eq!(problem.to_text(None), "timeout \"30s\"");
+    assert_eq!(SgProblem::new(StatusCode::NOT_FOUND).to_text(Some("zh")), "未找到");
+}
@@ -1,13 +1,12 @@
-use std::{
-    convert::Infallible,
-    time::{Duration, Instant},
-};
+use std::{convert::Infallible, time::Duration};
+
+use tokio::time::Instant;
 
 use futures_util::Future;
 use hyper::{Request, Response};
 use tower_layer::Layer;
 
-use crate::SgBody;
+use crate::{SgBody, SgResponseExt};
 #[derive(Clone)]
 pub struct TimeoutLayer {
     /// timeout duration, none value means no timeout
@@ -66,7 +65,7 @@ where
     fn call(&self, req: Request<SgBody>) -> Self::Future {
         TimeoutFuture {
             inner: self.inner.call(req),
-            timeout_at: self.timeout.map(|d| Instant::now() + d),
+            sleep: self.timeout.map(|d| tokio::time::sleep_until(Instant::now() + d)),
             timeout_response: self.timeout_response.clone(),
         }
     }
@@ -76,7 +75,8 @@ pin_project_lite::pin_project! {
     pub struct TimeoutFuture<F> {
         #[pin]
         inner: F,
-        timeout_at: Option<Instant>,
+        #[pin]
+        sleep: Option<tokio::time::Sleep>,
         timeout_response: hyper::body::Bytes,
     }
 }
@@ -89,12 +89,13 @@ where
 
     fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
         let this = self.project();
-        if let Some(timeout_at) = this.timeout_at {
-            if Instant::now() >= *timeout_at {
-                let response = Response::builder().status(hyper::StatusCode::GATEWAY_TIMEOUT).body(SgBody::full(this.timeout_response.clone())).expect("invalid response");
+        if let Some(sleep) = this.sleep.as_pin_mut() {
+            if sleep.poll(cx).is_ready() {
+                let response = Response::with_gateway_error(hyper::StatusCode::GATEWAY_TIMEOUT, this.timeout_response.clone());
                 return std::task::Poll::Ready(Ok(response));
             }
         }
+        // only the time to response is limited, streaming the response body is not, see `BackendTimeouts::deadline`
         this.inner.poll(cx)
     }
 }
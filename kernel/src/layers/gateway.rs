@@ -1,10 +1,15 @@
 pub mod builder;
+pub mod error_page;
+pub mod fallback;
+pub mod path_normalizer;
+pub mod route_index;
 
-use std::{convert::Infallible, ops::Index, sync::Arc};
+use std::{collections::BTreeMap, convert::Infallible, ops::Index, sync::Arc};
 
 use crate::{
-    extension::{GatewayName, MatchedSgRouter},
+    extension::{GatewayName, ListenerHostname, MatchedSgRouter},
     helper_layers::{
+        function::FnLayer,
         map_request::{add_extension::add_extension, MapRequestLayer},
         reload::Reloader,
         route::{Route, Router},
@@ -19,7 +24,9 @@ use tokio_util::sync::CancellationToken;
 use tower_layer::Layer;
 use tracing::{debug, instrument};
 
-use super::http_route::{match_hostname::HostnameTree, match_request::MatchRequest, SgHttpRoute, SgHttpRouter};
+use self::{error_page::SgErrorPages, path_normalizer::SgPathNormalizer, route_index::RouteIndex};
+
+use super::http_route::{match_hostname::HostnameTree, match_request::SgPredicateExplain, SgHttpRoute, SgHttpRouter};
 
 /****************************************************************************************
 
@@ -34,6 +41,8 @@ pub struct SgGatewayLayer {
     http_routes: Arc<[SgHttpRoute]>,
     http_plugins: Arc<[SgBoxLayer]>,
     http_fallback: SgBoxLayer,
+    path_normalizer: SgPathNormalizer,
+    error_pages: SgErrorPages,
     pub http_route_reloader: Reloader<SgGatewayRoute>,
 }
 
@@ -53,10 +62,103 @@ pub struct SgGatewayRoutedServices {
     services: Arc<[Vec<BoxHyperService>]>,
 }
 
+/// Routes requests by the hostname tree and then the [`RouteIndex`] of the host.
 #[derive(Debug, Clone)]
 pub struct SgGatewayRouter {
     pub routers: Arc<[SgHttpRouter]>,
-    pub hostname_tree: Arc<HostnameTree<Vec<(usize, i16)>>>,
+    pub hostname_tree: Arc<HostnameTree<RouteIndex>>,
+}
+
+impl SgGatewayRouter {
+    /// Host of the request, `None` if it's not one of the listener which accepted the request.
+    fn host(req: &Request<SgBody>) -> Option<&str> {
+        let host = req.headers().get(HOST).and_then(|x| x.to_str().ok())?;
+        match req.extensions().get::<ListenerHostname>() {
+            Some(hostname) if !hostname.matches(host) => None,
+            _ => Some(host),
+        }
+    }
+
+    /// Index rules of routes by hostname, routes without hostnames serve every host.
+    ///
+    /// Routes of a host are tried by priority from high to low, and then by the order they are given.
+    pub fn new(routes: &[SgHttpRoute]) -> Self {
+        let routers = routes
+            .iter()
+            .map(|route| SgHttpRouter {
+                hostnames: route.hostnames.clone(),
+                listeners: route.listeners.clone(),
+                rules: route.rules.iter().map(|rule| rule.r#match.clone()).collect(),
+            })
+            .collect::<Arc<[_]>>();
+        let mut hosts = BTreeMap::<&str, Vec<usize>>::new();
+        for (idx, route) in routes.iter().enumerate() {
+            if route.hostnames.is_empty() {
+                hosts.entry("*").or_default().push(idx);
+            }
+            for hostname in route.hostnames.iter() {
+                hosts.entry(hostname).or_default().push(idx);
+            }
+        }
+        let any_host = hosts.get("*").cloned().unwrap_or_default();
+        let mut hostname_tree = HostnameTree::new();
+        for (hostname, mut indices) in hosts {
+            if hostname != "*" {
+                indices.extend_from_slice(&any_host);
+            }
+            // a route may be listed under both its hostname and "*"
+            indices.sort_unstable();
+            indices.dedup();
+            // we put the highest priority at the front, stable sort keeps the order of routes with the same priority
+            indices.sort_by_key(|idx| std::cmp::Reverse(routes[*idx].priority));
+            hostname_tree.set(hostname, RouteIndex::new(&routers, indices));
+        }
+        debug!("hostname_tree: {hostname_tree:?}");
+        SgGatewayRouter {
+            routers,
+            hostname_tree: Arc::new(hostname_tree),
+        }
+    }
+}
+
+/// A candidate rule tried for a request, see [`SgGatewayRouter::explain`].
+#[derive(Debug, Clone)]
+pub struct SgCandidateExplain {
+    pub route: usize,
+    pub rule: usize,
+    /// Index of the match in the rule, `None` for rules without matches.
+    pub match_index: Option<usize>,
+    pub predicates: Vec<SgPredicateExplain>,
+    pub matched: bool,
+}
+
+impl SgGatewayRouter {
+    /// Try every candidate of the host of the request in order, and explain why each one is matched or not.
+    ///
+    /// Returns the candidates and the index of the selected rule, which is the same as [`Router::route`] gives.
+    pub fn explain(&self, req: &Request<SgBody>) -> (Vec<SgCandidateExplain>, Option<(usize, usize)>) {
+        let Some(index) = Self::host(req).and_then(|host| self.hostname_tree.get(host)) else {
+            return (Vec::new(), None);
+        };
+        let candidates = index
+            .candidates()
+            .iter()
+            .map(|candidate| {
+                let mut predicates = candidate.r#match.as_ref().map(|m| m.explain(req)).unwrap_or_default();
+                predicates.extend(candidate.explain_listener(req));
+                let matched = candidate.match_request(req);
+                SgCandidateExplain {
+                    route: candidate.route,
+                    rule: candidate.rule,
+                    match_index: candidate.match_index,
+                    predicates,
+                    matched,
+                }
+            })
+            .collect::<Vec<_>>();
+        let selected = candidates.iter().find(|candidate| candidate.matched).map(|candidate| (candidate.route, candidate.rule));
+        (candidates, selected)
+    }
 }
 
 impl Index<(usize, usize)> for SgGatewayRoutedServices {
@@ -70,28 +172,17 @@ impl Router for SgGatewayRouter {
     type Index = (usize, usize);
     #[instrument(skip_all, fields(uri = req.uri().to_string(), method = req.method().as_str(), host = ?req.headers().get(HOST) ))]
     fn route(&self, req: &mut Request<SgBody>) -> Option<Self::Index> {
-        let host = req.headers().get(HOST).and_then(|x| x.to_str().ok())?;
-        let indices = self.hostname_tree.get(host)?;
-        for (route_index, _p) in indices {
-            for (idx1, matches) in self.routers.as_ref().index(*route_index).rules.iter().enumerate() {
-                // tracing::trace!("try match {match:?} [{route_index},{idx1}:{_p}]");
-                let index = (*route_index, idx1);
-                if let Some(ref matches) = matches {
-                    for m in matches.as_ref() {
-                        if m.match_request(req) {
-                            req.extensions_mut().insert(MatchedSgRouter(m.clone()));
-                            tracing::trace!("matches {m:?} [{route_index},{idx1}:{_p}]");
-                            return Some(index);
-                        }
-                    }
-                    continue;
-                } else {
-                    tracing::trace!("matches wildcard [{route_index},{idx1}:{_p}]");
-                    return Some(index);
-                }
-            }
+        let host = Self::host(req)?;
+        let candidate = self.hostname_tree.get(host)?.route(req)?;
+        let index = (candidate.route, candidate.rule);
+        if let Some(m) = &candidate.r#match {
+            tracing::trace!("matches {m:?} [{},{}]", index.0, index.1);
+            let captures = m.path.as_ref().and_then(|path| path.captures(req.uri().path(), m.case_insensitive)).unwrap_or_default();
+            req.extensions_mut().insert(MatchedSgRouter(m.clone(), captures));
+        } else {
+            tracing::trace!("matches wildcard [{},{}]", index.0, index.1);
         }
-        None
+        Some(index)
     }
 }
 
@@ -114,7 +205,17 @@ where
         };
         #[cfg(not(feature = "reload"))]
         let service = route;
-        BoxHyperService::new(add_gateway_name_layer.layer(gateway_plugins.layer(service)))
+        let service = gateway_plugins.layer(service);
+        // errors of gateway plugins are replaced as well
+        let service = BoxHyperService::new(FnLayer::new(self.error_pages.clone()).layer(service));
+        // paths are normalized before gateway plugins, so they check the same path as the router
+        if self.path_normalizer.is_enabled() {
+            let normalizer = self.path_normalizer;
+            let normalize_path_layer = MapRequestLayer::new(move |req| normalizer.normalize_request(req));
+            BoxHyperService::new(add_gateway_name_layer.layer(normalize_path_layer.layer(service)))
+        } else {
+            BoxHyperService::new(add_gateway_name_layer.layer(service))
+        }
     }
 }
 
@@ -123,53 +224,12 @@ where
     S: Clone + hyper::service::Service<Request<SgBody>, Error = Infallible, Response = Response<SgBody>> + Send + Sync + 'static,
     <S as hyper::service::Service<Request<SgBody>>>::Future: std::marker::Send,
 {
-    let mut services = Vec::with_capacity(routes.len());
-    let mut routers = Vec::with_capacity(routes.len());
-    let mut hostname_tree = HostnameTree::<Vec<_>>::new();
-    for (idx, route) in routes.iter().enumerate() {
-        let priority = route.priority;
-        let idx_with_priority = (idx, priority);
-        let route_plugins = route.plugins.iter().collect::<SgBoxLayer>();
-        let mut rules_services = Vec::with_capacity(route.rules.len());
-        let mut rules_router = Vec::with_capacity(route.rules.len());
-        for rule in route.rules.iter() {
-            // let rule_service = route_plugins.layer(rule.layer(inner.clone()));
-            let rule_service = route_plugins.layer(rule.layer(inner.clone()));
-            rules_services.push(rule_service);
-            rules_router.push(rule.r#match.clone());
-        }
-        if route.hostnames.is_empty() {
-            if let Some(indices) = hostname_tree.get_mut("*") {
-                indices.push(idx_with_priority)
-            } else {
-                hostname_tree.set("*", vec![idx_with_priority]);
-            }
-        } else {
-            for hostname in route.hostnames.iter() {
-                if let Some(indices) = hostname_tree.get_mut(hostname) {
-                    indices.push(idx_with_priority)
-                } else {
-                    hostname_tree.set("*", vec![idx_with_priority]);
-                }
-            }
-        }
-        services.push(rules_services);
-        routers.push(SgHttpRouter {
-            hostnames: route.hostnames.clone(),
-            rules: rules_router.into(),
-        });
-    }
-
-    // sort the indices by priority
-    // we put the highest priority at the front of the vector
-    hostname_tree.iter_mut().for_each(|indices| indices.sort_unstable_by_key(|(_, p)| i16::MAX - *p));
-    debug!("hostname_tree: {hostname_tree:?}");
-    Route::new(
-        SgGatewayRoutedServices { services: services.into() },
-        SgGatewayRouter {
-            routers: routers.into(),
-            hostname_tree: Arc::new(hostname_tree),
-        },
-        fallback.layer(inner),
-    )
+    let services = routes
+        .iter()
+        .map(|route| {
+            let route_plugins = route.plugins.iter().collect::<SgBoxLayer>();
+            route.rules.iter().map(|rule| route_plugins.layer(rule.layer(inner.clone()))).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    Route::new(SgGatewayRoutedServices { services: services.into() }, SgGatewayRouter::new(routes), fallback.layer(inner))
 }
@@ -2,16 +2,9 @@ use std::sync::{Arc, OnceLock};
 
 use tokio_util::sync::CancellationToken;
 
-use crate::{
-    helper_layers::{
-        filter::{response_anyway::ResponseAnyway, FilterRequestLayer},
-        reload::Reloader,
-    },
-    layers::http_route::SgHttpRoute,
-    SgBoxLayer,
-};
+use crate::{helper_layers::reload::Reloader, layers::http_route::SgHttpRoute, SgBoxLayer};
 
-use super::{SgGatewayLayer, SgGatewayRoute};
+use super::{error_page::SgErrorPages, fallback::SgGatewayFallback, path_normalizer::SgPathNormalizer, SgGatewayLayer, SgGatewayRoute};
 
 pub struct SgGatewayLayerBuilder {
     pub gateway_name: Arc<str>,
@@ -19,18 +12,15 @@ pub struct SgGatewayLayerBuilder {
     http_routers: Vec<SgHttpRoute>,
     pub http_plugins: Vec<SgBoxLayer>,
     http_fallback: SgBoxLayer,
+    path_normalizer: SgPathNormalizer,
+    error_pages: SgErrorPages,
     http_route_reloader: Reloader<SgGatewayRoute>,
     pub extension: hyper::http::Extensions,
 }
 
 pub fn default_gateway_route_fallback() -> &'static SgBoxLayer {
     static LAYER: OnceLock<SgBoxLayer> = OnceLock::new();
-    LAYER.get_or_init(|| {
-        SgBoxLayer::new(FilterRequestLayer::new(ResponseAnyway {
-            status: hyper::StatusCode::NOT_FOUND,
-            message: "[Sg.HttpRouteRule] no rule matched".to_string().into(),
-        }))
-    })
+    LAYER.get_or_init(|| SgGatewayFallback::NotFound.into_layer())
 }
 
 impl SgGatewayLayerBuilder {
@@ -41,6 +31,8 @@ impl SgGatewayLayerBuilder {
             http_routers: Vec::new(),
             http_plugins: Vec::new(),
             http_fallback: default_gateway_route_fallback().clone(),
+            path_normalizer: SgPathNormalizer::default(),
+            error_pages: SgErrorPages::default(),
             http_route_reloader: Default::default(),
             extension: hyper::http::Extensions::default(),
         }
@@ -65,6 +57,21 @@ impl SgGatewayLayerBuilder {
         self.http_fallback = fallback;
         self
     }
+    /// Respond the fallback when no rule matches the request.
+    pub fn fallback(mut self, fallback: SgGatewayFallback) -> Self {
+        self.http_fallback = fallback.into_layer();
+        self
+    }
+    /// Replace errors generated by the gateway with error pages.
+    pub fn error_pages(mut self, error_pages: SgErrorPages) -> Self {
+        self.error_pages = error_pages;
+        self
+    }
+    /// Normalize request paths before gateway plugins and routing.
+    pub fn path_normalizer(mut self, path_normalizer: SgPathNormalizer) -> Self {
+        self.path_normalizer = path_normalizer;
+        self
+    }
     pub fn http_route_reloader(mut self, reloader: Reloader<SgGatewayRoute>) -> Self {
         self.http_route_reloader = reloader;
         self
@@ -79,6 +86,8 @@ impl SgGatewayLayerBuilder {
             http_routes: self.http_routers.into(),
             http_plugins: self.http_plugins.into(),
             http_fallback: self.http_fallback,
+            path_normalizer: self.path_normalizer,
+            error_pages: self.error_pages,
             http_route_reloader: self.http_route_reloader,
         }
     }
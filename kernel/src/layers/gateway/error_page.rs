@@ -0,0 +1,391 @@
+//! # Error Pages
+//!
+//! Errors generated by the gateway, see [`GatewayError`], are plain text by default.
+//! Error pages replace them by templates of the status code, picked by the `Accept` header of the request,
+//! and if no page is acceptable, errors are rendered as `application/problem+json` when it's preferred to text.
+//! Responses of backends and plugins are never negotiated, whatever their status codes are.
+//!
+//! Templates may refer to `${status}`, `${reason}`, `${title}`, `${message}`, `${plugin}`, `${instance}` and `${request_id}`,
+//! values are escaped for JSON and HTML/XML templates by their content type.
+//! Titles, and titles of problems, are localized by the language of the gateway.
+
+use std::{ops::RangeInclusive, sync::Arc};
+
+use hyper::{
+    header::{HeaderName, HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+    Request, Response,
+};
+
+use crate::{
+    extension::GatewayError,
+    helper_layers::{
+        function::{FnLayerMethod, Inner},
+        response_error::{escape_json, PROBLEM_JSON},
+    },
+    BoxError, SgBody,
+};
+
+/// Header of the request id, the one of the request is preferred to the one of the response.
+pub const DEFAULT_REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// A page template of a range of status codes.
+#[derive(Debug, Clone)]
+pub struct SgErrorPage {
+    pub status: RangeInclusive<u16>,
+    pub content_type: HeaderValue,
+    pub template: Arc<str>,
+}
+
+impl SgErrorPage {
+    /// Parse a status code like `404`, a class like `5xx`, or an inclusive range like `500-504`.
+    pub fn parse_status(status: &str) -> Result<RangeInclusive<u16>, BoxError> {
+        let status = status.trim();
+        let range = if let Some(class) = status.strip_suffix("xx").or_else(|| status.strip_suffix("XX")) {
+            let class: u16 = class.parse()?;
+            class * 100..=class * 100 + 99
+        } else if let Some((from, to)) = status.split_once('-') {
+            from.trim().parse()?..=to.trim().parse()?
+        } else {
+            let code: u16 = status.parse()?;
+            code..=code
+        };
+        if range.is_empty() || *range.start() < 100 || *range.end() > 999 {
+            return Err(format!("invalid status range {status}").into());
+        }
+        Ok(range)
+    }
+
+    fn escape(&self, value: &str) -> String {
+        let content_type = self.content_type.to_str().unwrap_or_default().to_ascii_lowercase();
+        if content_type.contains("json") {
+            escape_json(value)
+        } else if content_type.contains("html") || content_type.contains("xml") {
+            let mut escaped = String::with_capacity(value.len());
+            for c in value.chars() {
+                match c {
+                    '&' => escaped.push_str("&amp;"),
+                    '<' => escaped.push_str("&lt;"),
+                    '>' => escaped.push_str("&gt;"),
+                    '"' => escaped.push_str("&quot;"),
+                    '\'' => escaped.push_str("&#39;"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Substitute `${name}` placeholders by escaped values, unknown placeholders are left as is.
+    pub fn render(&self, vars: &[(&str, &str)]) -> String {
+        let mut rendered = String::with_capacity(self.template.len());
+        let mut rest = &*self.template;
+        while let Some(start) = rest.find("${") {
+            rendered.push_str(&rest[..start]);
+            let placeholder = &rest[start..];
+            let value = placeholder.find('}').and_then(|end| vars.iter().find(|(name, _)| *name == &placeholder[2..end]).map(|(_, value)| (end, value)));
+            match value {
+                Some((end, value)) => {
+                    rendered.push_str(&self.escape(value));
+                    rest = &placeholder[end + 1..];
+                }
+                None => {
+                    rendered.push_str("${");
+                    rest = &placeholder[2..];
+                }
+            }
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+/// Quality of the content type by the `Accept` header, the most specific media range decides, `0` if not acceptable.
+fn quality(accept: &str, content_type: &str) -> f32 {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let main_type = essence.split('/').next().unwrap_or_default();
+    let mut best: Option<(u8, f32)> = None;
+    for item in accept.split(',') {
+        let mut params = item.split(';');
+        let range = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let specificity = match range.split_once('/') {
+            _ if range == essence => 3,
+            Some((ty, "*")) if ty == main_type => 2,
+            Some(("*", "*")) => 1,
+            _ => continue,
+        };
+        let q = params.find_map(|param| param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok())).unwrap_or(1.0);
+        match best {
+            Some((best_specificity, _)) if best_specificity >= specificity => {}
+            _ => best = Some((specificity, q)),
+        }
+    }
+    best.map(|(_, q)| q).unwrap_or(0.0)
+}
+
+/// Error pages of a gateway, the first page of the highest quality among pages of the status code is picked.
+///
+/// Requests without `Accept` header take the first page of the status code,
+/// and if no page is acceptable, the error is rendered as a problem or left as text.
+#[derive(Debug, Clone)]
+pub struct SgErrorPages {
+    pages: Arc<[SgErrorPage]>,
+    request_id_header: HeaderName,
+    lang: Option<Arc<str>>,
+}
+
+impl Default for SgErrorPages {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl SgErrorPages {
+    pub fn new(pages: impl IntoIterator<Item = SgErrorPage>) -> Self {
+        Self {
+            pages: pages.into_iter().collect(),
+            request_id_header: DEFAULT_REQUEST_ID_HEADER,
+            lang: None,
+        }
+    }
+
+    /// Language of titles, e.g. `zh-CN`, see [`localized_reason`](crate::helper_layers::response_error::localized_reason).
+    pub fn lang(mut self, lang: impl Into<Arc<str>>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    pub fn request_id_header(mut self, header: HeaderName) -> Self {
+        self.request_id_header = header;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn select(&self, status: u16, accept: Option<&str>) -> Option<&SgErrorPage> {
+        let mut pages = self.pages.iter().filter(|page| page.status.contains(&status));
+        let Some(accept) = accept else {
+            return pages.next();
+        };
+        let mut selected: Option<(f32, &SgErrorPage)> = None;
+        for page in pages {
+            let q = quality(accept, page.content_type.to_str().unwrap_or_default());
+            match selected {
+                Some((best, _)) if best >= q => {}
+                _ if q > 0.0 => selected = Some((q, page)),
+                _ => {}
+            }
+        }
+        selected.map(|(_, page)| page)
+    }
+
+    /// Replace the response by a page, or a problem, if it's a gateway error.
+    ///
+    /// `instance` is the path of the request.
+    pub fn replace(&self, resp: Response<SgBody>, accept: Option<&str>, request_id: Option<&str>, instance: Option<&str>) -> Response<SgBody> {
+        let Some(error) = resp.extensions().get::<GatewayError>() else {
+            return resp;
+        };
+        let mut problem = error.problem.clone();
+        let request_id = request_id.or_else(|| resp.headers().get(&self.request_id_header).and_then(|id| id.to_str().ok()));
+        problem.request_id = problem.request_id.or(request_id.map(str::to_string));
+        problem.instance = problem.instance.or(instance.map(str::to_string));
+        let status = resp.status();
+        let lang = self.lang.as_deref();
+        let (content_type, body) = if let Some(page) = self.select(status.as_u16(), accept) {
+            let body = page.render(&[
+                ("status", status.as_str()),
+                ("reason", status.canonical_reason().unwrap_or_default()),
+                ("title", &problem.title_in(lang)),
+                ("message", problem.detail.as_deref().unwrap_or_default()),
+                ("plugin", problem.plugin.as_deref().unwrap_or_default()),
+                ("instance", problem.instance.as_deref().unwrap_or_default()),
+                ("request_id", problem.request_id.as_deref().unwrap_or_default()),
+            ]);
+            (page.content_type.clone(), body)
+        } else if accept.is_some_and(prefer_problem) {
+            (HeaderValue::from_static(PROBLEM_JSON), problem.to_json(lang))
+        } else {
+            return resp;
+        };
+        let (mut parts, _) = resp.into_parts();
+        parts.headers.remove(CONTENT_LENGTH);
+        parts.headers.insert(CONTENT_TYPE, content_type);
+        parts.extensions.insert(GatewayError { problem });
+        Response::from_parts(parts, SgBody::full(body))
+    }
+}
+
+/// If a problem in json is preferred to text, ties are in favor of text, e.g. for `*/*`.
+fn prefer_problem(accept: &str) -> bool {
+    let json = quality(accept, PROBLEM_JSON).max(quality(accept, "application/json"));
+    json > quality(accept, "text/plain")
+}
+
+impl FnLayerMethod for SgErrorPages {
+    async fn call(&self, req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        let accept = req.headers().get(ACCEPT).and_then(|accept| accept.to_str().ok()).map(str::to_string);
+        let request_id = req.headers().get(&self.request_id_header).and_then(|id| id.to_str().ok()).map(str::to_string);
+        let instance = req.uri().path().to_string();
+        let resp = inner.call(req).await;
+        self.replace(resp, accept.as_deref(), request_id.as_deref(), Some(&instance))
+    }
+}
+
+#[test]
+fn test_error_pages() {
+    use crate::SgResponseExt;
+    use hyper::StatusCode;
+
+    assert_eq!(SgErrorPage::parse_status("5xx").expect("invalid status"), 500..=599);
+    assert_eq!(SgErrorPage::parse_status("500-504").expect("invalid status"), 500..=504);
+    assert_eq!(SgErrorPage::parse_status("404").expect("invalid status"), 404..=404);
+    assert!(SgErrorPage::parse_status("504-500").is_err());
+
+    let pages = SgErrorPages::new([
+        SgErrorPage {
+            status: 500..=599,
+            content_type: HeaderValue::from_static("text/html; charset=utf-8"),
+            template: "<h1>${status} ${reason}</h1><p>${message}</p><p>${request_id}</p>".into(),
+        },
+        SgErrorPage {
+            status: 500..=599,
+            content_type: HeaderValue::from_static("application/json"),
+            template: r#"{"status":${status},"message":"${message}","id":"${request_id}","other":"${other}"}"#.into(),
+        },
+    ]);
+    let error = || Response::with_gateway_error(StatusCode::BAD_GATEWAY, "<bad> \"gateway\"");
+
+    let resp = pages.replace(error(), Some("text/html,application/json;q=0.9"), Some("req-1"), None);
+    assert_eq!(resp.headers().get(CONTENT_TYPE).expect("missing content type"), "text/html; charset=utf-8");
+    let body = resp.body().get_dumped().expect("not dumped");
+    assert_eq!(body.as_ref(), b"<h1>502 Bad Gateway</h1><p>&lt;bad&gt; &quot;gateway&quot;</p><p>req-1</p>");
+
+    let resp = pages.replace(error(), Some("application/*"), None, None);
+    let body = resp.body().get_dumped().expect("not dumped");
+    assert_eq!(body.as_ref(), br#"{"status":502,"message":"<bad> \"gateway\"","id":"","other":"${other}"}"#);
+
+    // not acceptable, not a gateway error or no page of the status, the response is left as is
+    for resp in [
+        pages.replace(error(), Some("text/plain"), None, None),
+        pages.replace(Response::new(SgBody::full("from backend")), None, None, None),
+        pages.replace(Response::with_code_message(StatusCode::BAD_GATEWAY, "from plugin"), Some("text/html"), None, None),
+        pages.replace(Response::with_gateway_error(StatusCode::NOT_FOUND, "not found"), Some("*/*"), None, None),
+    ] {
+        assert!(resp.headers().get(CONTENT_TYPE).is_none());
+    }
+    assert!(pages.select(503, None).is_some_and(|page| page.content_type == "text/html; charset=utf-8"));
+
+    // no page of the status, rendered as a problem if preferred
+    let pages = pages.lang("zh-CN");
+    let resp = pages.replace(
+        Response::with_gateway_error(StatusCode::NOT_FOUND, "no rule matched"),
+        Some("application/json"),
+        Some("req-2"),
+        Some("/api"),
+    );
+    assert_eq!(resp.headers().get(CONTENT_TYPE).expect("missing content type"), PROBLEM_JSON);
+    let body = resp.body().get_dumped().expect("not dumped");
+    assert_eq!(
+        body.as_ref(),
+        r#"{"type":"about:blank","title":"未找到","status":404,"detail":"no rule matched","instance":"/api","request_id":"req-2"}"#.as_bytes()
+    );
+}
+
+#[tokio::test]
+async fn test_plugin_response_passes_through() {
+    use crate::{helper_layers::function::FnLayer, layers::gateway::SgGatewayLayer, service::get_echo_service, Layer, SgBoxLayer, SgResponseExt};
+    use hyper::{service::Service, StatusCode};
+    use tokio_util::sync::CancellationToken;
+
+    let pages = SgErrorPages::new([SgErrorPage {
+        status: 403..=404,
+        content_type: HeaderValue::from_static("text/html"),
+        template: "<h1>${status}</h1>".into(),
+    }]);
+    let deny = SgBoxLayer::new(FnLayer::new_closure(|req: Request<SgBody>, inner: Inner| async move {
+        if req.uri().path() == "/deny" {
+            Response::with_code_message(StatusCode::FORBIDDEN, "denied by plugin")
+        } else {
+            inner.call(req).await
+        }
+    }));
+    let gateway = SgGatewayLayer::builder("test", CancellationToken::new()).error_pages(pages).http_plugin(deny).build().layer(get_echo_service());
+    let call = |path: &'static str| {
+        let gateway = gateway.clone();
+        async move {
+            let req = Request::builder().uri(path).header(ACCEPT, "text/html").body(SgBody::empty()).expect("invalid request");
+            let resp = gateway.call(req).await.expect("infallible");
+            let status = resp.status();
+            let body = resp.into_body().dump().await.expect("fail to dump").get_dumped().expect("not dumped").clone();
+            (status, body)
+        }
+    };
+    // made by a plugin on purpose, it's left as is
+    assert_eq!(call("/deny").await, (StatusCode::FORBIDDEN, "denied by plugin".into()));
+    // no route, it's generated by the gateway
+    assert_eq!(call("/other").await, (StatusCode::NOT_FOUND, "<h1>404</h1>".into()));
+}
+
+#[tokio::test]
+async fn test_problem_negotiation() {
+    use crate::{
+        helper_layers::function::FnLayer,
+        layers::gateway::SgGatewayLayer,
+        layers::http_route::{
+            match_request::{SgHttpPathMatch, SgHttpRouteMatch},
+            SgHttpRoute, SgHttpRouteRuleLayer,
+        },
+        Layer, SgBoxLayer, SgResponseExt,
+    };
+    use hyper::{header::HOST, service::Service, StatusCode};
+    use tokio_util::sync::CancellationToken;
+
+    const JSON: &str = r#"{"error":"invalid_token"}"#;
+    let plugin = SgBoxLayer::new(FnLayer::new_closure(|req: Request<SgBody>, inner: Inner| async move {
+        if req.uri().path() == "/plugin" {
+            let mut resp = Response::with_code_message(StatusCode::UNAUTHORIZED, JSON);
+            resp.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            resp
+        } else {
+            inner.call(req).await
+        }
+    }));
+    let backend = hyper::service::service_fn(|_: Request<SgBody>| async {
+        let resp = Response::builder().status(StatusCode::BAD_REQUEST).header(CONTENT_TYPE, "application/json").body(SgBody::full(JSON)).expect("invalid response");
+        Ok::<_, std::convert::Infallible>(resp)
+    });
+    let route = SgHttpRoute::builder()
+        .rule(
+            SgHttpRouteRuleLayer::builder()
+                .matches([SgHttpRouteMatch {
+                    path: Some(SgHttpPathMatch::Prefix("/backend".into())),
+                    ..Default::default()
+                }])
+                .build()
+                .expect("invalid rule"),
+        )
+        .build()
+        .expect("invalid route");
+    // no error page is configured
+    let gateway = SgGatewayLayer::builder("test", CancellationToken::new()).http_plugin(plugin).http_router(route).build().layer(backend);
+    let call = |path: &'static str| {
+        let gateway = gateway.clone();
+        async move {
+            let req = Request::builder().uri(path).header(HOST, "example.com").header(ACCEPT, "application/json").body(SgBody::empty()).expect("invalid request");
+            let resp = gateway.call(req).await.expect("infallible");
+            let content_type = resp.headers().get(CONTENT_TYPE).cloned();
+            let body = resp.into_body().dump().await.expect("fail to dump").get_dumped().expect("not dumped").clone();
+            (content_type, body)
+        }
+    };
+    // made by a plugin or a backend, it's left as is
+    for path in ["/plugin", "/backend"] {
+        assert_eq!(call(path).await, (Some(HeaderValue::from_static("application/json")), JSON.into()));
+    }
+    // generated by the gateway, it's negotiated
+    assert_eq!(call("/other").await.0, Some(HeaderValue::from_static(PROBLEM_JSON)));
+}
@@ -0,0 +1,63 @@
+//! # Gateway Fallback
+//!
+//! What the gateway responds when no rule matches a request.
+
+use hyper::{
+    body::Bytes,
+    header::{HeaderValue, CONTENT_TYPE, LOCATION},
+    Request, Response, StatusCode,
+};
+
+use crate::{
+    helper_layers::filter::{Filter, FilterRequestLayer},
+    layers::http_route::SgHttpRouteRuleLayer,
+    SgBody, SgBoxLayer, SgResponseExt,
+};
+
+#[derive(Debug, Clone, Default)]
+pub enum SgGatewayFallback {
+    /// `404 Not Found`, as a gateway error, so error pages apply to it.
+    #[default]
+    NotFound,
+    /// A static response.
+    Static {
+        status: StatusCode,
+        content_type: Option<HeaderValue>,
+        body: Bytes,
+    },
+    /// Redirect to the location, `status` should be a redirection.
+    Redirect { status: StatusCode, location: HeaderValue },
+    /// Forward to the backends of the rule, which matches all requests.
+    Backend(SgHttpRouteRuleLayer),
+}
+
+#[derive(Debug, Clone)]
+struct Respond(SgGatewayFallback);
+
+impl Filter for Respond {
+    fn filter(&self, _req: Request<SgBody>) -> Result<Request<SgBody>, Response<SgBody>> {
+        let response = match &self.0 {
+            SgGatewayFallback::Static { status, content_type, body } => {
+                let mut builder = Response::builder().status(*status);
+                if let Some(content_type) = content_type {
+                    builder = builder.header(CONTENT_TYPE, content_type);
+                }
+                builder.body(SgBody::full(body.clone())).expect("invalid response builder")
+            }
+            SgGatewayFallback::Redirect { status, location } => {
+                Response::builder().status(*status).header(LOCATION, location).body(SgBody::empty()).expect("invalid response builder")
+            }
+            _ => Response::with_gateway_error(StatusCode::NOT_FOUND, "[Sg.HttpRouteRule] no rule matched"),
+        };
+        Err(response)
+    }
+}
+
+impl SgGatewayFallback {
+    pub fn into_layer(self) -> SgBoxLayer {
+        match self {
+            SgGatewayFallback::Backend(rule) => SgBoxLayer::new(rule),
+            fallback => SgBoxLayer::new(FilterRequestLayer::new(Respond(fallback))),
+        }
+    }
+}
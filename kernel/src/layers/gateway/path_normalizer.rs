@@ -0,0 +1,196 @@
+//! # Path Normalization
+//!
+//! Requests are routed, and checked by plugins, by their paths.
+//! Different spellings of the same path, like `/a/%62`, `/a//b` or `/a/c/../b`, would bypass path based rules,
+//! so the gateway may normalize paths before anything else sees them.
+
+use std::borrow::Cow;
+
+use hyper::{
+    http::uri::{PathAndQuery, Uri},
+    Request,
+};
+
+use crate::SgBody;
+
+/// Normalizes request paths before routing, every step is disabled by default.
+///
+/// Steps are applied in the order of the fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SgPathNormalizer {
+    /// Decode percent-encoded unreserved characters, e.g. `%7E` to `~`, and uppercase hex digits of other percent-encodings.
+    /// See [RFC 3986 section 6.2.2](https://www.rfc-editor.org/rfc/rfc3986#section-6.2.2).
+    pub decode_unreserved: bool,
+    /// Merge consecutive slashes into one, e.g. `/a//b` to `/a/b`.
+    pub merge_slashes: bool,
+    /// Remove `.` and `..` segments, e.g. `/a/./c/../b` to `/a/b`.
+    /// See [RFC 3986 section 5.2.4](https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4).
+    pub remove_dot_segments: bool,
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+fn decode_unreserved(path: &str) -> Cow<'_, str> {
+    if !path.contains('%') {
+        return Cow::Borrowed(path);
+    }
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' && idx + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[idx + 1]), hex_value(bytes[idx + 2])) {
+                let byte = high << 4 | low;
+                if is_unreserved(byte) {
+                    decoded.push(byte);
+                } else {
+                    decoded.extend([b'%', bytes[idx + 1].to_ascii_uppercase(), bytes[idx + 2].to_ascii_uppercase()]);
+                }
+                idx += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+    // only ascii characters are decoded, so the path is still valid utf-8
+    String::from_utf8(decoded).map(Cow::Owned).unwrap_or(Cow::Borrowed(path))
+}
+
+fn merge_slashes(path: &str) -> Cow<'_, str> {
+    if !path.contains("//") {
+        return Cow::Borrowed(path);
+    }
+    let mut merged = String::with_capacity(path.len());
+    for c in path.chars() {
+        if !(c == '/' && merged.ends_with('/')) {
+            merged.push(c);
+        }
+    }
+    Cow::Owned(merged)
+}
+
+fn remove_dot_segments(path: &str) -> Cow<'_, str> {
+    if !path.split('/').any(|segment| segment == "." || segment == "..") {
+        return Cow::Borrowed(path);
+    }
+    let mut output: Vec<&str> = Vec::new();
+    let mut segments = path.split('/').peekable();
+    // the leading empty segment of an absolute path
+    let absolute = path.starts_with('/');
+    if absolute {
+        segments.next();
+    }
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        match segment {
+            "." | ".." => {
+                if segment == ".." {
+                    output.pop();
+                }
+                // `/a/b/..` is `/a/`, the trailing slash is kept
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    let mut normalized = String::with_capacity(path.len());
+    if absolute {
+        normalized.push('/');
+    }
+    normalized.push_str(&output.join("/"));
+    Cow::Owned(normalized)
+}
+
+impl SgPathNormalizer {
+    pub fn is_enabled(&self) -> bool {
+        self.decode_unreserved || self.merge_slashes || self.remove_dot_segments
+    }
+
+    pub fn normalize<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let mut path = Cow::Borrowed(path);
+        if self.decode_unreserved {
+            if let Cow::Owned(decoded) = decode_unreserved(&path) {
+                path = Cow::Owned(decoded);
+            }
+        }
+        if self.merge_slashes {
+            if let Cow::Owned(merged) = merge_slashes(&path) {
+                path = Cow::Owned(merged);
+            }
+        }
+        if self.remove_dot_segments {
+            if let Cow::Owned(removed) = remove_dot_segments(&path) {
+                path = Cow::Owned(removed);
+            }
+        }
+        path
+    }
+
+    /// Normalize the path of the request uri, the query is left as is.
+    pub fn normalize_request(&self, mut req: Request<SgBody>) -> Request<SgBody> {
+        let Cow::Owned(path) = self.normalize(req.uri().path()) else {
+            return req;
+        };
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{path}?{query}"),
+            None => path,
+        };
+        let mut parts = req.uri().clone().into_parts();
+        match PathAndQuery::try_from(path_and_query) {
+            Ok(path_and_query) => parts.path_and_query = Some(path_and_query),
+            Err(e) => {
+                tracing::debug!("[Sg.PathNormalizer] fail to normalize path {}: {e}", req.uri());
+                return req;
+            }
+        }
+        match Uri::from_parts(parts) {
+            Ok(uri) => *req.uri_mut() = uri,
+            Err(e) => tracing::debug!("[Sg.PathNormalizer] fail to normalize path {}: {e}", req.uri()),
+        }
+        req
+    }
+}
+
+#[test]
+fn test_path_normalizer() {
+    let normalizer = SgPathNormalizer {
+        decode_unreserved: true,
+        merge_slashes: true,
+        remove_dot_segments: true,
+    };
+    for (path, normalized) in [
+        ("/a/b", "/a/b"),
+        ("/a/%62/%7e", "/a/b/~"),
+        ("/a/%2f%3a", "/a/%2F%3A"),
+        ("/a//b///c", "/a/b/c"),
+        ("/a/./b/../c", "/a/c"),
+        ("/a/b/..", "/a/"),
+        ("/a/b/.", "/a/b/"),
+        ("/../../a", "/a"),
+        ("/a/%2E%2E/b", "/b"),
+        ("/a/..%2Fb", "/a/..%2Fb"),
+        ("/a/%zz", "/a/%zz"),
+        ("/a/%4", "/a/%4"),
+    ] {
+        assert_eq!(normalizer.normalize(path), normalized, "normalize {path}");
+    }
+    let merge_only = SgPathNormalizer {
+        merge_slashes: true,
+        ..Default::default()
+    };
+    assert_eq!(merge_only.normalize("/a//./b"), "/a/./b");
+    assert!(!SgPathNormalizer::default().is_enabled());
+
+    let req = Request::builder().uri("http://example.com/a/../b//c?x=/../y").body(SgBody::empty()).expect("invalid request");
+    let req = normalizer.normalize_request(req);
+    assert_eq!(req.uri().to_string(), "http://example.com/b/c?x=/../y");
+}
@@ -0,0 +1,391 @@
+//! # Route Index
+//!
+//! Rules of a host are tried in order, i.e. routes by priority and then rules and matches in declaration order.
+//! Instead of trying every match, candidates are looked up by the request path first:
+//!
+//! - `Exact` and `Prefix` matches, and `Template` matches by their literal prefix, are indexed in a trie of path segments,
+//! - `Regular` matches are compiled into a [`RegexSet`],
+//! - matches without path and rules without matches are always candidates.
+//!
+//! The candidates are then tried in the original order, so the first one matching the whole request wins as before.
+//! Every candidate list is sorted by that order, so they are merged while trying instead of being collected and sorted.
+
+use std::{borrow::Cow, collections::HashMap, fmt, sync::Arc};
+
+use hyper::Request;
+use regex::RegexSet;
+use smallvec::SmallVec;
+
+use crate::{
+    extension::ListenerName,
+    layers::http_route::{
+        match_request::{MatchRequest, SgHttpPathMatch, SgHttpRouteMatch, SgPredicateExplain},
+        SgHttpRouter,
+    },
+    SgBody,
+};
+
+/// A match of a rule, `r#match` and `match_index` are `None` for rules without matches.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub route: usize,
+    pub rule: usize,
+    pub match_index: Option<usize>,
+    pub r#match: Option<Arc<SgHttpRouteMatch>>,
+    /// Listeners the route attaches to, empty for all listeners.
+    pub listeners: Arc<[String]>,
+}
+
+impl Candidate {
+    /// If the route attaches to the listener which accepted the request, requests without the listener are from all listeners.
+    fn match_listener(&self, req: &Request<SgBody>) -> bool {
+        if self.listeners.is_empty() {
+            return true;
+        }
+        match req.extensions().get::<ListenerName>() {
+            Some(listener) => self.listeners.iter().any(|name| name == listener.as_str()),
+            None => true,
+        }
+    }
+
+    pub fn match_request(&self, req: &Request<SgBody>) -> bool {
+        self.match_listener(req)
+            && match &self.r#match {
+                Some(m) => m.match_request(req),
+                None => true,
+            }
+    }
+
+    /// Explain the listener predicate, `None` if the route attaches to all listeners.
+    pub fn explain_listener(&self, req: &Request<SgBody>) -> Option<SgPredicateExplain> {
+        if self.listeners.is_empty() {
+            return None;
+        }
+        Some(SgPredicateExplain {
+            kind: "listener",
+            predicate: format!("in [{}]", self.listeners.join(", ")),
+            passed: self.match_listener(req),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct PathNode {
+    /// Children by lowercase segment, so case insensitive matches are found as well.
+    children: HashMap<Box<str>, PathNode>,
+    exact: Vec<u32>,
+    prefix: Vec<u32>,
+}
+
+/// Sorted candidate lists of a request, they fit on the stack unless the path is deeply nested.
+type CandidateLists<'a> = SmallVec<[&'a [u32]; 8]>;
+
+/// Take the smallest head of sorted candidate lists.
+fn pop_min(lists: &mut [&[u32]]) -> Option<u32> {
+    let list = lists.iter_mut().filter(|list| !list.is_empty()).min_by_key(|list| list[0])?;
+    let (first, rest) = (*list).split_first()?;
+    *list = rest;
+    Some(*first)
+}
+
+/// Segments of a path, empty segments are skipped as prefix matches do.
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+impl PathNode {
+    fn node_mut(&mut self, path: &str) -> &mut PathNode {
+        segments(path).fold(self, |node, segment| node.children.entry(segment.to_ascii_lowercase().into()).or_default())
+    }
+
+    /// Collect candidate lists of prefixes of the path, and the exact one of the path.
+    fn collect<'a>(&'a self, path: &str, lists: &mut CandidateLists<'a>) {
+        let path = if path.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(path.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(path)
+        };
+        let mut push = |list: &'a [u32]| {
+            if !list.is_empty() {
+                lists.push(list)
+            }
+        };
+        let mut node = self;
+        push(&node.prefix);
+        for segment in segments(&path) {
+            match node.children.get(segment) {
+                Some(child) => node = child,
+                None => return,
+            }
+            push(&node.prefix);
+        }
+        push(&node.exact);
+    }
+}
+
+/// Index of route rules of a host.
+#[derive(Default)]
+pub struct RouteIndex {
+    /// Candidates in the order they are tried.
+    candidates: Vec<Candidate>,
+    always: Vec<u32>,
+    paths: PathNode,
+    regex: Option<(RegexSet, Vec<u32>)>,
+}
+
+impl fmt::Debug for RouteIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RouteIndex")
+            .field("candidates", &self.candidates.len())
+            .field("always", &self.always.len())
+            .field("regex", &self.regex.as_ref().map_or(0, |(_, candidates)| candidates.len()))
+            .finish()
+    }
+}
+
+impl RouteIndex {
+    /// Index rules of `routes`, which are given by their indices in the order they are tried.
+    pub fn new(routers: &[SgHttpRouter], routes: impl IntoIterator<Item = usize>) -> Self {
+        let mut index = RouteIndex::default();
+        let mut regex = Vec::new();
+        for route in routes {
+            for (rule, matches) in routers[route].rules.iter().enumerate() {
+                let Some(matches) = matches else {
+                    let candidate = Candidate {
+                        route,
+                        rule,
+                        match_index: None,
+                        r#match: None,
+                        listeners: routers[route].listeners.clone(),
+                    };
+                    index.push(candidate, |index, ordinal| index.always.push(ordinal));
+                    continue;
+                };
+                for (match_index, r#match) in matches.iter().enumerate() {
+                    let candidate = Candidate {
+                        route,
+                        rule,
+                        match_index: Some(match_index),
+                        r#match: Some(r#match.clone()),
+                        listeners: routers[route].listeners.clone(),
+                    };
+                    index.push(candidate, |index, ordinal| match &r#match.path {
+                        Some(SgHttpPathMatch::Exact(path)) => index.paths.node_mut(path).exact.push(ordinal),
+                        Some(SgHttpPathMatch::Prefix(path)) => index.paths.node_mut(path).prefix.push(ordinal),
+                        // a case insensitive regex is built with the `(?i)` flag, so the pattern keeps its case sensitivity
+                        Some(SgHttpPathMatch::Regular(path)) => regex.push((path.as_str(), ordinal)),
+                        Some(SgHttpPathMatch::Template(template)) => index.paths.node_mut(&template.literal_prefix()).prefix.push(ordinal),
+                        None => index.always.push(ordinal),
+                    });
+                }
+            }
+        }
+        if !regex.is_empty() {
+            match RegexSet::new(regex.iter().map(|(pattern, _)| pattern)) {
+                Ok(set) => index.regex = Some((set, regex.into_iter().map(|(_, ordinal)| ordinal).collect())),
+                Err(e) => {
+                    // the set may exceed the size limit, then the regexes are tried one by one
+                    tracing::warn!("[Sg.Route] fail to compile regex set: {e}");
+                    index.always.extend(regex.into_iter().map(|(_, ordinal)| ordinal));
+                    index.always.sort_unstable();
+                }
+            }
+        }
+        index
+    }
+
+    fn push(&mut self, candidate: Candidate, place: impl FnOnce(&mut Self, u32)) {
+        let ordinal = self.candidates.len() as u32;
+        self.candidates.push(candidate);
+        place(self, ordinal);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// All candidates in the order they are tried, without looking up by the path.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// Find the first candidate matching the request.
+    pub fn route(&self, req: &Request<SgBody>) -> Option<&Candidate> {
+        let path = req.uri().path();
+        // regex ordinals are sorted, so are the matched ones
+        let regex_matches: SmallVec<[u32; 4]> = match &self.regex {
+            Some((set, ordinals)) => set.matches(path).iter().map(|index| ordinals[index]).collect(),
+            None => SmallVec::new(),
+        };
+        let mut lists = CandidateLists::new();
+        lists.push(&self.always);
+        lists.push(&regex_matches);
+        self.paths.collect(path, &mut lists);
+        std::iter::from_fn(|| pop_min(&mut lists)).map(|ordinal| &self.candidates[ordinal as usize]).find(|candidate| candidate.match_request(req))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hyper::{Method, Request};
+    use regex::Regex;
+
+    use crate::{
+        extension::{ListenerHostname, ListenerName, MatchedSgRouter},
+        helper_layers::route::Router,
+        layers::{
+            gateway::SgGatewayRouter,
+            http_route::{
+                match_request::{SgHttpMethodMatch, SgHttpPathMatch, SgHttpRouteMatch},
+                path_template::SgPathTemplate,
+                SgHttpRoute, SgHttpRouteRuleLayer,
+            },
+        },
+        SgBody,
+    };
+
+    fn route(hostnames: &[&str], priority: i16, paths: impl IntoIterator<Item = Option<SgHttpPathMatch>>) -> SgHttpRoute {
+        let rules = paths.into_iter().map(|path| {
+            let builder = SgHttpRouteRuleLayer::builder();
+            match path {
+                Some(path) => builder.matches([SgHttpRouteMatch {
+                    path: Some(path),
+                    ..Default::default()
+                }]),
+                None => builder.match_all(),
+            }
+            .build()
+            .expect("invalid rule")
+        });
+        SgHttpRoute::builder().hostnames(hostnames.iter().map(|host| host.to_string())).rules(rules).priority(priority).build().expect("invalid route")
+    }
+
+    fn get(router: &SgGatewayRouter, host: &str, path: &str) -> Option<(usize, usize)> {
+        let mut req = Request::builder().uri(path).header("host", host).body(SgBody::empty()).expect("invalid request");
+        router.route(&mut req)
+    }
+
+    #[test]
+    fn test_route_index() {
+        let routes = [
+            route(&[], 1, [Some(SgHttpPathMatch::Prefix("/api".into())), Some(SgHttpPathMatch::Exact("/api/Exact".into()))]),
+            route(&[], 1, [Some(SgHttpPathMatch::Regular(Regex::new("^/re/[0-9]+$").expect("invalid regex"))), None]),
+            route(&["example.com"], 1, [Some(SgHttpPathMatch::Prefix("/api/v2".into()))]),
+            route(&["example.com"], 10, [Some(SgHttpPathMatch::Exact("/api/v1".into()))]),
+        ];
+        let router = SgGatewayRouter::new(&routes);
+        // rules are tried in order, so the prefix rule wins over the exact one
+        assert_eq!(get(&router, "any.org", "/api/Exact"), Some((0, 0)));
+        // paths are case sensitive by default
+        assert_eq!(get(&router, "any.org", "/API/Exact"), Some((1, 1)));
+        assert_eq!(get(&router, "any.org", "/re/42"), Some((1, 0)));
+        assert_eq!(get(&router, "any.org", "/other"), Some((1, 1)));
+        // routes of higher priority are tried first, routes without hostnames serve every host
+        assert_eq!(get(&router, "example.com", "/api/v1"), Some((3, 0)));
+        assert_eq!(get(&router, "example.com", "/api/v1/users"), Some((0, 0)));
+        assert_eq!(get(&router, "any.org:8080", "/api/v1"), Some((0, 0)));
+        assert_eq!(get(&router, "example.com", "/other"), Some((1, 1)));
+
+        let routes = [
+            route(&["example.com"], 1, [Some(SgHttpPathMatch::Exact("/a".into())), Some(SgHttpPathMatch::Prefix("/b".into()))]),
+            route(&["example.org"], 1, [Some(SgHttpPathMatch::Regular(Regex::new("^/(a|b)").expect("invalid regex")))]),
+        ];
+        let router = SgGatewayRouter::new(&routes);
+        assert_eq!(get(&router, "example.com", "/a"), Some((0, 0)));
+        // exact matches are case sensitive and don't ignore empty segments
+        assert_eq!(get(&router, "example.com", "/A"), None);
+        assert_eq!(get(&router, "example.com", "//a"), None);
+        assert_eq!(get(&router, "example.com", "/b//c"), Some((0, 1)));
+        assert_eq!(get(&router, "example.org", "/b"), Some((1, 0)));
+        assert_eq!(get(&router, "example.net", "/a"), None);
+
+        let get_rule = SgHttpRouteMatch {
+            path: Some(SgHttpPathMatch::Prefix("/".into())),
+            method: Some(vec![SgHttpMethodMatch("GET".into())]),
+            ..Default::default()
+        };
+        let rule = SgHttpRouteRuleLayer::builder().matches([get_rule]).build().expect("invalid rule");
+        let router = SgGatewayRouter::new(&[SgHttpRoute::builder().rule(rule).build().expect("invalid route")]);
+        let mut req = Request::builder().method(Method::POST).uri("/").header("host", "a").body(SgBody::empty()).expect("invalid request");
+        assert_eq!(router.route(&mut req), None);
+        assert_eq!(get(&router, "a", "/any"), Some((0, 0)));
+
+        let template = SgPathTemplate::new("/users/{id}/orders").expect("invalid template");
+        let router = SgGatewayRouter::new(&[route(&[], 1, [Some(SgHttpPathMatch::Template(template))])]);
+        let mut req = Request::builder().uri("/users/42/orders").header("host", "a").body(SgBody::empty()).expect("invalid request");
+        assert_eq!(router.route(&mut req), Some((0, 0)));
+        let matched = req.extensions().get::<MatchedSgRouter>().expect("missing matched router");
+        assert_eq!(matched.captures().get("id"), Some("42"));
+        assert_eq!(get(&router, "a", "/users/42"), None);
+
+        let case_insensitive = |path| SgHttpRouteMatch {
+            path: Some(path),
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let rule = SgHttpRouteRuleLayer::builder()
+            .matches([
+                case_insensitive(SgHttpPathMatch::Exact("/Exact".into())),
+                case_insensitive(SgHttpPathMatch::Regular(Regex::new("(?i)^/re$").expect("invalid regex"))),
+            ])
+            .build()
+            .expect("invalid rule");
+        let router = SgGatewayRouter::new(&[SgHttpRoute::builder().rule(rule).build().expect("invalid route")]);
+        assert_eq!(get(&router, "a", "/eXACT"), Some((0, 0)));
+        assert_eq!(get(&router, "a", "/RE"), Some((0, 0)));
+        assert_eq!(get(&router, "a", "/exact/more"), None);
+    }
+
+    #[test]
+    fn test_explain() {
+        let routes = [
+            route(&[], 1, [Some(SgHttpPathMatch::Prefix("/api".into())), None]),
+            route(&["example.com"], 10, [Some(SgHttpPathMatch::Exact("/api/v1".into()))]),
+        ];
+        let router = SgGatewayRouter::new(&routes);
+        let req = Request::builder().uri("/api/v2").header("host", "example.com").body(SgBody::empty()).expect("invalid request");
+        let (candidates, selected) = router.explain(&req);
+        // every candidate is listed in the order they are tried, whatever the path is
+        let tried = candidates.iter().map(|c| (c.route, c.rule, c.match_index, c.matched)).collect::<Vec<_>>();
+        assert_eq!(tried, [(1, 0, Some(0), false), (0, 0, Some(0), true), (0, 1, None, true)]);
+        assert_eq!(candidates[0].predicates[0].predicate, "Exact /api/v1");
+        assert_eq!(selected, Some((0, 0)));
+        let req = Request::builder().uri("/api/v2").body(SgBody::empty()).expect("invalid request");
+        assert_eq!(router.explain(&req).1, None);
+    }
+
+    #[test]
+    fn test_listener() {
+        let admin = SgHttpRoute::builder()
+            .listeners(["internal".to_string()])
+            .rule(SgHttpRouteRuleLayer::builder().match_all().build().expect("invalid rule"))
+            .priority(10)
+            .build()
+            .expect("invalid route");
+        let routes = [admin, route(&[], 1, [None])];
+        let router = SgGatewayRouter::new(&routes);
+        let get = |listener: Option<(&str, Option<&str>)>, host: &str| {
+            let mut req = Request::builder().uri("/admin").header("host", host).body(SgBody::empty()).expect("invalid request");
+            if let Some((name, hostname)) = listener {
+                req.extensions_mut().insert(ListenerName(name.into()));
+                if let Some(hostname) = hostname {
+                    req.extensions_mut().insert(ListenerHostname(hostname.into()));
+                }
+            }
+            router.route(&mut req)
+        };
+        assert_eq!(get(Some(("internal", None)), "example.com"), Some((0, 0)));
+        assert_eq!(get(Some(("public", None)), "example.com"), Some((1, 0)));
+        // requests not from a listener, e.g. explained ones, are from all listeners
+        assert_eq!(get(None, "example.com"), Some((0, 0)));
+        assert_eq!(get(Some(("public", Some("*.example.com"))), "api.example.com:443"), Some((1, 0)));
+        assert_eq!(get(Some(("public", Some("*.example.com"))), "internal.local"), None);
+
+        let mut req = Request::builder().uri("/admin").header("host", "example.com").body(SgBody::empty()).expect("invalid request");
+        req.extensions_mut().insert(ListenerName("public".into()));
+        let (candidates, selected) = router.explain(&req);
+        assert_eq!(candidates[0].predicates[0].predicate, "in [internal]");
+        assert!(!candidates[0].matched);
+        assert_eq!(selected, Some((1, 0)));
+    }
+}
@@ -1,12 +1,14 @@
 pub mod builder;
+pub mod canary;
 pub mod match_hostname;
 pub mod match_request;
-mod predicate;
+pub mod path_template;
+pub mod predicate;
 use std::{convert::Infallible, num::NonZeroU16, sync::Arc, time::Duration};
 
 use crate::{
-    extension::{BackendHost, Reflect},
-    helper_layers::random_pick,
+    extension::{BackendHost, BackendTimeouts, Reflect},
+    helper_layers::{map_request::MapRequestLayer, random_pick},
     service::BoxHyperService,
     utils::schema_port::port_to_schema,
     SgBody, SgBoxLayer,
@@ -20,6 +22,7 @@ use tower_layer::Layer;
 
 use self::{
     builder::{SgHttpBackendLayerBuilder, SgHttpRouteLayerBuilder, SgHttpRouteRuleLayerBuilder},
+    canary::{CanaryPick, SgHttpCanary},
     match_request::SgHttpRouteMatch,
 };
 
@@ -32,6 +35,8 @@ use self::{
 #[derive(Debug, Clone)]
 pub struct SgHttpRoute {
     pub hostnames: Arc<[String]>,
+    /// Names of listeners the route attaches to, empty for all listeners.
+    pub listeners: Arc<[String]>,
     pub plugins: Arc<[SgBoxLayer]>,
     pub rules: Arc<[SgHttpRouteRuleLayer]>,
     pub priority: i16,
@@ -45,6 +50,7 @@ impl SgHttpRoute {
 #[derive(Debug, Clone)]
 pub struct SgHttpRouter {
     pub hostnames: Arc<[String]>,
+    pub listeners: Arc<[String]>,
     pub rules: Arc<[Option<Arc<[Arc<SgHttpRouteMatch>]>>]>,
 }
 
@@ -59,7 +65,9 @@ pub struct SgHttpRouteRuleLayer {
     pub r#match: Option<Arc<[Arc<SgHttpRouteMatch>]>>,
     pub plugins: Arc<[SgBoxLayer]>,
     timeouts: Option<Duration>,
+    backend_timeouts: BackendTimeouts,
     backends: Arc<[SgHttpBackendLayer]>,
+    canary: Option<Arc<SgHttpCanary>>,
 }
 
 impl SgHttpRouteRuleLayer {
@@ -80,12 +88,21 @@ where
         let empty = self.backends.is_empty();
         let filter_layer = self.plugins.iter().collect::<SgBoxLayer>();
 
+        let timeouts_layer = MapRequestLayer::new(set_backend_timeouts(self.backend_timeouts));
         let service = if empty {
-            filter_layer.layer(TimeoutLayer::new(self.timeouts).layer(inner))
+            filter_layer.layer(TimeoutLayer::new(self.timeouts).layer(timeouts_layer.layer(inner)))
         } else {
-            let service_iter = self.backends.iter().map(|l| (l.weight, l.layer(inner.clone())));
-            let random_picker = random_pick::RandomPick::new(service_iter);
-            filter_layer.layer(TimeoutLayer::new(self.timeouts).layer(random_picker))
+            let service_iter = self.backends.iter().map(|l| (l.version.clone(), l.weight, l.layer(inner.clone())));
+            match &self.canary {
+                Some(canary) => {
+                    let canary_picker = CanaryPick::new(canary.clone(), service_iter);
+                    filter_layer.layer(TimeoutLayer::new(self.timeouts).layer(timeouts_layer.layer(canary_picker)))
+                }
+                None => {
+                    let random_picker = random_pick::RandomPick::new(service_iter.map(|(_, weight, service)| (weight, service)));
+                    filter_layer.layer(TimeoutLayer::new(self.timeouts).layer(timeouts_layer.layer(random_picker)))
+                }
+            }
         };
 
         SgRouteRule {
@@ -116,6 +133,17 @@ impl hyper::service::Service<Request<SgBody>> for SgRouteRule {
     }
 }
 
+/// Merge backend timeouts into request extension, the inner one takes precedence.
+fn set_backend_timeouts(timeouts: BackendTimeouts) -> impl Fn(Request<SgBody>) -> Request<SgBody> + Clone {
+    move |mut req: Request<SgBody>| {
+        if !timeouts.is_empty() {
+            let merged = req.extensions().get::<BackendTimeouts>().copied().unwrap_or_default().merge(timeouts);
+            req.extensions_mut().insert(merged);
+        }
+        req
+    }
+}
+
 /****************************************************************************************
 
                                         Backend
@@ -129,7 +157,10 @@ pub struct SgHttpBackendLayer {
     pub port: Option<NonZeroU16>,
     pub scheme: Option<Arc<str>>,
     pub weight: u16,
+    /// Version label, see [`SgHttpCanary`].
+    pub version: Option<Arc<str>>,
     pub timeout: Option<Duration>,
+    pub backend_timeouts: BackendTimeouts,
 }
 
 impl SgHttpBackendLayer {
@@ -147,7 +178,8 @@ where
 
     fn layer(&self, inner: S) -> Self::Service {
         let timeout_layer = crate::helper_layers::timeout::TimeoutLayer::new(self.timeout);
-        let filtered = self.filters.iter().collect::<SgBoxLayer>().layer(timeout_layer.layer(inner));
+        let timeouts_layer = MapRequestLayer::new(set_backend_timeouts(self.backend_timeouts));
+        let filtered = self.filters.iter().collect::<SgBoxLayer>().layer(timeout_layer.layer(timeouts_layer.layer(inner)));
         SgHttpBackend {
             weight: self.weight,
             host: self.host.clone(),
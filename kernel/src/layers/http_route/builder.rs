@@ -1,14 +1,16 @@
 use std::{num::NonZeroU16, sync::Arc, time::Duration};
 
+use crate::extension::BackendTimeouts;
 use crate::BoxError;
 
 use crate::SgBoxLayer;
 
-use super::{match_request::SgHttpRouteMatch, SgHttpBackendLayer, SgHttpRoute, SgHttpRouteRuleLayer};
+use super::{canary::SgHttpCanary, match_request::SgHttpRouteMatch, SgHttpBackendLayer, SgHttpRoute, SgHttpRouteRuleLayer};
 
 #[derive(Debug)]
 pub struct SgHttpRouteLayerBuilder {
     pub hostnames: Vec<String>,
+    pub listeners: Vec<String>,
     pub rules: Vec<SgHttpRouteRuleLayer>,
     pub plugins: Vec<SgBoxLayer>,
     pub priority: Option<i16>,
@@ -25,6 +27,7 @@ impl SgHttpRouteLayerBuilder {
     pub fn new() -> Self {
         Self {
             hostnames: Vec::new(),
+            listeners: Vec::new(),
             rules: Vec::new(),
             plugins: Vec::new(),
             priority: None,
@@ -35,6 +38,11 @@ impl SgHttpRouteLayerBuilder {
         self.hostnames = hostnames.into_iter().collect();
         self
     }
+    /// Attach the route to the listeners of these names only, it's attached to all listeners by default.
+    pub fn listeners(mut self, listeners: impl IntoIterator<Item = String>) -> Self {
+        self.listeners = listeners.into_iter().collect();
+        self
+    }
     pub fn rule(mut self, rule: SgHttpRouteRuleLayer) -> Self {
         self.rules.push(rule);
         self
@@ -66,6 +74,7 @@ impl SgHttpRouteLayerBuilder {
         Ok(SgHttpRoute {
             plugins: Arc::from(self.plugins),
             hostnames: self.hostnames.into(),
+            listeners: self.listeners.into(),
             rules: self.rules.into(),
             priority: self.priority.unwrap_or(1),
         })
@@ -77,7 +86,9 @@ pub struct SgHttpRouteRuleLayerBuilder {
     r#match: Option<Vec<SgHttpRouteMatch>>,
     pub plugins: Vec<SgBoxLayer>,
     timeouts: Option<Duration>,
+    backend_timeouts: BackendTimeouts,
     backends: Vec<SgHttpBackendLayer>,
+    canary: Option<SgHttpCanary>,
     pub extensions: hyper::http::Extensions,
 }
 impl Default for SgHttpRouteRuleLayerBuilder {
@@ -92,7 +103,9 @@ impl SgHttpRouteRuleLayerBuilder {
             r#match: None,
             plugins: Vec::new(),
             timeouts: None,
+            backend_timeouts: BackendTimeouts::default(),
             backends: Vec::new(),
+            canary: None,
             extensions: Default::default(),
         }
     }
@@ -112,10 +125,31 @@ impl SgHttpRouteRuleLayerBuilder {
         self.plugins.extend(plugins);
         self
     }
+    /// Timeout of a request to get the response, streaming the response body is not limited, see [`SgHttpRouteRuleLayerBuilder::deadline`].
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeouts = Some(timeout);
         self
     }
+    /// Default connect timeout for backends of this rule, see [`BackendTimeouts`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.connect = Some(timeout);
+        self
+    }
+    /// Default response header timeout for backends of this rule, see [`BackendTimeouts`].
+    pub fn response_header_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.response_header = Some(timeout);
+        self
+    }
+    /// Default idle stream timeout for backends of this rule, see [`BackendTimeouts`].
+    pub fn idle_stream_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.idle_stream = Some(timeout);
+        self
+    }
+    /// Default overall deadline for backends of this rule, including streaming the response body, see [`BackendTimeouts`].
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.backend_timeouts.deadline = Some(deadline);
+        self
+    }
     pub fn backend(mut self, backend: SgHttpBackendLayer) -> Self {
         self.backends.push(backend);
         self
@@ -124,13 +158,23 @@ impl SgHttpRouteRuleLayerBuilder {
         self.backends.extend(backend);
         self
     }
+    /// Pin users to canary or stable backends instead of picking backends randomly per request, see [`SgHttpCanary`].
+    pub fn canary(mut self, canary: SgHttpCanary) -> Self {
+        self.canary = Some(canary);
+        self
+    }
     pub fn build(self) -> Result<SgHttpRouteRuleLayer, BoxError> {
+        if self.canary.as_ref().is_some_and(|canary| canary.percentage > 100) {
+            return Err("canary percentage must be within 0 to 100".into());
+        }
         let r#match = self.r#match.map(|ms| ms.into_iter().map(Arc::new).collect());
         Ok(SgHttpRouteRuleLayer {
             r#match,
             plugins: Arc::from(self.plugins),
             timeouts: self.timeouts,
+            backend_timeouts: self.backend_timeouts,
             backends: Arc::from_iter(self.backends),
+            canary: self.canary.map(Arc::new),
         })
     }
     pub fn ext(mut self, extension: hyper::http::Extensions) -> Self {
@@ -146,7 +190,9 @@ pub struct SgHttpBackendLayerBuilder {
     protocol: Option<String>,
     pub plugins: Vec<SgBoxLayer>,
     timeout: Option<Duration>,
+    backend_timeouts: BackendTimeouts,
     weight: u16,
+    version: Option<String>,
     pub extensions: hyper::http::Extensions,
 }
 
@@ -158,7 +204,9 @@ impl Default for SgHttpBackendLayerBuilder {
             protocol: None,
             plugins: Vec::new(),
             timeout: None,
+            backend_timeouts: BackendTimeouts::default(),
             weight: 1,
+            version: None,
             extensions: Default::default(),
         }
     }
@@ -176,14 +224,36 @@ impl SgHttpBackendLayerBuilder {
         self.plugins.extend(plugins);
         self
     }
+    /// Timeout of a request to this backend to get the response, streaming the response body is not limited.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
         self
     }
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.connect = Some(timeout);
+        self
+    }
+    pub fn response_header_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.response_header = Some(timeout);
+        self
+    }
+    pub fn idle_stream_timeout(mut self, timeout: Duration) -> Self {
+        self.backend_timeouts.idle_stream = Some(timeout);
+        self
+    }
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.backend_timeouts.deadline = Some(deadline);
+        self
+    }
     pub fn weight(mut self, weight: u16) -> Self {
         self.weight = weight;
         self
     }
+    /// Version label of the backend, used by canary routing of the rule.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
     pub fn host(mut self, host: impl Into<String>) -> Self {
         self.host = Some(host.into());
         self
@@ -207,7 +277,9 @@ impl SgHttpBackendLayerBuilder {
             scheme: self.protocol.map(Into::into),
             filters: Arc::from(self.plugins),
             timeout: self.timeout,
+            backend_timeouts: self.backend_timeouts,
             weight: self.weight,
+            version: self.version.map(Into::into),
         })
     }
 }
@@ -0,0 +1,302 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use futures_util::future::BoxFuture;
+use hyper::{
+    header::{HeaderName, HeaderValue, COOKIE, SET_COOKIE},
+    Request, Response,
+};
+use rand::Rng;
+
+use crate::{helper_layers::random_pick::RandomPick, utils::query_kv::QueryKvIter, SgBody};
+
+const CANARY: &str = "canary";
+const STABLE: &str = "stable";
+
+/// Where the user identifier of canary routing is read from.
+#[derive(Debug, Clone)]
+pub enum SgCanaryKey {
+    Header(HeaderName),
+    Cookie(String),
+    Query(String),
+}
+
+/// Cookie set by the gateway to keep a user on the picked side, its value is `<version>:canary` or `<version>:stable`.
+#[derive(Debug, Clone)]
+pub struct SgCanaryCookie {
+    pub name: String,
+    /// Unset means a session cookie.
+    pub max_age: Option<Duration>,
+}
+
+/// Canary routing of a rule, users are pinned to either the canary version or the stable backends,
+/// instead of bouncing between backends picked randomly by weight per request.
+///
+/// The side of a request is decided in order by
+/// 1. the override header, whose value names a version to force,
+/// 2. the sticky cookie, unless it's issued for another canary version, or it pins to canary while the percentage is 0,
+/// 3. the hash of the user identifier,
+/// 4. randomly by the percentage.
+///
+/// Backends are still picked by weight within a side.
+#[derive(Debug, Clone)]
+pub struct SgHttpCanary {
+    /// Version of canary backends, backends of other versions or without version are stable.
+    pub version: Arc<str>,
+    /// Percentage of users pinned to the canary version, from 0 to 100.
+    pub percentage: u8,
+    pub key: Option<SgCanaryKey>,
+    pub override_header: Option<HeaderName>,
+    pub sticky_cookie: Option<SgCanaryCookie>,
+}
+
+impl SgHttpCanary {
+    /// Whether the user identifier falls into the canary percentage.
+    ///
+    /// It is a FNV-1a hash salted by the version, so it's the same across gateway instances and restarts,
+    /// and a new canary version samples other users.
+    pub fn is_canary_key(&self, key: &str) -> bool {
+        let hash = self.version.bytes().chain([b':']).chain(key.bytes()).fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+        hash % 100 < self.percentage as u64
+    }
+
+    fn key<'a>(&self, req: &'a Request<SgBody>) -> Option<&'a str> {
+        match self.key.as_ref()? {
+            SgCanaryKey::Header(name) => req.headers().get(name)?.to_str().ok(),
+            SgCanaryKey::Cookie(name) => cookie(req, name),
+            SgCanaryKey::Query(name) => QueryKvIter::new(req.uri().query()?).find_map(|(k, v)| (k == name).then_some(v).flatten()),
+        }
+        .filter(|key| !key.is_empty())
+    }
+
+    /// The side recorded by the sticky cookie, the cookie is ignored and issued again if it's stale.
+    fn sticky_side(&self, value: &str) -> Option<bool> {
+        let (version, side) = value.rsplit_once(':')?;
+        if version != &*self.version {
+            return None;
+        }
+        match side {
+            // rolled back, users pinned to canary are sampled again
+            CANARY => (self.percentage > 0).then_some(true),
+            STABLE => Some(false),
+            _ => None,
+        }
+    }
+
+    fn set_cookie(&self, cookie: &SgCanaryCookie, is_canary: bool) -> Option<HeaderValue> {
+        let side = if is_canary { CANARY } else { STABLE };
+        let max_age = cookie.max_age.map(|max_age| format!("; Max-Age={}", max_age.as_secs())).unwrap_or_default();
+        HeaderValue::from_str(&format!("{}={}:{side}; Path=/; HttpOnly; SameSite=Lax{max_age}", cookie.name, self.version)).ok()
+    }
+}
+
+fn cookie<'a>(req: &'a Request<SgBody>, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|kv| kv.trim().split_once('='))
+        .find_map(|(k, v)| (k == name).then_some(v))
+}
+
+/// Pick backends by [`SgHttpCanary`], a side without backends falls back to all backends.
+#[derive(Clone)]
+pub struct CanaryPick<S> {
+    canary: Arc<SgHttpCanary>,
+    all: RandomPick<u16, S>,
+    canary_backends: Option<RandomPick<u16, S>>,
+    stable_backends: Option<RandomPick<u16, S>>,
+    versions: Arc<[(Arc<str>, RandomPick<u16, S>)]>,
+}
+
+impl<S: Clone> CanaryPick<S> {
+    /// `services` are versions, weights and services of backends.
+    pub fn new(canary: Arc<SgHttpCanary>, services: impl IntoIterator<Item = (Option<Arc<str>>, u16, S)>) -> Self {
+        let services = services.into_iter().collect::<Vec<_>>();
+        let pick = |filter: &dyn Fn(Option<&str>) -> bool| {
+            let group =
+                services.iter().filter(|(version, weight, _)| *weight > 0 && filter(version.as_deref())).map(|(_, weight, service)| (*weight, service.clone())).collect::<Vec<_>>();
+            (!group.is_empty()).then(|| RandomPick::new(group))
+        };
+        let canary_backends = pick(&|version| version == Some(&*canary.version));
+        let stable_backends = pick(&|version| version != Some(&*canary.version));
+        let mut versions: Vec<(Arc<str>, RandomPick<u16, S>)> = Vec::new();
+        for version in services.iter().filter_map(|(version, _, _)| version.clone()) {
+            if versions.iter().all(|(picked, _)| *picked != version) {
+                if let Some(backends) = pick(&|v| v == Some(&*version)) {
+                    versions.push((version, backends));
+                }
+            }
+        }
+        Self {
+            all: RandomPick::new(services.into_iter().map(|(_, weight, service)| (weight, service))),
+            canary,
+            canary_backends,
+            stable_backends,
+            versions: versions.into(),
+        }
+    }
+}
+
+impl<S> hyper::service::Service<Request<SgBody>> for CanaryPick<S>
+where
+    S: hyper::service::Service<Request<SgBody>, Response = Response<SgBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<SgBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, req: Request<SgBody>) -> Self::Future {
+        let canary = &self.canary;
+        let forced = canary.override_header.as_ref().and_then(|name| req.headers().get(name)).and_then(|v| v.to_str().ok());
+        if let Some((version, backends)) = forced.and_then(|forced| self.versions.iter().find(|(version, _)| &**version == forced)) {
+            tracing::trace!(%version, "[Sg.Canary] forced by header");
+            return Box::pin(backends.call(req));
+        }
+        let sticky = canary.sticky_cookie.as_ref().and_then(|sticky| cookie(&req, &sticky.name)).and_then(|value| canary.sticky_side(value));
+        let (is_canary, set_cookie) = match sticky {
+            Some(is_canary) => (is_canary, None),
+            None => {
+                let is_canary = match canary.key(&req) {
+                    Some(key) => canary.is_canary_key(key),
+                    None => rand::thread_rng().gen_range(0..100) < canary.percentage,
+                };
+                (is_canary, canary.sticky_cookie.as_ref().and_then(|sticky| canary.set_cookie(sticky, is_canary)))
+            }
+        };
+        let backends = if is_canary { &self.canary_backends } else { &self.stable_backends };
+        tracing::trace!(is_canary, "[Sg.Canary] picked");
+        let fut = backends.as_ref().unwrap_or(&self.all).call(req);
+        Box::pin(async move {
+            let mut resp = fut.await?;
+            if let Some(set_cookie) = set_cookie {
+                resp.headers_mut().append(SET_COOKIE, set_cookie);
+            }
+            Ok(resp)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{convert::Infallible, sync::Arc};
+
+    use hyper::{
+        header::{HeaderName, COOKIE, SET_COOKIE},
+        service::Service,
+        Request, Response,
+    };
+
+    use super::{CanaryPick, SgCanaryCookie, SgCanaryKey, SgHttpCanary};
+    use crate::{SgBody, SgResponseExt};
+
+    #[derive(Clone)]
+    struct Version(&'static str);
+
+    impl Service<Request<SgBody>> for Version {
+        type Response = Response<SgBody>;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Response<SgBody>, Infallible>>;
+
+        fn call(&self, _req: Request<SgBody>) -> Self::Future {
+            std::future::ready(Ok(Response::with_code_message(hyper::StatusCode::OK, self.0)))
+        }
+    }
+
+    async fn call(pick: &CanaryPick<Version>, req: hyper::http::request::Builder) -> (String, Option<String>) {
+        let resp = pick.call(req.body(SgBody::empty()).expect("invalid request")).await.expect("infallible");
+        let set_cookie = resp.headers().get(SET_COOKIE).map(|v| v.to_str().expect("invalid cookie").to_string());
+        let body = resp.into_body().dump().await.expect("fail to dump");
+        (String::from_utf8_lossy(body.get_dumped().expect("not dumped")).to_string(), set_cookie)
+    }
+
+    #[tokio::test]
+    async fn test_canary_pick() {
+        let canary = SgHttpCanary {
+            version: "v2".into(),
+            percentage: 30,
+            key: Some(SgCanaryKey::Header(HeaderName::from_static("x-user-id"))),
+            override_header: Some(HeaderName::from_static("x-version")),
+            sticky_cookie: Some(SgCanaryCookie {
+                name: "sg_canary".to_string(),
+                max_age: None,
+            }),
+        };
+        let pick = CanaryPick::new(
+            Arc::new(canary.clone()),
+            [(Some("v1".into()), 1, Version("v1")), (Some("v2".into()), 1, Version("v2")), (None, 0, Version("v0"))],
+        );
+
+        // users are pinned deterministically by the identifier
+        let users = (0..1000).map(|user| user.to_string()).collect::<Vec<_>>();
+        let pinned = users.iter().filter(|user| canary.is_canary_key(user)).count();
+        assert!((200..400).contains(&pinned), "{pinned}");
+        for user in users.iter().take(20) {
+            let expected = if canary.is_canary_key(user) { "v2" } else { "v1" };
+            for _ in 0..3 {
+                let (version, set_cookie) = call(&pick, Request::builder().header("x-user-id", user)).await;
+                assert_eq!(version, expected);
+                assert_eq!(
+                    set_cookie,
+                    Some(format!(
+                        "sg_canary=v2:{}; Path=/; HttpOnly; SameSite=Lax",
+                        if expected == "v2" { "canary" } else { "stable" }
+                    ))
+                );
+            }
+        }
+
+        // the sticky cookie takes precedence over the identifier, the override header over both
+        let (version, set_cookie) = call(&pick, Request::builder().header(COOKIE, "theme=dark; sg_canary=v2:canary")).await;
+        assert_eq!((version.as_str(), set_cookie), ("v2", None));
+        let (version, _) = call(&pick, Request::builder().header(COOKIE, "sg_canary=v2:stable").header("x-version", "v2")).await;
+        assert_eq!(version, "v2");
+        let (version, set_cookie) = call(&pick, Request::builder().header("x-version", "v1")).await;
+        assert_eq!((version.as_str(), set_cookie), ("v1", None));
+
+        // rolled back, cookies pinning to canary are ignored and issued again
+        let rolled_back = CanaryPick::new(
+            Arc::new(SgHttpCanary { percentage: 0, ..canary.clone() }),
+            [(Some("v1".into()), 1, Version("v1")), (Some("v2".into()), 1, Version("v2"))],
+        );
+        let (version, set_cookie) = call(&rolled_back, Request::builder().header(COOKIE, "sg_canary=v2:canary")).await;
+        assert_eq!(
+            (version.as_str(), set_cookie.as_deref()),
+            ("v1", Some("sg_canary=v2:stable; Path=/; HttpOnly; SameSite=Lax"))
+        );
+        let (version, set_cookie) = call(&rolled_back, Request::builder().header(COOKIE, "sg_canary=v2:stable")).await;
+        assert_eq!((version.as_str(), set_cookie), ("v1", None));
+
+        // a new canary version ignores cookies of the former one, and samples users again
+        let bumped = SgHttpCanary {
+            version: "v3".into(),
+            ..canary.clone()
+        };
+        let pick_bumped = CanaryPick::new(Arc::new(bumped.clone()), [(Some("v1".into()), 1, Version("v1")), (Some("v3".into()), 1, Version("v3"))]);
+        for user in users.iter().take(20) {
+            let expected = if bumped.is_canary_key(user) { "v3" } else { "v1" };
+            let (version, set_cookie) = call(&pick_bumped, Request::builder().header(COOKIE, "sg_canary=v2:canary").header("x-user-id", user)).await;
+            assert_eq!(version, expected);
+            assert_eq!(
+                set_cookie,
+                Some(format!(
+                    "sg_canary=v3:{}; Path=/; HttpOnly; SameSite=Lax",
+                    if expected == "v3" { "canary" } else { "stable" }
+                ))
+            );
+        }
+
+        let by_query = SgHttpCanary {
+            key: Some(SgCanaryKey::Query("uid".to_string())),
+            sticky_cookie: None,
+            ..canary.clone()
+        };
+        let pick = CanaryPick::new(Arc::new(by_query.clone()), [(Some("v1".into()), 1, Version("v1")), (Some("v2".into()), 1, Version("v2"))]);
+        for user in users.iter().take(20) {
+            let expected = if by_query.is_canary_key(user) { "v2" } else { "v1" };
+            let (version, _) = call(&pick, Request::builder().uri(format!("http://sg.idealworld.group/?a=1&uid={user}"))).await;
+            assert_eq!(version, expected);
+        }
+    }
+}
@@ -1,8 +1,20 @@
+use std::{net::IpAddr, str::FromStr};
+
+use hyper::header::COOKIE;
+use ipnet::IpNet;
 use regex::Regex;
 
-use crate::{utils::query_kv::QueryKvIter, Request, SgBody};
+use crate::{
+    extension::{PathCaptures, PeerAddr, TlsServerName},
+    utils::query_kv::QueryKvIter,
+    BoxError, Request, SgBody,
+};
+
+use super::{path_template::SgPathTemplate, predicate::SgMatchExpr};
 
 /// PathMatchType specifies the semantics of how HTTP paths should be compared.
+///
+/// Paths are matched with case sensitivity, unless [`SgHttpRouteMatch::case_insensitive`] is set.
 #[derive(Debug, Clone)]
 pub enum SgHttpPathMatch {
     /// Matches the URL path exactly and with case sensitivity.
@@ -11,7 +23,58 @@ pub enum SgHttpPathMatch {
     /// A path element refers to the list of labels in the path split by the / separator. When specified, a trailing / is ignored.
     Prefix(String),
     /// Matches if the URL path matches the given regular expression with case sensitivity.
+    /// Named groups are captured as path parameters.
     Regular(Regex),
+    /// Matches if the URL path matches the given template, see [`SgPathTemplate`].
+    Template(SgPathTemplate),
+}
+
+impl SgHttpPathMatch {
+    /// Whether the path matches, a regular expression decides its case sensitivity by itself, e.g. by the `(?i)` flag.
+    pub fn is_match(&self, path: &str, case_insensitive: bool) -> bool {
+        let eq = |a: &str, b: &str| if case_insensitive { a.eq_ignore_ascii_case(b) } else { a == b };
+        match self {
+            SgHttpPathMatch::Exact(expected) => eq(path, expected),
+            SgHttpPathMatch::Prefix(prefix) => {
+                let mut path_segments = path.split('/').filter(|s| !s.is_empty());
+                let mut prefix_segments = prefix.split('/').filter(|s| !s.is_empty());
+                loop {
+                    match (path_segments.next(), prefix_segments.next()) {
+                        (Some(path_seg), Some(prefix_seg)) => {
+                            if !eq(path_seg, prefix_seg) {
+                                return false;
+                            }
+                        }
+                        (_, None) => return true,
+                        (None, Some(_)) => return false,
+                    }
+                }
+            }
+            SgHttpPathMatch::Regular(regex) => regex.is_match(path),
+            SgHttpPathMatch::Template(template) => template.match_path(path, case_insensitive, |_, _| {}),
+        }
+    }
+
+    /// Parameters captured from the path, `None` if there is nothing to capture or the path doesn't match.
+    pub fn captures(&self, path: &str, case_insensitive: bool) -> Option<PathCaptures> {
+        match self {
+            SgHttpPathMatch::Template(template) => {
+                let mut captures = PathCaptures::default();
+                template.match_path(path, case_insensitive, |name, value| captures.push(name.clone(), value)).then_some(captures)
+            }
+            SgHttpPathMatch::Regular(regex) if regex.capture_names().flatten().next().is_some() => {
+                let captured = regex.captures(path)?;
+                let mut captures = PathCaptures::default();
+                for name in regex.capture_names().flatten() {
+                    if let Some(value) = captured.name(name) {
+                        captures.push(name.into(), value.as_str());
+                    }
+                }
+                Some(captures)
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -20,6 +83,28 @@ pub enum SgHttpHeaderMatchPolicy {
     Exact(String),
     /// Matches if the Http header matches the given regular expression with case sensitivity.
     Regular(Regex),
+    /// Matches if the HTTP header is present, whatever its value is.
+    Present,
+    /// Matches if the HTTP header is absent.
+    Absent,
+    /// Matches if the HTTP header is absent or not equal to the value.
+    NotExact(String),
+    /// Matches if the HTTP header is absent or doesn't match the given regular expression.
+    NotRegular(Regex),
+}
+
+impl SgHttpHeaderMatchPolicy {
+    /// Match the values of a header, cookie or query parameter, `values` is empty if it's absent.
+    fn match_values<'a>(&self, mut values: impl Iterator<Item = &'a str>) -> bool {
+        match self {
+            Self::Exact(expected) => values.any(|v| v == expected),
+            Self::Regular(regex) => values.any(|v| regex.is_match(v)),
+            Self::Present => values.next().is_some(),
+            Self::Absent => values.next().is_none(),
+            Self::NotExact(expected) => !values.any(|v| v == expected),
+            Self::NotRegular(regex) => !values.any(|v| regex.is_match(v)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +120,14 @@ pub enum SgHttpQueryMatchPolicy {
     Exact(String),
     /// Matches if the Http query parameter matches the given regular expression with case sensitivity.
     Regular(Regex),
+    /// Matches if the HTTP query parameter is present, even without a value.
+    Present,
+    /// Matches if the HTTP query parameter is absent.
+    Absent,
+    /// Matches if the HTTP query parameter is absent or not equal to the value.
+    NotExact(String),
+    /// Matches if the HTTP query parameter is absent or doesn't match the given regular expression.
+    NotRegular(Regex),
 }
 
 #[derive(Debug, Clone)]
@@ -43,10 +136,53 @@ pub struct SgHttpQueryMatch {
     pub policy: SgHttpQueryMatchPolicy,
 }
 
+/// Cookies are matched by the same policies as headers.
+pub type SgHttpCookieMatchPolicy = SgHttpHeaderMatchPolicy;
+
+#[derive(Debug, Clone)]
+pub struct SgHttpCookieMatch {
+    /// Name of the cookie, matched with case sensitivity.
+    pub name: String,
+    pub policy: SgHttpCookieMatchPolicy,
+}
+
 #[derive(Default, Debug, Clone)]
 
 pub struct SgHttpMethodMatch(pub String);
 
+/// Matches if the peer address of the connection is in the network.
+#[derive(Debug, Clone)]
+pub struct SgHttpClientIpMatch(pub IpNet);
+
+impl SgHttpClientIpMatch {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.0, ip) {
+            // ipv4 clients of a dual stack listener come as ipv4-mapped ipv6 addresses
+            (IpNet::V4(net), IpAddr::V6(v6)) => v6.to_ipv4_mapped().is_some_and(|v4| net.contains(&v4)),
+            (net, ip) => net.contains(&ip),
+        }
+    }
+}
+
+/// Parses a network like `10.0.0.0/8`, a plain address is a network of itself.
+impl FromStr for SgHttpClientIpMatch {
+    type Err = BoxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.parse::<IpNet>() {
+            Ok(net) => Ok(Self(net)),
+            Err(e) => s.parse::<IpAddr>().map(|addr| Self(addr.into())).map_err(|_| e.into()),
+        }
+    }
+}
+
+/// Matches the server name indicated by the TLS client, `*.example.com` matches subdomains of `example.com`.
+///
+/// Plain connections have no server name, so they never match.
+#[derive(Debug, Clone)]
+pub struct SgHttpSniMatch(pub String);
+
 /// HTTPRouteMatch defines the predicate used to match requests to a given action.
 /// Multiple match types are ANDed together, i.e. the match will evaluate to true only if all conditions are satisfied.
 #[derive(Default, Debug, Clone)]
@@ -63,6 +199,18 @@ pub struct SgHttpRouteMatch {
     /// Method specifies HTTP method matcher.
     /// When specified, this route will be matched only if the request has the specified method.
     pub method: Option<Vec<SgHttpMethodMatch>>,
+    /// Cookie specifies HTTP cookie matchers.
+    pub cookie: Option<Vec<SgHttpCookieMatch>>,
+    /// ClientIp specifies networks of the client, the route is matched if the client is in any of them.
+    pub client_ip: Option<Vec<SgHttpClientIpMatch>>,
+    /// Sni specifies TLS server names, the route is matched if the client indicated any of them.
+    pub sni: Option<Vec<SgHttpSniMatch>>,
+    /// Expr specifies a compound condition over request attributes, see [`SgMatchExpr`].
+    pub expr: Option<SgMatchExpr>,
+    /// Match the path ignoring ASCII case, paths are matched with case sensitivity by default as the Gateway API specifies.
+    ///
+    /// Regular expressions are compiled beforehand, so they should be built case insensitive as well, e.g. by the `(?i)` flag.
+    pub case_insensitive: bool,
 }
 
 pub trait MatchRequest {
@@ -72,39 +220,21 @@ pub trait MatchRequest {
 impl MatchRequest for SgHttpQueryMatch {
     fn match_request(&self, req: &Request<SgBody>) -> bool {
         let query = req.uri().query();
-        if let Some(query) = query {
-            let mut iter = QueryKvIter::new(query);
-            match &self.policy {
-                SgHttpQueryMatchPolicy::Exact(query) => iter.any(|(k, v)| k == self.name && v == Some(query)),
-                SgHttpQueryMatchPolicy::Regular(query) => iter.any(|(k, v)| k == self.name && v.map_or(false, |v| query.is_match(v))),
-            }
-        } else {
-            false
+        let mut iter = QueryKvIter::new(query.unwrap_or_default()).filter(|(k, _)| *k == self.name);
+        match &self.policy {
+            SgHttpQueryMatchPolicy::Exact(query) => iter.any(|(_, v)| v == Some(query)),
+            SgHttpQueryMatchPolicy::Regular(query) => iter.any(|(_, v)| v.is_some_and(|v| query.is_match(v))),
+            SgHttpQueryMatchPolicy::Present => iter.next().is_some(),
+            SgHttpQueryMatchPolicy::Absent => iter.next().is_none(),
+            SgHttpQueryMatchPolicy::NotExact(query) => !iter.any(|(_, v)| v == Some(query)),
+            SgHttpQueryMatchPolicy::NotRegular(query) => !iter.any(|(_, v)| v.is_some_and(|v| query.is_match(v))),
         }
     }
 }
 
 impl MatchRequest for SgHttpPathMatch {
     fn match_request(&self, req: &Request<SgBody>) -> bool {
-        match self {
-            SgHttpPathMatch::Exact(path) => req.uri().path() == path,
-            SgHttpPathMatch::Prefix(path) => {
-                let mut path_segments = req.uri().path().split('/').filter(|s| !s.is_empty());
-                let mut prefix_segments = path.split('/').filter(|s| !s.is_empty());
-                loop {
-                    match (path_segments.next(), prefix_segments.next()) {
-                        (Some(path_seg), Some(prefix_seg)) => {
-                            if !path_seg.eq_ignore_ascii_case(prefix_seg) {
-                                return false;
-                            }
-                        }
-                        (_, None) => return true,
-                        (None, Some(_)) => return false,
-                    }
-                }
-            }
-            SgHttpPathMatch::Regular(path) => path.is_match(req.uri().path()),
-        }
+        self.is_match(req.uri().path(), false)
     }
 }
 
@@ -113,6 +243,33 @@ impl MatchRequest for SgHttpHeaderMatch {
         match &self.policy {
             SgHttpHeaderMatchPolicy::Exact(header) => req.headers().get(&self.name).is_some_and(|v| v == header),
             SgHttpHeaderMatchPolicy::Regular(header) => req.headers().iter().any(|(k, v)| k.as_str() == self.name && v.to_str().map_or(false, |v| header.is_match(v))),
+            policy => policy.match_values(req.headers().get_all(self.name.as_str()).iter().map(|v| v.to_str().unwrap_or_default())),
+        }
+    }
+}
+
+impl MatchRequest for SgHttpCookieMatch {
+    fn match_request(&self, req: &Request<SgBody>) -> bool {
+        let cookies = req.headers().get_all(COOKIE).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(';'));
+        self.policy.match_values(cookies.filter_map(|kv| kv.trim().split_once('=')).filter_map(|(k, v)| (k == self.name).then_some(v)))
+    }
+}
+
+impl MatchRequest for SgHttpClientIpMatch {
+    fn match_request(&self, req: &Request<SgBody>) -> bool {
+        req.extensions().get::<PeerAddr>().is_some_and(|peer| self.contains(peer.0.ip()))
+    }
+}
+
+impl MatchRequest for SgHttpSniMatch {
+    fn match_request(&self, req: &Request<SgBody>) -> bool {
+        let Some(server_name) = req.extensions().get::<TlsServerName>() else {
+            return false;
+        };
+        let server_name = server_name.as_str();
+        match self.0.strip_prefix("*.") {
+            Some(domain) => server_name.len() > domain.len() + 1 && server_name.ends_with(domain) && server_name[..server_name.len() - domain.len()].ends_with('.'),
+            None => server_name.eq_ignore_ascii_case(&self.0),
         }
     }
 }
@@ -125,7 +282,93 @@ impl MatchRequest for SgHttpMethodMatch {
 
 impl MatchRequest for SgHttpRouteMatch {
     fn match_request(&self, req: &Request<SgBody>) -> bool {
-        self.path.match_request(req) && self.header.match_request(req) && self.query.match_request(req) && self.method.match_request(req)
+        let path_matched = match &self.path {
+            Some(path) => path.is_match(req.uri().path(), self.case_insensitive),
+            None => true,
+        };
+        path_matched
+            && self.header.match_request(req)
+            && self.query.match_request(req)
+            && self.method.match_request(req)
+            && self.cookie.match_request(req)
+            && self.client_ip.match_request(req)
+            && self.sni.match_request(req)
+            && self.expr.match_request(req)
+    }
+}
+
+/// Result of a predicate of a route match, see [`SgHttpRouteMatch::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SgPredicateExplain {
+    /// Kind of the predicate, e.g. `path`, `header` or `expr`.
+    pub kind: &'static str,
+    /// The predicate in a human readable form, e.g. `Prefix /api`.
+    pub predicate: String,
+    pub passed: bool,
+}
+
+fn describe_policy(policy: &SgHttpHeaderMatchPolicy) -> String {
+    match policy {
+        SgHttpHeaderMatchPolicy::Exact(value) => format!("Exact {value:?}"),
+        SgHttpHeaderMatchPolicy::Regular(regex) => format!("Regular {:?}", regex.as_str()),
+        SgHttpHeaderMatchPolicy::Present => "Present".to_string(),
+        SgHttpHeaderMatchPolicy::Absent => "Absent".to_string(),
+        SgHttpHeaderMatchPolicy::NotExact(value) => format!("NotExact {value:?}"),
+        SgHttpHeaderMatchPolicy::NotRegular(regex) => format!("NotRegular {:?}", regex.as_str()),
+    }
+}
+
+impl SgHttpRouteMatch {
+    /// Evaluate every predicate of the match, instead of stopping at the first failed one as [`MatchRequest::match_request`] does.
+    ///
+    /// Headers, query parameters and cookies are listed one by one, methods, client networks and server names are listed as a whole.
+    pub fn explain(&self, req: &Request<SgBody>) -> Vec<SgPredicateExplain> {
+        let mut predicates = Vec::new();
+        let mut push = |kind, predicate, passed| predicates.push(SgPredicateExplain { kind, predicate, passed });
+        if let Some(path) = &self.path {
+            let predicate = match path {
+                SgHttpPathMatch::Exact(path) => format!("Exact {path}"),
+                SgHttpPathMatch::Prefix(path) => format!("Prefix {path}"),
+                SgHttpPathMatch::Regular(regex) => format!("Regular {}", regex.as_str()),
+                SgHttpPathMatch::Template(template) => format!("Template {template}"),
+            };
+            let predicate = if self.case_insensitive { format!("{predicate} (case insensitive)") } else { predicate };
+            push("path", predicate, path.is_match(req.uri().path(), self.case_insensitive));
+        }
+        for header in self.header.iter().flatten() {
+            push("header", format!("{} {}", header.name, describe_policy(&header.policy)), header.match_request(req));
+        }
+        for query in self.query.iter().flatten() {
+            let policy = match &query.policy {
+                SgHttpQueryMatchPolicy::Exact(value) => format!("Exact {value:?}"),
+                SgHttpQueryMatchPolicy::Regular(regex) => format!("Regular {:?}", regex.as_str()),
+                SgHttpQueryMatchPolicy::Present => "Present".to_string(),
+                SgHttpQueryMatchPolicy::Absent => "Absent".to_string(),
+                SgHttpQueryMatchPolicy::NotExact(value) => format!("NotExact {value:?}"),
+                SgHttpQueryMatchPolicy::NotRegular(regex) => format!("NotRegular {:?}", regex.as_str()),
+            };
+            push("query", format!("{} {policy}", query.name), query.match_request(req));
+        }
+        if let Some(methods) = &self.method {
+            push("method", methods.iter().map(|m| m.0.as_str()).collect::<Vec<_>>().join(" | "), methods.match_request(req));
+        }
+        for cookie in self.cookie.iter().flatten() {
+            push("cookie", format!("{} {}", cookie.name, describe_policy(&cookie.policy)), cookie.match_request(req));
+        }
+        if let Some(client_ip) = &self.client_ip {
+            push(
+                "client_ip",
+                client_ip.iter().map(|m| m.0.to_string()).collect::<Vec<_>>().join(" | "),
+                client_ip.match_request(req),
+            );
+        }
+        if let Some(sni) = &self.sni {
+            push("sni", sni.iter().map(|m| m.0.as_str()).collect::<Vec<_>>().join(" | "), sni.match_request(req));
+        }
+        if let Some(expr) = &self.expr {
+            push("expr", expr.as_str().to_string(), expr.match_request(req));
+        }
+        predicates
     }
 }
 
@@ -151,4 +394,107 @@ where
 fn test_match_path() {
     let req = Request::builder().uri("https://localhost:8080/child/subApp").body(SgBody::empty()).expect("invalid request");
     assert!(SgHttpPathMatch::Prefix("/child/subApp".into()).match_request(&req));
+    let template = SgHttpPathMatch::Template(SgPathTemplate::new("/child/{app}").expect("invalid template"));
+    assert!(template.match_request(&req));
+    assert_eq!(template.captures(req.uri().path(), false).expect("not captured").get("app"), Some("subApp"));
+    let regex = SgHttpPathMatch::Regular(Regex::new("^/child/(?<app>[a-zA-Z]+)$").expect("invalid regex"));
+    assert_eq!(regex.captures(req.uri().path(), false).expect("not captured").get("app"), Some("subApp"));
+}
+
+/// Path matching cases of the Gateway API conformance tests `HTTPRouteExactPathMatching` and `HTTPRouteMatching`.
+#[test]
+fn test_path_match_conformance() {
+    let exact = SgHttpPathMatch::Exact("/one".into());
+    for (path, matched) in [("/one", true), ("/one/", false), ("/one/example", false), ("/One", false), ("/", false)] {
+        assert_eq!(exact.is_match(path, false), matched, "exact /one with {path}");
+    }
+    let prefix = SgHttpPathMatch::Prefix("/v2".into());
+    for (path, matched) in [
+        ("/v2", true),
+        ("/v2/", true),
+        ("/v2/example", true),
+        ("/v2example", false),
+        ("/V2/example", false),
+        ("/", false),
+    ] {
+        assert_eq!(prefix.is_match(path, false), matched, "prefix /v2 with {path}");
+    }
+    // a trailing slash of the prefix is ignored
+    let prefix = SgHttpPathMatch::Prefix("/v2/".into());
+    assert!(prefix.is_match("/v2", false) && prefix.is_match("/v2/example", false));
+    let root = SgHttpPathMatch::Prefix("/".into());
+    assert!(root.is_match("/", false) && root.is_match("/any/path", false));
+
+    assert!(SgHttpPathMatch::Exact("/one".into()).is_match("/ONE", true));
+    assert!(SgHttpPathMatch::Prefix("/v2".into()).is_match("/V2/example", true));
+    let template = SgHttpPathMatch::Template(SgPathTemplate::new("/users/{id}").expect("invalid template"));
+    assert!(!template.is_match("/Users/42", false));
+    assert_eq!(template.captures("/Users/Alice", true).expect("not captured").get("id"), Some("Alice"));
+}
+
+#[test]
+fn test_match_predicates() {
+    let req = Request::builder()
+        .uri("/?debug&tenant=acme")
+        .header("x-canary", "on")
+        .header("cookie", "theme=dark; session=s1")
+        .extension(PeerAddr("[::ffff:10.1.2.3]:4567".parse().expect("invalid addr")))
+        .extension(TlsServerName("api.example.com".into()))
+        .body(SgBody::empty())
+        .expect("invalid request");
+    let header = |name: &str, policy| SgHttpHeaderMatch { name: name.into(), policy };
+    assert!(header("x-canary", SgHttpHeaderMatchPolicy::Present).match_request(&req));
+    assert!(header("x-absent", SgHttpHeaderMatchPolicy::Absent).match_request(&req));
+    assert!(!header("x-canary", SgHttpHeaderMatchPolicy::NotExact("on".into())).match_request(&req));
+    assert!(header("x-absent", SgHttpHeaderMatchPolicy::NotRegular(Regex::new(".*").expect("invalid regex"))).match_request(&req));
+    let query = |name: &str, policy| SgHttpQueryMatch { name: name.into(), policy };
+    assert!(query("debug", SgHttpQueryMatchPolicy::Present).match_request(&req));
+    assert!(!query("tenant", SgHttpQueryMatchPolicy::Absent).match_request(&req));
+    assert!(query("tenant", SgHttpQueryMatchPolicy::NotExact("other".into())).match_request(&req));
+    let cookie = |name: &str, policy| SgHttpCookieMatch { name: name.into(), policy };
+    assert!(cookie("session", SgHttpCookieMatchPolicy::Exact("s1".into())).match_request(&req));
+    assert!(!cookie("Session", SgHttpCookieMatchPolicy::Present).match_request(&req));
+    assert!("10.0.0.0/8".parse::<SgHttpClientIpMatch>().expect("invalid cidr").match_request(&req));
+    assert!(!"192.168.0.0/16".parse::<SgHttpClientIpMatch>().expect("invalid cidr").match_request(&req));
+    assert!(SgHttpSniMatch("*.example.com".into()).match_request(&req));
+    assert!(!SgHttpSniMatch("*.api.example.com".into()).match_request(&req));
+    assert!(!SgHttpSniMatch("example.com".into()).match_request(&req));
+}
+
+#[test]
+fn test_client_ip_match() {
+    let cidr: SgHttpClientIpMatch = "10.1.0.0/16".parse().expect("invalid cidr");
+    assert!(cidr.contains("10.1.2.3".parse().expect("invalid ip")));
+    assert!(cidr.contains("::ffff:10.1.2.3".parse().expect("invalid ip")));
+    assert!(!cidr.contains("10.2.0.1".parse().expect("invalid ip")));
+    let cidr: SgHttpClientIpMatch = "0.0.0.0/0".parse().expect("invalid cidr");
+    assert!(cidr.contains("192.168.1.1".parse().expect("invalid ip")));
+    let cidr: SgHttpClientIpMatch = "fd00::1".parse().expect("invalid cidr");
+    assert!(cidr.contains("fd00::1".parse().expect("invalid ip")));
+    assert!(!cidr.contains("fd00::2".parse().expect("invalid ip")));
+    assert!("10.0.0.0/33".parse::<SgHttpClientIpMatch>().is_err());
+}
+
+#[test]
+fn test_explain() {
+    let route_match = SgHttpRouteMatch {
+        path: Some(SgHttpPathMatch::Prefix("/api".into())),
+        header: Some(vec![SgHttpHeaderMatch {
+            name: "x-canary".into(),
+            policy: SgHttpHeaderMatchPolicy::Exact("on".into()),
+        }]),
+        method: Some(vec![SgHttpMethodMatch("GET".into()), SgHttpMethodMatch("HEAD".into())]),
+        ..Default::default()
+    };
+    let req = Request::builder().uri("/api/users").header("x-canary", "off").body(SgBody::empty()).expect("invalid request");
+    let explained = route_match.explain(&req).into_iter().map(|p| (p.kind, p.predicate, p.passed)).collect::<Vec<_>>();
+    assert_eq!(
+        explained,
+        [
+            ("path", "Prefix /api".to_string(), true),
+            ("header", "x-canary Exact \"on\"".to_string(), false),
+            ("method", "GET | HEAD".to_string(), true),
+        ]
+    );
+    assert!(!route_match.match_request(&req));
 }
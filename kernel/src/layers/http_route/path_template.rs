@@ -0,0 +1,156 @@
+use std::{fmt, sync::Arc};
+
+use regex::Regex;
+
+use crate::{extension::PathCaptures, BoxError};
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param {
+        name: Arc<str>,
+        pattern: Option<Regex>,
+    },
+    /// Matches the rest of the path, including no segment at all.
+    CatchAll {
+        name: Arc<str>,
+    },
+}
+
+/// A path template like `/users/{id}/orders/{order_id:[0-9]+}` or `/static/{*rest}`.
+///
+/// - `{name}` matches a single segment,
+/// - `{name:regex}` matches a single segment which the regex matches entirely,
+/// - `{*name}` matches the rest of the path, it must be the last segment.
+///
+/// Literal segments are case sensitive unless the route match is case insensitive, and empty segments are ignored as prefix matches do.
+#[derive(Debug, Clone)]
+pub struct SgPathTemplate {
+    template: Arc<str>,
+    segments: Arc<[Segment]>,
+}
+
+impl fmt::Display for SgPathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// Non-empty segments of a path with their offsets.
+fn segments(path: &str) -> impl Iterator<Item = (usize, &str)> {
+    path.split('/').filter(|segment| !segment.is_empty()).map(move |segment| (segment.as_ptr() as usize - path.as_ptr() as usize, segment))
+}
+
+impl SgPathTemplate {
+    pub fn new(template: &str) -> Result<Self, BoxError> {
+        let mut parsed = Vec::new();
+        for (_, segment) in segments(template) {
+            if matches!(parsed.last(), Some(Segment::CatchAll { .. })) {
+                return Err(format!("[Sg.PathTemplate] catch-all must be the last segment of {template}").into());
+            }
+            let Some(param) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) else {
+                if segment.contains(['{', '}']) {
+                    return Err(format!("[Sg.PathTemplate] parameter must be a whole segment, found {segment} in {template}").into());
+                }
+                parsed.push(Segment::Literal(segment.to_string()));
+                continue;
+            };
+            let (name, pattern) = match param.split_once(':') {
+                Some((name, pattern)) => (name, Some(Regex::new(&format!("^(?:{pattern})$"))?)),
+                None => (param, None),
+            };
+            let (name, catch_all) = match name.strip_prefix('*') {
+                Some(name) => (name, true),
+                None => (name, false),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("[Sg.PathTemplate] invalid parameter name {name:?} in {template}").into());
+            }
+            if parsed.iter().any(|s| matches!(s, Segment::Param { name: n, .. } | Segment::CatchAll { name: n } if &**n == name)) {
+                return Err(format!("[Sg.PathTemplate] duplicated parameter {name} in {template}").into());
+            }
+            parsed.push(match (catch_all, pattern) {
+                (true, Some(_)) => return Err(format!("[Sg.PathTemplate] catch-all {name} can't have a pattern in {template}").into()),
+                (true, None) => Segment::CatchAll { name: name.into() },
+                (false, pattern) => Segment::Param { name: name.into(), pattern },
+            });
+        }
+        Ok(Self {
+            template: template.into(),
+            segments: parsed.into(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Leading literal segments, every path matching the template starts with them.
+    pub fn literal_prefix(&self) -> String {
+        let mut prefix = String::new();
+        for segment in self.segments.iter() {
+            let Segment::Literal(literal) = segment else {
+                break;
+            };
+            prefix.push('/');
+            prefix.push_str(literal);
+        }
+        prefix
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.match_path(path, false, |_, _| {})
+    }
+
+    /// Captured parameters if the path matches.
+    pub fn captures(&self, path: &str) -> Option<PathCaptures> {
+        let mut captures = PathCaptures::default();
+        self.match_path(path, false, |name, value| captures.push(name.clone(), value)).then_some(captures)
+    }
+
+    pub(crate) fn match_path<'a>(&self, path: &'a str, case_insensitive: bool, mut capture: impl FnMut(&Arc<str>, &'a str)) -> bool {
+        let mut path_segments = segments(path);
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::CatchAll { name } => {
+                    let rest = match path_segments.next() {
+                        Some((offset, _)) => &path[offset..],
+                        None => "",
+                    };
+                    capture(name, rest);
+                    return true;
+                }
+                Segment::Literal(literal) => match path_segments.next() {
+                    Some((_, segment)) if segment == literal || case_insensitive && segment.eq_ignore_ascii_case(literal) => {}
+                    _ => return false,
+                },
+                Segment::Param { name, pattern } => match path_segments.next() {
+                    Some((_, segment)) if !matches!(pattern, Some(pattern) if !pattern.is_match(segment)) => capture(name, segment),
+                    _ => return false,
+                },
+            }
+        }
+        path_segments.next().is_none()
+    }
+}
+
+#[test]
+fn test_path_template() {
+    let template = SgPathTemplate::new("/users/{id}/orders/{order_id:[0-9]+}").expect("invalid template");
+    assert_eq!(template.literal_prefix(), "/users");
+    let captures = template.captures("/users/alice/orders/42/").expect("not matched");
+    assert_eq!((captures.get("id"), captures.get("order_id")), (Some("alice"), Some("42")));
+    assert!(!template.is_match("/users/alice/orders/x42"));
+    assert!(!template.is_match("/users/alice/orders/42/items"));
+    assert!(!template.is_match("/Users/alice/orders/42"));
+
+    let template = SgPathTemplate::new("/static/{*rest}").expect("invalid template");
+    assert_eq!(template.captures("/static/js/app.js").expect("not matched").get("rest"), Some("js/app.js"));
+    assert_eq!(template.captures("/static").expect("not matched").get("rest"), Some(""));
+    assert!(!template.is_match("/assets/app.js"));
+
+    assert!(SgPathTemplate::new("/static/{*rest}/more").is_err());
+    assert!(SgPathTemplate::new("/files/{name}.json").is_err());
+    assert!(SgPathTemplate::new("/{id}/{id}").is_err());
+    assert!(SgPathTemplate::new("/{id:[}").is_err());
+}
@@ -1,10 +1,18 @@
-use std::sync::Arc;
+use std::{borrow::Cow, sync::Arc};
 
-use hyper::{Request, Response, StatusCode};
+use hyper::{
+    header::{COOKIE, HOST},
+    Request, Response, StatusCode,
+};
+use regex::Regex;
 
+use crate::extension::{PeerAddr, TlsServerName};
 use crate::helper_layers::filter;
+use crate::utils::query_kv::QueryKvIter;
 use crate::SgResponseExt;
-use crate::{ReqOrResp, SgBody};
+use crate::{BoxError, ReqOrResp, SgBody};
+
+use super::match_request::{MatchRequest, SgHttpClientIpMatch};
 
 #[derive(Debug, Clone)]
 pub struct FilterByHostnames {
@@ -21,10 +29,10 @@ impl FilterByHostnames {
                 if self.hostnames.iter().any(|h| h == hostname) {
                     Ok(request)
                 } else {
-                    Err(Response::<SgBody>::with_code_message(StatusCode::FORBIDDEN, "hostname not allowed"))
+                    Err(Response::<SgBody>::with_gateway_error(StatusCode::FORBIDDEN, "hostname not allowed"))
                 }
             } else {
-                Err(Response::<SgBody>::with_code_message(StatusCode::FORBIDDEN, "missing hostname"))
+                Err(Response::<SgBody>::with_gateway_error(StatusCode::FORBIDDEN, "missing hostname"))
             }
         }
     }
@@ -35,3 +43,407 @@ impl filter::Filter for FilterByHostnames {
         FilterByHostnames::check(self, req)
     }
 }
+
+/****************************************************************************************
+
+                                    Match Expression
+
+*****************************************************************************************/
+
+/// A boolean expression over request attributes, in a subset of [CEL](https://github.com/google/cel-spec).
+///
+/// # Attributes
+/// - `method`, `path`, `host` and `sni` (server name of the TLS connection) are strings,
+/// - `client_ip` is the peer address of the connection,
+/// - `headers["name"]`, `query["key"]` and `cookies["name"]` are values, or null if absent.
+///
+/// # Operators
+/// - `==` and `!=` compare attributes and string literals, null equals nothing,
+/// - `"name" in headers` tests presence, as well as `query` and `cookies`,
+/// - `.startsWith("s")`, `.endsWith("s")`, `.contains("s")` and `.matches("regex")` test strings,
+/// - `client_ip.inCidr("10.0.0.0/8")` tests the client ip,
+/// - `!`, `&&`, `||` and parentheses combine conditions.
+///
+/// e.g. `method == "GET" && (headers["x-canary"] == "on" || !client_ip.inCidr("10.0.0.0/8"))`
+#[derive(Debug, Clone)]
+pub struct SgMatchExpr {
+    source: Arc<str>,
+    root: Arc<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Const(bool),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Eq(Operand, Operand),
+    Ne(Operand, Operand),
+    Has(Collection, String),
+    Call(Attr, Method),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Str(String),
+    Attr(Attr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Collection {
+    Headers,
+    Query,
+    Cookies,
+}
+
+#[derive(Debug)]
+enum Attr {
+    Method,
+    Path,
+    Host,
+    Sni,
+    ClientIp,
+    Item(Collection, String),
+}
+
+#[derive(Debug)]
+enum Method {
+    StartsWith(String),
+    EndsWith(String),
+    Contains(String),
+    Matches(Regex),
+    InCidr(SgHttpClientIpMatch),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    Not,
+    And,
+    Or,
+    Eq,
+    Ne,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, BoxError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '.' => Token::Dot,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Ne,
+            '!' => Token::Not,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Eq,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '"' | '\'' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => literal.extend(chars.next()),
+                        Some(end) if end == c => break,
+                        Some(other) => literal.push(other),
+                        None => return Err(format!("unterminated string in expression {source}").into()),
+                    }
+                }
+                Token::Str(literal)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                Token::Ident(ident)
+            }
+            other => return Err(format!("unexpected character {other:?} in expression {source}").into()),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+    fn eat(&mut self, token: &Token) -> bool {
+        let matched = self.peek() == Some(token);
+        if matched {
+            self.next();
+        }
+        matched
+    }
+    fn expect(&mut self, token: Token) -> Result<(), BoxError> {
+        match self.next() {
+            Some(next) if next == token => Ok(()),
+            next => Err(format!("expected {token:?}, found {next:?}").into()),
+        }
+    }
+    fn string(&mut self) -> Result<String, BoxError> {
+        match self.next() {
+            Some(Token::Str(literal)) => Ok(literal),
+            next => Err(format!("expected string literal, found {next:?}").into()),
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, BoxError> {
+        let mut node = self.and()?;
+        while self.eat(&Token::Or) {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+    fn and(&mut self) -> Result<Node, BoxError> {
+        let mut node = self.unary()?;
+        while self.eat(&Token::And) {
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+    fn unary(&mut self) -> Result<Node, BoxError> {
+        if self.eat(&Token::Not) {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let node = self.or()?;
+            self.expect(Token::RParen)?;
+            return Ok(node);
+        }
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident == "true" || ident == "false" => {
+                let value = ident == "true";
+                self.next();
+                return Ok(Node::Const(value));
+            }
+            Some(Token::Str(_)) => {
+                let literal = self.string()?;
+                if let Some(Token::Ident(ident)) = self.peek() {
+                    if ident == "in" {
+                        self.next();
+                        return Ok(Node::Has(self.collection()?, literal));
+                    }
+                }
+                return self.compare(Operand::Str(literal));
+            }
+            _ => {}
+        }
+        let attr = self.attr()?;
+        if self.eat(&Token::Dot) {
+            let method = match self.next() {
+                Some(Token::Ident(method)) => method,
+                next => return Err(format!("expected method, found {next:?}").into()),
+            };
+            self.expect(Token::LParen)?;
+            let arg = self.string()?;
+            self.expect(Token::RParen)?;
+            let method = match (method.as_str(), &attr) {
+                ("inCidr", Attr::ClientIp) => Method::InCidr(arg.parse()?),
+                ("inCidr", _) => return Err("inCidr is only applicable to client_ip".into()),
+                ("startsWith", _) => Method::StartsWith(arg),
+                ("endsWith", _) => Method::EndsWith(arg),
+                ("contains", _) => Method::Contains(arg),
+                ("matches", _) => Method::Matches(Regex::new(&arg)?),
+                (method, _) => return Err(format!("unknown method {method}").into()),
+            };
+            return Ok(Node::Call(attr, method));
+        }
+        self.compare(Operand::Attr(attr))
+    }
+    fn compare(&mut self, left: Operand) -> Result<Node, BoxError> {
+        let eq = match self.next() {
+            Some(Token::Eq) => true,
+            Some(Token::Ne) => false,
+            next => return Err(format!("expected == or !=, found {next:?}").into()),
+        };
+        let right = match self.peek() {
+            Some(Token::Str(_)) => Operand::Str(self.string()?),
+            _ => Operand::Attr(self.attr()?),
+        };
+        Ok(if eq { Node::Eq(left, right) } else { Node::Ne(left, right) })
+    }
+    fn collection(&mut self) -> Result<Collection, BoxError> {
+        match self.next() {
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "headers" => Ok(Collection::Headers),
+                "query" => Ok(Collection::Query),
+                "cookies" => Ok(Collection::Cookies),
+                other => Err(format!("unknown collection {other}").into()),
+            },
+            next => Err(format!("expected headers, query or cookies, found {next:?}").into()),
+        }
+    }
+    fn attr(&mut self) -> Result<Attr, BoxError> {
+        let ident = match self.peek() {
+            Some(Token::Ident(ident)) => ident.clone(),
+            next => return Err(format!("expected attribute, found {next:?}").into()),
+        };
+        let attr = match ident.as_str() {
+            "method" => Attr::Method,
+            "path" => Attr::Path,
+            "host" => Attr::Host,
+            "sni" => Attr::Sni,
+            "client_ip" => Attr::ClientIp,
+            _ => {
+                let collection = self.collection()?;
+                self.expect(Token::LBracket)?;
+                let key = self.string()?;
+                self.expect(Token::RBracket)?;
+                let key = if collection == Collection::Headers { key.to_ascii_lowercase() } else { key };
+                return Ok(Attr::Item(collection, key));
+            }
+        };
+        self.next();
+        Ok(attr)
+    }
+}
+
+fn cookie<'a>(req: &'a Request<SgBody>, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|kv| kv.trim().split_once('='))
+        .find_map(|(k, v)| (k == name).then_some(v))
+}
+
+impl Attr {
+    fn value<'a>(&self, req: &'a Request<SgBody>) -> Option<Cow<'a, str>> {
+        match self {
+            Attr::Method => Some(Cow::Borrowed(req.method().as_str())),
+            Attr::Path => Some(Cow::Borrowed(req.uri().path())),
+            Attr::Host => {
+                let host = req.headers().get(HOST).and_then(|host| host.to_str().ok()).or(req.uri().host())?;
+                // strip the port, but not colons of an ipv6 address
+                let host = match host.rsplit_once(':') {
+                    Some((host, port)) if !host.is_empty() && !port.contains(']') && (host.ends_with(']') || !host.contains(':')) => host,
+                    _ => host,
+                };
+                Some(Cow::Borrowed(host))
+            }
+            Attr::Sni => req.extensions().get::<TlsServerName>().map(|name| Cow::Borrowed(name.as_str())),
+            Attr::ClientIp => req.extensions().get::<PeerAddr>().map(|peer| Cow::Owned(peer.0.ip().to_string())),
+            Attr::Item(Collection::Headers, name) => req.headers().get(name.as_str()).and_then(|v| v.to_str().ok()).map(Cow::Borrowed),
+            Attr::Item(Collection::Query, key) => QueryKvIter::new(req.uri().query()?).find_map(|(k, v)| (k == key).then(|| Cow::Borrowed(v.unwrap_or_default()))),
+            Attr::Item(Collection::Cookies, name) => cookie(req, name).map(Cow::Borrowed),
+        }
+    }
+}
+
+impl Operand {
+    fn value<'a>(&'a self, req: &'a Request<SgBody>) -> Option<Cow<'a, str>> {
+        match self {
+            Operand::Str(literal) => Some(Cow::Borrowed(literal)),
+            Operand::Attr(attr) => attr.value(req),
+        }
+    }
+}
+
+impl Node {
+    fn eval(&self, req: &Request<SgBody>) -> bool {
+        match self {
+            Node::Const(value) => *value,
+            Node::Not(node) => !node.eval(req),
+            Node::And(left, right) => left.eval(req) && right.eval(req),
+            Node::Or(left, right) => left.eval(req) || right.eval(req),
+            Node::Eq(left, right) => matches!((left.value(req), right.value(req)), (Some(left), Some(right)) if left == right),
+            Node::Ne(left, right) => !matches!((left.value(req), right.value(req)), (Some(left), Some(right)) if left == right),
+            Node::Has(collection, key) => Attr::Item(*collection, if *collection == Collection::Headers { key.to_ascii_lowercase() } else { key.clone() }).value(req).is_some(),
+            Node::Call(Attr::ClientIp, Method::InCidr(cidr)) => req.extensions().get::<PeerAddr>().is_some_and(|peer| cidr.contains(peer.0.ip())),
+            Node::Call(attr, method) => {
+                let Some(value) = attr.value(req) else {
+                    return false;
+                };
+                match method {
+                    Method::StartsWith(s) => value.starts_with(s.as_str()),
+                    Method::EndsWith(s) => value.ends_with(s.as_str()),
+                    Method::Contains(s) => value.contains(s.as_str()),
+                    Method::Matches(regex) => regex.is_match(&value),
+                    Method::InCidr(cidr) => value.parse().is_ok_and(|ip| cidr.contains(ip)),
+                }
+            }
+        }
+    }
+}
+
+impl SgMatchExpr {
+    pub fn new(source: &str) -> Result<Self, BoxError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?.into_iter(),
+            peeked: None,
+        };
+        let root = parser.or().map_err(|e| format!("[Sg.MatchExpr] invalid expression {source}: {e}"))?;
+        if let Some(token) = parser.next() {
+            return Err(format!("[Sg.MatchExpr] invalid expression {source}: unexpected {token:?}").into());
+        }
+        Ok(Self {
+            source: source.into(),
+            root: Arc::new(root),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl MatchRequest for SgMatchExpr {
+    fn match_request(&self, req: &Request<SgBody>) -> bool {
+        self.root.eval(req)
+    }
+}
+
+#[test]
+fn test_match_expr() {
+    use crate::extension::PeerAddr;
+    let req = Request::builder()
+        .method("POST")
+        .uri("/api/users?debug&tenant=acme")
+        .header("host", "example.com:8080")
+        .header("x-canary", "on")
+        .header("cookie", "theme=dark; session=s1")
+        .extension(PeerAddr("10.1.2.3:4567".parse().expect("invalid addr")))
+        .body(SgBody::empty())
+        .expect("invalid request");
+    let matches = |source: &str| SgMatchExpr::new(source).expect("invalid expression").match_request(&req);
+    assert!(matches(r#"method == "POST" && path.startsWith("/api/")"#));
+    assert!(matches(r#"host == "example.com" && headers["X-Canary"] == 'on'"#));
+    assert!(matches(r#""x-canary" in headers && "debug" in query && !("missing" in cookies)"#));
+    assert!(matches(r#"query["tenant"] == "acme" && cookies["theme"].matches("^d")"#));
+    assert!(matches(r#"client_ip.inCidr("10.0.0.0/8") && !client_ip.inCidr("10.2.0.0/16")"#));
+    assert!(matches(r#"headers["absent"] != "x" && !(headers["absent"] == "x") && sni != "example.com""#));
+    assert!(matches(r#"method == "GET" || path.endsWith("users") && true"#));
+    assert!(!matches(r#"(method == "GET" || path.endsWith("users")) && false"#));
+    for invalid in [
+        r#"method"#,
+        r#"path.inCidr("10.0.0.0/8")"#,
+        r#"path == "#,
+        r#"headers == "x""#,
+        r#"method == "GET")"#,
+        r#"path.matches("[")"#,
+    ] {
+        assert!(SgMatchExpr::new(invalid).is_err(), "{invalid}");
+    }
+}
@@ -8,12 +8,13 @@ pub mod helper_layers;
 pub mod layers;
 pub mod listener;
 pub mod marker;
+pub mod plugin_layers;
 pub mod service;
 pub mod utils;
 
 pub use body::SgBody;
 use extension::Reflect;
-use helper_layers::response_error::ErrorFormatter;
+use helper_layers::response_error::{ErrorFormatter, SgProblem};
 pub use marker::Marker;
 pub use service::BoxHyperService;
 use std::{convert::Infallible, fmt, sync::Arc};
@@ -74,14 +75,26 @@ impl SgRequestExt for SgRequest {
 }
 
 pub trait SgResponseExt {
+    /// Response of the message, it's left as is by the gateway, e.g. a page made by a plugin on purpose.
     fn with_code_message(code: StatusCode, message: impl Into<Bytes>) -> Self;
+    /// Response of an error generated by the gateway itself, it's marked by [`GatewayError`](extension::GatewayError)
+    /// and may be replaced by error pages, see [`layers::gateway::error_page`].
+    fn with_gateway_error(code: StatusCode, message: impl Into<Bytes>) -> Self
+    where
+        Self: Sized,
+    {
+        let message = message.into();
+        Self::with_problem(SgProblem::new(code).detail(String::from_utf8_lossy(&message)))
+    }
+    /// Response of the problem in text, it's a gateway error, rendered by the `Accept` header in the gateway, see [`layers::gateway::error_page`].
+    fn with_problem(problem: SgProblem) -> Self;
     fn bad_gateway<E: std::error::Error>(e: E) -> Self
     where
         Self: Sized,
     {
         let message = e.to_string();
         tracing::debug!(message, "[Sg] gateway internal error");
-        Self::with_code_message(StatusCode::BAD_GATEWAY, message)
+        Self::with_gateway_error(StatusCode::BAD_GATEWAY, message)
     }
     fn plugin_error<E: std::error::Error>(e: E) -> Self
     where
@@ -89,15 +102,15 @@ pub trait SgResponseExt {
     {
         let message = e.to_string();
         tracing::debug!(message, "[Sg] gateway plugin internal error");
-        Self::with_code_message(StatusCode::INTERNAL_SERVER_ERROR, message)
+        Self::with_gateway_error(StatusCode::INTERNAL_SERVER_ERROR, message)
     }
     fn from_error<E: std::error::Error, F: ErrorFormatter>(e: E, formatter: &F) -> Self
     where
         Self: Sized,
     {
-        let message = formatter.format(&e);
-        tracing::debug!(message, "[Sg] gateway internal error");
-        Self::with_code_message(StatusCode::INTERNAL_SERVER_ERROR, formatter.format(&e))
+        let problem = formatter.problem(StatusCode::INTERNAL_SERVER_ERROR, &e);
+        tracing::debug!(message = problem.detail.as_deref(), "[Sg] gateway internal error");
+        Self::with_problem(problem)
     }
 }
 
@@ -108,6 +121,12 @@ impl SgResponseExt for Response<SgBody> {
         resp.extensions_mut().insert(Reflect::new());
         resp
     }
+    fn with_problem(problem: SgProblem) -> Self {
+        let code = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut resp = Self::with_code_message(code, problem.to_text(None));
+        resp.extensions_mut().insert(extension::GatewayError { problem });
+        resp
+    }
 }
 
 pub type ReqOrResp = Result<Request<SgBody>, Response<SgBody>>;
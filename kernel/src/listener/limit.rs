@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use tokio::time::Instant;
+
+/// Connection level limits of a listener.
+///
+/// `None` means no limit, or hyper's default.
+#[derive(Debug, Clone, Default)]
+pub struct SgListenLimits {
+    /// Maximum number of concurrent connections.
+    pub max_connections: Option<usize>,
+    /// Maximum number of concurrent connections from the same source ip.
+    pub max_connections_per_ip: Option<usize>,
+    /// Timeout for a client to send the entire request header, and to complete the tls handshake.
+    ///
+    /// For http2, it's the timeout of keep-alive pings, which are sent at this interval.
+    pub header_read_timeout: Option<Duration>,
+    /// A connection without any read or write for this duration will be shutdown gracefully.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum size of request header in bytes.
+    ///
+    /// For http1 this is the read buffer size, which can't be less than 8192 bytes.
+    pub max_header_size: Option<usize>,
+    /// Maximum number of request headers, for http1 only.
+    pub max_headers: Option<usize>,
+    /// Maximum number of concurrent streams of a http2 connection.
+    pub http2_max_concurrent_streams: Option<u32>,
+}
+
+impl SgListenLimits {
+    /// http1 read buffer can't be smaller than this
+    const MIN_HTTP1_BUF_SIZE: usize = 8192;
+    pub(crate) fn apply(&self, builder: &mut hyper_util::server::conn::auto::Builder<TokioExecutor>) {
+        let mut http1 = builder.http1();
+        http1.timer(TokioTimer::new());
+        if let Some(header_read_timeout) = self.header_read_timeout {
+            http1.header_read_timeout(header_read_timeout);
+        }
+        if let Some(max_header_size) = self.max_header_size {
+            http1.max_buf_size(max_header_size.max(Self::MIN_HTTP1_BUF_SIZE));
+        }
+        if let Some(max_headers) = self.max_headers {
+            http1.max_headers(max_headers);
+        }
+        let mut http2 = builder.http2();
+        http2.timer(TokioTimer::new());
+        if let Some(header_read_timeout) = self.header_read_timeout {
+            http2.keep_alive_interval(header_read_timeout);
+            http2.keep_alive_timeout(header_read_timeout);
+        }
+        if let Some(max_header_size) = self.max_header_size {
+            http2.max_header_list_size(max_header_size.try_into().unwrap_or(u32::MAX));
+        }
+        if let Some(max_concurrent_streams) = self.http2_max_concurrent_streams {
+            http2.max_concurrent_streams(max_concurrent_streams);
+        }
+    }
+}
+
+/// Statistics of a listener.
+#[derive(Debug, Default)]
+pub struct SgListenStats {
+    /// Number of connections being served.
+    pub active_connections: AtomicUsize,
+    /// Connections rejected due to [`SgListenLimits::max_connections`].
+    pub rejected_max_connections: AtomicU64,
+    /// Connections rejected due to [`SgListenLimits::max_connections_per_ip`].
+    pub rejected_max_connections_per_ip: AtomicU64,
+    /// Connections closed due to timeout of reading request header.
+    pub header_read_timeouts: AtomicU64,
+    /// Connections closed due to [`SgListenLimits::idle_timeout`].
+    pub idle_timeouts: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRejected {
+    MaxConnections,
+    MaxConnectionsPerIp,
+}
+
+impl std::fmt::Display for ConnectionRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionRejected::MaxConnections => write!(f, "max connections exceeded"),
+            ConnectionRejected::MaxConnectionsPerIp => write!(f, "max connections per ip exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionRejected {}
+
+#[derive(Debug, Default)]
+pub struct ConnectionLimiter {
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    per_ip: Mutex<HashMap<IpAddr, usize>>,
+    pub stats: Arc<SgListenStats>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: &SgListenLimits) -> Self {
+        Self {
+            max_connections: limits.max_connections,
+            max_connections_per_ip: limits.max_connections_per_ip,
+            ..Default::default()
+        }
+    }
+
+    /// Try to acquire a permit for a new connection from `ip`, the permit should be held until the connection is closed.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, ConnectionRejected> {
+        let stats = &self.stats;
+        let active = stats.active_connections.fetch_add(1, Ordering::AcqRel);
+        if self.max_connections.is_some_and(|max| active >= max) {
+            stats.active_connections.fetch_sub(1, Ordering::AcqRel);
+            stats.rejected_max_connections.fetch_add(1, Ordering::Relaxed);
+            return Err(ConnectionRejected::MaxConnections);
+        }
+        let ip = if let Some(max) = self.max_connections_per_ip {
+            let mut per_ip = self.per_ip.lock().expect("poisoned lock");
+            let count = per_ip.entry(ip).or_default();
+            if *count >= max {
+                drop(per_ip);
+                stats.active_connections.fetch_sub(1, Ordering::AcqRel);
+                stats.rejected_max_connections_per_ip.fetch_add(1, Ordering::Relaxed);
+                return Err(ConnectionRejected::MaxConnectionsPerIp);
+            }
+            *count += 1;
+            Some(ip)
+        } else {
+            None
+        };
+        Ok(ConnectionPermit { limiter: self.clone(), ip })
+    }
+}
+
+/// Release the connection slot when dropped.
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: Option<IpAddr>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.stats.active_connections.fetch_sub(1, Ordering::AcqRel);
+        if let Some(ip) = self.ip {
+            let mut per_ip = self.limiter.per_ip.lock().expect("poisoned lock");
+            if let Some(count) = per_ip.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// Time of last read or write of a connection.
+#[derive(Debug)]
+pub struct LastActive {
+    since: Instant,
+    elapsed_ms: AtomicU64,
+}
+
+impl LastActive {
+    fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            elapsed_ms: AtomicU64::new(0),
+        }
+    }
+    fn touch(&self) {
+        self.elapsed_ms.store(self.since.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+    pub fn get(&self) -> Instant {
+        self.since + Duration::from_millis(self.elapsed_ms.load(Ordering::Relaxed))
+    }
+    /// Wait until there is no read or write for `timeout`.
+    pub async fn idle(&self, timeout: Duration) {
+        loop {
+            let deadline = self.get() + timeout;
+            if deadline <= Instant::now() {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// An io wrapper which records the time of last read or write.
+    pub struct IdleIo<I> {
+        #[pin]
+        inner: I,
+        last_active: Arc<LastActive>,
+    }
+}
+
+impl<I> IdleIo<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            last_active: Arc::new(LastActive::new()),
+        }
+    }
+    pub fn last_active(&self) -> Arc<LastActive> {
+        self.last_active.clone()
+    }
+}
+
+impl<I: Read> Read for IdleIo<I> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: ReadBufCursor<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let poll = this.inner.poll_read(cx, buf);
+        if poll.is_ready() {
+            this.last_active.touch();
+        }
+        poll
+    }
+}
+
+impl<I: Write> Write for IdleIo<I> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.project();
+        let poll = this.inner.poll_write(cx, buf);
+        if poll.is_ready() {
+            this.last_active.touch();
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[std::io::IoSlice<'_>]) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.project();
+        let poll = this.inner.poll_write_vectored(cx, bufs);
+        if poll.is_ready() {
+            this.last_active.touch();
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_connection_limiter() {
+        let limiter = Arc::new(ConnectionLimiter::new(&SgListenLimits {
+            max_connections: Some(3),
+            max_connections_per_ip: Some(2),
+            ..Default::default()
+        }));
+        let ip_a: IpAddr = [10, 0, 0, 1].into();
+        let ip_b: IpAddr = [10, 0, 0, 2].into();
+        let a1 = limiter.try_acquire(ip_a).expect("should acquire");
+        let _a2 = limiter.try_acquire(ip_a).expect("should acquire");
+        assert_eq!(limiter.try_acquire(ip_a).expect_err("should reject"), ConnectionRejected::MaxConnectionsPerIp);
+        let _b1 = limiter.try_acquire(ip_b).expect("should acquire");
+        assert_eq!(limiter.try_acquire(ip_b).expect_err("should reject"), ConnectionRejected::MaxConnections);
+        drop(a1);
+        let _b2 = limiter.try_acquire(ip_b).expect("should acquire");
+        assert_eq!(limiter.stats.active_connections.load(Ordering::Relaxed), 3);
+        assert_eq!(limiter.stats.rejected_max_connections.load(Ordering::Relaxed), 1);
+        assert_eq!(limiter.stats.rejected_max_connections_per_ip.load(Ordering::Relaxed), 1);
+    }
+}
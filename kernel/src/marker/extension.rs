@@ -1,3 +1,5 @@
+use std::ops::Deref;
+
 use hyper::Request;
 
 use crate::{Marker, SgBody};
@@ -16,6 +18,14 @@ impl<E> Extension<E> {
     }
 }
 
+impl<E> Deref for Extension<E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl<E> Marker for Extension<E>
 where
     E: Send + Sync + 'static + Clone,
@@ -1,2 +1,2 @@
-
-
+#[cfg(feature = "comde")]
+pub mod comde;
@@ -1,128 +1,292 @@
-//! This layer is used to make response's encoding compatible with the request's accept encoding.
-//!
-//! see also:
-//! https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Accept-Encoding
-//! https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Content-Encoding
+//! Layers to make response's encoding compatible with the request's accept encoding.
 //!
+//! - [`CompressionLayer`] compresses responses with the preferred encoding of the client.
+//! - [`DecompressionLayer`] decompresses responses whose encoding is not accepted by the client.
 //!
+//! see also:
+//! - https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Accept-Encoding
+//! - https://developer.mozilla.org/zh-CN/docs/Web/HTTP/Headers/Content-Encoding
 pub mod accept_encoding;
 pub mod compress_type;
 pub mod content_encoding;
-pub mod future;
-use std::{cmp::Ordering, str::FromStr};
-
-use crate::{plugin_layers::comde::content_encoding::ContentEncodingType, SgBoxService,};
-use hyper::{Request, Response};
-use hyper::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
-use serde::{Deserialize, Serialize};
-use tower::{BoxError, ServiceExt, service_fn};
-use tower_http::decompression::{Decompression as TowerDecompression, DecompressionLayer as TowerDecompressionLayer};
-use tower_http::compression::{Compression as TowerCompression, CompressionLayer as TowerCompressionLayer};
-use tower_layer::Layer;
-use tower_service::Service;
-
-use self::{accept_encoding::AcceptEncoding, compress_type::CompressionType};
 
-pub struct ComdeLayer {}
+use std::{convert::Infallible, sync::Arc};
 
-pub struct DecompressionLayer;
+pub use async_compression::Level;
+use futures_util::future::BoxFuture;
+use hyper::{
+    header::{HeaderValue, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, VARY},
+    Method, Request, Response, StatusCode,
+};
+use tower_layer::Layer;
 
-impl DecompressionLayer {}
+use crate::{helper_layers::body_limit::content_length, SgBody};
 
-// impl Layer<SgBoxService> for DecompressionLayer {
-//     type Service = SgBoxService;
+use self::{accept_encoding::AcceptEncoding, compress_type::CompressionType, content_encoding::ContentEncoding};
 
-//     fn layer(&self, inner: SgBoxService) -> Self::Service {
-//         TowerCompression::new(inner)
-//         inner
-//     }
-// }
+/// Content types which are already compressed, or not worth compressing.
+pub const DEFAULT_EXCLUDED_CONTENT_TYPES: &[&str] = &[
+    "image/",
+    "video/",
+    "audio/",
+    "font/woff",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/x-brotli",
+    "application/grpc",
+    "text/event-stream",
+];
 
-// pub struct CompressionService<> {
-//     inner: TowerCompression,
-// }
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    /// Supported encodings, in the order of server preference.
+    pub encodings: Vec<CompressionType>,
+    /// Responses with a smaller `Content-Length` are not compressed.
+    pub min_size: u64,
+    /// Content type prefixes which are not compressed, `image/svg+xml` is always compressible.
+    pub excluded_content_types: Vec<String>,
+    pub level: Level,
+}
 
-pub struct ComdeService {
-    inner: SgBoxService,
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            encodings: CompressionType::ALL.to_vec(),
+            min_size: 1024,
+            excluded_content_types: DEFAULT_EXCLUDED_CONTENT_TYPES.iter().map(|s| s.to_string()).collect(),
+            level: Level::Default,
+        }
+    }
 }
 
-impl ComdeService {
+impl CompressionConfig {
+    fn compressible(&self, resp: &Response<SgBody>) -> bool {
+        let headers = resp.headers();
+        if matches!(resp.status(), StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::PARTIAL_CONTENT) || resp.status().is_informational() {
+            return false;
+        }
+        if ContentEncoding::from_headers(headers) != ContentEncoding::Identity || headers.contains_key(CONTENT_RANGE) {
+            return false;
+        }
+        if headers.get_all(CACHE_CONTROL).iter().filter_map(|v| v.to_str().ok()).any(|v| v.split(',').any(|d| d.trim().eq_ignore_ascii_case("no-transform"))) {
+            return false;
+        }
+        if content_length(headers).is_some_and(|len| len < self.min_size) {
+            return false;
+        }
+        let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default().trim().to_ascii_lowercase();
+        content_type.starts_with("image/svg+xml") || !self.excluded_content_types.iter().any(|excluded| content_type.starts_with(excluded.to_ascii_lowercase().as_str()))
+    }
+}
 
+fn append_vary_accept_encoding(headers: &mut hyper::HeaderMap) {
+    let varied = headers.get_all(VARY).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("accept-encoding"));
+    if !varied {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
 
+/// A strong etag promises byte-identical bodies, which no longer holds once the body is re-encoded.
+fn weaken_etag(headers: &mut hyper::HeaderMap) {
+    let weakened = headers.get(ETAG).filter(|etag| !etag.as_bytes().starts_with(b"W/")).and_then(|etag| HeaderValue::from_bytes(&[b"W/", etag.as_bytes()].concat()).ok());
+    if let Some(weakened) = weakened {
+        headers.insert(ETAG, weakened);
+    }
 }
 
-pub fn echo_body<B: hyper::body::Body>(mut req: Request<B>) -> Response<B> {
-    Response::new(req.into_body())
+/// Compress responses with the encoding preferred by the client.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionLayer {
+    config: Arc<CompressionConfig>,
 }
 
-impl ComdeService {
-    pub fn get_accept_encoding(&self, req: &Request<SgBody>) -> Option<CompressionType> {
-        req.request.headers().get(ACCEPT_ENCODING).and_then(|h| CompressionType::try_from(h).ok())
+impl CompressionLayer {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self { config: Arc::new(config) }
     }
-    pub fn on_response(&self, mut resp: Response<SgBody>, accept: AcceptEncoding) -> Response<SgBody> {
-        let service = TowerDecompression::new(service_fn(echo_body));
-        let content_encoding = if let Some(s) = resp.response.headers().get(CONTENT_ENCODING) {
-            let Ok(content_encoding) = ContentEncodingType::try_from(s) else {
-                return resp;
-            };
-            if accept.is_compatible(content_encoding) {
-                return resp;
-            }
-            Some(content_encoding)
-        } else {
-            if accept.accept_identity() {
-                return resp;
-            }
-            None
-        };
-        let target_type = accept.get_preferred().unwrap_or(accept_encoding::AcceptEncodingType::Identity);
-        match content_encoding {
-            Some(ContentEncodingType::Br) => {
-                service.br(true);
-                resp.response
-            }
-            Some(ContentEncodingType::Deflate) => {
-                service.deflate(true);
+}
 
-            }
-            Some(ContentEncodingType::Gzip) => {
-                service.gzip(true);
+impl<S> Layer<S> for CompressionLayer {
+    type Service = Compression<S>;
 
-            }
-            ,
-            None => {
-            },
+    fn layer(&self, inner: S) -> Self::Service {
+        Compression {
+            inner,
+            config: self.config.clone(),
         }
-        service.call();
-        match target_type {
-            accept_encoding::AcceptEncodingType::Br => {
-                resp.map_body()
-            }
-            accept_encoding::AcceptEncodingType::Deflate => {
-                resp.map_body()
-            }
-            accept_encoding::AcceptEncodingType::Gzip => {
-                resp.map_body()
-            }
-            _ => {
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Compression<S> {
+    inner: S,
+    config: Arc<CompressionConfig>,
+}
+
+impl<S> hyper::service::Service<Request<SgBody>> for Compression<S>
+where
+    S: hyper::service::Service<Request<SgBody>, Response = Response<SgBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<SgBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
+    fn call(&self, req: Request<SgBody>) -> Self::Future {
+        let encoding = if req.method() == Method::HEAD { None } else { AcceptEncoding::from_headers(req.headers()).preferred(&self.config.encodings) };
+        let config = self.config.clone();
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let mut resp = fut.await?;
+            if !config.compressible(&resp) {
+                return Ok(resp);
             }
-        }
+            // the response varies by accept-encoding even if it's not compressed this time
+            append_vary_accept_encoding(resp.headers_mut());
+            let Some(encoding) = encoding else {
+                return Ok(resp);
+            };
+            let (mut parts, body) = resp.into_parts();
+            parts.headers.insert(CONTENT_ENCODING, encoding.into());
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.remove(ACCEPT_RANGES);
+            weaken_etag(&mut parts.headers);
+            Ok(Response::from_parts(parts, encoding.encode(body, config.level)))
+        })
+    }
+}
 
+/// Decompress responses whose `Content-Encoding` is not accepted by the client.
+#[derive(Debug, Clone, Default)]
+pub struct DecompressionLayer {}
+
+impl DecompressionLayer {
+    pub fn new() -> Self {
+        Self {}
     }
 }
-impl Service<Request<SgBody>> for DecompressionService {
+
+impl<S> Layer<S> for DecompressionLayer {
+    type Service = Decompression<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Decompression { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Decompression<S> {
+    inner: S,
+}
+
+impl<S> hyper::service::Service<Request<SgBody>> for Decompression<S>
+where
+    S: hyper::service::Service<Request<SgBody>, Response = Response<SgBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
     type Response = Response<SgBody>;
     type Error = Infallible;
-    type Future = <SgBoxService as Service<Request<SgBody>>>::Future;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, req: Request<SgBody>) -> Self::Future {
+        let accept = AcceptEncoding::from_headers(req.headers());
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let resp = fut.await?;
+            let ContentEncoding::Compression(encoding) = ContentEncoding::from_headers(resp.headers()) else {
+                return Ok(resp);
+            };
+            if accept.accepts(encoding) {
+                return Ok(resp);
+            }
+            let (mut parts, body) = resp.into_parts();
+            parts.headers.remove(CONTENT_ENCODING);
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.remove(ACCEPT_RANGES);
+            weaken_etag(&mut parts.headers);
+            Ok(Response::from_parts(parts, encoding.decode(body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http_body_util::BodyExt;
+    use hyper::{header::HeaderMap, service::Service};
 
-    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-        todo!()
+    use super::*;
+    use crate::BoxHyperService;
+
+    #[test]
+    fn test_accept_encoding() {
+        let parse = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(hyper::header::ACCEPT_ENCODING, HeaderValue::from_static(value));
+            AcceptEncoding::from_headers(&headers)
+        };
+        let all = CompressionType::ALL;
+        assert_eq!(parse("gzip, deflate, br").preferred(&all), Some(CompressionType::Br));
+        assert_eq!(parse("gzip;q=1.0, br;q=0.5").preferred(&all), Some(CompressionType::Gzip));
+        assert_eq!(parse("*;q=0.1, gzip;q=0").preferred(&[CompressionType::Gzip, CompressionType::Zstd]), Some(CompressionType::Zstd));
+        assert_eq!(parse("identity").preferred(&all), None);
+        assert!(!parse("identity;q=0").accepts_identity());
+        assert!(!parse("br").accepts(CompressionType::Gzip));
     }
 
-    fn call(&mut self, req: Request<SgBody>) -> Self::Future {
-        let accept_encoding = req.request.headers().get(ACCEPT_ENCODING).map(|h|CompressionType::try_from(h).ok()).flatten();
-        let fut = self.inner.call(req);
-        todo!()
+    fn upstream(encoding: Option<CompressionType>) -> BoxHyperService {
+        BoxHyperService::new(hyper::service::service_fn(move |req: Request<SgBody>| async move {
+            let content_length = req.headers().get(CONTENT_LENGTH).cloned();
+            let mut resp = match encoding {
+                Some(encoding) => {
+                    let mut resp = Response::new(encoding.encode(req.into_body(), Level::Default));
+                    resp.headers_mut().insert(CONTENT_ENCODING, encoding.into());
+                    resp
+                }
+                None => Response::new(req.into_body()),
+            };
+            if let Some(content_length) = content_length {
+                resp.headers_mut().insert(CONTENT_LENGTH, content_length);
+            }
+            resp.headers_mut().insert(ETAG, HeaderValue::from_static("\"v1\""));
+            Ok::<_, Infallible>(resp)
+        }))
+    }
+
+    fn request(accept_encoding: &'static str, body: String) -> Request<SgBody> {
+        Request::builder().header(hyper::header::ACCEPT_ENCODING, accept_encoding).header(CONTENT_LENGTH, body.len()).body(SgBody::full(body)).expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_compress_decompress() {
+        let message = "hello from spacegate ".repeat(100);
+        let compression = CompressionLayer::new(CompressionConfig::default()).layer(upstream(None));
+        for encoding in CompressionType::ALL {
+            let resp = compression.call(request(encoding.as_str(), message.clone())).await.expect("infallible");
+            assert_eq!(resp.headers().get(CONTENT_ENCODING).expect("missing content-encoding"), encoding.as_str());
+            assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+            assert_eq!(resp.headers().get(ETAG).expect("missing etag"), "W/\"v1\"");
+            let compressed = resp.into_body().collect().await.expect("invalid body").to_bytes();
+            assert!(compressed.len() < message.len());
+            let decoded = encoding.decode(SgBody::full(compressed)).collect().await.expect("invalid body").to_bytes();
+            assert_eq!(decoded, message.as_bytes());
+
+            // client doesn't accept the encoding of upstream
+            let decompression = DecompressionLayer::new().layer(upstream(Some(encoding)));
+            let resp = decompression.call(request("identity", message.clone())).await.expect("infallible");
+            assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+            assert_eq!(resp.headers().get(ETAG).expect("missing etag"), "W/\"v1\"");
+            assert_eq!(resp.into_body().collect().await.expect("invalid body").to_bytes(), message.as_bytes());
+            let resp = decompression.call(request(encoding.as_str(), message.clone())).await.expect("infallible");
+            assert_eq!(resp.headers().get(CONTENT_ENCODING).expect("missing content-encoding"), encoding.as_str());
+            assert_eq!(resp.headers().get(ETAG).expect("missing etag"), "\"v1\"");
+        }
+
+        // small body is not compressed
+        let resp = compression.call(request("gzip", "hello".to_string())).await.expect("infallible");
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(resp.headers().get(ETAG).expect("missing etag"), "\"v1\"");
+        assert_eq!(resp.into_body().collect().await.expect("invalid body").to_bytes(), "hello".as_bytes());
     }
 }
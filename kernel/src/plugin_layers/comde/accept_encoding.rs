@@ -1,90 +1,83 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::str::FromStr;
 
-use hyper::header::HeaderValue;
-use tower::BoxError;
+use hyper::header::{HeaderMap, ACCEPT_ENCODING};
 
-use super::content_encoding::{ContentEncoding, ContentEncodingType};
+use super::compress_type::CompressionType;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AcceptEncodingType {
-    Gzip,
-    Deflate,
-    Br,
+    Compression(CompressionType),
     Identity,
     Any,
 }
 
-#[derive(Debug)]
-pub struct AcceptEncodingItem {
-    r#type: AcceptEncodingType,
-    q: f32,
-}
-
-#[derive(Debug, Default)]
-pub struct AcceptEncoding {
-    items: Vec<AcceptEncodingItem>,
-}
-
-impl AcceptEncodingType {
-    pub const fn as_str(&self) -> &'static str {
-        match self {
-            AcceptEncodingType::Gzip => "gzip",
-            AcceptEncodingType::Deflate => "deflate",
-            AcceptEncodingType::Br => "br",
-            AcceptEncodingType::Identity => "identity",
-            AcceptEncodingType::Any => "*",
-        }
-    }
-}
-
 impl FromStr for AcceptEncodingType {
-    type Err = BoxError;
+    type Err = crate::BoxError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "gzip" => Ok(AcceptEncodingType::Gzip),
-            "deflate" => Ok(AcceptEncodingType::Deflate),
-            "br" => Ok(AcceptEncodingType::Br),
-            "identity" => Ok(AcceptEncodingType::Identity),
+        match s.trim() {
             "*" => Ok(AcceptEncodingType::Any),
-            _ => Err("Invalid compression type".into()),
+            s if s.eq_ignore_ascii_case("identity") => Ok(AcceptEncodingType::Identity),
+            s => CompressionType::from_str(s).map(AcceptEncodingType::Compression),
         }
     }
 }
 
-impl From<&HeaderValue> for AcceptEncoding {
-    fn from(header_value: &HeaderValue) -> Self {
-        let s = header_value.to_str().unwrap_or_default();
-        let v = s
-            .split(',')
-            .filter_map(|encode_item| {
-                if let Some((encode, q_str)) = encode_item.split_once(';') {
-                    let q = q_str.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()).unwrap_or(1.0f32);
-                    AcceptEncodingType::from_str(encode).ok().map(|encode| AcceptEncodingItem { r#type: encode, q })
-                } else {
-                    AcceptEncodingType::from_str(encode_item).ok().map(|encode| AcceptEncodingItem { r#type: encode, q: 1.0f32 })
-                }
-            })
-            .collect::<Vec<_>>();
-        AcceptEncoding { items: v }
-    }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AcceptEncodingItem {
+    pub r#type: AcceptEncodingType,
+    pub q: f32,
+}
+
+/// Parsed `Accept-Encoding` header, unknown encodings are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct AcceptEncoding {
+    items: Vec<AcceptEncodingItem>,
 }
 
 impl AcceptEncoding {
-    pub fn is_compatible(&self, content_encoding: ContentEncodingType) -> bool {
-        // if Content-Encoding are in Accept-Encoding, return true
-        self.items.iter().any(|accept_encoding_item| accept_encoding_item.r#type == AcceptEncodingType::from(content_encoding))
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let items = headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|item| {
+                let (encoding, q) = match item.split_once(';') {
+                    Some((encoding, params)) => (encoding, params.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()).unwrap_or(1.0)),
+                    None => (item, 1.0),
+                };
+                AcceptEncodingType::from_str(encoding).ok().map(|r#type| AcceptEncodingItem { r#type, q })
+            })
+            .collect();
+        Self { items }
     }
 
-    pub fn accept_identity(&self) -> bool {
-        !self.items.iter().any(|accept_encoding_item| accept_encoding_item.r#type == AcceptEncodingType::Identity && accept_encoding_item.q == 0.0f32)
+    /// The q-value of an encoding, an explicit item takes precedence over `*`.
+    fn q(&self, r#type: AcceptEncodingType) -> Option<f32> {
+        self.items.iter().find(|item| item.r#type == r#type).or_else(|| self.items.iter().find(|item| item.r#type == AcceptEncodingType::Any)).map(|item| item.q)
     }
 
-    pub fn get_preferred(&self) -> Option<AcceptEncodingType> {
-        self.items
-            .iter()
-            .max_by_key(|item| if item.r#type == AcceptEncodingType::Any { Ordering::Less } else { Ordering::Greater })
-            .map(|item| (item.q > 0.0).then_some(item.r#type))
-            .flatten()
+    pub fn accepts(&self, compression: CompressionType) -> bool {
+        self.q(AcceptEncodingType::Compression(compression)).is_some_and(|q| q > 0.0)
+    }
+
+    /// Identity is acceptable unless it's explicitly, or by `*`, given a zero q-value.
+    pub fn accepts_identity(&self) -> bool {
+        !self.q(AcceptEncodingType::Identity).is_some_and(|q| q <= 0.0)
+    }
+
+    /// The acceptable encoding with the highest q-value among `supported`, ties are broken by the order of `supported`.
+    pub fn preferred(&self, supported: &[CompressionType]) -> Option<CompressionType> {
+        let mut preferred: Option<(CompressionType, f32)> = None;
+        for &compression in supported {
+            let Some(q) = self.q(AcceptEncodingType::Compression(compression)).filter(|q| *q > 0.0) else {
+                continue;
+            };
+            if !preferred.is_some_and(|(_, preferred_q)| q <= preferred_q) {
+                preferred = Some((compression, q));
+            }
+        }
+        preferred.map(|(compression, _)| compression)
     }
 }
@@ -1,114 +1,81 @@
-use std::str::FromStr;
+use std::{io, str::FromStr};
 
-use hyper::header::HeaderValue;
-use serde::{Deserialize, Serialize};
-use tower::BoxError;
+use async_compression::{
+    tokio::bufread::{BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZlibDecoder, ZlibEncoder, ZstdDecoder, ZstdEncoder},
+    Level,
+};
+use futures_util::TryStreamExt;
+use http_body_util::{BodyDataStream, StreamBody};
+use hyper::{body::Frame, header::HeaderValue};
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
 
-#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq)]
+use crate::{BoxError, SgBody};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CompressionType {
     #[default]
     Gzip,
     Deflate,
     Br,
+    Zstd,
 }
 
 impl CompressionType {
-    pub const fn as_bytes(&self) -> &'static [u8] {
-        match self {
-            CompressionType::Gzip => b"gzip",
-            CompressionType::Deflate => b"deflate",
-            CompressionType::Br => b"br",
-        }
-    }
+    pub const ALL: [CompressionType; 4] = [CompressionType::Br, CompressionType::Zstd, CompressionType::Gzip, CompressionType::Deflate];
 
     pub const fn as_str(&self) -> &'static str {
         match self {
             CompressionType::Gzip => "gzip",
             CompressionType::Deflate => "deflate",
             CompressionType::Br => "br",
+            CompressionType::Zstd => "zstd",
         }
     }
-}
 
-impl From<CompressionType> for HeaderValue {
-    #[inline]
-    fn from(algo: CompressionType) -> Self {
-        HeaderValue::from_static(algo.as_str())
+    /// Compress the body as a stream.
+    pub fn encode(self, body: SgBody, level: Level) -> SgBody {
+        let reader = body_reader(body);
+        match self {
+            CompressionType::Gzip => reader_body(GzipEncoder::with_quality(reader, level)),
+            CompressionType::Deflate => reader_body(ZlibEncoder::with_quality(reader, level)),
+            CompressionType::Br => reader_body(BrotliEncoder::with_quality(reader, level)),
+            CompressionType::Zstd => reader_body(ZstdEncoder::with_quality(reader, level)),
+        }
     }
-}
 
-impl From<CompressionType> for &str {
-    #[inline]
-    fn from(algo: CompressionType) -> Self {
-        algo.as_str()
+    /// Decompress the body as a stream.
+    pub fn decode(self, body: SgBody) -> SgBody {
+        let reader = body_reader(body);
+        match self {
+            CompressionType::Gzip => reader_body(GzipDecoder::new(reader)),
+            CompressionType::Deflate => reader_body(ZlibDecoder::new(reader)),
+            CompressionType::Br => reader_body(BrotliDecoder::new(reader)),
+            CompressionType::Zstd => reader_body(ZstdDecoder::new(reader)),
+        }
     }
 }
 
-impl PartialEq<CompressionType> for &str {
-    fn eq(&self, other: &CompressionType) -> bool {
-        let other_str: &str = other.clone().into();
-        self.eq_ignore_ascii_case(other_str)
-    }
+fn body_reader(body: SgBody) -> impl tokio::io::AsyncBufRead + Send + Sync + 'static {
+    StreamReader::new(BodyDataStream::new(body).map_err(io::Error::other))
 }
 
-impl FromStr for CompressionType {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.eq_ignore_ascii_case(CompressionType::Gzip.as_str()) {
-            Ok(CompressionType::Gzip)
-        } else if s.eq_ignore_ascii_case(CompressionType::Br.as_str()) {
-            Ok(CompressionType::Br)
-        } else if s.eq_ignore_ascii_case(CompressionType::Deflate.as_str()) {
-            Ok(CompressionType::Deflate)
-        } else {
-            Err(())
-        }
-    }
+fn reader_body(reader: impl AsyncRead + Send + Sync + 'static) -> SgBody {
+    SgBody::new_boxed_error(StreamBody::new(ReaderStream::new(reader).map_ok(Frame::data).map_err(BoxError::from)))
 }
 
-impl TryFrom<&[u8]> for CompressionType {
-    type Error = ();
-
-    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        if bytes.eq_ignore_ascii_case(CompressionType::Gzip.as_bytes()) {
-            Ok(CompressionType::Gzip)
-        } else if bytes.eq_ignore_ascii_case(CompressionType::Br.as_bytes()) {
-            Ok(CompressionType::Br)
-        } else if bytes.eq_ignore_ascii_case(CompressionType::Deflate.as_bytes()) {
-            Ok(CompressionType::Deflate)
-        } else {
-            Err(())
-        }
+impl From<CompressionType> for HeaderValue {
+    #[inline]
+    fn from(algo: CompressionType) -> Self {
+        HeaderValue::from_static(algo.as_str())
     }
 }
 
-impl TryFrom<&HeaderValue> for CompressionType {
-    type Error = Infallible;
+impl FromStr for CompressionType {
+    type Err = BoxError;
 
-    fn try_from(header_value: &HeaderValue) -> Result<Self, Self::Error> {
-        let s = header_value.to_str()?;
-        s.split(',')
-            .filter_map(|encode_item| {
-                if let Some((encode, q_str)) = encode_item.split_once(';') {
-                    let q = q_str.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()).unwrap_or(1.0f32);
-                    CompressionType::from_str(encode).ok().map(|encode| (encode, q))
-                } else {
-                    CompressionType::from_str(encode_item).ok().map(|encode| (encode, 1.0f32))
-                }
-            })
-            .fold(None, |acc, (encode, q)| {
-                if let Some((_, acc_q)) = acc {
-                    if q > acc_q {
-                        Some((encode, q))
-                    } else {
-                        acc
-                    }
-                } else {
-                    Some((encode, q))
-                }
-            })
-            .map(|(encode, _)| encode)
-            .ok_or_else(|| "Invalid compression type".into())
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        CompressionType::ALL.into_iter().find(|t| s.eq_ignore_ascii_case(t.as_str())).ok_or_else(|| format!("unsupported compression type {s}").into())
     }
 }
@@ -1,63 +1,33 @@
 use std::str::FromStr;
 
-use hyper::header::HeaderValue;
-use tower::BoxError;
+use hyper::header::{HeaderMap, CONTENT_ENCODING};
 
-use super::accept_encoding::{AcceptEncoding, AcceptEncodingType};
+use super::compress_type::CompressionType;
 
-pub struct ContentEncoding {
-    // don't support multiple encoding
-    pub r#type: ContentEncodingType,
+/// Parsed `Content-Encoding` header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ContentEncoding {
+    Identity,
+    Compression(CompressionType),
+    /// Unknown or multiple encodings, we don't touch such body.
+    Other(String),
 }
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum ContentEncodingType {
-    Gzip,
-    Deflate,
-    Br,
-}
-
-impl ContentEncodingType {
-    pub const fn as_str(&self) -> &'static str {
-        match self {
-            ContentEncodingType::Gzip => "gzip",
-            ContentEncodingType::Deflate => "deflate",
-            ContentEncodingType::Br => "br",
-        }
-    }
-}
-
-impl From<ContentEncodingType> for AcceptEncodingType {
-    fn from(val: ContentEncodingType) -> Self {
-        match val {
-            ContentEncodingType::Gzip => AcceptEncodingType::Gzip,
-            ContentEncodingType::Deflate => AcceptEncodingType::Deflate,
-            ContentEncodingType::Br => AcceptEncodingType::Br,
-        }
-    }
-}
-
-
-impl TryFrom<&HeaderValue> for ContentEncodingType {
-    type Error = Infallible;
-
-    fn try_from(header_value: &HeaderValue) -> Result<Self, Self::Error> {
-        match header_value.as_bytes() {
-            b"gzip" => Ok(ContentEncodingType::Gzip),
-            b"deflate" => Ok(ContentEncodingType::Deflate),
-            b"br" => Ok(ContentEncodingType::Br),
-            _ => Err("Invalid compression type".into()),
-        }
-    }
-}
-impl FromStr for ContentEncodingType {
-    type Err = BoxError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "gzip" => Ok(ContentEncodingType::Gzip),
-            "deflate" => Ok(ContentEncodingType::Deflate),
-            "br" => Ok(ContentEncodingType::Br),
-            _ => Err("Invalid compression type".into()),
+impl ContentEncoding {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut values = headers.get_all(CONTENT_ENCODING).iter();
+        let (Some(value), None) = (values.next(), values.next()) else {
+            return if headers.contains_key(CONTENT_ENCODING) {
+                ContentEncoding::Other(String::from("multiple"))
+            } else {
+                ContentEncoding::Identity
+            };
+        };
+        let value = value.to_str().unwrap_or_default().trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("identity") {
+            ContentEncoding::Identity
+        } else {
+            CompressionType::from_str(value).map(ContentEncoding::Compression).unwrap_or_else(|_| ContentEncoding::Other(value.to_string()))
         }
     }
 }
@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::convert::Infallible;
 use std::sync::Arc;
 
@@ -6,8 +7,9 @@ use futures_util::Future;
 use hyper::{header::UPGRADE, Request, Response, StatusCode};
 use tracing::instrument;
 
+use crate::extension::BackendTimeouts;
 use crate::helper_layers::map_future::MapFuture;
-use crate::service::http_client_service::get_client;
+use crate::service::http_client_service::{get_client, ClientRepo, SgHttpClient};
 use crate::utils::x_forwarded_for;
 use crate::BoxError;
 use crate::SgBody;
@@ -49,26 +51,49 @@ impl Clone for BoxHyperService {
 }
 
 impl BoxHyperService {
+    /// Box a service.
+    ///
+    /// A service already boxed is returned as is, and a future already boxed is not boxed again,
+    /// so stacking layers doesn't add an allocation per request for each boundary.
     pub fn new<T>(service: T) -> Self
     where
         T: Clone + CloneHyperService<Request<SgBody>, Response = Response<SgBody>, Error = Infallible> + Send + Sync + 'static,
         T::Future: Future<Output = Result<Response<SgBody>, Infallible>> + 'static + Send,
     {
-        let map_fut = MapFuture::new(service, |fut| Box::pin(fut) as _);
+        if let Some(boxed) = (&service as &dyn Any).downcast_ref::<BoxHyperService>() {
+            return boxed.clone();
+        }
+        let map_fut = MapFuture::new(service, box_future);
         Self { boxed: Arc::new(map_fut) }
     }
 }
 
+/// Pin a future on heap, unless it's already a [`BoxFuture`].
+fn box_future<F, T>(fut: F) -> BoxFuture<'static, T>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: 'static,
+{
+    let mut fut = Some(fut);
+    if let Some(boxed) = (&mut fut as &mut dyn Any).downcast_mut::<Option<BoxFuture<'static, T>>>() {
+        return boxed.take().expect("future is taken only once");
+    }
+    Box::pin(fut.expect("future is taken only once"))
+}
+
 impl hyper::service::Service<Request<SgBody>> for BoxHyperService {
     type Response = Response<SgBody>;
     type Error = Infallible;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn call(&self, req: Request<SgBody>) -> Self::Future {
-        Box::pin(self.boxed.call(req))
+        self.boxed.call(req)
     }
 }
 
+/// Upgrade handshakes are not expected to carry a body, so a small limit is enough.
+const UPGRADE_BODY_LIMIT: usize = 0x10000;
+
 /// Http backend service
 ///
 /// This function could be a bottom layer of a http router, it will handle http and websocket request.
@@ -77,23 +102,27 @@ impl hyper::service::Service<Request<SgBody>> for BoxHyperService {
 pub async fn http_backend_service_inner(mut req: Request<SgBody>) -> Result<SgResponse, BoxError> {
     tracing::trace!(elapsed = ?req.extensions().get::<crate::extension::EnterTime>().map(crate::extension::EnterTime::elapsed), "start a backend request");
     x_forwarded_for(&mut req)?;
-    let mut client = get_client();
-    let mut response = if let Some(upgrade) = req.headers().get(UPGRADE) {
+    let timeouts = req.extensions().get::<BackendTimeouts>().copied().unwrap_or_default();
+    let mut client = match timeouts.connect {
+        Some(connect_timeout) => ClientRepo::global().get_default_with_connect_timeout(connect_timeout),
+        None => get_client(),
+    };
+    let response = if let Some(upgrade) = req.headers().get(UPGRADE) {
         // we only support websocket upgrade now
         if !upgrade.as_bytes().eq_ignore_ascii_case(b"websocket") {
-            return Ok(Response::with_code_message(StatusCode::NOT_IMPLEMENTED, "[Sg.Websocket] unsupported upgrade protocol"));
+            return Ok(Response::with_gateway_error(StatusCode::NOT_IMPLEMENTED, "[Sg.Websocket] unsupported upgrade protocol"));
         }
         // dump request
         let (part, body) = req.into_parts();
-        let body = body.dump().await?;
+        let body = body.dump_with_limit(UPGRADE_BODY_LIMIT).await?;
         let req = Request::from_parts(part, body);
 
         // forward request
-        let resp = client.request(req.clone()).await;
+        let resp = request_with_timeouts(&mut client, req.clone(), timeouts).await;
 
         // dump response
         let (part, body) = resp.into_parts();
-        let body = body.dump().await?;
+        let body = body.dump_with_limit(UPGRADE_BODY_LIMIT).await?;
         let resp = Response::from_parts(part, body);
 
         let req_for_upgrade = req.clone();
@@ -113,19 +142,35 @@ pub async fn http_backend_service_inner(mut req: Request<SgBody>) -> Result<SgRe
         // return response to client
         resp
     } else {
-        let resp = client.request(req).await;
+        let resp = request_with_timeouts(&mut client, req, timeouts).await;
         tracing::trace!(elapsed = ?resp.extensions().get::<crate::extension::EnterTime>().map(crate::extension::EnterTime::elapsed), "finish backend request");
         resp
     };
-    response.extensions_mut().insert(unsafe { crate::extension::FromBackend::new() });
     Ok(response)
 }
 
+async fn request_with_timeouts(client: &mut SgHttpClient, req: Request<SgBody>, timeouts: BackendTimeouts) -> SgResponse {
+    let deadline = timeouts.deadline.map(|deadline| tokio::time::Instant::now() + deadline);
+    let request = async {
+        match timeouts.response_header {
+            Some(response_header_timeout) => client.request_header_timeout(req, response_header_timeout).await,
+            None => client.request(req).await,
+        }
+    };
+    let resp = match deadline {
+        Some(deadline) => {
+            tokio::time::timeout_at(deadline, request).await.unwrap_or_else(|_| Response::with_gateway_error(StatusCode::GATEWAY_TIMEOUT, "[Sg.Client] deadline exceeded"))
+        }
+        None => request.await,
+    };
+    resp.map(|body| body.with_timeout(timeouts.idle_stream, deadline))
+}
+
 #[instrument]
 pub async fn http_backend_service(req: Request<SgBody>) -> Result<Response<SgBody>, Infallible> {
     match http_backend_service_inner(req).await {
         Ok(resp) => Ok(resp),
-        Err(err) => Ok(Response::with_code_message(StatusCode::BAD_GATEWAY, format!("[Sg.Client] Client error: {err}"))),
+        Err(err) => Ok(Response::with_gateway_error(StatusCode::BAD_GATEWAY, format!("[Sg.Client] Client error: {err}"))),
     }
 }
 
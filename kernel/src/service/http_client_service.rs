@@ -1,5 +1,7 @@
-use crate::{extension::Reflect, SgBody, SgResponseExt};
+use crate::{extension::Reflect, BoxError, SgBody, SgResponseExt};
 
+use http_body_util::combinators::BoxBody;
+use hyper::body::{Body, Bytes, Frame, SizeHint};
 use hyper::StatusCode;
 use hyper::{Request, Response};
 use hyper_rustls::HttpsConnector;
@@ -10,9 +12,12 @@ use hyper_util::{
 };
 use std::{
     collections::HashMap,
+    pin::Pin,
     sync::{Arc, Mutex, OnceLock},
+    task::{Context, Poll},
     time::Duration,
 };
+use tokio::sync::oneshot;
 use tokio_rustls::rustls::{self, client::danger::ServerCertVerifier, SignatureScheme};
 
 #[derive(Debug, Clone)]
@@ -73,6 +78,8 @@ pub fn get_client() -> SgHttpClient {
 pub struct ClientRepo {
     default: SgHttpClient,
     repo: Mutex<HashMap<String, SgHttpClient>>,
+    /// clients derived from default client with connect timeout
+    connect_timeout_repo: Mutex<HashMap<Duration, SgHttpClient>>,
 }
 
 impl Default for ClientRepo {
@@ -82,6 +89,7 @@ impl Default for ClientRepo {
         Self {
             default,
             repo: Default::default(),
+            connect_timeout_repo: Default::default(),
         }
     }
 }
@@ -97,11 +105,17 @@ impl ClientRepo {
     pub fn get_default(&self) -> SgHttpClient {
         self.default.clone()
     }
+    /// Get a client with the same tls config as the default client, but with a connect timeout.
+    pub fn get_default_with_connect_timeout(&self, connect_timeout: Duration) -> SgHttpClient {
+        let mut repo = self.connect_timeout_repo.lock().expect("failed to lock client repo");
+        repo.entry(connect_timeout).or_insert_with(|| SgHttpClient::with_connect_timeout(self.default.tls_config.as_ref().clone(), Some(connect_timeout))).clone()
+    }
     pub fn register(&self, code: &str, client: SgHttpClient) {
         self.repo.lock().expect("failed to lock client repo").insert(code.to_string(), client);
     }
     pub fn set_default(&mut self, client: SgHttpClient) {
         self.default = client;
+        self.connect_timeout_repo.get_mut().expect("failed to lock client repo").clear();
     }
     pub fn global() -> &'static Self {
         unsafe { &GLOBAL }.get_or_init(Default::default)
@@ -122,6 +136,7 @@ pub struct SgHttpClientConfig {
 #[derive(Debug, Clone)]
 pub struct SgHttpClient {
     inner: Client<HttpsConnector<HttpConnector>, SgBody>,
+    tls_config: Arc<rustls::ClientConfig>,
 }
 
 impl Default for SgHttpClient {
@@ -132,8 +147,17 @@ impl Default for SgHttpClient {
 
 impl SgHttpClient {
     pub fn new(tls_config: rustls::ClientConfig) -> Self {
+        Self::with_connect_timeout(tls_config, None)
+    }
+    /// Create a client, connecting to backend will fail with `504 Gateway Timeout` after `connect_timeout`.
+    pub fn with_connect_timeout(tls_config: rustls::ClientConfig, connect_timeout: Option<Duration>) -> Self {
+        let mut http_connector = HttpConnector::new();
+        http_connector.enforce_http(false);
+        http_connector.set_connect_timeout(connect_timeout);
+        let connector = HttpsConnectorBuilder::new().with_tls_config(tls_config.clone()).https_or_http().enable_http1().wrap_connector(http_connector);
         SgHttpClient {
-            inner: Client::builder(TokioExecutor::new()).build(HttpsConnectorBuilder::new().with_tls_config(tls_config).https_or_http().enable_http1().build()),
+            inner: Client::builder(TokioExecutor::new()).build(connector),
+            tls_config: Arc::new(tls_config),
         }
     }
     pub fn new_dangerous() -> Self {
@@ -142,14 +166,17 @@ impl SgHttpClient {
     }
     pub async fn request(&mut self, mut req: Request<SgBody>) -> Response<SgBody> {
         let reflect = req.extensions_mut().remove::<Reflect>();
-        match self.inner.request(req).await.map_err(Response::bad_gateway) {
+        match self.inner.request(req).await {
             Ok(mut response) => {
                 if let Some(reflect) = reflect {
                     response.extensions_mut().extend(reflect.into_inner());
                 }
+                // errors of connecting or requesting are responded by the gateway, only this one is from the backend
+                response.extensions_mut().insert(unsafe { crate::extension::FromBackend::new() });
                 response.map(SgBody::new)
             }
-            Err(err) => err,
+            Err(err) if err.is_connect() && is_timed_out(&err) => Response::with_gateway_error(StatusCode::GATEWAY_TIMEOUT, "connect timeout"),
+            Err(err) => Response::bad_gateway(err),
         }
     }
     pub async fn request_timeout(&mut self, req: Request<SgBody>, timeout: Duration) -> Response<SgBody> {
@@ -157,14 +184,141 @@ impl SgHttpClient {
         let resp = tokio::time::timeout(timeout, fut).await;
         match resp {
             Ok(resp) => resp,
-            Err(_) => Response::with_code_message(StatusCode::GATEWAY_TIMEOUT, "request timeout"),
+            Err(_) => Response::with_gateway_error(StatusCode::GATEWAY_TIMEOUT, "request timeout"),
+        }
+    }
+    /// Like [`SgHttpClient::request_timeout`], but the timer starts after the request is sent,
+    /// so connecting to backend and sending the request body don't count.
+    pub async fn request_header_timeout(&mut self, req: Request<SgBody>, timeout: Duration) -> Response<SgBody> {
+        let (sent_tx, sent_rx) = oneshot::channel();
+        let req = req.map(|body| SgBody {
+            body: BoxBody::new(SentBody {
+                inner: body.body,
+                sent: Some(sent_tx),
+            }),
+            dump: body.dump,
+        });
+        let response = self.request(req);
+        let timer = async {
+            // the sender is dropped without sending if the request fails before sent, the response comes then
+            if sent_rx.await.is_err() {
+                std::future::pending::<()>().await;
+            }
+            tokio::time::sleep(timeout).await;
+        };
+        tokio::select! {
+            response = response => response,
+            _ = timer => Response::with_gateway_error(StatusCode::GATEWAY_TIMEOUT, "response header timeout"),
         }
     }
 }
 
+pin_project_lite::pin_project! {
+    /// Reports through `sent` once the whole body is polled, i.e. the request is sent.
+    struct SentBody {
+        #[pin]
+        inner: BoxBody<Bytes, BoxError>,
+        sent: Option<oneshot::Sender<()>>,
+    }
+}
+
+impl Body for SentBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let polled = this.inner.poll_frame(cx);
+        if matches!(polled, Poll::Ready(None) | Poll::Ready(Some(Err(_)))) {
+            if let Some(sent) = this.sent.take() {
+                let _ = sent.send(());
+            }
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Check if the error is caused by a timed out io error.
+fn is_timed_out(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if err.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut) {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::extension::GatewayError;
+
+    #[tokio::test]
+    async fn test_bad_gateway() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("fail to bind");
+        let addr = listener.local_addr().expect("no local addr");
+        drop(listener);
+        let req = Request::builder().uri(format!("http://{addr}/")).body(SgBody::empty()).expect("invalid request");
+        let resp = SgHttpClient::default().request(req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        assert!(resp.extensions().get::<GatewayError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_timeout() {
+        // accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("fail to bind");
+        let addr = listener.local_addr().expect("no local addr");
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+        let req = Request::builder().uri(format!("http://{addr}/")).body(SgBody::empty()).expect("invalid request");
+        let resp = SgHttpClient::default().request_timeout(req, Duration::from_millis(50)).await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(resp.extensions().get::<GatewayError>().is_some());
+
+        assert!(is_timed_out(&std::io::Error::from(std::io::ErrorKind::TimedOut)));
+        assert!(!is_timed_out(&std::io::Error::from(std::io::ErrorKind::ConnectionRefused)));
+    }
+    #[tokio::test]
+    async fn test_response_header_timeout_after_sent() {
+        use http_body_util::{BodyExt, StreamBody};
+        // responds once the whole request body is read
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("fail to bind");
+        let addr = listener.local_addr().expect("no local addr");
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(|req: Request<hyper::body::Incoming>| async move {
+                    let body = req.into_body().collect().await?.to_bytes();
+                    Ok::<_, hyper::Error>(Response::new(http_body_util::Full::new(body)))
+                });
+                tokio::spawn(hyper_util::server::conn::auto::Builder::new(TokioExecutor::new()).serve_connection(hyper_util::rt::TokioIo::new(stream), service).into_owned());
+            }
+        });
+        // sending the body takes longer than the timeout, but it doesn't count
+        let slow = futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            Ok::<_, BoxError>(Frame::data(Bytes::from("hello")))
+        });
+        let req = Request::builder().method("POST").uri(format!("http://{addr}/")).body(SgBody::new_boxed_error(StreamBody::new(slow))).expect("invalid request");
+        let resp = SgHttpClient::default().request_header_timeout(req, Duration::from_millis(100)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().dump().await.expect("fail to dump");
+        assert_eq!(body.get_dumped().expect("not dumped"), "hello");
+    }
     #[tokio::test]
     async fn test_client() {
         let mut client = get_client();
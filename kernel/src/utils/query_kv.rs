@@ -15,19 +15,11 @@ impl<'a> Iterator for QueryKvIter<'a> {
         if self.inner.is_empty() {
             return None;
         }
-        match self.inner.split_once('&') {
-            Some((k, v)) => {
-                self.inner = v;
-                match k.split_once('=') {
-                    Some((k, v)) => Some((k, Some(v))),
-                    None => Some((k, None)),
-                }
-            }
-            None => {
-                let k = self.inner;
-                self.inner = "";
-                Some((k, None))
-            }
+        let (kv, rest) = self.inner.split_once('&').unwrap_or((self.inner, ""));
+        self.inner = rest;
+        match kv.split_once('=') {
+            Some((k, v)) => Some((k, Some(v))),
+            None => Some((kv, None)),
         }
     }
 }
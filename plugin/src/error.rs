@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use hyper::{header::HeaderValue, Response, StatusCode};
-use spacegate_kernel::{SgBody, SgResponseExt};
+use spacegate_kernel::{helper_layers::response_error::SgProblem, SgBody, SgResponseExt};
 
 use crate::Plugin;
 
@@ -19,7 +19,7 @@ where
     E: Display,
 {
     fn from(val: PluginError<E>) -> Self {
-        let mut resp = Response::with_code_message(val.status, val.to_string());
+        let mut resp = Response::with_problem(SgProblem::new(val.status).detail(val.to_string()).plugin(val.plugin_code));
         resp.headers_mut().insert(PLUGIN_ERROR_HEADER, HeaderValue::from_static(val.plugin_code));
         resp
     }
@@ -87,6 +87,10 @@ impl SgPluginRepository {
 
     pub fn register_prelude(&self) {
         self.register::<plugins::static_resource::StaticResourcePlugin>();
+        #[cfg(feature = "body-limit")]
+        self.register::<plugins::body_limit::BodyLimitPlugin>();
+        #[cfg(feature = "response-cache")]
+        self.register::<plugins::cache::CachePlugin>();
         #[cfg(feature = "limit")]
         self.register::<plugins::limit::RateLimitPlugin>();
         #[cfg(feature = "redirect")]
@@ -101,8 +105,28 @@ impl SgPluginRepository {
         self.register::<plugins::rewrite::RewritePlugin>();
         #[cfg(feature = "maintenance")]
         self.register::<plugins::maintenance::MaintenancePlugin>();
+        #[cfg(feature = "mirror")]
+        self.register::<plugins::mirror::MirrorPlugin>();
         // #[cfg(feature = "status")]
         // self.register::<plugins::status::StatusPlugin>();
+        #[cfg(feature = "compression")]
+        self.register::<plugins::compression::CompressionPlugin>();
+        #[cfg(feature = "cors")]
+        self.register::<plugins::cors::CorsPlugin>();
+        #[cfg(feature = "jwt-auth")]
+        self.register::<plugins::jwt_auth::JwtAuthPlugin>();
+        #[cfg(feature = "ext-auth")]
+        self.register::<plugins::ext_auth::ExtAuthPlugin>();
+        #[cfg(feature = "key-auth")]
+        self.register::<plugins::key_auth::KeyAuthPlugin>();
+        #[cfg(feature = "basic-auth")]
+        self.register::<plugins::basic_auth::BasicAuthPlugin>();
+        #[cfg(feature = "oidc")]
+        self.register::<plugins::oidc::OidcPlugin>();
+        #[cfg(feature = "ip-restriction")]
+        self.register::<plugins::ip_restriction::IpRestrictionPlugin>();
+        #[cfg(feature = "fault")]
+        self.register::<plugins::fault::FaultPlugin>();
         #[cfg(feature = "decompression")]
         self.register::<plugins::decompression::DecompressionPlugin>();
         #[cfg(feature = "redis")]
@@ -124,6 +148,11 @@ impl SgPluginRepository {
         map.insert(P::CODE, Box::new(create_fn));
     }
 
+    /// Whether a plugin of `code` is registered.
+    pub fn contains(&self, code: &str) -> bool {
+        self.map.read().expect("SgPluginTypeMap register error").contains_key(code)
+    }
+
     pub fn create(&self, name: Option<String>, code: &str, value: JsonValue) -> Result<Box<dyn MakeSgLayer>, BoxError> {
         let map = self.map.read().expect("SgPluginTypeMap register error");
         if let Some(t) = map.get(code) {
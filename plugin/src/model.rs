@@ -1,5 +1,7 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Serialize};
-use spacegate_kernel::layers::http_route::match_request::SgHttpPathMatch;
+use spacegate_kernel::{extension::MatchedSgRouter, layers::http_route::match_request::SgHttpPathMatch};
 
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -7,6 +9,8 @@ pub struct SgHttpPathModifier {
     /// Type defines the type of path modifier.
     pub kind: SgHttpPathModifierType,
     /// Value is the value to be used to replace the path during forwarding.
+    ///
+    /// It may refer to parameters captured by the matched path template or regex, e.g. `/v2/users/${path.id}`.
     pub value: String,
 }
 
@@ -24,16 +28,33 @@ pub enum SgHttpPathModifierType {
 }
 
 impl SgHttpPathModifier {
+    /// Replace the path by the matched route, with `${path.<name>}` placeholders substituted by captured parameters.
+    pub fn replace_matched(&self, path: &str, matched: &MatchedSgRouter) -> Option<String> {
+        let modifier = match matched.captures().substitute(&self.value) {
+            Cow::Borrowed(_) => Cow::Borrowed(self),
+            Cow::Owned(value) => Cow::Owned(SgHttpPathModifier { kind: self.kind, value }),
+        };
+        match (&matched.path, modifier.kind) {
+            // a template has no other way to use its captures in the full path, so its full path is always replaced
+            (Some(SgHttpPathMatch::Template(_)), SgHttpPathModifierType::ReplaceFullPath) => Some(modifier.value.clone()),
+            (Some(path_match), _) => modifier.replace(path, path_match),
+            (None, SgHttpPathModifierType::ReplaceFullPath) => modifier.replace_full_path(path),
+            (None, _) => None,
+        }
+    }
+
+    fn replace_full_path(&self, path: &str) -> Option<String> {
+        if self.value.eq_ignore_ascii_case(path) {
+            Some(self.value.clone())
+        } else {
+            None
+        }
+    }
+
     pub fn replace(&self, path: &str, path_match: &SgHttpPathMatch) -> Option<String> {
         let value = &self.value;
         match (self.kind, path_match) {
-            (SgHttpPathModifierType::ReplaceFullPath, _) => {
-                if value.eq_ignore_ascii_case(path) {
-                    Some(value.clone())
-                } else {
-                    None
-                }
-            }
+            (SgHttpPathModifierType::ReplaceFullPath, _) => self.replace_full_path(path),
             (SgHttpPathModifierType::ReplacePrefixMatch, SgHttpPathMatch::Prefix(prefix)) => {
                 fn not_empty(s: &&str) -> bool {
                     !s.is_empty()
@@ -105,3 +126,28 @@ fn test_regex_replace() {
     // won't match
     assert_eq!(Some("/api/iam/subpath2"), modifier.replace("/api/iam/subpath2", &replace).as_deref());
 }
+
+#[test]
+fn test_full_path_replace() {
+    use spacegate_kernel::layers::http_route::{match_request::SgHttpRouteMatch, path_template::SgPathTemplate};
+    let modifier = SgHttpPathModifier {
+        kind: SgHttpPathModifierType::ReplaceFullPath,
+        value: "/v2/accounts/${path.id}/${path.rest}".into(),
+    };
+    let path_match = SgHttpPathMatch::Template(SgPathTemplate::new("/users/{id}/{*rest}").expect("invalid template"));
+    let captures = path_match.captures("/users/42/orders/7", false).expect("not matched");
+    let route_match = SgHttpRouteMatch {
+        path: Some(path_match),
+        ..Default::default()
+    };
+    let matched = MatchedSgRouter(route_match.into(), captures);
+    assert_eq!(Some("/v2/accounts/42/orders/7"), modifier.replace_matched("/users/42/orders/7", &matched).as_deref());
+    // other matches only keep a full path equal to the value
+    let exact = SgHttpPathMatch::Exact("/users/42".into());
+    assert_eq!(None, modifier.replace("/users/42", &exact));
+    let modifier = SgHttpPathModifier {
+        kind: SgHttpPathModifierType::ReplaceFullPath,
+        value: "/Users/42".into(),
+    };
+    assert_eq!(Some("/Users/42"), modifier.replace("/users/42", &exact).as_deref());
+}
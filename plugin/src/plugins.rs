@@ -1,13 +1,39 @@
+#[cfg(feature = "basic-auth")]
+pub mod basic_auth;
+#[cfg(feature = "body-limit")]
+pub mod body_limit;
+#[cfg(feature = "response-cache")]
+pub mod cache;
+#[cfg(feature = "compression")]
+pub mod compression;
+#[cfg(feature = "cors")]
+pub mod cors;
+#[cfg(any(feature = "key-auth", feature = "basic-auth"))]
+pub mod credential;
 #[cfg(feature = "decompression")]
 pub mod decompression;
+#[cfg(feature = "ext-auth")]
+pub mod ext_auth;
+#[cfg(feature = "fault")]
+pub mod fault;
 #[cfg(feature = "header-modifier")]
 pub mod header_modifier;
 #[cfg(feature = "inject")]
 pub mod inject;
+#[cfg(feature = "ip-restriction")]
+pub mod ip_restriction;
+#[cfg(feature = "jwt-auth")]
+pub mod jwt_auth;
+#[cfg(feature = "key-auth")]
+pub mod key_auth;
 #[cfg(feature = "limit")]
 pub mod limit;
 #[cfg(feature = "maintenance")]
 pub mod maintenance;
+#[cfg(feature = "mirror")]
+pub mod mirror;
+#[cfg(feature = "oidc")]
+pub mod oidc;
 #[cfg(feature = "redirect")]
 pub mod redirect;
 #[cfg(feature = "retry")]
@@ -0,0 +1,313 @@
+use std::{
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    http::Extensions,
+    Request, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use spacegate_kernel::{
+    extension::GatewayName,
+    helper_layers::{
+        check::{Check, CheckLayer},
+        map_request::MapRequestLayer,
+    },
+    marker::extension::Extension,
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+use tower_layer::Stack;
+
+use super::credential::{consumer_extensions, set_consumer_header, CredentialStore, SgCredentialSource};
+use crate::{def_plugin, MakeSgLayer};
+
+/// Authenticate requests by HTTP Basic authentication, the username is attached to the request as
+/// [`Consumer`](spacegate_kernel::extension::Consumer).
+///
+/// Passwords are stored as bcrypt (`$2a$`, `$2b$`, `$2y$`), `{SHA}` or plain text, the formats `htpasswd` produces
+/// except for `$apr1$`. Passwords in redis are stored as `<prefix>:<username>`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterBasicAuth {
+    pub source: SgCredentialSource,
+    pub realm: String,
+    /// Whether the `authorization` header is removed from the request before it's forwarded.
+    pub hide_credentials: bool,
+    /// Header to pass the consumer name to the backend, the one sent by the client is always removed.
+    pub consumer_header: Option<String>,
+}
+
+impl Default for SgFilterBasicAuth {
+    fn default() -> Self {
+        Self {
+            source: SgCredentialSource::default(),
+            realm: "spacegate".to_string(),
+            hide_credentials: false,
+            consumer_header: None,
+        }
+    }
+}
+
+/// The username and password carried by a request.
+#[derive(Clone)]
+pub struct BasicCredential {
+    pub username: Arc<str>,
+    pub password: Arc<str>,
+    pub gateway_name: Option<GatewayName>,
+}
+
+impl BasicCredential {
+    fn parse(value: &HeaderValue) -> Option<(String, String)> {
+        let value = value.to_str().ok()?;
+        let (scheme, encoded) = value.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+        Some((username.to_string(), password.to_string()))
+    }
+}
+
+enum PasswordHash<'a> {
+    Bcrypt(&'a str),
+    Sha1(&'a str),
+    Plain(&'a str),
+    Unsupported,
+}
+
+impl<'a> PasswordHash<'a> {
+    fn new(stored: &'a str) -> Self {
+        if ["$2a$", "$2b$", "$2y$"].iter().any(|prefix| stored.starts_with(prefix)) {
+            PasswordHash::Bcrypt(stored)
+        } else if let Some(digest) = stored.strip_prefix("{SHA}") {
+            PasswordHash::Sha1(digest)
+        } else if stored.starts_with('$') {
+            PasswordHash::Unsupported
+        } else {
+            PasswordHash::Plain(stored)
+        }
+    }
+
+    /// Verify `password`, bcrypt hashes are verified on the blocking pool since they are expensive by design.
+    async fn verify(&self, password: &str) -> bool {
+        match self {
+            PasswordHash::Bcrypt(hash) => {
+                let (hash, password) = (hash.to_string(), password.to_string());
+                tokio::task::spawn_blocking(move || bcrypt::verify(password, &hash).unwrap_or(false)).await.unwrap_or(false)
+            }
+            PasswordHash::Sha1(digest) => constant_time_eq(STANDARD.encode(Sha1::digest(password)).as_bytes(), digest.as_bytes()),
+            PasswordHash::Plain(plain) => constant_time_eq(password.as_bytes(), plain.as_bytes()),
+            PasswordHash::Unsupported => false,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// How many verified bcrypt credentials are remembered, so a client sending the same credentials doesn't pay for bcrypt every request.
+const VERIFIED_CACHE_CAPACITY: usize = 1024;
+
+#[derive(Clone)]
+pub struct BasicAuthCheck {
+    store: CredentialStore,
+    challenge: HeaderValue,
+    consumer_header: Option<HeaderName>,
+    /// Digests of verified `(bcrypt hash, password)` pairs, the password itself is never kept.
+    verified: Arc<Mutex<lru::LruCache<[u8; 20], ()>>>,
+}
+
+impl BasicAuthCheck {
+    async fn verify(&self, hash: PasswordHash<'_>, password: &str) -> bool {
+        let PasswordHash::Bcrypt(stored) = hash else {
+            return hash.verify(password).await;
+        };
+        let key: [u8; 20] = Sha1::new().chain_update(stored).chain_update([0]).chain_update(password).finalize().into();
+        if self.verified.lock().expect("poisoned verified credential cache").get(&key).is_some() {
+            return true;
+        }
+        let verified = hash.verify(password).await;
+        if verified {
+            self.verified.lock().expect("poisoned verified credential cache").put(key, ());
+        }
+        verified
+    }
+
+    fn unauthorized(&self, message: &'static str) -> Response<SgBody> {
+        let mut resp = Response::with_code_message(StatusCode::UNAUTHORIZED, message);
+        resp.headers_mut().insert(WWW_AUTHENTICATE, self.challenge.clone());
+        resp
+    }
+}
+
+impl Check<Extension<BasicCredential>> for BasicAuthCheck {
+    async fn check(&self, marker: &Extension<BasicCredential>) -> bool {
+        self.check_and_attach(marker).await.is_some()
+    }
+    async fn check_and_attach(&self, marker: &Extension<BasicCredential>) -> Option<Extensions> {
+        let stored =
+            self.store.get(&marker.username, marker.gateway_name.as_ref()).await.inspect_err(|e| tracing::warn!("[SG.Filter.BasicAuth] fail to look up user: {e}")).ok()??;
+        let hash = PasswordHash::new(&stored);
+        if matches!(hash, PasswordHash::Unsupported) {
+            tracing::warn!("[SG.Filter.BasicAuth] unsupported password hash of user {}", marker.username);
+        }
+        self.verify(hash, &marker.password).await.then(|| consumer_extensions(marker.username.clone()))
+    }
+    fn on_forbidden(&self, _marker: Extension<BasicCredential>) -> Response<SgBody> {
+        self.unauthorized("[SG.Filter.BasicAuth] invalid username or password")
+    }
+    fn on_missing(&self) -> Response<SgBody> {
+        self.unauthorized("[SG.Filter.BasicAuth] missing credentials")
+    }
+    fn on_pass(&self, request: Request<SgBody>) -> Request<SgBody> {
+        set_consumer_header(request, self.consumer_header.as_ref())
+    }
+}
+
+impl MakeSgLayer for SgFilterBasicAuth {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        if self.realm.contains('"') {
+            return Err("[SG.Filter.BasicAuth] realm must not contain '\"'".into());
+        }
+        let store = self.source.make_store::<BasicAuthPlugin>(|credential| (credential.consumer, credential.secret))?;
+        if let CredentialStore::Local(users) = &store {
+            if let Some((username, _)) = users.iter().find(|(_, stored)| matches!(PasswordHash::new(stored), PasswordHash::Unsupported)) {
+                return Err(format!("[SG.Filter.BasicAuth] unsupported password hash of user {username}, expect bcrypt, {{SHA}} or plain text").into());
+            }
+        }
+        let consumer_header = self.consumer_header.as_deref().map(HeaderName::from_str).transpose()?;
+        let check = BasicAuthCheck {
+            store,
+            challenge: HeaderValue::from_str(&format!(r#"Basic realm="{}", charset="UTF-8""#, self.realm))?,
+            consumer_header: consumer_header.clone(),
+            verified: Arc::new(Mutex::new(lru::LruCache::new(NonZeroUsize::new(VERIFIED_CACHE_CAPACITY).expect("non-zero capacity")))),
+        };
+        let hide_credentials = self.hide_credentials;
+        let extract = move |mut req: Request<SgBody>| {
+            if let Some(consumer_header) = &consumer_header {
+                req.headers_mut().remove(consumer_header);
+            }
+            let Some((username, password)) = req.headers().get(AUTHORIZATION).and_then(BasicCredential::parse) else {
+                return req;
+            };
+            if hide_credentials {
+                req.headers_mut().remove(AUTHORIZATION);
+            }
+            let gateway_name = req.extensions().get::<GatewayName>().cloned();
+            req.extensions_mut().insert(BasicCredential {
+                username: username.into(),
+                password: password.into(),
+                gateway_name,
+            });
+            req
+        };
+        Ok(SgBoxLayer::new(Stack::new(
+            CheckLayer::<_, Extension<BasicCredential>>::new(check),
+            MapRequestLayer::new(extract),
+        )))
+    }
+}
+
+def_plugin!("basic-auth", BasicAuthPlugin, SgFilterBasicAuth);
+#[cfg(feature = "schema")]
+crate::schema!(BasicAuthPlugin, SgFilterBasicAuth);
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use http_body_util::BodyExt;
+    use hyper::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        service::Service,
+        Request, StatusCode,
+    };
+    use serde_json::json;
+    use spacegate_kernel::{
+        extension::Consumer,
+        helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+        BoxError, SgBody,
+    };
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    /// Echo the consumer and whether the authorization header is forwarded.
+    #[derive(Clone)]
+    struct Backend;
+    impl FnLayerMethod for Backend {
+        async fn call(&self, req: Request<SgBody>, _inner: Inner) -> hyper::Response<SgBody> {
+            let consumer = req.extensions().get::<Consumer>().map(|c| c.to_string()).unwrap_or_default();
+            hyper::Response::new(SgBody::full(format!("{consumer}|{}", req.headers().contains_key(AUTHORIZATION))))
+        }
+    }
+
+    fn backend() -> spacegate_kernel::BoxHyperService {
+        spacegate_kernel::BoxHyperService::new(FnLayer::new(Backend).layer(spacegate_kernel::service::get_echo_service()))
+    }
+
+    fn request(username: &str, password: &str) -> Request<SgBody> {
+        let credential = STANDARD.encode(format!("{username}:{password}"));
+        Request::builder().uri("http://sg.idealworld.group/api").header(AUTHORIZATION, format!("Basic {credential}")).body(SgBody::empty()).expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth_file() -> Result<(), BoxError> {
+        let path = std::env::temp_dir().join(format!("sg-basic-auth-{}", std::process::id()));
+        let bcrypt = bcrypt::hash("alice-password", 4)?;
+        // `{SHA}` of `bob-password`
+        write!(
+            std::fs::File::create(&path)?,
+            "alice:{bcrypt}\nbob:{{SHA}}oHryCTyM4ObJvET53dSBiRe/fXQ=\ncarol:carol-password\n"
+        )?;
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "source": { "kind": "File", "path": path }, "hide_credentials": true }));
+        std::fs::remove_file(&path)?;
+        let service = layer?.layer(backend());
+
+        for (username, password) in [("alice", "alice-password"), ("bob", "bob-password"), ("carol", "carol-password")] {
+            let resp = service.call(request(username, password)).await?;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.into_body().collect().await?.to_bytes(), format!("{username}|false"));
+        }
+        // verified bcrypt credentials are cached, a wrong password must not hit the cache
+        assert_eq!(service.call(request("alice", "alice-password")).await?.status(), StatusCode::OK);
+
+        let resp = service.call(request("alice", "bob-password")).await?;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get(WWW_AUTHENTICATE).expect("missing www-authenticate"),
+            r#"Basic realm="spacegate", charset="UTF-8""#
+        );
+        assert_eq!(service.call(request("dave", "carol-password")).await?.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = service.call(Request::builder().uri("http://sg.idealworld.group/api").body(SgBody::empty())?).await?;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(resp.headers().contains_key(WWW_AUTHENTICATE));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth_inline() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({ "source": { "kind": "Inline", "credentials": [{ "consumer": "alice", "secret": "alice-password" }] }, "consumer_header": "x-consumer" }),
+        )?;
+        let service = layer.layer(backend());
+        let resp = service.call(request("alice", "alice-password")).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "alice|true");
+
+        let unsupported = json!({ "source": { "kind": "Inline", "credentials": [{ "consumer": "alice", "secret": "$apr1$salt$hash" }] } });
+        assert!(crate::SgPluginRepository::global().create_layer(None, super::CODE, unsupported).is_err());
+        Ok(())
+    }
+}
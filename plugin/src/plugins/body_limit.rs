@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{helper_layers::body_limit::BodyLimitLayer, BoxError, SgBoxLayer};
+
+use crate::{def_plugin, MakeSgLayer};
+
+/// Limit the size of request and response body.
+///
+/// A request exceeding the limit will be rejected with `413 Payload Too Large`,
+/// and an upstream response exceeding the limit will be replaced with `502 Bad Gateway` or aborted while streaming.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterBodyLimit {
+    /// Max bytes of request body, unset means no limit.
+    pub request_max_bytes: Option<usize>,
+    /// Max bytes of response body, unset means no limit.
+    pub response_max_bytes: Option<usize>,
+}
+
+impl MakeSgLayer for SgFilterBodyLimit {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        Ok(SgBoxLayer::new(BodyLimitLayer::new(self.request_max_bytes, self.response_max_bytes)))
+    }
+}
+
+def_plugin!("body-limit", BodyLimitPlugin, SgFilterBodyLimit);
+#[cfg(feature = "schema")]
+crate::schema!(
+    BodyLimitPlugin,
+    SgFilterBodyLimit {
+        request_max_bytes: Some(1 << 20),
+        response_max_bytes: Some(8 << 20),
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper::{body::Frame, service::Service, Method, Request, Response, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{extension::GatewayError, service::get_echo_service, BoxError, BoxHyperService, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    #[tokio::test]
+    async fn test_body_limit() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "request_max_bytes": 8 }))?;
+        let service = layer.layer(get_echo_service());
+
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group").body(SgBody::full("12345678"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group").header("content-length", "9").body(SgBody::full("123456789"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(resp.extensions().get::<GatewayError>().is_some());
+
+        // a service reading the whole body is answered with 413 as well
+        let dump_service = layer.layer(BoxHyperService::new(hyper::service::service_fn(|req: Request<SgBody>| async move {
+            let status = match req.into_body().dump().await {
+                Ok(_) => StatusCode::OK,
+                Err(_) => StatusCode::BAD_REQUEST,
+            };
+            Ok::<_, Infallible>(Response::builder().status(status).body(SgBody::empty()).expect("invalid response"))
+        })));
+        let chunks = futures_util::stream::iter(["1234", "5678", "9"].map(|chunk| Ok::<_, BoxError>(Frame::data(hyper::body::Bytes::from(chunk)))));
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group").body(SgBody::new_boxed_error(StreamBody::new(chunks)))?;
+        let resp = dump_service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(resp.extensions().get::<GatewayError>().is_some());
+
+        // chunked body without content-length, echo service streams the body back, so the error shows up while reading
+        let chunks = futures_util::stream::iter(["1234", "5678", "9"].map(|chunk| Ok::<_, BoxError>(Frame::data(hyper::body::Bytes::from(chunk)))));
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group").body(SgBody::new_boxed_error(StreamBody::new(chunks)))?;
+        let resp = service.call(req).await?;
+        assert!(resp.into_body().collect().await.is_err());
+
+        let body = SgBody::full("123456789");
+        assert!(body.dump_with_limit(8).await.is_err());
+        let chunks = futures_util::stream::iter(["1234", "5678"].map(|chunk| Ok::<_, BoxError>(Frame::data(hyper::body::Bytes::from(chunk)))));
+        let body = SgBody::new_boxed_error(StreamBody::new(chunks)).dump_with_limit(8).await?;
+        assert_eq!(body.collect().await?.to_bytes(), "12345678");
+        Ok(())
+    }
+}
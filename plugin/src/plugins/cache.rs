@@ -0,0 +1,614 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION, CACHE_CONTROL, DATE, ETAG, EXPIRES, HOST, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, SET_COOKIE, VARY},
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    helper_layers::{
+        body_limit::content_length,
+        function::{FnLayer, FnLayerMethod, Inner},
+    },
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+use tokio::sync::watch;
+
+use crate::{def_plugin, MakeSgLayer};
+
+pub mod store;
+use store::{unix_now, CacheEntry, CacheStore, CachedResponse, MemoryStore};
+
+/// Header telling how the response is served: `HIT`, `STALE`, `REVALIDATED`, `MISS` or `BYPASS`.
+pub const SG_CACHE_STATUS_HEADER: &str = "sg-cache-status";
+/// Header carrying the token of a `PURGE` request.
+pub const SG_CACHE_PURGE_TOKEN_HEADER: &str = "sg-cache-purge-token";
+const DEFAULT_CAPACITY: usize = 1024;
+const DEFAULT_MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Where cached responses are stored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum SgCacheStore {
+    /// A bounded in-process LRU store, `capacity` is the max count of entries.
+    Memory { capacity: usize },
+    /// A cluster-wide store, using the redis client of the gateway.
+    /// `id` distinguishes cache plugins sharing the same redis.
+    #[cfg(feature = "redis")]
+    Redis { id: Option<String> },
+}
+
+impl Default for SgCacheStore {
+    fn default() -> Self {
+        SgCacheStore::Memory { capacity: DEFAULT_CAPACITY }
+    }
+}
+
+/// Cache upstream responses of `GET` requests, following `Cache-Control`, `Expires` and `Vary` of responses.
+///
+/// Conditional requests with `If-None-Match` or `If-Modified-Since` are answered with `304 Not Modified` from cache,
+/// and expired responses with a validator are revalidated with the upstream.
+///
+/// When `purge_token` is set, a `PURGE` request with header `sg-cache-purge-token` removes all cached responses
+/// of the host of the request, whose path is the one of the request or under it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterCache {
+    pub store: SgCacheStore,
+    /// Query params included in the cache key, unset means the whole query string.
+    pub query_keys: Option<Vec<String>>,
+    /// TTL of responses without `Cache-Control: max-age` or `Expires`, unset means such responses are not cached.
+    pub default_ttl_secs: Option<u64>,
+    /// How long a stale response can be served while revalidating in background,
+    /// `stale-while-revalidate` of the response takes precedence.
+    pub stale_while_revalidate_secs: u64,
+    /// Responses with larger or unknown body size are not cached.
+    pub max_body_size: usize,
+    /// Only send one request to upstream for concurrent misses of the same key.
+    pub coalesce: bool,
+    pub purge_token: Option<String>,
+}
+
+impl Default for SgFilterCache {
+    fn default() -> Self {
+        Self {
+            store: SgCacheStore::default(),
+            query_keys: None,
+            default_ttl_secs: None,
+            stale_while_revalidate_secs: 0,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            coalesce: true,
+            purge_token: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut cc = CacheControl::default();
+        for directive in headers.get_all(CACHE_CONTROL).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')) {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let secs = value.and_then(|v| v.parse::<u64>().ok());
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cc.no_store = true,
+                "no-cache" => cc.no_cache = true,
+                "private" => cc.private = true,
+                "public" => cc.public = true,
+                "max-age" => cc.max_age = secs,
+                "s-maxage" => cc.s_maxage = secs,
+                "stale-while-revalidate" => cc.stale_while_revalidate = secs,
+                _ => {}
+            }
+        }
+        cc
+    }
+}
+
+fn parse_http_date(value: &str) -> Option<u64> {
+    chrono::DateTime::parse_from_rfc2822(value).ok().and_then(|t| u64::try_from(t.timestamp()).ok())
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Lowercased header names in `Vary`.
+fn vary_names(headers: &HeaderMap) -> Vec<String> {
+    let mut names = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Weak comparison of entity tags, as `If-None-Match` requires.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim().trim_start_matches("W/");
+    if_none_match.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+impl SgFilterCache {
+    fn primary_key(&self, req: &Request<SgBody>) -> String {
+        let uri = req.uri();
+        let host = header_str(req.headers(), &HOST).or(uri.host()).unwrap_or_default();
+        let query = match (&self.query_keys, uri.query()) {
+            (None, Some(query)) => query.to_string(),
+            (Some(keys), Some(query)) => {
+                let mut pairs = query.split('&').filter(|pair| keys.iter().any(|key| pair.split('=').next() == Some(key.as_str()))).collect::<Vec<_>>();
+                pairs.sort_unstable();
+                pairs.join("&")
+            }
+            (_, None) => String::new(),
+        };
+        // HEAD requests are served by the response of GET
+        format!("{host}{path}?{query}|GET", path = uri.path())
+    }
+
+    /// Returns freshness and stale-while-revalidate lifetime, `None` means the response should not be cached.
+    fn lifetime(&self, req_headers: &HeaderMap, resp: &Response<SgBody>, now: u64) -> Option<(u64, u64)> {
+        if !matches!(resp.status().as_u16(), 200 | 203 | 204 | 300 | 301 | 308 | 404 | 410) {
+            return None;
+        }
+        let headers = resp.headers();
+        let cc = CacheControl::parse(headers);
+        if cc.no_store || cc.no_cache || cc.private || headers.contains_key(SET_COOKIE) || vary_names(headers).iter().any(|name| name == "*") {
+            return None;
+        }
+        if req_headers.contains_key(AUTHORIZATION) && !cc.public && cc.s_maxage.is_none() {
+            return None;
+        }
+        let expires = || {
+            let expires = header_str(headers, &EXPIRES).map(|v| parse_http_date(v).unwrap_or_default())?;
+            let date = header_str(headers, &DATE).and_then(parse_http_date).unwrap_or(now);
+            Some(expires.saturating_sub(date))
+        };
+        let fresh = cc.s_maxage.or(cc.max_age).or_else(expires).or(self.default_ttl_secs)?;
+        let age = header_str(headers, &hyper::header::AGE).and_then(|v| v.parse::<u64>().ok()).unwrap_or_default();
+        let fresh = fresh.saturating_sub(age);
+        let stale = cc.stale_while_revalidate.unwrap_or(self.stale_while_revalidate_secs);
+        (fresh + stale > 0).then_some((fresh, stale))
+    }
+}
+
+/// Concurrent misses waiting for the same key.
+#[derive(Debug, Default)]
+struct InFlight {
+    map: Mutex<HashMap<String, watch::Receiver<()>>>,
+}
+
+/// Removes the key when dropped, which also wakes up all followers.
+struct Leader {
+    key: String,
+    in_flight: Arc<InFlight>,
+    _tx: watch::Sender<()>,
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        self.in_flight.map.lock().expect("poisoned in flight map").remove(&self.key);
+    }
+}
+
+enum Flight {
+    Leader(Leader),
+    Follower(watch::Receiver<()>),
+}
+
+impl InFlight {
+    fn join(self: &Arc<Self>, key: &str) -> Flight {
+        let mut map = self.map.lock().expect("poisoned in flight map");
+        if let Some(rx) = map.get(key) {
+            return Flight::Follower(rx.clone());
+        }
+        let (tx, rx) = watch::channel(());
+        map.insert(key.to_string(), rx);
+        Flight::Leader(Leader {
+            key: key.to_string(),
+            in_flight: self.clone(),
+            _tx: tx,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Cache {
+    config: Arc<SgFilterCache>,
+    memory: Option<Arc<MemoryStore>>,
+    in_flight: Arc<InFlight>,
+}
+
+fn with_status(mut resp: Response<SgBody>, status: &'static str) -> Response<SgBody> {
+    resp.headers_mut().insert(SG_CACHE_STATUS_HEADER, HeaderValue::from_static(status));
+    resp
+}
+
+impl Cache {
+    fn store(&self, _req: &Request<SgBody>) -> Result<Arc<dyn CacheStore>, BoxError> {
+        match &self.config.store {
+            SgCacheStore::Memory { .. } => Ok(self.memory.clone().expect("memory store should be created") as Arc<dyn CacheStore>),
+            #[cfg(feature = "redis")]
+            SgCacheStore::Redis { id } => {
+                use spacegate_kernel::extension::GatewayName;
+                let gateway_name = _req.extensions().get::<GatewayName>().ok_or("missing gateway name")?;
+                let client = spacegate_ext_redis::global_repo().get(gateway_name).ok_or("missing redis client")?;
+                Ok(Arc::new(store::RedisStore::new(client, <CachePlugin as crate::Plugin>::redis_prefix(id.as_deref()))))
+            }
+        }
+    }
+
+    /// Find the cached response, following `Vary` of the stored response.
+    async fn lookup(store: &dyn CacheStore, key: &str, req_headers: &HeaderMap) -> Result<Option<CachedResponse>, BoxError> {
+        match store.get(key).await? {
+            Some(CacheEntry::Response(resp)) => Ok(Some(resp)),
+            Some(CacheEntry::Variants { vary }) => match store.get(&Self::variant_key(key, &vary, req_headers)).await? {
+                Some(CacheEntry::Response(resp)) => Ok(Some(resp)),
+                _ => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn variant_key(key: &str, vary: &[String], req_headers: &HeaderMap) -> String {
+        let values = vary
+            .iter()
+            .map(|name| {
+                let value = req_headers.get_all(name.as_str()).iter().filter_map(|v| v.to_str().ok()).collect::<Vec<_>>().join(",");
+                format!("{name}={value}")
+            })
+            .collect::<Vec<_>>();
+        format!("{key}|{}", values.join(";"))
+    }
+
+    async fn save(store: &dyn CacheStore, key: &str, req_headers: &HeaderMap, cached: CachedResponse, now: u64) -> Result<(), BoxError> {
+        let ttl = Duration::from_secs(cached.stale_until.saturating_sub(now));
+        let vary = vary_names(&cached.header_map());
+        if vary.is_empty() {
+            store.set(key, CacheEntry::Response(cached), ttl).await
+        } else {
+            store.set(&Self::variant_key(key, &vary, req_headers), CacheEntry::Response(cached), ttl).await?;
+            store.set(key, CacheEntry::Variants { vary }, ttl).await
+        }
+    }
+
+    /// Answer the request from a cached response, with `304 Not Modified` if the request is conditional and matches.
+    fn serve(cached: &CachedResponse, req: &Request<SgBody>, now: u64, status: &'static str) -> Response<SgBody> {
+        let headers = req.headers();
+        let not_modified = if let Some(if_none_match) = header_str(headers, &IF_NONE_MATCH) {
+            cached.header(&ETAG).is_some_and(|etag| etag_matches(if_none_match, etag))
+        } else if let Some(if_modified_since) = header_str(headers, &IF_MODIFIED_SINCE).and_then(parse_http_date) {
+            cached.header(&LAST_MODIFIED).and_then(parse_http_date).is_some_and(|last_modified| last_modified <= if_modified_since)
+        } else {
+            false
+        };
+        let mut resp = cached.to_response(now);
+        if not_modified {
+            *resp.status_mut() = StatusCode::NOT_MODIFIED;
+            *resp.body_mut() = SgBody::empty();
+            resp.headers_mut().remove(hyper::header::CONTENT_LENGTH);
+        } else if req.method() == Method::HEAD {
+            *resp.body_mut() = SgBody::empty();
+        }
+        with_status(resp, status)
+    }
+
+    /// Forward the request to upstream, and cache the response if possible.
+    ///
+    /// If there is an expired response with validators, the request is made conditional,
+    /// and the expired response is refreshed when upstream answers `304 Not Modified`.
+    async fn fetch(&self, store: &dyn CacheStore, key: &str, req: Request<SgBody>, inner: Inner, expired: Option<CachedResponse>) -> Response<SgBody> {
+        let (mut parts, body) = req.into_parts();
+        let client_conditional = parts.headers.contains_key(IF_NONE_MATCH) || parts.headers.contains_key(IF_MODIFIED_SINCE);
+        let mut revalidating = None;
+        if let Some(expired) = expired.filter(|_| !client_conditional && parts.method == Method::GET) {
+            if let Some(etag) = expired.header(&ETAG).and_then(|v| HeaderValue::from_str(v).ok()) {
+                parts.headers.insert(IF_NONE_MATCH, etag);
+                revalidating = Some(expired);
+            } else if let Some(last_modified) = expired.header(&LAST_MODIFIED).and_then(|v| HeaderValue::from_str(v).ok()) {
+                parts.headers.insert(IF_MODIFIED_SINCE, last_modified);
+                revalidating = Some(expired);
+            }
+        }
+        let req_headers = parts.headers.clone();
+        let req_for_serve = Request::from_parts(parts.clone(), SgBody::empty());
+        let resp = inner.call(Request::from_parts(parts, body)).await;
+        let now = unix_now();
+
+        if let Some(expired) = revalidating.filter(|_| resp.status() == StatusCode::NOT_MODIFIED) {
+            // refresh the expired response with headers of 304 response
+            let mut headers = expired.header_map();
+            for (name, value) in resp.headers() {
+                headers.insert(name, value.clone());
+            }
+            let mut refreshed = Response::new(SgBody::empty());
+            *refreshed.status_mut() = StatusCode::from_u16(expired.status).unwrap_or(StatusCode::OK);
+            *refreshed.headers_mut() = headers;
+            let Some(cached) = self
+                .config
+                .lifetime(&req_headers, &refreshed, now)
+                .and_then(|(fresh, stale)| CachedResponse::new(refreshed.status(), refreshed.headers(), expired.body.clone(), now, fresh, stale))
+            else {
+                return with_status(expired.to_response(now), "REVALIDATED");
+            };
+            if let Err(e) = Self::save(store, key, &req_headers, cached.clone(), now).await {
+                tracing::warn!("[SG.Filter.Cache] failed to save response: {e}");
+            }
+            let mut req = req_for_serve;
+            req.headers_mut().remove(IF_NONE_MATCH);
+            req.headers_mut().remove(IF_MODIFIED_SINCE);
+            return Self::serve(&cached, &req, now, "REVALIDATED");
+        }
+
+        if req_for_serve.method() != Method::GET {
+            return with_status(resp, "MISS");
+        }
+        let Some((fresh, stale)) = self.config.lifetime(&req_headers, &resp, now) else {
+            return with_status(resp, "MISS");
+        };
+        let (resp_parts, body) = resp.into_parts();
+        let cacheable_size =
+            body.get_dumped().map(|dumped| dumped.len() as u64).or_else(|| content_length(&resp_parts.headers)).is_some_and(|len| len <= self.config.max_body_size as u64);
+        if !cacheable_size {
+            return with_status(Response::from_parts(resp_parts, body), "MISS");
+        }
+        let body = match body.dump().await {
+            Ok(body) => body,
+            Err(e) => return Response::with_code_message(StatusCode::BAD_GATEWAY, format!("[SG.Filter.Cache] failed to read upstream response: {e}")),
+        };
+        let dumped = body.get_dumped().cloned().unwrap_or_default();
+        if let Some(cached) = CachedResponse::new(resp_parts.status, &resp_parts.headers, dumped, now, fresh, stale) {
+            if let Err(e) = Self::save(store, key, &req_headers, cached, now).await {
+                tracing::warn!("[SG.Filter.Cache] failed to save response: {e}");
+            }
+        }
+        with_status(Response::from_parts(resp_parts, body), "MISS")
+    }
+
+    async fn purge(&self, req: &Request<SgBody>) -> Option<Response<SgBody>> {
+        let token = self.config.purge_token.as_deref()?;
+        if header_str(req.headers(), &HeaderName::from_static(SG_CACHE_PURGE_TOKEN_HEADER)) != Some(token) {
+            return Some(Response::with_code_message(StatusCode::FORBIDDEN, "[SG.Filter.Cache] invalid purge token"));
+        }
+        let host = header_str(req.headers(), &HOST).or(req.uri().host()).unwrap_or_default();
+        let prefix = format!("{host}{path}", path = req.uri().path());
+        let resp = match self.store(req) {
+            Ok(store) => match store.purge(&prefix).await {
+                Ok(count) => Response::with_code_message(StatusCode::OK, format!("purged {count} entries")),
+                Err(e) => Response::with_code_message(StatusCode::INTERNAL_SERVER_ERROR, format!("[SG.Filter.Cache] failed to purge: {e}")),
+            },
+            Err(e) => Response::with_code_message(StatusCode::INTERNAL_SERVER_ERROR, format!("[SG.Filter.Cache] {e}")),
+        };
+        Some(resp)
+    }
+}
+
+impl FnLayerMethod for Cache {
+    async fn call(&self, req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        if req.method().as_str() == "PURGE" {
+            if let Some(resp) = self.purge(&req).await {
+                return resp;
+            }
+        }
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return inner.call(req).await;
+        }
+        let req_cc = CacheControl::parse(req.headers());
+        if req_cc.no_store {
+            return with_status(inner.call(req).await, "BYPASS");
+        }
+        let store = match self.store(&req) {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::warn!("[SG.Filter.Cache] cache store unavailable: {e}");
+                return with_status(inner.call(req).await, "BYPASS");
+            }
+        };
+        let key = self.config.primary_key(&req);
+        let lookup = |req_headers: HeaderMap| {
+            let store = store.clone();
+            let key = key.clone();
+            async move {
+                Self::lookup(store.as_ref(), &key, &req_headers).await.unwrap_or_else(|e| {
+                    tracing::warn!("[SG.Filter.Cache] failed to lookup cache: {e}");
+                    None
+                })
+            }
+        };
+        let cached = if req_cc.no_cache { None } else { lookup(req.headers().clone()).await };
+        let now = unix_now();
+        match cached {
+            Some(cached) if cached.is_fresh(now) => Self::serve(&cached, &req, now, "HIT"),
+            Some(cached) if cached.is_usable_stale(now) => {
+                if let Flight::Leader(leader) = self.in_flight.join(&key) {
+                    let this = self.clone();
+                    let (store, key) = (store.clone(), key.clone());
+                    let mut revalidate_req = Request::new(SgBody::empty());
+                    *revalidate_req.uri_mut() = req.uri().clone();
+                    *revalidate_req.headers_mut() = req.headers().clone();
+                    *revalidate_req.extensions_mut() = req.extensions().clone();
+                    revalidate_req.headers_mut().remove(IF_NONE_MATCH);
+                    revalidate_req.headers_mut().remove(IF_MODIFIED_SINCE);
+                    let expired = cached.clone();
+                    // serve the stale response, and revalidate in background
+                    tokio::spawn(async move {
+                        this.fetch(store.as_ref(), &key, revalidate_req, inner, Some(expired)).await;
+                        drop(leader);
+                    });
+                }
+                Self::serve(&cached, &req, now, "STALE")
+            }
+            expired => {
+                if self.config.coalesce && req.method() == Method::GET {
+                    match self.in_flight.join(&key) {
+                        Flight::Leader(leader) => {
+                            let resp = self.fetch(store.as_ref(), &key, req, inner, expired).await;
+                            drop(leader);
+                            return resp;
+                        }
+                        Flight::Follower(mut rx) => {
+                            // woken up when the leader finished
+                            let _ = rx.changed().await;
+                            let now = unix_now();
+                            if let Some(cached) = lookup(req.headers().clone()).await.filter(|cached| cached.is_fresh(now)) {
+                                return Self::serve(&cached, &req, now, "HIT");
+                            }
+                        }
+                    }
+                }
+                self.fetch(store.as_ref(), &key, req, inner, expired).await
+            }
+        }
+    }
+}
+
+impl MakeSgLayer for SgFilterCache {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        let memory = match &self.store {
+            SgCacheStore::Memory { capacity } => Some(Arc::new(MemoryStore::new(
+                NonZeroUsize::new(*capacity).ok_or("[SG.Filter.Cache] capacity should be greater than 0")?,
+            ))),
+            #[cfg(feature = "redis")]
+            SgCacheStore::Redis { .. } => None,
+        };
+        let cache = Cache {
+            config: Arc::new(self.clone()),
+            memory,
+            in_flight: Default::default(),
+        };
+        Ok(SgBoxLayer::new(FnLayer::new(cache)))
+    }
+}
+
+def_plugin!("cache", CachePlugin, SgFilterCache);
+#[cfg(feature = "schema")]
+crate::schema!(
+    CachePlugin,
+    SgFilterCache {
+        query_keys: Some(vec!["page".to_string()]),
+        default_ttl_secs: Some(60),
+        stale_while_revalidate_secs: 10,
+        ..Default::default()
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use http_body_util::BodyExt;
+    use hyper::{header::IF_NONE_MATCH, service::Service, Method, Request, Response, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{service::BoxHyperService, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    use super::SG_CACHE_STATUS_HEADER;
+
+    fn upstream(counter: Arc<AtomicUsize>) -> BoxHyperService {
+        BoxHyperService::new(hyper::service::service_fn(move |req: Request<SgBody>| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let cache_control = if req.uri().path().starts_with("/no-store") { "no-store" } else { "max-age=60" };
+                let body = format!("{}", req.uri());
+                let resp = Response::builder()
+                    .header("cache-control", cache_control)
+                    .header("etag", "\"v1\"")
+                    .header("content-length", body.len())
+                    .body(SgBody::full(body))
+                    .expect("invalid response");
+                Ok::<_, Infallible>(resp)
+            }
+        }))
+    }
+
+    fn get(path: &str) -> Request<SgBody> {
+        Request::builder().uri(format!("http://sg.idealworld.group{path}")).body(SgBody::empty()).expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_cache() -> Result<(), BoxError> {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "query_keys": ["page"], "purge_token": "secret" }))?;
+        let service = layer.layer(upstream(counter.clone()));
+
+        let resp = service.call(get("/a?page=1&t=1")).await?;
+        assert_eq!(resp.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "MISS");
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "http://sg.idealworld.group/a?page=1&t=1");
+        // ignored query params are not part of the key
+        let resp = service.call(get("/a?t=2&page=1")).await?;
+        assert_eq!(resp.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "HIT");
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "http://sg.idealworld.group/a?page=1&t=1");
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        let mut req = get("/a?page=1");
+        req.headers_mut().insert(IF_NONE_MATCH, "W/\"v1\"".parse()?);
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        // not cacheable
+        service.call(get("/no-store")).await?;
+        let resp = service.call(get("/no-store")).await?;
+        assert_eq!(resp.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "MISS");
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        // concurrent misses are coalesced
+        let tasks = (0..5).map(|_| tokio::spawn(service.call(get("/b")))).collect::<Vec<_>>();
+        for task in tasks {
+            let resp = task.await??;
+            assert_eq!(resp.into_body().collect().await?.to_bytes(), "http://sg.idealworld.group/b");
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+
+        // purge removes `/a` and what's under it, but not `/ab`
+        assert_eq!(service.call(get("/ab")).await?.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "MISS");
+        assert_eq!(counter.load(Ordering::SeqCst), 5);
+        let purge =
+            || Request::builder().method(Method::from_bytes(b"PURGE").expect("invalid method")).uri("http://sg.idealworld.group/a").body(SgBody::empty()).expect("invalid request");
+        assert_eq!(service.call(purge()).await?.status(), StatusCode::FORBIDDEN);
+        let mut req = purge();
+        req.headers_mut().insert(super::SG_CACHE_PURGE_TOKEN_HEADER, "secret".parse()?);
+        assert_eq!(service.call(req).await?.status(), StatusCode::OK);
+        let resp = service.call(get("/a?page=1")).await?;
+        assert_eq!(resp.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "MISS");
+        assert_eq!(counter.load(Ordering::SeqCst), 6);
+        assert_eq!(service.call(get("/ab")).await?.headers().get(SG_CACHE_STATUS_HEADER).expect("missing cache status"), "HIT");
+        assert_eq!(counter.load(Ordering::SeqCst), 6);
+        Ok(())
+    }
+}
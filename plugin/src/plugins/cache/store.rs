@@ -0,0 +1,256 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::future::BoxFuture;
+use hyper::{
+    body::Bytes,
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Response, StatusCode,
+};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{BoxError, SgBody};
+
+/// Seconds since unix epoch, entries are shared between gateway instances through redis, so we don't use monotonic time here.
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// A response stored in cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(skip)]
+    pub body: Bytes,
+    /// When the response is received, in unix seconds.
+    pub stored_at: u64,
+    /// The response can be served without revalidation until this time.
+    pub fresh_until: u64,
+    /// The response can be served while revalidating in background until this time.
+    pub stale_until: u64,
+}
+
+impl CachedResponse {
+    /// Returns `None` if some header value is not valid utf-8, such response is not cached.
+    pub fn new(status: StatusCode, headers: &HeaderMap, body: Bytes, stored_at: u64, fresh_secs: u64, stale_secs: u64) -> Option<Self> {
+        let headers = headers.iter().map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string()))).collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            status: status.as_u16(),
+            headers,
+            body,
+            stored_at,
+            fresh_until: stored_at + fresh_secs,
+            stale_until: stored_at + fresh_secs + stale_secs,
+        })
+    }
+
+    pub fn header_map(&self) -> HeaderMap {
+        let mut map = HeaderMap::with_capacity(self.headers.len());
+        for (k, v) in &self.headers {
+            if let (Ok(k), Ok(v)) = (HeaderName::from_bytes(k.as_bytes()), HeaderValue::from_str(v)) {
+                map.append(k, v);
+            }
+        }
+        map
+    }
+
+    pub fn header(&self, name: &HeaderName) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name.as_str())).map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_fresh(&self, now: u64) -> bool {
+        now < self.fresh_until
+    }
+
+    pub fn is_usable_stale(&self, now: u64) -> bool {
+        now < self.stale_until
+    }
+
+    pub fn to_response(&self, now: u64) -> Response<SgBody> {
+        let mut resp = Response::new(SgBody::full(self.body.clone()));
+        *resp.status_mut() = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        *resp.headers_mut() = self.header_map();
+        resp.headers_mut().insert(hyper::header::AGE, HeaderValue::from(now.saturating_sub(self.stored_at)));
+        resp
+    }
+}
+
+/// A cache entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum CacheEntry {
+    /// The response varies by these request headers, the response itself is stored under a secondary key.
+    Variants {
+        vary: Vec<String>,
+    },
+    Response(CachedResponse),
+}
+
+/// Storage of cached responses.
+pub trait CacheStore: Send + Sync {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<CacheEntry>, BoxError>>;
+    fn set<'a>(&'a self, key: &'a str, entry: CacheEntry, ttl: Duration) -> BoxFuture<'a, Result<(), BoxError>>;
+    /// Remove all entries under `prefix`, see [`is_under`], returns the count of removed entries.
+    fn purge<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<usize, BoxError>>;
+}
+
+/// Whether `key` is under `prefix` on a path segment boundary, so purging `/api` removes `/api?page=1` and `/api/users`
+/// but not `/apis`.
+pub fn is_under(key: &str, prefix: &str) -> bool {
+    key.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?']))
+}
+
+/// A bounded in-process LRU store.
+#[derive(Debug)]
+pub struct MemoryStore {
+    cache: Mutex<LruCache<String, (CacheEntry, u64)>>,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<CacheEntry>, BoxError>> {
+        let mut cache = self.cache.lock().expect("poisoned cache store");
+        let entry = match cache.get(key) {
+            Some((_, expire_at)) if *expire_at <= unix_now() => {
+                cache.pop(key);
+                None
+            }
+            Some((entry, _)) => Some(entry.clone()),
+            None => None,
+        };
+        Box::pin(futures_util::future::ready(Ok(entry)))
+    }
+
+    fn set<'a>(&'a self, key: &'a str, entry: CacheEntry, ttl: Duration) -> BoxFuture<'a, Result<(), BoxError>> {
+        self.cache.lock().expect("poisoned cache store").put(key.to_string(), (entry, unix_now() + ttl.as_secs()));
+        Box::pin(futures_util::future::ready(Ok(())))
+    }
+
+    fn purge<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<usize, BoxError>> {
+        let mut cache = self.cache.lock().expect("poisoned cache store");
+        let keys = cache.iter().filter(|(k, _)| is_under(k, prefix)).map(|(k, _)| k.clone()).collect::<Vec<_>>();
+        for key in &keys {
+            cache.pop(key);
+        }
+        Box::pin(futures_util::future::ready(Ok(keys.len())))
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_store::RedisStore;
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    use std::time::Duration;
+
+    use futures_util::future::BoxFuture;
+    use spacegate_ext_redis::{redis::AsyncCommands, RedisClient};
+    use spacegate_kernel::BoxError;
+
+    use super::{is_under, CacheEntry, CacheStore};
+
+    const META_FIELD: &str = "meta";
+    const BODY_FIELD: &str = "body";
+
+    /// A cluster-wide store, each entry is a redis hash with the metadata in json and the raw body.
+    #[derive(Debug, Clone)]
+    pub struct RedisStore {
+        pub client: RedisClient,
+        pub prefix: String,
+    }
+
+    impl RedisStore {
+        pub fn new(client: RedisClient, prefix: impl Into<String>) -> Self {
+            Self { client, prefix: prefix.into() }
+        }
+
+        fn redis_key(&self, key: &str) -> String {
+            format!("{}:{}", self.prefix, key)
+        }
+    }
+
+    /// Escape glob pattern characters used by `SCAN MATCH`.
+    fn escape_pattern(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    impl CacheStore for RedisStore {
+        fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<CacheEntry>, BoxError>> {
+            Box::pin(async move {
+                let mut conn = self.client.get_conn().await;
+                let (meta, body): (Option<String>, Option<Vec<u8>>) = conn.hget(self.redis_key(key), &[META_FIELD, BODY_FIELD]).await?;
+                let Some(meta) = meta else { return Ok(None) };
+                let mut entry = serde_json::from_str::<CacheEntry>(&meta)?;
+                if let CacheEntry::Response(resp) = &mut entry {
+                    resp.body = body.unwrap_or_default().into();
+                }
+                Ok(Some(entry))
+            })
+        }
+
+        fn set<'a>(&'a self, key: &'a str, entry: CacheEntry, ttl: Duration) -> BoxFuture<'a, Result<(), BoxError>> {
+            Box::pin(async move {
+                let mut conn = self.client.get_conn().await;
+                let redis_key = self.redis_key(key);
+                let meta = serde_json::to_string(&entry)?;
+                let body = match &entry {
+                    CacheEntry::Response(resp) => resp.body.to_vec(),
+                    CacheEntry::Variants { .. } => Vec::new(),
+                };
+                spacegate_ext_redis::redis::pipe()
+                    .atomic()
+                    .del(&redis_key)
+                    .ignore()
+                    .hset_multiple(&redis_key, &[(META_FIELD, meta.into_bytes()), (BODY_FIELD, body)])
+                    .ignore()
+                    .cmd("EXPIRE")
+                    .arg(&redis_key)
+                    .arg(ttl.as_secs().max(1))
+                    .ignore()
+                    .query_async::<_, ()>(&mut conn)
+                    .await?;
+                Ok(())
+            })
+        }
+
+        fn purge<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<usize, BoxError>> {
+            Box::pin(async move {
+                let mut conn = self.client.get_conn().await;
+                let pattern = format!("{}*", escape_pattern(&self.redis_key(prefix)));
+                let keys = {
+                    let mut iter = conn.scan_match::<_, String>(pattern).await?;
+                    let mut keys = Vec::new();
+                    while let Some(key) = iter.next_item().await {
+                        if key.strip_prefix(&self.redis_key("")).is_some_and(|key| is_under(key, prefix)) {
+                            keys.push(key);
+                        }
+                    }
+                    keys
+                };
+                if keys.is_empty() {
+                    return Ok(0);
+                }
+                let removed: usize = conn.del(keys).await?;
+                Ok(removed)
+            })
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    plugin_layers::comde::{compress_type::CompressionType, CompressionConfig, CompressionLayer, Level, DEFAULT_EXCLUDED_CONTENT_TYPES},
+    BoxError, SgBoxLayer,
+};
+
+use crate::{def_plugin, MakeSgLayer};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SgCompressionEncoding {
+    Br,
+    Zstd,
+    Gzip,
+    Deflate,
+}
+
+impl From<SgCompressionEncoding> for CompressionType {
+    fn from(value: SgCompressionEncoding) -> Self {
+        match value {
+            SgCompressionEncoding::Br => CompressionType::Br,
+            SgCompressionEncoding::Zstd => CompressionType::Zstd,
+            SgCompressionEncoding::Gzip => CompressionType::Gzip,
+            SgCompressionEncoding::Deflate => CompressionType::Deflate,
+        }
+    }
+}
+
+/// Compress responses with the encoding negotiated from the request's `Accept-Encoding`.
+///
+/// The body is compressed as a stream, responses which are small, already encoded,
+/// or of an excluded content type are passed through untouched.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterCompression {
+    /// Supported encodings, in the order of server preference when the client accepts several of them with the same q-value.
+    pub encodings: Vec<SgCompressionEncoding>,
+    /// Responses with a `Content-Length` smaller than this are not compressed.
+    pub min_size: u64,
+    /// Content type prefixes which are not compressed.
+    pub excluded_content_types: Vec<String>,
+    /// Compression level, unset means the default level of each algorithm.
+    pub level: Option<i32>,
+}
+
+impl Default for SgFilterCompression {
+    fn default() -> Self {
+        Self {
+            encodings: vec![
+                SgCompressionEncoding::Br,
+                SgCompressionEncoding::Zstd,
+                SgCompressionEncoding::Gzip,
+                SgCompressionEncoding::Deflate,
+            ],
+            min_size: 1024,
+            excluded_content_types: DEFAULT_EXCLUDED_CONTENT_TYPES.iter().map(|s| s.to_string()).collect(),
+            level: None,
+        }
+    }
+}
+
+impl MakeSgLayer for SgFilterCompression {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        if self.encodings.is_empty() {
+            return Err("[SG.Filter.Compression] encodings must not be empty".into());
+        }
+        let config = CompressionConfig {
+            encodings: self.encodings.iter().map(|e| CompressionType::from(*e)).collect(),
+            min_size: self.min_size,
+            excluded_content_types: self.excluded_content_types.clone(),
+            level: self.level.map(Level::Precise).unwrap_or(Level::Default),
+        };
+        Ok(SgBoxLayer::new(CompressionLayer::new(config)))
+    }
+}
+
+def_plugin!("compression", CompressionPlugin, SgFilterCompression);
+#[cfg(feature = "schema")]
+crate::schema!(CompressionPlugin, SgFilterCompression);
+
+#[cfg(test)]
+mod test {
+    use http_body_util::BodyExt;
+    use hyper::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, VARY},
+        service::Service,
+        Request,
+    };
+    use serde_json::json;
+    use spacegate_kernel::{plugin_layers::comde::compress_type::CompressionType, service::get_echo_service, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    #[tokio::test]
+    async fn test_compression() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "encodings": ["gzip", "br"], "min_size": 0 }))?;
+        let service = layer.layer(get_echo_service());
+        let message = "hello from spacegate ".repeat(64);
+
+        let req = Request::builder().header(ACCEPT_ENCODING, "br;q=0.5, gzip, zstd").body(SgBody::full(message.clone()))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).expect("missing content-encoding"), "gzip");
+        assert_eq!(resp.headers().get(VARY).expect("missing vary"), "accept-encoding");
+        let body = CompressionType::Gzip.decode(resp.into_body()).collect().await?.to_bytes();
+        assert_eq!(body, message.as_bytes());
+
+        // zstd is not enabled
+        let req = Request::builder().header(ACCEPT_ENCODING, "zstd").body(SgBody::full(message.clone()))?;
+        let resp = service.call(req).await?;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), message.as_bytes());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_excluded_content_type() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "min_size": 0 }))?;
+        let service = layer.layer(spacegate_kernel::BoxHyperService::new(hyper::service::service_fn(|req: Request<SgBody>| async move {
+            let mut resp = hyper::Response::new(req.into_body());
+            resp.headers_mut().insert(CONTENT_TYPE, "image/png".parse().expect("invalid header"));
+            Ok::<_, std::convert::Infallible>(resp)
+        })));
+        let req = Request::builder().header(ACCEPT_ENCODING, "gzip").body(SgBody::full("not really a png"))?;
+        let resp = service.call(req).await?;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        Ok(())
+    }
+}
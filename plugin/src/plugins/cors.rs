@@ -0,0 +1,444 @@
+use std::sync::Arc;
+
+use hyper::{
+    header::{
+        HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+
+use crate::{def_plugin, MakeSgLayer};
+
+/// Cross-Origin Resource Sharing.
+///
+/// Preflight requests are answered by the gateway directly, actual requests are forwarded and
+/// the CORS headers are added to their responses. Preflight requests from a disallowed origin are rejected with `403 Forbidden`,
+/// actual requests from a disallowed origin are forwarded without the CORS headers, unless `reject_disallowed_origin` is set.
+///
+/// see also: https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterCors {
+    /// Allowed origins, an item is one of:
+    /// - `*`, any origin;
+    /// - an exact origin, e.g. `https://app.example.com`;
+    /// - a wildcard subdomain, e.g. `https://*.example.com` or `*.example.com`.
+    pub allow_origins: Vec<String>,
+    /// Allowed origins in regular expressions, matched against the whole `Origin` header.
+    pub allow_origin_regexes: Vec<String>,
+    /// Allowed methods, `*` allows any method.
+    pub allow_methods: Vec<String>,
+    /// Allowed request headers, `*` allows any header requested by the preflight.
+    pub allow_headers: Vec<String>,
+    /// Response headers exposed to the browser.
+    pub expose_headers: Vec<String>,
+    /// Whether the response can be shared when the request is made with credentials.
+    pub allow_credentials: bool,
+    /// How long the result of a preflight can be cached by the browser.
+    pub max_age_secs: Option<u64>,
+    /// Reject actual requests from a disallowed origin with `403 Forbidden`, instead of forwarding them without the CORS headers.
+    pub reject_disallowed_origin: bool,
+}
+
+impl Default for SgFilterCors {
+    fn default() -> Self {
+        Self {
+            allow_origins: vec!["*".to_string()],
+            allow_origin_regexes: Vec::new(),
+            allow_methods: ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"].into_iter().map(String::from).collect(),
+            allow_headers: vec!["*".to_string()],
+            expose_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_secs: None,
+            reject_disallowed_origin: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum OriginMatcher {
+    Any,
+    Exact(String),
+    /// `scheme` is `None` if the pattern doesn't specify one, `suffix` starts with a dot.
+    WildcardSubdomain {
+        scheme: Option<String>,
+        suffix: String,
+    },
+    Regex(Regex),
+}
+
+impl OriginMatcher {
+    fn parse(pattern: &str) -> Result<Self, BoxError> {
+        let pattern = pattern.trim().to_ascii_lowercase();
+        if pattern == "*" {
+            return Ok(OriginMatcher::Any);
+        }
+        let (scheme, authority) = match pattern.split_once("://") {
+            Some((scheme, authority)) => (Some(scheme.to_string()), authority),
+            None => (None, pattern.as_str()),
+        };
+        if let Some(suffix) = authority.strip_prefix('*') {
+            if !suffix.starts_with('.') || suffix.contains('*') {
+                return Err(format!("[SG.Filter.Cors] invalid wildcard origin {pattern}").into());
+            }
+            return Ok(OriginMatcher::WildcardSubdomain {
+                scheme,
+                suffix: suffix.to_string(),
+            });
+        }
+        if scheme.is_none() || authority.contains('*') {
+            return Err(format!("[SG.Filter.Cors] invalid origin {pattern}, expect `*`, `scheme://host[:port]` or a wildcard subdomain").into());
+        }
+        Ok(OriginMatcher::Exact(pattern.trim_end_matches('/').to_string()))
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginMatcher::Any => true,
+            OriginMatcher::Exact(exact) => origin.eq_ignore_ascii_case(exact),
+            OriginMatcher::WildcardSubdomain { scheme, suffix } => {
+                let origin = origin.to_ascii_lowercase();
+                let Some((origin_scheme, authority)) = origin.split_once("://") else {
+                    return false;
+                };
+                scheme.as_deref().unwrap_or(origin_scheme) == origin_scheme && authority.len() > suffix.len() && authority.ends_with(suffix.as_str())
+            }
+            OriginMatcher::Regex(regex) => regex.is_match(origin),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cors {
+    origins: Vec<OriginMatcher>,
+    /// `None` means any method.
+    allow_methods: Option<Vec<Method>>,
+    allow_methods_value: HeaderValue,
+    /// `None` means any requested header.
+    allow_headers: Option<Vec<String>>,
+    expose_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age: Option<HeaderValue>,
+    reject_disallowed_origin: bool,
+}
+
+impl Cors {
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.origins.iter().any(|m| m.matches(origin))
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.origins.iter().any(|m| matches!(m, OriginMatcher::Any))
+    }
+
+    /// With credentials, `*` is not allowed by browsers, so the origin is reflected instead.
+    fn allow_origin_value(&self, origin: &HeaderValue) -> HeaderValue {
+        if self.allows_any_origin() && !self.allow_credentials {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        }
+    }
+
+    /// With credentials, `*` is taken literally by browsers, so the requested method is reflected instead.
+    fn allow_methods_value(&self, method: &Method) -> HeaderValue {
+        if self.allow_methods.is_none() && self.allow_credentials {
+            HeaderValue::from_str(method.as_str()).unwrap_or_else(|_| self.allow_methods_value.clone())
+        } else {
+            self.allow_methods_value.clone()
+        }
+    }
+
+    fn forbidden(message: String) -> Response<SgBody> {
+        tracing::debug!("[SG.Filter.Cors] {message}");
+        Response::with_code_message(StatusCode::FORBIDDEN, format!("[SG.Filter.Cors] {message}"))
+    }
+
+    fn preflight(&self, origin: &HeaderValue, req_headers: &HeaderMap) -> Response<SgBody> {
+        let Some(method) = req_headers.get(ACCESS_CONTROL_REQUEST_METHOD).and_then(|v| Method::from_bytes(v.as_bytes()).ok()) else {
+            return Self::forbidden("invalid access-control-request-method".to_string());
+        };
+        if self.allow_methods.as_ref().is_some_and(|methods| !methods.contains(&method)) {
+            return Self::forbidden(format!("method {method} is not allowed"));
+        }
+        let requested_headers =
+            req_headers.get_all(ACCESS_CONTROL_REQUEST_HEADERS).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).map(str::trim).filter(|h| !h.is_empty());
+        let mut allow_headers = Vec::new();
+        for header in requested_headers {
+            if self.allow_headers.as_ref().is_some_and(|allowed| !allowed.iter().any(|h| h.eq_ignore_ascii_case(header))) {
+                return Self::forbidden(format!("header {header} is not allowed"));
+            }
+            allow_headers.push(header.to_ascii_lowercase());
+        }
+        let mut resp = Response::with_code_message(StatusCode::NO_CONTENT, "");
+        let headers = resp.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin));
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, self.allow_methods_value(&method));
+        if !allow_headers.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&allow_headers.join(", ")) {
+                headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, value);
+            }
+        }
+        if self.allow_credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if let Some(max_age) = &self.max_age {
+            headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.clone());
+        }
+        headers.append(VARY, HeaderValue::from_static("origin, access-control-request-method, access-control-request-headers"));
+        resp
+    }
+
+    fn decorate(&self, origin: &HeaderValue, resp: &mut Response<SgBody>) {
+        let headers = resp.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, self.allow_origin_value(origin));
+        if self.allow_credentials {
+            headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if let Some(expose_headers) = &self.expose_headers {
+            headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose_headers.clone());
+        }
+        vary_origin(headers);
+    }
+}
+
+/// Responses differ by the origin, whether the CORS headers are added or not.
+fn vary_origin(headers: &mut HeaderMap) {
+    let varied = headers.get_all(VARY).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("origin"));
+    if !varied {
+        headers.append(VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+impl FnLayerMethod for Cors {
+    async fn call(&self, req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        let Some(origin) = req.headers().get(ORIGIN).cloned() else {
+            // not a cors request
+            return inner.call(req).await;
+        };
+        let is_preflight = req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        let Some(origin_str) = origin.to_str().ok().filter(|o| self.allows_origin(o)) else {
+            let message = format!("origin {} is not allowed", String::from_utf8_lossy(origin.as_bytes()));
+            if is_preflight || self.reject_disallowed_origin {
+                return Self::forbidden(message);
+            }
+            // same origin requests carry `Origin` as well, the browser enforces the policy by the missing CORS headers
+            tracing::trace!("[SG.Filter.Cors] {message}, forward without cors headers");
+            let mut resp = inner.call(req).await;
+            vary_origin(resp.headers_mut());
+            return resp;
+        };
+        if is_preflight {
+            tracing::trace!("[SG.Filter.Cors] preflight from {origin_str}");
+            return self.preflight(&origin, req.headers());
+        }
+        let mut resp = inner.call(req).await;
+        self.decorate(&origin, &mut resp);
+        resp
+    }
+}
+
+impl MakeSgLayer for SgFilterCors {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        let mut origins = self.allow_origins.iter().map(|o| OriginMatcher::parse(o)).collect::<Result<Vec<_>, _>>()?;
+        for regex in &self.allow_origin_regexes {
+            // anchored, so a pattern can't be matched by a part of the origin
+            origins.push(OriginMatcher::Regex(
+                Regex::new(&format!("^(?:{regex})$")).map_err(|e| format!("[SG.Filter.Cors] invalid origin regex {regex}: {e}"))?,
+            ));
+        }
+        let allow_methods = if self.allow_methods.iter().any(|m| m == "*") {
+            None
+        } else {
+            Some(
+                self.allow_methods
+                    .iter()
+                    .map(|m| Method::from_bytes(m.trim().to_ascii_uppercase().as_bytes()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("[SG.Filter.Cors] invalid method: {e}"))?,
+            )
+        };
+        let allow_methods_value = match &allow_methods {
+            Some(methods) => HeaderValue::from_str(&methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", "))?,
+            None => HeaderValue::from_static("*"),
+        };
+        let allow_headers = if self.allow_headers.iter().any(|h| h == "*") {
+            None
+        } else {
+            Some(self.allow_headers.iter().map(|h| h.trim().to_string()).collect())
+        };
+        let expose_headers = if self.expose_headers.is_empty() {
+            None
+        } else {
+            Some(HeaderValue::from_str(&self.expose_headers.join(", "))?)
+        };
+        let cors = Cors {
+            origins,
+            allow_methods,
+            allow_methods_value,
+            allow_headers,
+            expose_headers,
+            allow_credentials: self.allow_credentials,
+            max_age: self.max_age_secs.map(HeaderValue::from),
+            reject_disallowed_origin: self.reject_disallowed_origin,
+        };
+        Ok(SgBoxLayer::new(FnLayer::new(Arc::new(cors))))
+    }
+}
+
+def_plugin!("cors", CorsPlugin, SgFilterCors);
+#[cfg(feature = "schema")]
+crate::schema!(
+    CorsPlugin,
+    SgFilterCors {
+        allow_origins: vec!["https://app.example.com".to_string(), "https://*.example.com".to_string()],
+        allow_origin_regexes: vec![r"^https://preview-\d+\.example\.dev$".to_string()],
+        allow_methods: vec!["GET".to_string(), "POST".to_string()],
+        allow_headers: vec!["content-type".to_string(), "authorization".to_string()],
+        expose_headers: vec!["x-request-id".to_string()],
+        allow_credentials: true,
+        max_age_secs: Some(600),
+        reject_disallowed_origin: false,
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use http_body_util::BodyExt;
+    use hyper::{
+        header::{
+            ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+            ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+        },
+        service::Service,
+        Method, Request, StatusCode,
+    };
+    use serde_json::json;
+    use spacegate_kernel::{service::get_echo_service, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    fn preflight(origin: &str, method: &str, headers: &str) -> Request<SgBody> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("http://sg.idealworld.group/api")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, method)
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, headers)
+            .body(SgBody::empty())
+            .expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_cors() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({
+                "allow_origins": ["https://app.example.com", "https://*.example.org"],
+                "allow_origin_regexes": [r"^https://preview-\d+\.example\.dev$"],
+                "allow_methods": ["GET", "POST"],
+                "allow_headers": ["content-type", "authorization"],
+                "expose_headers": ["x-request-id"],
+                "allow_credentials": true,
+                "max_age_secs": 600
+            }),
+        )?;
+        let service = layer.layer(get_echo_service());
+
+        // preflight is answered without reaching the backend
+        let resp = service.call(preflight("https://a.b.example.org", "POST", "Content-Type, Authorization")).await?;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).expect("missing allow origin"), "https://a.b.example.org");
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_METHODS).expect("missing allow methods"), "GET, POST");
+        assert_eq!(
+            resp.headers().get(ACCESS_CONTROL_ALLOW_HEADERS).expect("missing allow headers"),
+            "content-type, authorization"
+        );
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_CREDENTIALS).expect("missing allow credentials"), "true");
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_MAX_AGE).expect("missing max age"), "600");
+        assert!(resp.into_body().collect().await?.to_bytes().is_empty());
+
+        let resp = service.call(preflight("https://app.example.com", "DELETE", "")).await?;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let resp = service.call(preflight("https://app.example.com", "GET", "x-custom")).await?;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        // the bare domain doesn't match a wildcard subdomain
+        let resp = service.call(preflight("https://example.org", "GET", "")).await?;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(String::from_utf8_lossy(&resp.into_body().collect().await?.to_bytes()).contains("origin https://example.org is not allowed"));
+
+        // actual request
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api").header(ORIGIN, "https://preview-42.example.dev").body(SgBody::full("hello"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).expect("missing allow origin"),
+            "https://preview-42.example.dev"
+        );
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_EXPOSE_HEADERS).expect("missing expose headers"), "x-request-id");
+        assert_eq!(resp.headers().get(VARY).expect("missing vary"), "origin");
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "hello");
+
+        // an actual request from a disallowed origin is forwarded without the cors headers
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api").header(ORIGIN, "https://evil.com").body(SgBody::full("hello"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(resp.headers().get(VARY).expect("missing vary"), "origin");
+        // the regex matches the whole origin, not a part of it
+        let req =
+            Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api").header(ORIGIN, "https://preview-1.example.dev.evil.com").body(SgBody::full("hello"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        // not a cors request
+        let req = Request::builder().uri("http://sg.idealworld.group/api").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_any_origin() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({}))?;
+        let service = layer.layer(get_echo_service());
+        let resp = service.call(preflight("https://anywhere.io", "PUT", "x-custom")).await?;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).expect("missing allow origin"), "*");
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_HEADERS).expect("missing allow headers"), "x-custom");
+
+        // with credentials, `*` is not honored by browsers
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "allow_methods": ["*"], "allow_credentials": true }))?;
+        let service = layer.layer(get_echo_service());
+        let resp = service.call(preflight("https://anywhere.io", "PATCH", "")).await?;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).expect("missing allow origin"), "https://anywhere.io");
+        assert_eq!(resp.headers().get(ACCESS_CONTROL_ALLOW_METHODS).expect("missing allow methods"), "PATCH");
+
+        assert!(crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "allow_origins": ["example.com"] })).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_disallowed_origin() -> Result<(), BoxError> {
+        let layer =
+            crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "allow_origins": ["https://app.example.com"], "reject_disallowed_origin": true }))?;
+        let service = layer.layer(get_echo_service());
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api").header(ORIGIN, "https://evil.com").body(SgBody::full("hello"))?;
+        assert_eq!(service.call(req).await?.status(), StatusCode::FORBIDDEN);
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api").header(ORIGIN, "https://app.example.com").body(SgBody::full("hello"))?;
+        assert_eq!(service.call(req).await?.status(), StatusCode::OK);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Credential stores shared by the `key-auth` and `basic-auth` plugins.
+
+use std::{collections::HashMap, sync::Arc};
+
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    http::Extensions,
+    Request,
+};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "redis")]
+use spacegate_kernel::helper_layers::check::redis::RedisCheck;
+use spacegate_kernel::{
+    extension::{Consumer, GatewayName},
+    BoxError, SgBody,
+};
+
+use crate::Plugin;
+
+/// A credential of a consumer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgCredential {
+    pub consumer: String,
+    /// The api key for `key-auth`, or the password for `basic-auth`.
+    pub secret: String,
+}
+
+/// Where credentials come from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind")]
+pub enum SgCredentialSource {
+    Inline {
+        credentials: Vec<SgCredential>,
+    },
+    /// An htpasswd-style file, one `consumer:secret` per line, lines starting with `#` are ignored.
+    ///
+    /// The file is read when the plugin is created.
+    File {
+        path: String,
+    },
+    /// Credentials in the redis of the gateway, looked up by `<prefix>:<lookup>`.
+    /// `id` distinguishes plugins sharing the same redis.
+    #[cfg(feature = "redis")]
+    Redis {
+        id: Option<String>,
+    },
+}
+
+impl Default for SgCredentialSource {
+    fn default() -> Self {
+        SgCredentialSource::Inline { credentials: Vec::new() }
+    }
+}
+
+pub fn parse_htpasswd(content: &str) -> Result<Vec<SgCredential>, BoxError> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(no, line)| (no, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(no, line)| {
+            let (consumer, secret) = line.split_once(':').ok_or_else(|| format!("line {}: expect `consumer:secret`", no + 1))?;
+            Ok(SgCredential {
+                consumer: consumer.to_string(),
+                secret: secret.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl SgCredentialSource {
+    /// Make a store indexed by `entry`, which maps a credential into the lookup and the stored value.
+    pub fn make_store<P: Plugin>(&self, entry: fn(SgCredential) -> (String, String)) -> Result<CredentialStore, BoxError> {
+        let credentials = match self {
+            SgCredentialSource::Inline { credentials } => credentials.clone(),
+            SgCredentialSource::File { path } => {
+                let content = std::fs::read_to_string(path).map_err(|e| format!("fail to read credential file {path}: {e}"))?;
+                parse_htpasswd(&content).map_err(|e| format!("invalid credential file {path}, {e}"))?
+            }
+            #[cfg(feature = "redis")]
+            SgCredentialSource::Redis { id } => {
+                return Ok(CredentialStore::Redis {
+                    prefix: P::redis_prefix(id.as_deref()).into(),
+                })
+            }
+        };
+        let mut map = HashMap::with_capacity(credentials.len());
+        for credential in credentials {
+            let (lookup, value) = entry(credential);
+            if map.insert(lookup, value).is_some() {
+                return Err("duplicated credential".into());
+            }
+        }
+        Ok(CredentialStore::Local(Arc::new(map)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CredentialStore {
+    Local(Arc<HashMap<String, String>>),
+    #[cfg(feature = "redis")]
+    Redis {
+        prefix: Arc<str>,
+    },
+}
+
+impl CredentialStore {
+    /// Get the stored value by `lookup`, the redis store needs the gateway name to find its client.
+    pub async fn get(&self, lookup: &str, _gateway_name: Option<&GatewayName>) -> Result<Option<String>, BoxError> {
+        match self {
+            CredentialStore::Local(map) => Ok(map.get(lookup).cloned()),
+            #[cfg(feature = "redis")]
+            CredentialStore::Redis { prefix } => {
+                let gateway_name = _gateway_name.ok_or("missing gateway name")?;
+                let client = spacegate_ext_redis::global_repo().get(gateway_name).ok_or("missing redis client")?;
+                let check = RedisCheck {
+                    check_script: None,
+                    response_script: None,
+                    key_prefix: prefix.clone(),
+                    client,
+                    on_fail: None,
+                };
+                Ok(check.get(lookup).await?)
+            }
+        }
+    }
+}
+
+/// The extensions attached to the request of an authenticated consumer.
+pub fn consumer_extensions(consumer: impl Into<Arc<str>>) -> Extensions {
+    let mut extensions = Extensions::new();
+    extensions.insert(Consumer::new(consumer));
+    extensions
+}
+
+/// Pass the authenticated consumer to the backend by `header`.
+pub fn set_consumer_header(mut req: Request<SgBody>, header: Option<&HeaderName>) -> Request<SgBody> {
+    if let Some(header) = header {
+        let consumer = req.extensions().get::<Consumer>().and_then(|consumer| HeaderValue::from_str(consumer).ok());
+        if let Some(consumer) = consumer {
+            req.headers_mut().insert(header.clone(), consumer);
+        }
+    }
+    req
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_htpasswd;
+
+    #[test]
+    fn test_parse_htpasswd() {
+        let credentials = parse_htpasswd("# consumers\nalice:{SHA}qUqP5cyxm6YcTAhz05Hph5gvu9M=\n\n  bob:pass:word  \n").expect("valid file");
+        assert_eq!(credentials.len(), 2);
+        assert_eq!(credentials[0].consumer, "alice");
+        assert_eq!(credentials[1].secret, "pass:word");
+        assert!(parse_htpasswd("alice").is_err());
+    }
+}
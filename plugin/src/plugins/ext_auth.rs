@@ -0,0 +1,512 @@
+use std::{
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    body::Bytes,
+    header::{HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH, COOKIE, HOST, TRANSFER_ENCODING},
+    HeaderMap, Method, Request, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    extension::PeerAddr,
+    header::X_FORWARDED_FOR,
+    helper_layers::{
+        body_limit::content_length,
+        function::{FnLayer, FnLayerMethod, Inner},
+    },
+    service::http_client_service::get_client,
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+
+use crate::{def_plugin, MakeSgLayer};
+
+const X_FORWARDED_METHOD: HeaderName = HeaderName::from_static("x-forwarded-method");
+const X_FORWARDED_URI: HeaderName = HeaderName::from_static("x-forwarded-uri");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+/// Denial responses larger than this, or without `content-length`, are not cached.
+const MAX_CACHED_DENIAL_SIZE: u64 = 0x10000;
+
+/// Cache of authorization decisions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgExtAuthCache {
+    /// Parts of the request the decision is keyed by, an item is one of
+    /// `method`, `host`, `path`, `header:<name>` or `cookie:<name>`.
+    ///
+    /// Requests missing any `header` or `cookie` part are not cached.
+    pub key: Vec<String>,
+    pub ttl_secs: u64,
+    /// Max count of cached decisions.
+    pub capacity: usize,
+    /// Whether denials are cached as well as allowances.
+    pub cache_denials: bool,
+}
+
+impl Default for SgExtAuthCache {
+    fn default() -> Self {
+        Self {
+            key: vec!["header:authorization".to_string()],
+            ttl_secs: 60,
+            capacity: 1024,
+            cache_denials: false,
+        }
+    }
+}
+
+/// Authorize requests with an external service, like Envoy `ext_authz` or Traefik `ForwardAuth`.
+///
+/// The selected request metadata is sent to the auth service, along with `x-forwarded-method`, `x-forwarded-uri`,
+/// `x-forwarded-host`, `x-forwarded-proto` and `x-forwarded-for`.
+/// A `2xx` response allows the request, `5xx`, timeout or an unreachable service is a failure,
+/// and any other response is returned to the client as the denial.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterExtAuth {
+    pub url: String,
+    /// Method of the auth request, unset means the method of the original request.
+    pub method: Option<String>,
+    /// Request headers sent to the auth service, `*` means all.
+    pub request_headers: Vec<String>,
+    /// Whether the request body is sent to the auth service.
+    pub include_body: bool,
+    /// Requests with a larger body are rejected with `413 Payload Too Large` when `include_body` is set.
+    pub max_body_size: usize,
+    /// Headers of an allowing response copied to the upstream request.
+    ///
+    /// These headers sent by the client are always removed.
+    pub upstream_headers: Vec<String>,
+    pub timeout_ms: u64,
+    /// Allow the request when the auth service fails.
+    pub failure_mode_allow: bool,
+    /// Status returned to the client when the auth service fails and `failure_mode_allow` is not set.
+    pub status_on_error: u16,
+    pub cache: Option<SgExtAuthCache>,
+}
+
+impl Default for SgFilterExtAuth {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            method: None,
+            request_headers: vec!["authorization".to_string(), "cookie".to_string()],
+            include_body: false,
+            max_body_size: 0x10000,
+            upstream_headers: Vec::new(),
+            timeout_ms: 5000,
+            failure_mode_allow: false,
+            status_on_error: StatusCode::FORBIDDEN.as_u16(),
+            cache: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum KeyPart {
+    Method,
+    Host,
+    Path,
+    Header(HeaderName),
+    Cookie(String),
+}
+
+impl FromStr for KeyPart {
+    type Err = BoxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            None if s.trim() == "method" => Ok(KeyPart::Method),
+            None if s.trim() == "host" => Ok(KeyPart::Host),
+            None if s.trim() == "path" => Ok(KeyPart::Path),
+            Some(("header", name)) => Ok(KeyPart::Header(HeaderName::from_str(name.trim())?)),
+            Some(("cookie", name)) => Ok(KeyPart::Cookie(name.trim().to_string())),
+            _ => Err(format!("invalid cache key part {s}").into()),
+        }
+    }
+}
+
+fn host(req: &Request<SgBody>) -> Option<&str> {
+    req.headers().get(HOST).and_then(|v| v.to_str().ok()).or_else(|| req.uri().authority().map(|a| a.as_str()))
+}
+
+#[derive(Debug, Clone)]
+enum Decision {
+    Allow(Vec<(HeaderName, HeaderValue)>),
+    Deny { status: StatusCode, headers: HeaderMap, body: Bytes },
+}
+
+enum Outcome {
+    Decided(Decision),
+    /// A denial with a large or streamed body, which is passed to the client as is and never cached.
+    Denied(Response<SgBody>),
+    Failed,
+}
+
+struct DecisionCache {
+    key: Vec<KeyPart>,
+    ttl: Duration,
+    cache_denials: bool,
+    decisions: Mutex<lru::LruCache<String, (Decision, Instant)>>,
+}
+
+impl DecisionCache {
+    fn key(&self, req: &Request<SgBody>) -> Option<String> {
+        let mut key = String::new();
+        for part in &self.key {
+            match part {
+                KeyPart::Method => key.push_str(req.method().as_str()),
+                KeyPart::Host => key.push_str(host(req).unwrap_or_default()),
+                KeyPart::Path => key.push_str(req.uri().path()),
+                KeyPart::Header(name) => key.push_str(req.headers().get(name)?.to_str().ok()?),
+                KeyPart::Cookie(name) => {
+                    let cookie = req
+                        .headers()
+                        .get_all(COOKIE)
+                        .iter()
+                        .filter_map(|v| v.to_str().ok())
+                        .flat_map(|v| v.split(';'))
+                        .filter_map(|kv| kv.trim().split_once('='))
+                        .find(|(k, _)| k == name)?;
+                    key.push_str(cookie.1)
+                }
+            }
+            key.push('\n');
+        }
+        Some(key)
+    }
+
+    fn get(&self, key: &str) -> Option<Decision> {
+        let mut decisions = self.decisions.lock().expect("poisoned");
+        match decisions.get(key) {
+            Some((decision, expire_at)) if *expire_at > Instant::now() => Some(decision.clone()),
+            Some(_) => {
+                decisions.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn set(&self, key: String, decision: Decision) {
+        self.decisions.lock().expect("poisoned").put(key, (decision, Instant::now() + self.ttl));
+    }
+}
+
+struct ExtAuth {
+    config: SgFilterExtAuth,
+    method: Option<Method>,
+    request_headers: Option<Vec<HeaderName>>,
+    upstream_headers: Vec<HeaderName>,
+    status_on_error: StatusCode,
+    cache: Option<DecisionCache>,
+}
+
+impl ExtAuth {
+    fn auth_request(&self, req: &Request<SgBody>, body: SgBody) -> Result<Request<SgBody>, BoxError> {
+        let mut auth_req = Request::builder().method(self.method.clone().unwrap_or_else(|| req.method().clone())).uri(self.config.url.as_str()).body(body)?;
+        let headers = auth_req.headers_mut();
+        match &self.request_headers {
+            Some(names) => {
+                for name in names {
+                    for value in req.headers().get_all(name) {
+                        headers.append(name.clone(), value.clone());
+                    }
+                }
+            }
+            None => {
+                for (name, value) in req.headers() {
+                    if ![HOST, CONTENT_LENGTH, TRANSFER_ENCODING, CONNECTION].contains(name) {
+                        headers.append(name.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        if let Some(len) = auth_req.body().get_dumped().map(Bytes::len) {
+            auth_req.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(len));
+        }
+        let headers = auth_req.headers_mut();
+        headers.insert(X_FORWARDED_METHOD, HeaderValue::from_str(req.method().as_str())?);
+        headers.insert(X_FORWARDED_URI, HeaderValue::from_str(req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/"))?);
+        if let Some(host) = host(req) {
+            headers.insert(X_FORWARDED_HOST, HeaderValue::from_str(host)?);
+        }
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_str(req.uri().scheme_str().unwrap_or("http"))?);
+        if let Some(peer) = req.extensions().get::<PeerAddr>() {
+            headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(&peer.0.ip().to_string())?);
+        }
+        Ok(auth_req)
+    }
+
+    async fn check(&self, req: &Request<SgBody>, body: SgBody) -> Result<Outcome, BoxError> {
+        let auth_req = self.auth_request(req, body)?;
+        let resp = get_client().request_timeout(auth_req, Duration::from_millis(self.config.timeout_ms)).await;
+        let status = resp.status();
+        if status.is_server_error() {
+            tracing::warn!("[SG.Filter.ExtAuth] auth service {} failed with {status}", self.config.url);
+            return Ok(Outcome::Failed);
+        }
+        if status.is_success() {
+            let allowed = self.upstream_headers.iter().flat_map(|name| resp.headers().get_all(name).iter().map(|value| (name.clone(), value.clone()))).collect();
+            return Ok(Outcome::Decided(Decision::Allow(allowed)));
+        }
+        if content_length(resp.headers()).filter(|len| *len <= MAX_CACHED_DENIAL_SIZE).is_none() {
+            return Ok(Outcome::Denied(resp));
+        }
+        let (mut parts, body) = resp.into_parts();
+        let body = body.dump_with_limit(MAX_CACHED_DENIAL_SIZE as usize).await?.get_dumped().cloned().unwrap_or_default();
+        parts.headers.remove(CONNECTION);
+        Ok(Outcome::Decided(Decision::Deny {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }))
+    }
+
+    /// The denial of the auth service is relayed as is, it's not a gateway error.
+    fn deny(status: StatusCode, headers: HeaderMap, body: Bytes) -> Response<SgBody> {
+        let mut resp = Response::builder().status(status).body(SgBody::full(body)).expect("invalid response builder");
+        *resp.headers_mut() = headers;
+        resp
+    }
+}
+
+impl FnLayerMethod for ExtAuth {
+    async fn call(&self, mut req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        for name in &self.upstream_headers {
+            req.headers_mut().remove(name);
+        }
+        let cache_key = self.cache.as_ref().and_then(|cache| cache.key(&req));
+        let cached = self.cache.as_ref().zip(cache_key.as_deref()).and_then(|(cache, key)| cache.get(key));
+        let outcome = match cached {
+            Some(decision) => Outcome::Decided(decision),
+            None => {
+                let (parts, body) = req.into_parts();
+                let (auth_body, body) = if self.config.include_body {
+                    match body.dump_with_limit(self.config.max_body_size).await {
+                        Ok(body) => (body.dump_clone().unwrap_or_default(), body),
+                        Err(e) => return Response::with_code_message(StatusCode::PAYLOAD_TOO_LARGE, format!("[SG.Filter.ExtAuth] {e}")),
+                    }
+                } else {
+                    (SgBody::empty(), body)
+                };
+                req = Request::from_parts(parts, body);
+                let outcome = match self.check(&req, auth_body).await {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        tracing::warn!("[SG.Filter.ExtAuth] failed to check request: {e}");
+                        Outcome::Failed
+                    }
+                };
+                if let (Some(cache), Some(key), Outcome::Decided(decision)) = (&self.cache, cache_key, &outcome) {
+                    if matches!(decision, Decision::Allow(_)) || cache.cache_denials {
+                        cache.set(key, decision.clone());
+                    }
+                }
+                outcome
+            }
+        };
+        match outcome {
+            Outcome::Decided(Decision::Allow(headers)) => {
+                for (name, value) in headers {
+                    req.headers_mut().append(name, value);
+                }
+                inner.call(req).await
+            }
+            Outcome::Decided(Decision::Deny { status, headers, body }) => Self::deny(status, headers, body),
+            Outcome::Denied(resp) => resp,
+            Outcome::Failed if self.config.failure_mode_allow => inner.call(req).await,
+            Outcome::Failed => Response::with_code_message(self.status_on_error, "[SG.Filter.ExtAuth] auth service unavailable"),
+        }
+    }
+}
+
+impl MakeSgLayer for SgFilterExtAuth {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        if self.url.is_empty() {
+            return Err("[SG.Filter.ExtAuth] url is required".into());
+        }
+        let method =
+            self.method.as_deref().map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes())).transpose().map_err(|e| format!("[SG.Filter.ExtAuth] invalid method: {e}"))?;
+        let parse_headers = |names: &[String]| names.iter().map(|name| HeaderName::from_str(name.trim())).collect::<Result<Vec<_>, _>>();
+        let request_headers = if self.request_headers.iter().any(|h| h == "*") {
+            None
+        } else {
+            Some(parse_headers(&self.request_headers).map_err(|e| format!("[SG.Filter.ExtAuth] invalid request header: {e}"))?)
+        };
+        let upstream_headers = parse_headers(&self.upstream_headers).map_err(|e| format!("[SG.Filter.ExtAuth] invalid upstream header: {e}"))?;
+        let status_on_error = StatusCode::from_u16(self.status_on_error)?;
+        let cache = match &self.cache {
+            Some(cache) => Some(DecisionCache {
+                key: cache.key.iter().map(|part| part.parse()).collect::<Result<Vec<KeyPart>, BoxError>>().map_err(|e| format!("[SG.Filter.ExtAuth] {e}"))?,
+                ttl: Duration::from_secs(cache.ttl_secs),
+                cache_denials: cache.cache_denials,
+                decisions: Mutex::new(lru::LruCache::new(
+                    NonZeroUsize::new(cache.capacity).ok_or("[SG.Filter.ExtAuth] cache capacity should be greater than 0")?,
+                )),
+            }),
+            None => None,
+        };
+        let ext_auth = ExtAuth {
+            config: self.clone(),
+            method,
+            request_headers,
+            upstream_headers,
+            status_on_error,
+            cache,
+        };
+        Ok(SgBoxLayer::new(FnLayer::new(Arc::new(ext_auth))))
+    }
+}
+
+def_plugin!("ext-auth", ExtAuthPlugin, SgFilterExtAuth);
+#[cfg(feature = "schema")]
+crate::schema!(
+    ExtAuthPlugin,
+    SgFilterExtAuth {
+        url: "http://auth.default:8080/verify".to_string(),
+        upstream_headers: vec!["x-user-id".to_string()],
+        cache: Some(SgExtAuthCache::default()),
+        ..Default::default()
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use http_body_util::BodyExt;
+    use hyper::{
+        header::{HeaderValue, ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE},
+        service::Service,
+        Request, StatusCode,
+    };
+    use serde_json::json;
+    use spacegate_kernel::{
+        helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+        layers::gateway::error_page::{SgErrorPage, SgErrorPages},
+        BoxError, BoxHyperService, SgBody,
+    };
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    #[derive(Clone)]
+    struct Backend;
+    impl FnLayerMethod for Backend {
+        async fn call(&self, req: Request<SgBody>, _inner: Inner) -> hyper::Response<SgBody> {
+            let user = req.headers().get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("anonymous").to_string();
+            hyper::Response::new(SgBody::full(user))
+        }
+    }
+
+    fn backend() -> BoxHyperService {
+        BoxHyperService::new(FnLayer::new(Backend).layer(spacegate_kernel::service::get_echo_service()))
+    }
+
+    fn request(token: &str) -> Request<SgBody> {
+        Request::builder().uri("http://sg.idealworld.group/api?a=1").header(AUTHORIZATION, token).header("x-user-id", "spoofed").body(SgBody::empty()).expect("invalid request")
+    }
+
+    /// An auth service allowing `good`, denying `bad` and failing on anything else.
+    async fn auth_server(checked: Arc<AtomicUsize>) -> Result<u16, BoxError> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let checked = checked.clone();
+                tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(
+                    hyper_util::rt::TokioIo::new(stream),
+                    hyper::service::service_fn(move |req: Request<hyper::body::Incoming>| {
+                        checked.fetch_add(1, Ordering::SeqCst);
+                        let builder = hyper::Response::builder();
+                        let builder = match req.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+                            Some("good") if req.headers().get("x-forwarded-uri").is_some_and(|v| v == "/api?a=1") => builder.header("x-user-id", "alice"),
+                            Some("bad") => builder.status(StatusCode::UNAUTHORIZED).header(WWW_AUTHENTICATE, "Basic"),
+                            _ => builder.status(StatusCode::INTERNAL_SERVER_ERROR),
+                        };
+                        async move { builder.body(http_body_util::Full::new(hyper::body::Bytes::from("from auth"))) }
+                    }),
+                ));
+            }
+        });
+        Ok(port)
+    }
+
+    #[tokio::test]
+    async fn test_ext_auth() -> Result<(), BoxError> {
+        let checked = Arc::new(AtomicUsize::new(0));
+        let port = auth_server(checked.clone()).await?;
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({ "url": format!("http://127.0.0.1:{port}/verify"), "upstream_headers": ["x-user-id"], "cache": { "key": ["header:authorization"] } }),
+        )?;
+        let service = layer.layer(backend());
+
+        let resp = service.call(request("good")).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "alice");
+        // cached
+        let resp = service.call(request("good")).await?;
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "alice");
+        assert_eq!(checked.load(Ordering::SeqCst), 1);
+
+        let resp = service.call(request("bad")).await?;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(WWW_AUTHENTICATE).expect("missing www-authenticate"), "Basic");
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "from auth");
+        // denials are not cached by default
+        assert_eq!(service.call(request("bad")).await?.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(checked.load(Ordering::SeqCst), 3);
+
+        // failure closed
+        assert_eq!(service.call(request("boom")).await?.status(), StatusCode::FORBIDDEN);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ext_auth_denial_with_error_pages() -> Result<(), BoxError> {
+        let port = auth_server(Arc::new(AtomicUsize::new(0))).await?;
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "url": format!("http://127.0.0.1:{port}/verify") }))?;
+        let pages = SgErrorPages::new([SgErrorPage {
+            status: 400..=499,
+            content_type: HeaderValue::from_static("text/html"),
+            template: "<h1>${status}</h1>".into(),
+        }]);
+        let service = FnLayer::new(pages).layer(layer.layer(backend()));
+        for accept in ["text/html", "application/json"] {
+            let mut req = request("bad");
+            req.headers_mut().insert(ACCEPT, HeaderValue::from_static(accept));
+            let resp = service.call(req).await?;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(resp.headers().get(WWW_AUTHENTICATE).expect("missing www-authenticate"), "Basic");
+            assert_eq!(resp.into_body().collect().await?.to_bytes(), "from auth");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ext_auth_failure_open() -> Result<(), BoxError> {
+        // nothing listens on this port
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({ "url": format!("http://127.0.0.1:{port}/verify"), "failure_mode_allow": true, "upstream_headers": ["x-user-id"] }),
+        )?;
+        let service = layer.layer(backend());
+        let resp = service.call(request("good")).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // the spoofed header is removed
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "anonymous");
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    body::{Bytes, Frame},
+    header::{HeaderName, HeaderValue, CONTENT_LENGTH},
+    Request, Response, StatusCode,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+
+use crate::{def_plugin, MakeSgLayer};
+
+fn full_percentage() -> f64 {
+    100.0
+}
+
+/// Delay requests before they are forwarded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgFaultDelay {
+    /// Percentage of affected requests, from 0 to 100.
+    #[serde(default = "full_percentage")]
+    pub percentage: f64,
+    /// Delay in milliseconds.
+    pub fixed_ms: u64,
+    /// If set, the delay is random between `fixed_ms` and `max_ms`, which must not be less than `fixed_ms`.
+    #[serde(default)]
+    pub max_ms: Option<u64>,
+}
+
+/// Respond with `status` instead of forwarding requests.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgFaultAbort {
+    #[serde(default = "full_percentage")]
+    pub percentage: f64,
+    pub status: u16,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Cut the upstream response body to `max_bytes`, the truncated body ends normally without `Content-Length`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgFaultTruncate {
+    #[serde(default = "full_percentage")]
+    pub percentage: f64,
+    pub max_bytes: usize,
+}
+
+/// Simulate the upstream resetting the connection.
+///
+/// Before headers, requests are not forwarded and get a `502 Bad Gateway` as if the upstream connection was reset.
+/// After headers, the upstream response is forwarded and its body is aborted, so the downstream connection is reset.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgFaultReset {
+    #[serde(default = "full_percentage")]
+    pub percentage: f64,
+    #[serde(default)]
+    pub after_headers: bool,
+}
+
+/// Inject faults for resilience testing.
+///
+/// Faults apply in the order of delay, abort, reset and truncate, each with its own percentage.
+/// Only requests matching all `match_headers` are affected, e.g. `{"x-chaos": "on"}`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterFault {
+    pub match_headers: HashMap<String, String>,
+    pub delay: Option<SgFaultDelay>,
+    pub abort: Option<SgFaultAbort>,
+    pub reset: Option<SgFaultReset>,
+    pub truncate: Option<SgFaultTruncate>,
+}
+
+/// Percentage as a probability.
+fn probability(percentage: f64) -> Result<f64, BoxError> {
+    if (0.0..=100.0).contains(&percentage) {
+        Ok(percentage / 100.0)
+    } else {
+        Err(format!("[SG.Filter.Fault] percentage {percentage} is out of 0 to 100").into())
+    }
+}
+
+fn hit(probability: f64) -> bool {
+    probability >= 1.0 || (probability > 0.0 && rand::thread_rng().gen_bool(probability))
+}
+
+fn truncated(body: SgBody, max_bytes: usize) -> SgBody {
+    let frames = futures_util::stream::unfold((body, max_bytes), |(mut body, remaining)| async move {
+        if remaining == 0 {
+            return None;
+        }
+        let frame = match body.frame().await? {
+            Ok(frame) => frame,
+            Err(e) => return Some((Err(e), (body, 0))),
+        };
+        match frame.into_data() {
+            Ok(mut data) => {
+                data.truncate(remaining);
+                let remaining = remaining - data.len();
+                Some((Ok(Frame::data(data)), (body, remaining)))
+            }
+            Err(frame) => Some((Ok(frame), (body, remaining))),
+        }
+    });
+    SgBody::new_boxed_error(StreamBody::new(frames))
+}
+
+fn aborted() -> SgBody {
+    let frames = futures_util::stream::once(async { Err::<Frame<Bytes>, BoxError>("[SG.Filter.Fault] injected connection reset".into()) });
+    SgBody::new_boxed_error(StreamBody::new(frames))
+}
+
+struct Fault {
+    match_headers: Vec<(HeaderName, HeaderValue)>,
+    delay: Option<(f64, Duration, Duration)>,
+    abort: Option<(f64, StatusCode, String)>,
+    reset: Option<(f64, bool)>,
+    truncate: Option<(f64, usize)>,
+}
+
+impl FnLayerMethod for Fault {
+    async fn call(&self, req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        if !self.match_headers.iter().all(|(name, value)| req.headers().get_all(name).iter().any(|v| v == value)) {
+            return inner.call(req).await;
+        }
+        if let Some((probability, min, max)) = self.delay {
+            if hit(probability) {
+                let delay = if max > min { rand::thread_rng().gen_range(min..=max) } else { min };
+                tracing::debug!("[SG.Filter.Fault] delay {delay:?}");
+                tokio::time::sleep(delay).await;
+            }
+        }
+        if let Some((probability, status, message)) = &self.abort {
+            if hit(*probability) {
+                tracing::debug!("[SG.Filter.Fault] abort with {status}");
+                return Response::with_code_message(*status, message.clone());
+            }
+        }
+        let reset_after_headers = match self.reset {
+            Some((probability, after_headers)) if hit(probability) => {
+                if !after_headers {
+                    tracing::debug!("[SG.Filter.Fault] reset before headers");
+                    return Response::with_code_message(StatusCode::BAD_GATEWAY, "[SG.Filter.Fault] upstream connection reset");
+                }
+                true
+            }
+            _ => false,
+        };
+        let mut resp = inner.call(req).await;
+        if reset_after_headers {
+            tracing::debug!("[SG.Filter.Fault] reset after headers");
+            return resp.map(|_| aborted());
+        }
+        if let Some((probability, max_bytes)) = self.truncate {
+            if hit(probability) {
+                tracing::debug!("[SG.Filter.Fault] truncate response body to {max_bytes} bytes");
+                resp.headers_mut().remove(CONTENT_LENGTH);
+                return resp.map(|body| truncated(body, max_bytes));
+            }
+        }
+        resp
+    }
+}
+
+impl MakeSgLayer for SgFilterFault {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        let fault = Fault {
+            match_headers: self.match_headers.iter().map(|(name, value)| Ok((HeaderName::try_from(name)?, HeaderValue::try_from(value)?))).collect::<Result<_, BoxError>>()?,
+            delay: match &self.delay {
+                Some(delay) => {
+                    let max_ms = delay.max_ms.unwrap_or(delay.fixed_ms);
+                    if max_ms < delay.fixed_ms {
+                        return Err(format!("[SG.Filter.Fault] max_ms {max_ms} is less than fixed_ms {}", delay.fixed_ms).into());
+                    }
+                    Some((probability(delay.percentage)?, Duration::from_millis(delay.fixed_ms), Duration::from_millis(max_ms)))
+                }
+                None => None,
+            },
+            abort: match &self.abort {
+                Some(abort) => {
+                    let status = StatusCode::from_u16(abort.status)?;
+                    let message = abort.message.clone().unwrap_or_else(|| format!("[SG.Filter.Fault] injected {status}"));
+                    Some((probability(abort.percentage)?, status, message))
+                }
+                None => None,
+            },
+            reset: match &self.reset {
+                Some(reset) => Some((probability(reset.percentage)?, reset.after_headers)),
+                None => None,
+            },
+            truncate: match &self.truncate {
+                Some(truncate) => Some((probability(truncate.percentage)?, truncate.max_bytes)),
+                None => None,
+            },
+        };
+        Ok(SgBoxLayer::new(FnLayer::new(Arc::new(fault))))
+    }
+}
+
+def_plugin!("fault", FaultPlugin, SgFilterFault);
+#[cfg(feature = "schema")]
+crate::schema!(
+    FaultPlugin,
+    SgFilterFault {
+        match_headers: HashMap::from([("x-chaos".to_string(), "on".to_string())]),
+        delay: Some(SgFaultDelay {
+            percentage: 50.0,
+            fixed_ms: 100,
+            max_ms: Some(500),
+        }),
+        abort: Some(SgFaultAbort {
+            percentage: 10.0,
+            status: 503,
+            message: None,
+        }),
+        ..Default::default()
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use http_body_util::BodyExt;
+    use hyper::{header::CONTENT_LENGTH, service::Service, Method, Request, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{service::get_echo_service, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    fn request(chaos: bool) -> Request<SgBody> {
+        let mut req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/api");
+        if chaos {
+            req = req.header("x-chaos", "on");
+        }
+        req.body(SgBody::full("0123456789")).expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_fault() -> Result<(), BoxError> {
+        let create = |config| crate::SgPluginRepository::global().create_layer(None, super::CODE, config).map(|layer| layer.layer(get_echo_service()));
+
+        let service = create(json!({ "match_headers": { "x-chaos": "on" }, "abort": { "status": 503 } }))?;
+        assert_eq!(service.call(request(true)).await?.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(service.call(request(false)).await?.status(), StatusCode::OK);
+
+        let service = create(json!({ "abort": { "status": 503, "percentage": 0 } }))?;
+        assert_eq!(service.call(request(true)).await?.status(), StatusCode::OK);
+        assert!(create(json!({ "abort": { "status": 503, "percentage": 101 } })).is_err());
+
+        let service = create(json!({ "delay": { "fixed_ms": 50, "max_ms": 80 } }))?;
+        let start = Instant::now();
+        assert_eq!(service.call(request(false)).await?.status(), StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(create(json!({ "delay": { "fixed_ms": 80, "max_ms": 50 } })).is_err());
+
+        let service = create(json!({ "truncate": { "max_bytes": 4 } }))?;
+        let resp = service.call(request(false)).await?;
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "0123");
+
+        let service = create(json!({ "reset": {} }))?;
+        assert_eq!(service.call(request(false)).await?.status(), StatusCode::BAD_GATEWAY);
+        let service = create(json!({ "reset": { "after_headers": true } }))?;
+        let resp = service.call(request(false)).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.into_body().collect().await.is_err());
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ use hyper::header::HeaderValue;
 use hyper::{header::HeaderName, HeaderMap};
 use hyper::{Request, Response};
 use serde::{Deserialize, Serialize};
+use spacegate_kernel::extension::{MatchedSgRouter, PathCaptures};
 use spacegate_kernel::helper_layers::{map_request::MapRequestLayer, map_response::MapResponseLayer};
 use spacegate_kernel::service::BoxHyperService;
 use tower_layer::Layer;
@@ -25,6 +26,7 @@ pub enum SgFilterHeaderModifierKind {
 
 pub struct SgFilterHeaderModifier {
     pub kind: SgFilterHeaderModifierKind,
+    /// Headers to append, values of request headers may refer to captured path parameters, e.g. `${path.id}`.
     pub sets: Option<HashMap<String, String>>,
     pub remove: Option<Vec<String>>,
 }
@@ -32,9 +34,15 @@ pub struct SgFilterHeaderModifier {
 impl MakeSgLayer for SgFilterHeaderModifier {
     fn make_layer(&self) -> Result<spacegate_kernel::SgBoxLayer, spacegate_kernel::BoxError> {
         let mut sets = HeaderMap::new();
+        let mut templates = Vec::new();
         if let Some(set) = &self.sets {
             for (k, v) in set.iter() {
-                sets.insert(HeaderName::from_bytes(k.as_bytes())?, HeaderValue::from_bytes(v.as_bytes())?);
+                let name = HeaderName::from_bytes(k.as_bytes())?;
+                if self.kind == SgFilterHeaderModifierKind::Request && PathCaptures::has_placeholder(v) {
+                    templates.push((name, v.clone()));
+                } else {
+                    sets.insert(name, HeaderValue::from_bytes(v.as_bytes())?);
+                }
             }
         }
         let mut remove = Vec::new();
@@ -43,7 +51,7 @@ impl MakeSgLayer for SgFilterHeaderModifier {
                 remove.push(k.parse()?);
             }
         }
-        let filter = Filter { sets, remove };
+        let filter = Filter { sets, templates, remove };
         let layer = match self.kind {
             SgFilterHeaderModifierKind::Request => HeaderModifierLayer {
                 request: Arc::new(filter),
@@ -61,6 +69,8 @@ impl MakeSgLayer for SgFilterHeaderModifier {
 #[derive(Clone, Default, Debug)]
 struct Filter {
     pub sets: HeaderMap,
+    /// Request header values with `${path.<name>}` placeholders.
+    pub templates: Vec<(HeaderName, String)>,
     pub remove: Vec<HeaderName>,
 }
 
@@ -80,6 +90,17 @@ impl Layer<BoxHyperService> for HeaderModifierLayer {
             for (k, v) in &req_filter.sets {
                 req.headers_mut().append(k, v.clone());
             }
+            if !req_filter.templates.is_empty() {
+                let captures = req.extensions().get::<MatchedSgRouter>().map(|matched| matched.captures().clone()).unwrap_or_default();
+                for (k, v) in &req_filter.templates {
+                    match HeaderValue::from_str(&captures.substitute(v)) {
+                        Ok(value) => {
+                            req.headers_mut().append(k, value);
+                        }
+                        Err(e) => tracing::warn!("[SG.Filter.HeaderModifier] invalid value of header {k}: {e}"),
+                    }
+                }
+            }
             for k in &req_filter.remove {
                 req.headers_mut().remove(k);
             }
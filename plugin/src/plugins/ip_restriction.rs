@@ -0,0 +1,402 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use hyper::{header::HeaderName, Request, Response, StatusCode};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    extension::{GatewayName, PeerAddr},
+    header::X_FORWARDED_FOR,
+    helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+
+use crate::{def_plugin, MakeSgLayer};
+
+pub mod ip_trie;
+use ip_trie::IpTrie;
+
+/// How to find the real client address behind proxies.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgRealIp {
+    /// Header carrying the chain of addresses, which each proxy appends its peer to.
+    pub header: String,
+    /// Proxies trusted to append to the header, the header is ignored unless the peer is one of them.
+    ///
+    /// The chain is walked from the right, the first address which isn't a trusted proxy is the client.
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for SgRealIp {
+    fn default() -> Self {
+        Self {
+            header: X_FORWARDED_FOR.to_string(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+/// Lists in redis sets `<prefix>:allow` and `<prefix>:deny`.
+/// `id` distinguishes plugins sharing the same redis.
+#[cfg(feature = "redis")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SgIpListRedis {
+    pub id: Option<String>,
+}
+
+/// Allow or deny requests by the client address.
+///
+/// Denied addresses are rejected first, then addresses not allowed are rejected if the allow list isn't empty.
+/// Entries are addresses or CIDRs, and are merged from the config, files and redis.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterIpRestriction {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// File of allowed entries, one per line, lines starting with `#` are ignored.
+    pub allow_file: Option<String>,
+    /// File of denied entries, in the format of `allow_file`.
+    pub deny_file: Option<String>,
+    #[cfg(feature = "redis")]
+    pub redis: Option<SgIpListRedis>,
+    /// Interval to reload the files and redis sets.
+    pub refresh_interval_secs: u64,
+    /// Use the real client address instead of the peer address.
+    pub real_ip: Option<SgRealIp>,
+    /// Status of rejections.
+    pub status: u16,
+}
+
+impl Default for SgFilterIpRestriction {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            allow_file: None,
+            deny_file: None,
+            #[cfg(feature = "redis")]
+            redis: None,
+            refresh_interval_secs: 60,
+            real_ip: None,
+            status: StatusCode::FORBIDDEN.as_u16(),
+        }
+    }
+}
+
+fn parse_net(entry: &str) -> Result<IpNet, BoxError> {
+    entry.parse::<IpNet>().or_else(|_| entry.parse::<IpAddr>().map(IpNet::from)).map_err(|e| format!("invalid ip or cidr {entry}: {e}").into())
+}
+
+fn read_entries(path: &str, content: std::io::Result<String>) -> Result<Vec<IpNet>, BoxError> {
+    Ok(parse_entries(content.map_err(|e| format!("fail to read {path}: {e}"))?.lines()))
+}
+
+/// Invalid entries of files and redis sets are skipped rather than failing the whole list.
+fn parse_entries<'a>(entries: impl Iterator<Item = &'a str>) -> Vec<IpNet> {
+    entries
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty() && !entry.starts_with('#'))
+        .filter_map(|entry| parse_net(entry).inspect_err(|e| tracing::warn!("[SG.Filter.IpRestriction] skip {e}")).ok())
+        .collect()
+}
+
+struct RealIp {
+    header: HeaderName,
+    trusted_proxies: IpTrie,
+}
+
+impl RealIp {
+    fn client_ip(&self, req: &Request<SgBody>, peer: IpAddr) -> IpAddr {
+        if !self.trusted_proxies.contains(peer) {
+            return peer;
+        }
+        let chain = req.headers().get_all(&self.header).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(',')).map(str::trim).collect::<Vec<_>>();
+        let mut client = peer;
+        for hop in chain.into_iter().rev() {
+            let Some(ip) = hop.parse::<IpAddr>().ok().or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip())) else {
+                break;
+            };
+            client = ip;
+            if !self.trusted_proxies.contains(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+struct IpLists {
+    allow: IpTrie,
+    deny: IpTrie,
+}
+
+#[derive(Default)]
+struct ListsState {
+    lists: Option<Arc<IpLists>>,
+    loaded_at: Option<Instant>,
+}
+
+struct IpRestriction {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    allow_file: Option<String>,
+    deny_file: Option<String>,
+    #[cfg(feature = "redis")]
+    redis_prefix: Option<String>,
+    refresh_interval: Duration,
+    state: RwLock<ListsState>,
+    refreshing: tokio::sync::Mutex<()>,
+    real_ip: Option<RealIp>,
+    status: StatusCode,
+}
+
+impl IpRestriction {
+    fn is_dynamic(&self) -> bool {
+        #[cfg(feature = "redis")]
+        if self.redis_prefix.is_some() {
+            return true;
+        }
+        self.allow_file.is_some() || self.deny_file.is_some()
+    }
+
+    /// Reads the lists files off the runtime threads, they're read again on every refresh.
+    async fn load_local(&self) -> Result<(Vec<IpNet>, Vec<IpNet>), BoxError> {
+        async fn read(path: &Option<String>) -> Result<Vec<IpNet>, BoxError> {
+            match path {
+                Some(path) => read_entries(path, tokio::fs::read_to_string(path).await),
+                None => Ok(Vec::new()),
+            }
+        }
+        Ok(self.with_configured(read(&self.allow_file).await?, read(&self.deny_file).await?))
+    }
+
+    /// Reads the lists files in place, only for building the layer.
+    fn load_local_blocking(&self) -> Result<(Vec<IpNet>, Vec<IpNet>), BoxError> {
+        let read = |path: &Option<String>| match path {
+            Some(path) => read_entries(path, std::fs::read_to_string(path)),
+            None => Ok(Vec::new()),
+        };
+        Ok(self.with_configured(read(&self.allow_file)?, read(&self.deny_file)?))
+    }
+
+    fn with_configured(&self, allow_file: Vec<IpNet>, deny_file: Vec<IpNet>) -> (Vec<IpNet>, Vec<IpNet>) {
+        let mut allow = self.allow.clone();
+        allow.extend(allow_file);
+        let mut deny = self.deny.clone();
+        deny.extend(deny_file);
+        (allow, deny)
+    }
+
+    async fn load(&self, _gateway_name: Option<&GatewayName>) -> Result<IpLists, BoxError> {
+        #[allow(unused_mut)]
+        let (mut allow, mut deny) = self.load_local().await?;
+        #[cfg(feature = "redis")]
+        if let Some(prefix) = &self.redis_prefix {
+            use spacegate_ext_redis::redis::AsyncCommands;
+            let gateway_name = _gateway_name.ok_or("missing gateway name")?;
+            let client = spacegate_ext_redis::global_repo().get(gateway_name).ok_or("missing redis client")?;
+            let mut conn = client.get_conn().await;
+            let allow_set: Vec<String> = conn.smembers(format!("{prefix}:allow")).await?;
+            let deny_set: Vec<String> = conn.smembers(format!("{prefix}:deny")).await?;
+            allow.extend(parse_entries(allow_set.iter().map(String::as_str)));
+            deny.extend(parse_entries(deny_set.iter().map(String::as_str)));
+        }
+        Ok(IpLists {
+            allow: allow.into_iter().collect(),
+            deny: deny.into_iter().collect(),
+        })
+    }
+
+    /// The current lists, reloaded when they are stale.
+    ///
+    /// Only the first load is waited for, other requests keep using the stale lists while one of them reloads,
+    /// and the stale lists are kept when a reload fails.
+    async fn lists(&self, gateway_name: Option<&GatewayName>) -> Option<Arc<IpLists>> {
+        let fresh = |state: &ListsState| match state.loaded_at {
+            Some(at) => !self.is_dynamic() || at.elapsed() < self.refresh_interval,
+            None => false,
+        };
+        let current = {
+            let state = self.state.read().expect("poisoned lock");
+            if fresh(&state) {
+                return state.lists.clone();
+            }
+            state.lists.clone()
+        };
+        let _refreshing = match (&current, self.refreshing.try_lock()) {
+            (_, Ok(refreshing)) => refreshing,
+            (Some(_), Err(_)) => return current,
+            (None, Err(_)) => self.refreshing.lock().await,
+        };
+        {
+            let state = self.state.read().expect("poisoned lock");
+            if fresh(&state) {
+                return state.lists.clone();
+            }
+        }
+        let loaded = self.load(gateway_name).await;
+        let mut state = self.state.write().expect("poisoned lock");
+        state.loaded_at = Some(Instant::now());
+        match loaded {
+            Ok(lists) => {
+                tracing::debug!("[SG.Filter.IpRestriction] loaded {} allowed and {} denied entries", lists.allow.len(), lists.deny.len());
+                state.lists = Some(Arc::new(lists));
+            }
+            Err(e) => tracing::warn!("[SG.Filter.IpRestriction] fail to load ip lists: {e}"),
+        }
+        state.lists.clone()
+    }
+}
+
+impl FnLayerMethod for IpRestriction {
+    async fn call(&self, req: Request<SgBody>, inner: Inner) -> Response<SgBody> {
+        let Some(peer) = req.extensions().get::<PeerAddr>().map(|peer| peer.0.ip()) else {
+            return Response::with_code_message(StatusCode::INTERNAL_SERVER_ERROR, "[SG.Filter.IpRestriction] missing peer addr");
+        };
+        let ip = match &self.real_ip {
+            Some(real_ip) => real_ip.client_ip(&req, peer),
+            None => peer,
+        };
+        let Some(lists) = self.lists(req.extensions().get::<GatewayName>()).await else {
+            return Response::with_code_message(StatusCode::SERVICE_UNAVAILABLE, "[SG.Filter.IpRestriction] ip lists unavailable");
+        };
+        if lists.deny.contains(ip) || !(lists.allow.is_empty() || lists.allow.contains(ip)) {
+            tracing::debug!("[SG.Filter.IpRestriction] reject {ip}");
+            return Response::with_code_message(self.status, "[SG.Filter.IpRestriction] forbidden");
+        }
+        inner.call(req).await
+    }
+}
+
+impl MakeSgLayer for SgFilterIpRestriction {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        let parse = |entries: &Vec<String>| entries.iter().map(|entry| parse_net(entry)).collect::<Result<Vec<_>, _>>();
+        let real_ip = match &self.real_ip {
+            Some(real_ip) => Some(RealIp {
+                header: HeaderName::from_str(&real_ip.header)?,
+                trusted_proxies: parse(&real_ip.trusted_proxies)?.into_iter().collect(),
+            }),
+            None => None,
+        };
+        let ip_restriction = IpRestriction {
+            allow: parse(&self.allow).map_err(|e| format!("[SG.Filter.IpRestriction] {e}"))?,
+            deny: parse(&self.deny).map_err(|e| format!("[SG.Filter.IpRestriction] {e}"))?,
+            allow_file: self.allow_file.clone(),
+            deny_file: self.deny_file.clone(),
+            #[cfg(feature = "redis")]
+            redis_prefix: self.redis.as_ref().map(|redis| <IpRestrictionPlugin as crate::Plugin>::redis_prefix(redis.id.as_deref())),
+            refresh_interval: Duration::from_secs(self.refresh_interval_secs),
+            state: Default::default(),
+            refreshing: Default::default(),
+            real_ip,
+            status: StatusCode::from_u16(self.status)?,
+        };
+        #[cfg(feature = "redis")]
+        let local = ip_restriction.redis_prefix.is_none();
+        #[cfg(not(feature = "redis"))]
+        let local = true;
+        if local {
+            // fail early on unreadable files, redis is loaded on the first request
+            let (allow, deny) = ip_restriction.load_local_blocking().map_err(|e| format!("[SG.Filter.IpRestriction] {e}"))?;
+            *ip_restriction.state.write().expect("poisoned lock") = ListsState {
+                lists: Some(Arc::new(IpLists {
+                    allow: allow.into_iter().collect(),
+                    deny: deny.into_iter().collect(),
+                })),
+                loaded_at: Some(Instant::now()),
+            };
+        }
+        Ok(SgBoxLayer::new(FnLayer::new(Arc::new(ip_restriction))))
+    }
+}
+
+def_plugin!("ip-restriction", IpRestrictionPlugin, SgFilterIpRestriction);
+#[cfg(feature = "schema")]
+crate::schema!(
+    IpRestrictionPlugin,
+    SgFilterIpRestriction {
+        allow: vec!["10.0.0.0/8".to_string(), "192.168.0.0/16".to_string()],
+        deny: vec!["10.0.0.1".to_string()],
+        real_ip: Some(SgRealIp {
+            trusted_proxies: vec!["172.16.0.0/12".to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+
+    use hyper::{service::Service, Request, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{extension::PeerAddr, service::get_echo_service, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    fn request(peer: &str, forwarded_for: Option<&str>) -> Request<SgBody> {
+        let mut req = Request::builder().uri("http://sg.idealworld.group/api").extension(PeerAddr(SocketAddr::new(peer.parse().expect("invalid peer"), 40000)));
+        if let Some(forwarded_for) = forwarded_for {
+            req = req.header("x-forwarded-for", forwarded_for);
+        }
+        req.body(SgBody::empty()).expect("invalid request")
+    }
+
+    #[tokio::test]
+    async fn test_ip_restriction() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({
+                "allow": ["10.0.0.0/8", "2001:db8::/32"],
+                "deny": ["10.0.0.1"],
+                "real_ip": { "trusted_proxies": ["192.168.0.0/16"] }
+            }),
+        )?;
+        let service = layer.layer(get_echo_service());
+        for (peer, forwarded_for, status) in [
+            ("10.1.2.3", None, StatusCode::OK),
+            ("10.0.0.1", None, StatusCode::FORBIDDEN),
+            ("11.0.0.1", None, StatusCode::FORBIDDEN),
+            ("2001:db8::1", None, StatusCode::OK),
+            // the header of untrusted peers is ignored
+            ("10.1.2.3", Some("11.0.0.1"), StatusCode::OK),
+            ("11.0.0.1", Some("10.1.2.3"), StatusCode::FORBIDDEN),
+            // the client is the rightmost untrusted address
+            ("192.168.0.1", Some("11.0.0.1, 10.1.2.3, 192.168.0.2"), StatusCode::OK),
+            ("192.168.0.1", Some("10.1.2.3, 11.0.0.1"), StatusCode::FORBIDDEN),
+            ("192.168.0.1", Some("10.0.0.1"), StatusCode::FORBIDDEN),
+        ] {
+            assert_eq!(service.call(request(peer, forwarded_for)).await?.status(), status, "{peer} {forwarded_for:?}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ip_restriction_file() -> Result<(), BoxError> {
+        let path = std::env::temp_dir().join(format!("sg-ip-restriction-{}", std::process::id()));
+        std::fs::write(&path, "# blocked\n10.0.0.0/8\nnot-an-ip\n")?;
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "deny_file": path, "refresh_interval_secs": 0 }))?;
+        let service = layer.layer(get_echo_service());
+        assert_eq!(service.call(request("10.0.0.1", None)).await?.status(), StatusCode::FORBIDDEN);
+        assert_eq!(service.call(request("11.0.0.1", None)).await?.status(), StatusCode::OK);
+
+        std::fs::write(&path, "11.0.0.0/8\n")?;
+        assert_eq!(service.call(request("10.0.0.1", None)).await?.status(), StatusCode::OK);
+        assert_eq!(service.call(request("11.0.0.1", None)).await?.status(), StatusCode::FORBIDDEN);
+
+        // the stale list is kept when the file is gone
+        std::fs::remove_file(&path)?;
+        assert_eq!(service.call(request("11.0.0.1", None)).await?.status(), StatusCode::FORBIDDEN);
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+/// A binary trie of address prefixes, checking whether an address is in any of the networks in `O(address bits)`.
+#[derive(Debug, Clone, Default)]
+pub struct IpTrie {
+    v4: BitTrie,
+    v6: BitTrie,
+    len: usize,
+}
+
+impl IpTrie {
+    pub fn insert(&mut self, net: IpNet) {
+        let inserted = match net.trunc() {
+            IpNet::V4(net) => self.v4.insert(u32::from(net.addr()) as u128, 32, net.prefix_len()),
+            IpNet::V6(net) => self.v6.insert(u128::from(net.addr()), 128, net.prefix_len()),
+        };
+        if inserted {
+            self.len += 1;
+        }
+    }
+
+    /// IPv4-mapped IPv6 addresses are checked as IPv4 addresses.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => self.v4.contains(u32::from(ip) as u128, 32),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => self.v4.contains(u32::from(ip) as u128, 32),
+                None => self.v6.contains(u128::from(ip), 128),
+            },
+        }
+    }
+
+    /// Count of inserted networks, except those already covered when inserted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl FromIterator<IpNet> for IpTrie {
+    fn from_iter<T: IntoIterator<Item = IpNet>>(iter: T) -> Self {
+        let mut trie = IpTrie::default();
+        iter.into_iter().for_each(|net| trie.insert(net));
+        trie
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Node {
+    /// Indexes of children, `0` means none since the root is never a child.
+    children: [u32; 2],
+    terminal: bool,
+}
+
+#[derive(Debug, Clone)]
+struct BitTrie {
+    nodes: Vec<Node>,
+}
+
+impl Default for BitTrie {
+    fn default() -> Self {
+        Self { nodes: vec![Node::default()] }
+    }
+}
+
+fn bit(addr: u128, bits: u8, index: u8) -> usize {
+    ((addr >> (bits - 1 - index)) & 1) as usize
+}
+
+impl BitTrie {
+    /// Returns false if the prefix is already covered.
+    fn insert(&mut self, addr: u128, bits: u8, prefix_len: u8) -> bool {
+        let mut node = 0;
+        for index in 0..prefix_len {
+            if self.nodes[node].terminal {
+                return false;
+            }
+            let bit = bit(addr, bits, index);
+            node = match self.nodes[node].children[bit] {
+                0 => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[bit] = child as u32;
+                    child
+                }
+                child => child as usize,
+            };
+        }
+        if self.nodes[node].terminal {
+            return false;
+        }
+        // longer prefixes under this one are covered now, they are left unreachable
+        self.nodes[node] = Node { children: [0, 0], terminal: true };
+        true
+    }
+
+    fn contains(&self, addr: u128, bits: u8) -> bool {
+        let mut node = &self.nodes[0];
+        for index in 0..bits {
+            if node.terminal {
+                return true;
+            }
+            match node.children[bit(addr, bits, index)] {
+                0 => return false,
+                child => node = &self.nodes[child as usize],
+            }
+        }
+        node.terminal
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IpTrie;
+
+    #[test]
+    fn test_ip_trie() {
+        let trie: IpTrie = ["10.0.0.0/8", "192.168.1.1/32", "192.168.1.0/24", "2001:db8::/32"].iter().map(|net| net.parse().expect("invalid net")).collect();
+        assert_eq!(trie.len(), 4);
+        for ip in ["10.1.2.3", "192.168.1.200", "2001:db8::1", "::ffff:10.0.0.1"] {
+            assert!(trie.contains(ip.parse().expect("invalid ip")), "{ip}");
+        }
+        for ip in ["11.0.0.1", "192.168.2.1", "2001:db9::1", "::1"] {
+            assert!(!trie.contains(ip.parse().expect("invalid ip")), "{ip}");
+        }
+
+        let mut any = IpTrie::default();
+        any.insert("10.0.0.0/8".parse().expect("invalid net"));
+        any.insert("0.0.0.0/0".parse().expect("invalid net"));
+        any.insert("10.1.0.0/16".parse().expect("invalid net"));
+        assert_eq!(any.len(), 2);
+        assert!(any.contains("8.8.8.8".parse().expect("invalid ip")));
+        assert!(!any.contains("::1".parse().expect("invalid ip")));
+    }
+}
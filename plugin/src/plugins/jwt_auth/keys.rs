@@ -0,0 +1,173 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hyper::{Request, StatusCode};
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey,
+};
+use spacegate_kernel::{service::http_client_service::get_client, BoxError, SgBody};
+use tokio::sync::{Mutex, RwLock};
+
+/// JWKS documents larger than this are rejected.
+const MAX_JWKS_SIZE: usize = 1 << 20;
+
+/// A key to verify tokens with.
+#[derive(Clone)]
+pub struct VerifyingKey {
+    pub kid: Option<String>,
+    /// The algorithm the key is bound to, if declared.
+    pub alg: Option<Algorithm>,
+    pub key: DecodingKey,
+}
+
+impl VerifyingKey {
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self, BoxError> {
+        Ok(Self {
+            kid: jwk.common.key_id.clone(),
+            alg: jwk.common.key_algorithm.and_then(|alg| alg.to_string().parse().ok()),
+            key: DecodingKey::from_jwk(jwk)?,
+        })
+    }
+
+    /// Parse a public key in PEM, RSA, EC and Ed25519 keys are supported.
+    pub fn from_pem(pem: &str) -> Result<Self, BoxError> {
+        let pem = pem.as_bytes();
+        let key = DecodingKey::from_rsa_pem(pem)
+            .or_else(|_| DecodingKey::from_ec_pem(pem))
+            .or_else(|_| DecodingKey::from_ed_pem(pem))
+            .map_err(|e| format!("invalid public key pem: {e}"))?;
+        Ok(Self { kid: None, alg: None, key })
+    }
+
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            kid: None,
+            alg: None,
+            key: DecodingKey::from_secret(secret),
+        }
+    }
+
+    pub fn from_base64_secret(secret: &str) -> Result<Self, BoxError> {
+        Ok(Self {
+            kid: None,
+            alg: None,
+            key: DecodingKey::from_base64_secret(secret)?,
+        })
+    }
+
+    pub fn accepts(&self, alg: Algorithm) -> bool {
+        !self.alg.is_some_and(|key_alg| key_alg != alg)
+    }
+}
+
+/// Parse a JWKS document, keys which are not supported are skipped.
+pub fn parse_jwks(jwks: &[u8]) -> Result<Vec<VerifyingKey>, BoxError> {
+    let jwks: JwkSet = serde_json::from_slice(jwks).map_err(|e| format!("invalid jwks: {e}"))?;
+    Ok(jwks
+        .keys
+        .iter()
+        .filter_map(|jwk| VerifyingKey::from_jwk(jwk).inspect_err(|e| tracing::warn!("[SG.Filter.JwtAuth] skip unsupported jwk {:?}: {e}", jwk.common.key_id)).ok())
+        .collect())
+}
+
+#[derive(Default)]
+struct JwksState {
+    keys: Option<Arc<Vec<VerifyingKey>>>,
+    fetched_at: Option<Instant>,
+    attempted_at: Option<Instant>,
+}
+
+/// Keys fetched from a JWKS url.
+///
+/// Keys are cached for `refresh_interval`, and are refetched early when a token carries an unknown `kid`,
+/// which is how the issuer rotates its keys. Fetches are throttled by `min_refresh_interval`,
+/// and the cached keys are kept when a fetch fails.
+pub struct RemoteJwks {
+    url: String,
+    refresh_interval: Duration,
+    min_refresh_interval: Duration,
+    timeout: Duration,
+    state: RwLock<JwksState>,
+    refreshing: Mutex<()>,
+}
+
+impl RemoteJwks {
+    pub fn new(url: String, refresh_interval: Duration, min_refresh_interval: Duration, timeout: Duration) -> Self {
+        Self {
+            url,
+            refresh_interval,
+            min_refresh_interval,
+            timeout,
+            state: Default::default(),
+            refreshing: Default::default(),
+        }
+    }
+
+    fn should_refresh(&self, state: &JwksState, kid: Option<&str>) -> bool {
+        let throttled = state.attempted_at.is_some_and(|at| at.elapsed() < self.min_refresh_interval);
+        let Some(keys) = &state.keys else {
+            return !throttled;
+        };
+        let expired = match state.fetched_at {
+            Some(at) => at.elapsed() >= self.refresh_interval,
+            None => true,
+        };
+        let unknown_kid = kid.is_some_and(|kid| !keys.iter().any(|k| k.kid.as_deref() == Some(kid)));
+        (expired || unknown_kid) && !throttled
+    }
+
+    pub async fn keys(&self, kid: Option<&str>) -> Result<Arc<Vec<VerifyingKey>>, BoxError> {
+        {
+            let state = self.state.read().await;
+            if !self.should_refresh(&state, kid) {
+                return state.keys.clone().ok_or_else(|| format!("jwks from {} is unavailable", self.url).into());
+            }
+        }
+        let _refreshing = self.refreshing.lock().await;
+        // someone else may have refreshed while we are waiting
+        if !self.should_refresh(&*self.state.read().await, kid) {
+            return self.state.read().await.keys.clone().ok_or_else(|| format!("jwks from {} is unavailable", self.url).into());
+        }
+        let fetched = self.fetch().await;
+        let mut state = self.state.write().await;
+        state.attempted_at = Some(Instant::now());
+        match fetched {
+            Ok(keys) => {
+                tracing::debug!("[SG.Filter.JwtAuth] fetched {} keys from {}", keys.len(), self.url);
+                state.keys = Some(Arc::new(keys));
+                state.fetched_at = state.attempted_at;
+            }
+            Err(e) => {
+                tracing::warn!("[SG.Filter.JwtAuth] failed to fetch jwks from {}: {e}", self.url);
+            }
+        }
+        state.keys.clone().ok_or_else(|| format!("jwks from {} is unavailable", self.url).into())
+    }
+
+    async fn fetch(&self) -> Result<Vec<VerifyingKey>, BoxError> {
+        let req = Request::get(self.url.as_str()).body(SgBody::empty())?;
+        let resp = get_client().request_timeout(req, self.timeout).await;
+        if resp.status() != StatusCode::OK {
+            return Err(format!("unexpected status {}", resp.status()).into());
+        }
+        let body = resp.into_body().dump_with_limit(MAX_JWKS_SIZE).await?;
+        parse_jwks(body.get_dumped().map(|b| b.as_ref()).unwrap_or_default())
+    }
+}
+
+pub enum KeyStore {
+    Static(Arc<Vec<VerifyingKey>>),
+    Remote(RemoteJwks),
+}
+
+impl KeyStore {
+    pub async fn keys(&self, kid: Option<&str>) -> Result<Arc<Vec<VerifyingKey>>, BoxError> {
+        match self {
+            KeyStore::Static(keys) => Ok(keys.clone()),
+            KeyStore::Remote(remote) => remote.keys(kid).await,
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::{str::FromStr, sync::Arc};
+
+use hyper::{
+    header::{HeaderName, HeaderValue, COOKIE},
+    http::Extensions,
+    Request, Response, StatusCode, Uri,
+};
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::{
+    extension::GatewayName,
+    helper_layers::{
+        check::{Check, CheckLayer},
+        map_request::MapRequestLayer,
+    },
+    marker::extension::Extension,
+    BoxError, SgBody, SgBoxLayer, SgResponseExt,
+};
+use tower_layer::Stack;
+use url::form_urlencoded;
+
+use super::credential::{consumer_extensions, set_consumer_header, CredentialStore, SgCredentialSource};
+use crate::{def_plugin, MakeSgLayer};
+
+/// Authenticate requests by api keys, the authenticated consumer is attached to the request as
+/// [`Consumer`](spacegate_kernel::extension::Consumer).
+///
+/// The key is taken from `header`, `query` and `cookie` in order. Keys in redis are stored as
+/// `<prefix>:<key>` with the consumer name as the value.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterKeyAuth {
+    pub source: SgCredentialSource,
+    pub header: Option<String>,
+    pub query: Option<String>,
+    pub cookie: Option<String>,
+    /// Whether the key is removed from the request before it's forwarded.
+    pub hide_credentials: bool,
+    /// Header to pass the consumer name to the backend, the one sent by the client is always removed.
+    pub consumer_header: Option<String>,
+}
+
+impl Default for SgFilterKeyAuth {
+    fn default() -> Self {
+        Self {
+            source: SgCredentialSource::default(),
+            header: Some("x-api-key".to_string()),
+            query: None,
+            cookie: None,
+            hide_credentials: false,
+            consumer_header: None,
+        }
+    }
+}
+
+/// The api key carried by a request.
+#[derive(Clone)]
+pub struct ApiKey {
+    pub key: Arc<str>,
+    pub gateway_name: Option<GatewayName>,
+}
+
+struct KeyExtractor {
+    header: Option<HeaderName>,
+    query: Option<String>,
+    cookie: Option<String>,
+    hide_credentials: bool,
+    consumer_header: Option<HeaderName>,
+}
+
+impl KeyExtractor {
+    fn key(&self, req: &Request<SgBody>) -> Option<String> {
+        if let Some(key) = self.header.as_ref().and_then(|header| req.headers().get(header)).and_then(|v| v.to_str().ok()) {
+            return Some(key.to_string());
+        }
+        if let Some(query) = &self.query {
+            // keys are percent-encoded in the query, e.g. `+` and `/` of base64 keys
+            let key = req.uri().query().and_then(|q| form_urlencoded::parse(q.as_bytes()).find_map(|(k, v)| (k == query.as_str()).then(|| v.into_owned())));
+            if key.is_some() {
+                return key;
+            }
+        }
+        let cookie = self.cookie.as_deref()?;
+        cookies(req).find_map(|(k, v)| (k == cookie).then(|| v.to_string()))
+    }
+
+    fn extract(&self, mut req: Request<SgBody>) -> Request<SgBody> {
+        if let Some(consumer_header) = &self.consumer_header {
+            req.headers_mut().remove(consumer_header);
+        }
+        let Some(key) = self.key(&req).filter(|key| !key.is_empty()) else {
+            return req;
+        };
+        if self.hide_credentials {
+            self.hide(&mut req);
+        }
+        let gateway_name = req.extensions().get::<GatewayName>().cloned();
+        req.extensions_mut().insert(ApiKey { key: key.into(), gateway_name });
+        req
+    }
+
+    fn hide(&self, req: &mut Request<SgBody>) {
+        if let Some(header) = &self.header {
+            req.headers_mut().remove(header);
+        }
+        if let Some(uri) = self.query.as_deref().and_then(|query| remove_query(req.uri(), query)) {
+            *req.uri_mut() = uri;
+        }
+        if let Some(cookie) = &self.cookie {
+            let rest = cookies(req).filter(|(k, _)| k != cookie).map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join("; ");
+            req.headers_mut().remove(COOKIE);
+            if let Ok(rest) = HeaderValue::from_str(&rest) {
+                if !rest.is_empty() {
+                    req.headers_mut().insert(COOKIE, rest);
+                }
+            }
+        }
+    }
+}
+
+fn cookies(req: &Request<SgBody>) -> impl Iterator<Item = (&str, &str)> {
+    req.headers().get_all(COOKIE).iter().filter_map(|v| v.to_str().ok()).flat_map(|v| v.split(';')).filter_map(|kv| kv.trim().split_once('='))
+}
+
+fn remove_query(uri: &Uri, name: &str) -> Option<Uri> {
+    let query = uri.query()?;
+    let rest = query.split('&').filter(|kv| kv.split_once('=').map_or(*kv, |(k, _)| k) != name).collect::<Vec<_>>().join("&");
+    let path_and_query = if rest.is_empty() { uri.path().to_string() } else { format!("{}?{rest}", uri.path()) };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+#[derive(Clone)]
+pub struct KeyAuthCheck {
+    store: CredentialStore,
+    consumer_header: Option<HeaderName>,
+}
+
+impl Check<Extension<ApiKey>> for KeyAuthCheck {
+    async fn check(&self, marker: &Extension<ApiKey>) -> bool {
+        self.check_and_attach(marker).await.is_some()
+    }
+    async fn check_and_attach(&self, marker: &Extension<ApiKey>) -> Option<Extensions> {
+        let consumer =
+            self.store.get(&marker.key, marker.gateway_name.as_ref()).await.inspect_err(|e| tracing::warn!("[SG.Filter.KeyAuth] fail to look up api key: {e}")).ok()??;
+        Some(consumer_extensions(consumer))
+    }
+    fn on_forbidden(&self, _marker: Extension<ApiKey>) -> Response<SgBody> {
+        Response::with_code_message(StatusCode::UNAUTHORIZED, "[SG.Filter.KeyAuth] invalid api key")
+    }
+    fn on_missing(&self) -> Response<SgBody> {
+        Response::with_code_message(StatusCode::UNAUTHORIZED, "[SG.Filter.KeyAuth] missing api key")
+    }
+    fn on_pass(&self, request: Request<SgBody>) -> Request<SgBody> {
+        set_consumer_header(request, self.consumer_header.as_ref())
+    }
+}
+
+impl MakeSgLayer for SgFilterKeyAuth {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        if self.header.is_none() && self.query.is_none() && self.cookie.is_none() {
+            return Err("[SG.Filter.KeyAuth] one of header, query and cookie is required".into());
+        }
+        let consumer_header = self.consumer_header.as_deref().map(HeaderName::from_str).transpose()?;
+        let extractor = Arc::new(KeyExtractor {
+            header: self.header.as_deref().map(HeaderName::from_str).transpose()?,
+            query: self.query.clone(),
+            cookie: self.cookie.clone(),
+            hide_credentials: self.hide_credentials,
+            consumer_header: consumer_header.clone(),
+        });
+        let check = KeyAuthCheck {
+            store: self.source.make_store::<KeyAuthPlugin>(|credential| (credential.secret, credential.consumer))?,
+            consumer_header,
+        };
+        Ok(SgBoxLayer::new(Stack::new(
+            CheckLayer::<_, Extension<ApiKey>>::new(check),
+            MapRequestLayer::new(move |req| extractor.extract(req)),
+        )))
+    }
+}
+
+def_plugin!("key-auth", KeyAuthPlugin, SgFilterKeyAuth);
+#[cfg(feature = "schema")]
+crate::schema!(KeyAuthPlugin, SgFilterKeyAuth);
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use http_body_util::BodyExt;
+    use hyper::{header::COOKIE, service::Service, Request, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{
+        extension::Consumer,
+        helper_layers::function::{FnLayer, FnLayerMethod, Inner},
+        BoxError, SgBody,
+    };
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    /// Echo the consumer, the forwarded consumer header and the remaining credentials.
+    #[derive(Clone)]
+    struct Backend;
+    impl FnLayerMethod for Backend {
+        async fn call(&self, req: Request<SgBody>, _inner: Inner) -> hyper::Response<SgBody> {
+            let consumer = req.extensions().get::<Consumer>().map(|c| c.to_string()).unwrap_or_default();
+            let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+            let body = format!(
+                "{consumer}|{}|{}|{}|{}",
+                header("x-consumer"),
+                header("x-api-key"),
+                req.uri().query().unwrap_or_default(),
+                header("cookie")
+            );
+            hyper::Response::new(SgBody::full(body))
+        }
+    }
+
+    fn backend() -> spacegate_kernel::BoxHyperService {
+        spacegate_kernel::BoxHyperService::new(FnLayer::new(Backend).layer(spacegate_kernel::service::get_echo_service()))
+    }
+
+    #[tokio::test]
+    async fn test_key_auth_inline() -> Result<(), BoxError> {
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({
+                "source": { "kind": "Inline", "credentials": [{ "consumer": "alice", "secret": "alice-key" }, { "consumer": "bob", "secret": "bob-key" }, { "consumer": "carol", "secret": "carol+key/=" }] },
+                "query": "apikey",
+                "cookie": "apikey",
+                "hide_credentials": true,
+                "consumer_header": "x-consumer"
+            }),
+        )?;
+        let service = layer.layer(backend());
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api").header("x-api-key", "alice-key").header("x-consumer", "spoofed").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "alice|alice|||");
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api?a=1&apikey=bob-key&b=2").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "bob|bob||a=1&b=2|");
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api?apikey=carol%2Bkey%2F%3D").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "carol|carol|||");
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api").header(COOKIE, "theme=dark; apikey=bob-key").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "bob|bob|||theme=dark");
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api").header("x-api-key", "wrong-key").body(SgBody::empty())?;
+        assert_eq!(service.call(req).await?.status(), StatusCode::UNAUTHORIZED);
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api").header("x-consumer", "alice").body(SgBody::empty())?;
+        assert_eq!(service.call(req).await?.status(), StatusCode::UNAUTHORIZED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_key_auth_file() -> Result<(), BoxError> {
+        let path = std::env::temp_dir().join(format!("sg-key-auth-{}", std::process::id()));
+        std::fs::File::create(&path)?.write_all(b"# consumer:key\nalice:alice-key\n")?;
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "source": { "kind": "File", "path": path } }));
+        std::fs::remove_file(&path)?;
+        let service = layer?.layer(backend());
+
+        let req = Request::builder().uri("http://sg.idealworld.group/api").header("x-api-key", "alice-key").body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // the key is kept by default, and the consumer header is not set
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "alice||alice-key||");
+
+        let duplicated = json!({ "source": { "kind": "Inline", "credentials": [{ "consumer": "alice", "secret": "key" }, { "consumer": "bob", "secret": "key" }] } });
+        assert!(crate::SgPluginRepository::global().create_layer(None, super::CODE, duplicated).is_err());
+        Ok(())
+    }
+}
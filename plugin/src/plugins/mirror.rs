@@ -0,0 +1,282 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use http_body_util::StreamBody;
+use hyper::body::{Bytes, Frame};
+use hyper::header::{HeaderMap, HeaderValue, CONNECTION, CONTENT_LENGTH, TE, TRANSFER_ENCODING, UPGRADE};
+use hyper::{header::HeaderName, Request, Response};
+use serde::{Deserialize, Serialize};
+use spacegate_kernel::helper_layers::bidirection_filter::{Bdf, BdfLayer, BoxReqFut, BoxRespFut};
+use spacegate_kernel::helper_layers::body_limit::content_length;
+use spacegate_kernel::service::http_client_service::get_client;
+use spacegate_kernel::{BoxError, SgBody, SgBoxLayer};
+
+use crate::{def_plugin, MakeSgLayer};
+
+/// Header added to mirrored requests.
+pub const SG_MIRROR_HEADER: &str = "sg-mirror";
+const DEFAULT_MAX_BODY_SIZE: usize = 0x10000;
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// A shadow backend which receives a copy of the request.
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgMirrorBackend {
+    pub host: String,
+    /// Port of the backend, default to the port of the protocol.
+    pub port: Option<u16>,
+    /// `http` or `https`, default to `http`.
+    pub protocol: Option<String>,
+}
+
+impl SgMirrorBackend {
+    fn uri(&self, path_and_query: &str) -> String {
+        let protocol = self.protocol.as_deref().unwrap_or("http");
+        match self.port {
+            Some(port) => format!("{protocol}://{}:{port}{path_and_query}", self.host),
+            None => format!("{protocol}://{}{path_and_query}", self.host),
+        }
+    }
+}
+
+/// Send a copy of matched requests to shadow backends, the responses of shadow backends are ignored.
+///
+/// This is the counterpart of Gateway API `RequestMirror` filter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SgFilterMirror {
+    pub backends: Vec<SgMirrorBackend>,
+    /// Percentage of requests to be mirrored, from 0 to 100.
+    pub percentage: f64,
+    /// Whether to mirror the request body, requests without body are mirrored without body.
+    pub mirror_body: bool,
+    /// Bodies larger than this, or with unknown size, are not mirrored.
+    pub max_body_size: usize,
+    /// Timeout for a mirrored request.
+    pub timeout_ms: u64,
+}
+
+impl Default for SgFilterMirror {
+    fn default() -> Self {
+        Self {
+            backends: Vec::new(),
+            percentage: 100.0,
+            mirror_body: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Hop-by-hop headers describe the connection of the client, so they are not copied to mirrored requests.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect::<Vec<_>>();
+    for name in listed.into_iter().chain([CONNECTION, TE, TRANSFER_ENCODING, UPGRADE, HeaderName::from_static("keep-alive")]) {
+        headers.remove(name);
+    }
+}
+
+impl SgFilterMirror {
+    fn sampled(&self) -> bool {
+        self.percentage >= 100.0 || (self.percentage > 0.0 && rand::random::<f64>() * 100.0 < self.percentage)
+    }
+
+    /// Prepare the body to be mirrored, returns the request to be forwarded and the body of mirrored requests.
+    ///
+    /// The body of mirrored requests is `Err` if the request body fails to be read, then nothing is mirrored and the
+    /// failure is left to the forwarded request.
+    async fn mirror_body(&self, req: Request<SgBody>) -> (Request<SgBody>, Result<Option<SgBody>, ()>) {
+        if !self.mirror_body {
+            return (req, Ok(None));
+        }
+        let (parts, body) = req.into_parts();
+        let body = match body.get_dumped() {
+            Some(dumped) if dumped.len() <= self.max_body_size => body,
+            Some(_) => return (Request::from_parts(parts, body), Ok(None)),
+            None if content_length(&parts.headers).is_some_and(|len| len <= self.max_body_size as u64) => match body.dump().await {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::debug!("[SG.Filter.Mirror] fail to read request body, skip mirroring: {e}");
+                    let failed = StreamBody::new(futures_util::stream::once(async move { Err::<Frame<Bytes>, BoxError>(e) }));
+                    return (Request::from_parts(parts, SgBody::new_boxed_error(failed)), Err(()));
+                }
+            },
+            None => return (Request::from_parts(parts, body), Ok(None)),
+        };
+        let mirror_body = body.dump_clone();
+        (Request::from_parts(parts, body), Ok(mirror_body))
+    }
+
+    async fn req_filter(&self, req: Request<SgBody>) -> Request<SgBody> {
+        if self.backends.is_empty() || !self.sampled() {
+            return req;
+        }
+        let (req, body) = self.mirror_body(req).await;
+        let Ok(body) = body else {
+            return req;
+        };
+        let path_and_query = req.uri().path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+        let timeout = Duration::from_millis(self.timeout_ms);
+        for backend in &self.backends {
+            let mut builder = Request::builder().method(req.method().clone()).uri(backend.uri(path_and_query));
+            if let Some(headers) = builder.headers_mut() {
+                headers.extend(req.headers().clone());
+                remove_hop_by_hop_headers(headers);
+                if body.is_none() {
+                    headers.remove(CONTENT_LENGTH);
+                }
+                headers.insert(HeaderName::from_static(SG_MIRROR_HEADER), HeaderValue::from_static("true"));
+            }
+            let mirror_req = match builder.body(body.as_ref().and_then(SgBody::dump_clone).unwrap_or_else(SgBody::empty)) {
+                Ok(mirror_req) => mirror_req,
+                Err(e) => {
+                    tracing::debug!("[SG.Filter.Mirror] fail to build mirrored request: {e}");
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                let uri = mirror_req.uri().clone();
+                let resp = get_client().request_timeout(mirror_req, timeout).await;
+                tracing::trace!("[SG.Filter.Mirror] mirrored request to {uri} responded {status}", status = resp.status());
+            });
+        }
+        req
+    }
+}
+
+impl Bdf for SgFilterMirror {
+    type FutureReq = BoxReqFut;
+
+    type FutureResp = BoxRespFut;
+
+    fn on_req(self: Arc<Self>, req: Request<SgBody>) -> Self::FutureReq {
+        Box::pin(async move { Ok(self.req_filter(req).await) })
+    }
+
+    fn on_resp(self: Arc<Self>, resp: Response<SgBody>) -> Self::FutureResp {
+        Box::pin(async move { resp })
+    }
+}
+
+impl MakeSgLayer for SgFilterMirror {
+    fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+        for backend in &self.backends {
+            backend.uri("/").parse::<hyper::Uri>()?;
+        }
+        if !(0.0..=100.0).contains(&self.percentage) {
+            return Err(format!("[SG.Filter.Mirror] percentage should be in 0..=100, got {}", self.percentage).into());
+        }
+        Ok(SgBoxLayer::new(BdfLayer::new(self.clone())))
+    }
+}
+
+def_plugin!("mirror", MirrorPlugin, SgFilterMirror);
+#[cfg(feature = "schema")]
+crate::schema!(
+    MirrorPlugin,
+    SgFilterMirror {
+        backends: vec![SgMirrorBackend {
+            host: "shadow.default".to_string(),
+            port: Some(80),
+            protocol: Some("http".to_string()),
+        }],
+        percentage: 10.0,
+        mirror_body: true,
+        ..Default::default()
+    }
+);
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper::{
+        body::{Bytes, Frame},
+        service::Service,
+        Method, Request, StatusCode,
+    };
+    use serde_json::json;
+    use spacegate_kernel::{service::get_echo_service, BoxError, SgBody};
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    #[tokio::test]
+    async fn test_mirror() -> Result<(), BoxError> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(
+                    hyper_util::rt::TokioIo::new(stream),
+                    hyper::service::service_fn(move |req: Request<hyper::body::Incoming>| {
+                        let tx = tx.clone();
+                        async move {
+                            let (parts, body) = req.into_parts();
+                            let body = body.collect().await?.to_bytes();
+                            let _ = tx.send((parts, body));
+                            Ok::<_, hyper::Error>(hyper::Response::new(http_body_util::Empty::<hyper::body::Bytes>::new()))
+                        }
+                    }),
+                ));
+            }
+        });
+
+        let layer = crate::SgPluginRepository::global().create_layer(
+            None,
+            super::CODE,
+            json!({ "backends": [{ "host": "127.0.0.1", "port": port }], "mirror_body": true, "max_body_size": 8 }),
+        )?;
+        let service = layer.layer(get_echo_service());
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("http://sg.idealworld.group/mirror?a=1")
+            .header("content-length", "5")
+            .header("connection", "keep-alive, x-hop")
+            .header("te", "trailers")
+            .header("x-hop", "1")
+            .body(SgBody::full("hello"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "hello");
+        let (parts, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?.expect("mirrored request not received");
+        assert_eq!(parts.uri, "/mirror?a=1");
+        assert_eq!(parts.headers.get(super::SG_MIRROR_HEADER).expect("missing mirror header"), "true");
+        assert_eq!(body, "hello");
+        assert!(["te", "x-hop"].iter().all(|name| !parts.headers.contains_key(*name)));
+
+        // body exceeds the limit, mirrored without body
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/large").header("content-length", "9").body(SgBody::full("123456789"))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.into_body().collect().await?.to_bytes(), "123456789");
+        let (parts, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await?.expect("mirrored request not received");
+        assert_eq!(parts.uri, "/large");
+        assert!(body.is_empty());
+
+        // the body fails to be read, the request is forwarded without mirroring
+        let failed = StreamBody::new(futures_util::stream::once(async { Err::<Frame<Bytes>, BoxError>("client gone".into()) }));
+        let req = Request::builder().method(Method::POST).uri("http://sg.idealworld.group/failed").header("content-length", "5").body(SgBody::new_boxed_error(failed))?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.into_body().collect().await.is_err());
+        assert!(tokio::time::timeout(Duration::from_millis(200), rx.recv()).await.is_err());
+
+        let layer = crate::SgPluginRepository::global().create_layer(None, super::CODE, json!({ "backends": [{ "host": "127.0.0.1", "port": port }], "percentage": 0 }))?;
+        let service = layer.layer(get_echo_service());
+        let resp = service.call(Request::builder().uri("http://sg.idealworld.group/none").body(SgBody::empty())?).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(tokio::time::timeout(Duration::from_millis(200), rx.recv()).await.is_err());
+        Ok(())
+    }
+}
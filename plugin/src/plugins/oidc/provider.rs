@@ -0,0 +1,215 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    Method, Request, StatusCode,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use spacegate_kernel::{service::http_client_service::get_client, BoxError, SgBody};
+use tokio::sync::{Mutex, RwLock};
+use url::form_urlencoded;
+
+use crate::plugins::jwt_auth::keys::RemoteJwks;
+
+use super::SgOidcClientAuth;
+
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// Failed discoveries are not retried within this interval.
+const DISCOVERY_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Discovery documents and token responses larger than this are rejected.
+const MAX_RESPONSE_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    end_session_endpoint: Option<String>,
+}
+
+pub struct Endpoints {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub end_session_endpoint: Option<String>,
+    jwks: RemoteJwks,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub expires_in: Option<i64>,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+}
+
+pub struct ClientConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub client_auth: SgOidcClientAuth,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub end_session_endpoint: Option<String>,
+    pub leeway_secs: u64,
+    pub timeout: Duration,
+}
+
+#[derive(Default)]
+struct DiscoveryState {
+    endpoints: Option<Arc<Endpoints>>,
+    failed_at: Option<Instant>,
+}
+
+/// The OpenID provider, its endpoints are discovered on the first use unless all of them are configured.
+pub struct Provider {
+    pub config: ClientConfig,
+    state: RwLock<DiscoveryState>,
+    discovering: Mutex<()>,
+}
+
+impl Provider {
+    pub fn new(config: ClientConfig) -> Self {
+        let endpoints = match (&config.authorization_endpoint, &config.token_endpoint, &config.jwks_uri) {
+            (Some(authorization_endpoint), Some(token_endpoint), Some(jwks_uri)) => Some(Arc::new(Endpoints {
+                authorization_endpoint: authorization_endpoint.clone(),
+                token_endpoint: token_endpoint.clone(),
+                end_session_endpoint: config.end_session_endpoint.clone(),
+                jwks: RemoteJwks::new(jwks_uri.clone(), JWKS_REFRESH_INTERVAL, JWKS_MIN_REFRESH_INTERVAL, config.timeout),
+            })),
+            _ => None,
+        };
+        Self {
+            config,
+            state: RwLock::new(DiscoveryState { endpoints, failed_at: None }),
+            discovering: Default::default(),
+        }
+    }
+
+    pub async fn endpoints(&self) -> Result<Arc<Endpoints>, BoxError> {
+        let ready = |state: &DiscoveryState| -> Option<Result<Arc<Endpoints>, BoxError>> {
+            if let Some(endpoints) = &state.endpoints {
+                return Some(Ok(endpoints.clone()));
+            }
+            state.failed_at.filter(|at| at.elapsed() < DISCOVERY_RETRY_INTERVAL).map(|_| Err(format!("discovery of {} failed recently", self.config.issuer).into()))
+        };
+        if let Some(result) = ready(&*self.state.read().await) {
+            return result;
+        }
+        let _discovering = self.discovering.lock().await;
+        if let Some(result) = ready(&*self.state.read().await) {
+            return result;
+        }
+        let discovered = self.discover().await;
+        let mut state = self.state.write().await;
+        match discovered {
+            Ok(endpoints) => {
+                let endpoints = Arc::new(endpoints);
+                state.endpoints = Some(endpoints.clone());
+                Ok(endpoints)
+            }
+            Err(e) => {
+                state.failed_at = Some(Instant::now());
+                Err(format!("fail to discover {}: {e}", self.config.issuer).into())
+            }
+        }
+    }
+
+    async fn discover(&self) -> Result<Endpoints, BoxError> {
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer.trim_end_matches('/'));
+        let req = Request::get(url).header(ACCEPT, "application/json").body(SgBody::empty())?;
+        let resp = get_client().request_timeout(req, self.config.timeout).await;
+        if resp.status() != StatusCode::OK {
+            return Err(format!("unexpected status {}", resp.status()).into());
+        }
+        let body = resp.into_body().dump_with_limit(MAX_RESPONSE_SIZE).await?;
+        let metadata: Metadata = serde_json::from_slice(body.get_dumped().map(|b| b.as_ref()).unwrap_or_default())?;
+        if metadata.issuer != self.config.issuer {
+            return Err(format!("issuer mismatch, got {}", metadata.issuer).into());
+        }
+        let config = &self.config;
+        Ok(Endpoints {
+            authorization_endpoint: config.authorization_endpoint.clone().unwrap_or(metadata.authorization_endpoint),
+            token_endpoint: config.token_endpoint.clone().unwrap_or(metadata.token_endpoint),
+            end_session_endpoint: config.end_session_endpoint.clone().or(metadata.end_session_endpoint),
+            jwks: RemoteJwks::new(
+                config.jwks_uri.clone().unwrap_or(metadata.jwks_uri),
+                JWKS_REFRESH_INTERVAL,
+                JWKS_MIN_REFRESH_INTERVAL,
+                config.timeout,
+            ),
+        })
+    }
+
+    fn token_request(&self, endpoints: &Endpoints, params: &[(&str, &str)]) -> Result<Request<SgBody>, BoxError> {
+        let mut req =
+            Request::builder().method(Method::POST).uri(&endpoints.token_endpoint).header(CONTENT_TYPE, "application/x-www-form-urlencoded").header(ACCEPT, "application/json");
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.extend_pairs(params).append_pair("client_id", &self.config.client_id);
+        match (&self.config.client_secret, self.config.client_auth) {
+            (Some(secret), SgOidcClientAuth::ClientSecretBasic) => {
+                let encode = |s: &str| form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+                let credential = STANDARD.encode(format!("{}:{}", encode(&self.config.client_id), encode(secret)));
+                req = req.header(AUTHORIZATION, format!("Basic {credential}"));
+            }
+            (Some(secret), SgOidcClientAuth::ClientSecretPost) => {
+                form.append_pair("client_secret", secret);
+            }
+            (None, _) => {}
+        }
+        Ok(req.body(SgBody::full(form.finish()))?)
+    }
+
+    /// Call the token endpoint with the `params` of a grant.
+    pub async fn token(&self, endpoints: &Endpoints, params: &[(&str, &str)]) -> Result<TokenResponse, BoxError> {
+        let resp = get_client().request_timeout(self.token_request(endpoints, params)?, self.config.timeout).await;
+        let status = resp.status();
+        let body = resp.into_body().dump_with_limit(MAX_RESPONSE_SIZE).await?;
+        let body = body.get_dumped().map(|b| b.as_ref()).unwrap_or_default();
+        if status != StatusCode::OK {
+            return Err(format!("token endpoint responded {status}: {}", String::from_utf8_lossy(body)).into());
+        }
+        Ok(serde_json::from_slice(body)?)
+    }
+
+    /// Validate an id token, `nonce` is checked when given, i.e. for the authorization code grant.
+    pub async fn verify_id_token(&self, endpoints: &Endpoints, token: &str, nonce: Option<&str>) -> Result<Map<String, Value>, BoxError> {
+        let header = jsonwebtoken::decode_header(token)?;
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.config.leeway_secs;
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        let claims = if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            // symmetric id tokens are signed with the client secret
+            let secret = self.config.client_secret.as_deref().ok_or("symmetric id token without client secret")?;
+            jsonwebtoken::decode::<Map<String, Value>>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)?.claims
+        } else {
+            let keys = endpoints.jwks.keys(header.kid.as_deref()).await?;
+            let mut candidates = keys.iter().filter(|key| key.accepts(header.alg) && (header.kid.is_none() || key.kid.is_none() || key.kid == header.kid));
+            let mut error: BoxError = "no key matches the id token".into();
+            loop {
+                let Some(key) = candidates.next() else {
+                    return Err(error);
+                };
+                match jsonwebtoken::decode::<Map<String, Value>>(token, &key.key, &validation) {
+                    Ok(data) => break data.claims,
+                    Err(e) => error = e.into(),
+                }
+            }
+        };
+        if let Some(nonce) = nonce {
+            if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+                return Err("nonce mismatch".into());
+            }
+        }
+        Ok(claims)
+    }
+}
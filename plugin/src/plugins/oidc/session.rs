@@ -0,0 +1,216 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hyper::{
+    header::{HeaderValue, COOKIE, SET_COOKIE},
+    HeaderMap,
+};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use spacegate_kernel::BoxError;
+
+/// Browsers limit a cookie to about 4KB, larger values are split into `<name>`, `<name>.1`, `<name>.2` and so on.
+const MAX_CHUNK_SIZE: usize = 3800;
+
+/// An authenticated session, kept in the encrypted session cookie.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+    /// When the access token expires, in unix seconds.
+    pub expires_at: Option<i64>,
+    /// Claims of the validated id token.
+    pub claims: Map<String, Value>,
+    /// When the session expires, in unix seconds.
+    pub session_expires_at: i64,
+}
+
+/// An ongoing login, kept in the encrypted login cookie until the provider redirects back.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    /// Path and query to return to after login.
+    pub return_to: String,
+    pub expires_at: i64,
+}
+
+/// Random url-safe string of `len` random bytes.
+pub fn random_string(len: usize) -> String {
+    let mut bytes = vec![0; len];
+    SystemRandom::new().fill(&mut bytes).expect("system random is unavailable");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The PKCE `S256` code challenge of `verifier`.
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, verifier.as_bytes()))
+}
+
+pub struct CookieCodec {
+    key: LessSafeKey,
+    secure: bool,
+}
+
+impl CookieCodec {
+    /// The key is derived from `secret` by SHA-256.
+    pub fn new(secret: &str, secure: bool) -> Result<Self, BoxError> {
+        let key = UnboundKey::new(&AES_256_GCM, digest(&SHA256, secret.as_bytes()).as_ref()).map_err(|_| "invalid session key")?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            secure,
+        })
+    }
+
+    /// Encrypt `value` with AES-256-GCM, the cookie name is used as the associated data,
+    /// so a cookie can't be replayed under another name.
+    fn seal<T: Serialize>(&self, name: &str, value: &T) -> Result<String, BoxError> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| "fail to generate nonce")?;
+        let mut data = serde_json::to_vec(value)?;
+        self.key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(name.as_bytes()), &mut data).map_err(|_| "fail to encrypt cookie")?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(data);
+        Ok(URL_SAFE_NO_PAD.encode(sealed))
+    }
+
+    fn open<T: DeserializeOwned>(&self, name: &str, value: &str) -> Option<T> {
+        let mut sealed = URL_SAFE_NO_PAD.decode(value).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let mut data = sealed.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&sealed).ok()?;
+        let plain = self.key.open_in_place(nonce, Aad::from(name.as_bytes()), &mut data).ok()?;
+        serde_json::from_slice(plain).ok()
+    }
+
+    /// Read and decrypt the cookie `name` from request headers, joining its chunks.
+    pub fn read<T: DeserializeOwned>(&self, headers: &HeaderMap, name: &str) -> Option<T> {
+        let mut value = cookie(headers, name)?.to_string();
+        for index in 1.. {
+            match cookie(headers, &chunk_name(name, index)) {
+                Some(chunk) => value.push_str(chunk),
+                None => break,
+            }
+        }
+        self.open(name, &value)
+    }
+
+    /// Encrypt `value` into `set-cookie` headers of the response, stale chunks sent by the request are expired.
+    pub fn write<T: Serialize>(&self, request_headers: &HeaderMap, response_headers: &mut HeaderMap, name: &str, value: &T, max_age_secs: i64) -> Result<(), BoxError> {
+        let sealed = self.seal(name, value)?;
+        let chunks = sealed.as_bytes().chunks(MAX_CHUNK_SIZE).map(|chunk| std::str::from_utf8(chunk).expect("base64 is ascii")).collect::<Vec<_>>();
+        for (index, chunk) in chunks.iter().enumerate() {
+            response_headers.append(SET_COOKIE, self.set_cookie(&chunk_name(name, index), chunk, max_age_secs)?);
+        }
+        for index in chunks.len().. {
+            let chunk = chunk_name(name, index);
+            if cookie(request_headers, &chunk).is_none() {
+                break;
+            }
+            response_headers.append(SET_COOKIE, self.set_cookie(&chunk, "", 0)?);
+        }
+        Ok(())
+    }
+
+    /// Expire the cookie `name` and its chunks sent by the request.
+    pub fn clear(&self, request_headers: &HeaderMap, response_headers: &mut HeaderMap, name: &str) -> Result<(), BoxError> {
+        for index in 0.. {
+            let chunk = chunk_name(name, index);
+            if cookie(request_headers, &chunk).is_none() {
+                break;
+            }
+            response_headers.append(SET_COOKIE, self.set_cookie(&chunk, "", 0)?);
+        }
+        Ok(())
+    }
+
+    fn set_cookie(&self, name: &str, value: &str, max_age_secs: i64) -> Result<HeaderValue, BoxError> {
+        let secure = if self.secure { "; Secure" } else { "" };
+        Ok(HeaderValue::from_str(&format!(
+            "{name}={value}; Path=/; Max-Age={max_age_secs}; HttpOnly; SameSite=Lax{secure}"
+        ))?)
+    }
+}
+
+fn chunk_name(name: &str, index: usize) -> String {
+    if index == 0 {
+        name.to_string()
+    } else {
+        format!("{name}.{index}")
+    }
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|kv| kv.trim().split_once('='))
+        .find_map(|(k, v)| (k == name).then_some(v))
+}
+
+/// Remove the cookie `name` and its chunks from the request headers, so they are not forwarded to the backend.
+pub fn remove_cookies(headers: &mut HeaderMap, name: &str) {
+    let is_session = |k: &str| k == name || k.strip_prefix(name).and_then(|rest| rest.strip_prefix('.')).is_some_and(|index| index.parse::<usize>().is_ok());
+    let rest = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .map(str::trim)
+        .filter(|kv| !kv.is_empty() && !is_session(kv.split_once('=').map_or(*kv, |(k, _)| k)))
+        .collect::<Vec<_>>()
+        .join("; ");
+    headers.remove(COOKIE);
+    if let Ok(rest) = HeaderValue::from_str(&rest) {
+        if !rest.is_empty() {
+            headers.insert(COOKIE, rest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hyper::{
+        header::{HeaderValue, COOKIE, SET_COOKIE},
+        HeaderMap,
+    };
+
+    use super::{remove_cookies, CookieCodec};
+
+    fn as_request_cookies(set_cookies: &HeaderMap) -> HeaderMap {
+        let cookies = set_cookies.get_all(SET_COOKIE).iter().filter_map(|v| v.to_str().ok()?.split(';').next()).collect::<Vec<_>>().join("; ");
+        HeaderMap::from_iter([(COOKIE, HeaderValue::from_str(&cookies).expect("invalid cookie"))])
+    }
+
+    #[test]
+    fn test_cookie_chunks() {
+        let codec = CookieCodec::new("session-secret", true).expect("invalid codec");
+        let value = "x".repeat(10000);
+        let mut set_cookies = HeaderMap::new();
+        codec.write(&HeaderMap::new(), &mut set_cookies, "sg_oidc", &value, 60).expect("fail to write");
+        assert_eq!(set_cookies.get_all(SET_COOKIE).iter().count(), 4);
+        let mut request = as_request_cookies(&set_cookies);
+        assert_eq!(codec.read::<String>(&request, "sg_oidc").as_deref(), Some(value.as_str()));
+        // a cookie can't be read under another name
+        assert!(codec.read::<String>(&request, "sg_oidc.1").is_none());
+        assert!(CookieCodec::new("another-secret", true).expect("invalid codec").read::<String>(&request, "sg_oidc").is_none());
+
+        // stale chunks are expired when the value shrinks
+        let mut shrunk = HeaderMap::new();
+        codec.write(&request, &mut shrunk, "sg_oidc", &"short", 60).expect("fail to write");
+        assert_eq!(shrunk.get_all(SET_COOKIE).iter().filter(|v| v.to_str().is_ok_and(|v| v.contains("Max-Age=0"))).count(), 3);
+
+        request.append(COOKIE, HeaderValue::from_static("theme=dark"));
+        remove_cookies(&mut request, "sg_oidc");
+        assert_eq!(request.get(COOKIE).expect("missing cookie"), "theme=dark");
+    }
+}
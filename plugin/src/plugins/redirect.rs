@@ -1,4 +1,7 @@
-use hyper::{Request, Response, StatusCode};
+use hyper::{
+    header::{HeaderValue, HOST, LOCATION},
+    Request, Response, StatusCode,
+};
 use serde::{Deserialize, Serialize};
 use spacegate_kernel::{helper_layers::filter::FilterRequest, SgResponseExt};
 use spacegate_kernel::{
@@ -7,7 +10,7 @@ use spacegate_kernel::{
 };
 use url::Url;
 
-use spacegate_kernel::SgBody;
+use spacegate_kernel::{extension::MatchedSgRouter, SgBody};
 
 use crate::{def_plugin, model::SgHttpPathModifier, MakeSgLayer};
 
@@ -20,21 +23,33 @@ pub struct RedirectFilter {
     /// Scheme is the scheme to be used in the value of the Location header in the response. When empty, the scheme of the request is used.
     pub scheme: Option<String>,
     /// Hostname is the hostname to be used in the value of the Location header in the response. When empty, the hostname in the Host header of the request is used.
+    /// It may refer to captured path parameters, e.g. `${path.tenant}.example.com`.
     pub hostname: Option<String>,
     /// Path defines parameters used to modify the path of the incoming request. The modified path is then used to construct the Location header. When empty, the request path is used as-is.
     pub path: Option<SgHttpPathModifier>,
     /// Port is the port to be used in the value of the Location header in the response.
     pub port: Option<u16>,
-    /// StatusCode is the HTTP status code to be used in response.
+    /// StatusCode is the HTTP status code to be used in response, `302` by default.
     pub status_code: Option<u16>,
 }
 
 impl RedirectFilter {
     fn on_req(&self, req: Request<SgBody>) -> Result<Request<SgBody>, Response<SgBody>> {
-        let mut url = Url::parse(&req.uri().to_string())
-            .map_err(|e| Response::<SgBody>::with_code_message(StatusCode::BAD_REQUEST, format!("[SG.Filter.Redirect] Url parsing error: {}", e)))?;
+        let uri = req.uri();
+        let raw_url = if uri.scheme().is_some() && uri.authority().is_some() {
+            uri.to_string()
+        } else {
+            let host = req.headers().get(HOST).and_then(|host| host.to_str().ok()).unwrap_or_default();
+            format!("http://{host}{}", uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/"))
+        };
+        let mut url = Url::parse(&raw_url).map_err(|e| Response::<SgBody>::with_code_message(StatusCode::BAD_REQUEST, format!("[SG.Filter.Redirect] Url parsing error: {}", e)))?;
+        let matched = req.extensions().get::<MatchedSgRouter>();
         if let Some(hostname) = &self.hostname {
-            url.set_host(Some(hostname))
+            let hostname = match matched {
+                Some(matched) => matched.captures().substitute(hostname),
+                None => hostname.into(),
+            };
+            url.set_host(Some(&hostname))
                 .map_err(|_| Response::<SgBody>::with_code_message(StatusCode::BAD_REQUEST, format!("[SG.Filter.Redirect] Host {hostname} parsing error")))?;
         }
         if let Some(scheme) = &self.scheme {
@@ -43,13 +58,17 @@ impl RedirectFilter {
         if let Some(port) = self.port {
             url.set_port(Some(port)).map_err(|_| Response::<SgBody>::with_code_message(StatusCode::BAD_REQUEST, format!("[SG.Filter.Redirect] Port {port} parsing error")))?;
         }
-        // todo!();
-        // let matched_match_inst = req.context.get_rule_matched();
-        // if let Some(new_url) = http_common_modify_path(req.request.get_uri(), &self.path, matched_match_inst.as_ref())? {
-        //     req.request.set_uri(new_url);
-        // }
-        // ctx.set_action(SgRouteFilterRequestAction::Redirect);
-        Ok(req)
+        if let (Some(modifier), Some(matched)) = (&self.path, matched) {
+            if let Some(new_path) = modifier.replace_matched(url.path(), matched) {
+                url.set_path(&new_path);
+            }
+        }
+        let status = self.status_code.and_then(|code| StatusCode::from_u16(code).ok()).unwrap_or(StatusCode::FOUND);
+        let location = HeaderValue::from_str(url.as_str()).map_err(Response::bad_gateway)?;
+        tracing::debug!("[SG.Filter.Redirect] redirect to {url} with {status}");
+        let mut resp = Response::<SgBody>::with_code_message(status, "");
+        resp.headers_mut().insert(LOCATION, location);
+        Err(resp)
     }
 }
 
@@ -64,6 +83,11 @@ pub type Redirect<S> = FilterRequest<RedirectFilter, S>;
 
 impl MakeSgLayer for RedirectFilter {
     fn make_layer(&self) -> Result<SgBoxLayer, spacegate_kernel::BoxError> {
+        if let Some(code) = self.status_code {
+            if !StatusCode::from_u16(code)?.is_redirection() {
+                return Err(format!("[SG.Filter.Redirect] status code {code} is not a redirection").into());
+            }
+        }
         let layer = FilterRequestLayer::new(self.clone());
         Ok(SgBoxLayer::new(layer))
     }
@@ -72,3 +96,44 @@ impl MakeSgLayer for RedirectFilter {
 def_plugin!("redirect", RedirectPlugin, RedirectFilter);
 #[cfg(feature = "schema")]
 crate::schema!(RedirectPlugin, RedirectFilter);
+
+#[cfg(test)]
+mod test {
+    use hyper::{header::LOCATION, service::Service, Request, StatusCode};
+    use serde_json::json;
+    use spacegate_kernel::{
+        extension::MatchedSgRouter,
+        layers::http_route::{
+            match_request::{SgHttpPathMatch, SgHttpRouteMatch},
+            path_template::SgPathTemplate,
+        },
+        service::get_echo_service,
+        BoxError, SgBody,
+    };
+    use tardis::{serde_json, tokio};
+    use tower_layer::Layer;
+
+    #[tokio::test]
+    async fn test_redirect() -> Result<(), BoxError> {
+        let config = json!({ "hostname": "${path.tenant}.example.com", "path": { "kind": "ReplaceFullPath", "value": "/v2/${path.rest}" }, "status_code": 301 });
+        let service = crate::SgPluginRepository::global().create_layer(None, super::CODE, config)?.layer(get_echo_service());
+        let path_match = SgHttpPathMatch::Template(SgPathTemplate::new("/{tenant}/{*rest}")?);
+        let captures = path_match.captures("/acme/users/42", false).expect("not matched");
+        let route_match = SgHttpRouteMatch {
+            path: Some(path_match),
+            ..Default::default()
+        };
+        let req = Request::builder()
+            .uri("/acme/users/42?detail=true")
+            .header("host", "sg.idealworld.group:8080")
+            .extension(MatchedSgRouter(route_match.into(), captures))
+            .body(SgBody::empty())?;
+        let resp = service.call(req).await?;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(LOCATION).expect("missing location"),
+            "http://acme.example.com:8080/v2/users/42?detail=true"
+        );
+        Ok(())
+    }
+}
@@ -16,6 +16,7 @@ fn redis_format_key(req: &Request<SgBody>, matched: &MatchedSgRouter, header: &H
             SgHttpPathMatch::Exact(path) => path,
             SgHttpPathMatch::Prefix(path) => path,
             SgHttpPathMatch::Regular(regex) => regex.as_str(),
+            SgHttpPathMatch::Template(template) => template.as_str(),
         })
         .unwrap_or("*");
     let header = req.headers().get(header).and_then(|v| v.to_str().ok())?;
@@ -153,7 +153,7 @@ mod test {
                     .uri("http://127.0.0.1/op-res/example")
                     .method("GET")
                     .extension(GatewayName::new(GW_NAME))
-                    .extension(MatchedSgRouter(
+                    .extension(MatchedSgRouter::new(
                         SgHttpRouteMatch {
                             path: Some(SgHttpPathMatch::Prefix("op-res".to_string())),
                             ..Default::default()
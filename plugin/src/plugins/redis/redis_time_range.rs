@@ -151,7 +151,7 @@ mod test {
                 .uri("http://127.0.0.1/op-res/example")
                 .method("GET")
                 .extension(GatewayName::new(GW_NAME))
-                .extension(MatchedSgRouter(
+                .extension(MatchedSgRouter::new(
                     SgHttpRouteMatch {
                         path: Some(SgHttpPathMatch::Prefix("op-res".to_string())),
                         ..Default::default()
@@ -172,7 +172,7 @@ mod test {
                 .uri("http://127.0.0.1/op-res/example")
                 .method("GET")
                 .extension(GatewayName::new(GW_NAME))
-                .extension(MatchedSgRouter(
+                .extension(MatchedSgRouter::new(
                     SgHttpRouteMatch {
                         path: Some(SgHttpPathMatch::Prefix("op-res".to_string())),
                         ..Default::default()
@@ -192,7 +192,7 @@ mod test {
                 .uri("http://127.0.0.1/op-res/example")
                 .method("POST")
                 .extension(GatewayName::new(GW_NAME))
-                .extension(MatchedSgRouter(
+                .extension(MatchedSgRouter::new(
                     SgHttpRouteMatch {
                         path: Some(SgHttpPathMatch::Prefix("op-res".to_string())),
                         ..Default::default()
@@ -211,7 +211,7 @@ mod test {
                 .uri("http://127.0.0.1/op-res/example")
                 .method("DELETE")
                 .extension(GatewayName::new(GW_NAME))
-                .extension(MatchedSgRouter(
+                .extension(MatchedSgRouter::new(
                     SgHttpRouteMatch {
                         path: Some(SgHttpPathMatch::Prefix("op-res".to_string())),
                         method: Some(vec![SgHttpMethodMatch("DELETE".into())]),
@@ -36,20 +36,17 @@ impl SgFilterRewrite {
         }
         if let Some(ref modifier) = self.path {
             let mut uri_part = req.uri().clone().into_parts();
-            if let Some(matched_path) = req.extensions().get::<MatchedSgRouter>() {
-                let path_match = matched_path.0.path.as_ref();
+            if let Some(matched) = req.extensions().get::<MatchedSgRouter>() {
                 if let Some(ref pq) = uri_part.path_and_query {
-                    if let Some(path_match) = path_match {
-                        if let Some(new_path) = modifier.replace(pq.path(), path_match) {
-                            tracing::debug!("[Sg.Plugin.Rewrite] rewrite path from {} to {}", pq.path(), new_path);
-                            let mut new_pq = new_path;
-                            if let Some(query) = pq.query() {
-                                new_pq.push('?');
-                                new_pq.push_str(query)
-                            }
-                            let new_pq = hyper::http::uri::PathAndQuery::from_maybe_shared(new_pq).map_err(Response::bad_gateway)?;
-                            uri_part.path_and_query = Some(new_pq)
+                    if let Some(new_path) = modifier.replace_matched(pq.path(), matched) {
+                        tracing::debug!("[Sg.Plugin.Rewrite] rewrite path from {} to {}", pq.path(), new_path);
+                        let mut new_pq = new_path;
+                        if let Some(query) = pq.query() {
+                            new_pq.push('?');
+                            new_pq.push_str(query)
                         }
+                        let new_pq = hyper::http::uri::PathAndQuery::from_maybe_shared(new_pq).map_err(Response::bad_gateway)?;
+                        uri_part.path_and_query = Some(new_pq)
                     }
                 }
             } else {
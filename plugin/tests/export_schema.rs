@@ -20,10 +20,23 @@ macro_rules! export_plugins {
 #[test]
 fn export_schema() {
     use plugins::{
+        basic_auth::BasicAuthPlugin,
+        body_limit::BodyLimitPlugin,
+        cache::CachePlugin,
+        compression::CompressionPlugin,
+        cors::CorsPlugin,
+        decompression::DecompressionPlugin,
+        ext_auth::ExtAuthPlugin,
+        fault::FaultPlugin,
         header_modifier::HeaderModifierPlugin,
         inject::InjectPlugin,
+        ip_restriction::IpRestrictionPlugin,
+        jwt_auth::JwtAuthPlugin,
+        key_auth::KeyAuthPlugin,
         limit::RateLimitPlugin,
         maintenance::MaintenancePlugin,
+        mirror::MirrorPlugin,
+        oidc::OidcPlugin,
         redirect::RedirectPlugin,
         redis::{redis_count::RedisCountPlugin, redis_dynamic_route::RedisDynamicRoutePlugin, redis_limit::RedisLimitPlugin, redis_time_range::RedisTimeRangePlugin},
         retry::RetryPlugin,
@@ -31,10 +44,23 @@ fn export_schema() {
         static_resource::StaticResourcePlugin,
     };
     export_plugins!("schema":
+        BasicAuthPlugin
+        BodyLimitPlugin
+        CachePlugin
+        CompressionPlugin
+        CorsPlugin
+        DecompressionPlugin
+        ExtAuthPlugin
+        FaultPlugin
         HeaderModifierPlugin
         InjectPlugin
+        IpRestrictionPlugin
+        JwtAuthPlugin
+        KeyAuthPlugin
         RateLimitPlugin
         MaintenancePlugin
+        MirrorPlugin
+        OidcPlugin
         RedirectPlugin
         RetryPlugin
         RewritePlugin
@@ -2,6 +2,8 @@ use spacegate_shell::BoxError;
 use tardis::{basic::tracing::TardisTracing, tokio};
 
 fn main() -> Result<(), BoxError> {
+    // before the runtime starts
+    spacegate_shell::upgrade::init();
     TardisTracing::initializer().with_env_layer().with_fmt_layer().init();
     let ns_from_env = std::env::var("NAMESPACE").ok();
     let ns_from_arg = std::env::args().nth(1);
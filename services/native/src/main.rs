@@ -1,8 +1,9 @@
 use spacegate_shell::BoxError;
 use tardis::{basic::tracing::TardisTracing, tokio};
 
-#[tokio::main]
-async fn main() -> Result<(), BoxError> {
+fn main() -> Result<(), BoxError> {
+    // before the runtime starts
+    spacegate_shell::upgrade::init();
     TardisTracing::initializer().with_env_layer().with_fmt_layer().init();
     let conf_url = std::env::args().nth(1).expect("The first parameter is missing: configuration connection url");
     let check_interval_sec = std::env::args().nth(2).expect("The second parameter is missing: configuration change check period (in seconds)").parse()?;
@@ -10,7 +10,6 @@ use tokio_util::sync::CancellationToken;
 // pub mod config_by_redis;
 pub use spacegate_config::model::*;
 
-pub(crate) mod matches_convert;
 pub mod plugin_filter_dto;
 
 pub struct ListenerWrapper(Box<dyn Listen>);
@@ -31,14 +30,15 @@ where
     tokio::task::spawn_local(async move {
         let (init_config, listener) = config.create_listener().await?;
         for (name, item) in init_config.gateways {
-            let (gateway, routes) = item.into_gateway_and_routes();
-            match RunningSgGateway::create(gateway, routes, shutdown_signal.clone()) {
+            match RunningSgGateway::create(item.gateway, item.routes, shutdown_signal.clone()) {
                 Ok(inst) => RunningSgGateway::global_save(name, inst),
                 Err(e) => {
                     tracing::error!("[SG.Config] fail to init gateway [{name}]: {e}")
                 }
             }
         }
+        #[cfg(unix)]
+        crate::upgrade::notify_ready();
         let mut listener = ListenerWrapper(listener);
         tracing::info!("[SG.Config] Entering listening");
         loop {
@@ -63,22 +63,22 @@ where
             match event {
                 (ConfigType::Gateway { name }, ConfigEventType::Create) => {
                     if let Some(config) = config.retrieve_config_item(&name).await? {
-                        let (gateway, routes) = config.into_gateway_and_routes();
                         tracing::info!("[SG.Config] gateway {name} created", name = name);
-                        if let Ok(gateway) = RunningSgGateway::create(gateway, routes, shutdown_signal.clone()) {
-                            RunningSgGateway::global_save(name, gateway);
+                        match RunningSgGateway::create(config.gateway, config.routes, shutdown_signal.clone()) {
+                            Ok(gateway) => RunningSgGateway::global_save(name, gateway),
+                            Err(e) => tracing::error!("[SG.Config] fail to create gateway [{name}]: {e}"),
                         }
                     }
                 }
                 (ConfigType::Gateway { name }, ConfigEventType::Update) => {
                     if let Some(config) = config.retrieve_config_item(&name).await? {
-                        let (gateway, routes) = config.into_gateway_and_routes();
                         tracing::info!("[SG.Config] gateway {name} updated", name = name);
                         if let Some(inst) = RunningSgGateway::global_remove(&name) {
                             inst.shutdown().await;
                         }
-                        if let Ok(gateway) = RunningSgGateway::create(gateway, routes, shutdown_signal.clone()) {
-                            RunningSgGateway::global_save(name, gateway);
+                        match RunningSgGateway::create(config.gateway, config.routes, shutdown_signal.clone()) {
+                            Ok(gateway) => RunningSgGateway::global_save(name, gateway),
+                            Err(e) => tracing::error!("[SG.Config] fail to recreate gateway [{name}]: {e}"),
                         }
                     }
                 }
@@ -88,10 +88,14 @@ where
                         inst.shutdown().await;
                     }
                 }
-                (ConfigType::Route { gateway_name, name }, _) => {
-                    let routes = config.retrieve_config_item_all_routes(&gateway_name).await?.into_values().collect::<Vec<_>>();
+                (ConfigType::Route { gateway_name, name }, event) => {
+                    // only the modified route is rebuilt, other routes are kept as they are
+                    let route = match event {
+                        ConfigEventType::Delete => None,
+                        _ => config.retrieve_config_item_route(&gateway_name, &name).await?,
+                    };
                     tracing::info!("[SG.Config] route {name} modified", name = name);
-                    if let Err(e) = RunningSgGateway::global_update(&gateway_name, routes).await {
+                    if let Err(e) = RunningSgGateway::global_update_route(&gateway_name, &name, route) {
                         tracing::error!("[SG.Config] route {name} modified failed: {e}", name = name, e = e);
                     }
                 }
@@ -0,0 +1,16 @@
+//! # Route Explain
+//!
+//! See [`spacegate_config::explain`], plugins are looked up in the global plugin repository of this process.
+
+use spacegate_config::{
+    model::{SgExplainRequest, SgRouteExplain},
+    Config,
+};
+use spacegate_kernel::BoxError;
+use spacegate_plugin::SgPluginRepository;
+
+/// Explain how the gateway `request.gateway` of `config` would route the request.
+pub fn explain(config: &Config, request: &SgExplainRequest) -> Result<SgRouteExplain, BoxError> {
+    let repo = SgPluginRepository::global();
+    spacegate_config::explain::explain(config, request, |code| repo.contains(code))
+}
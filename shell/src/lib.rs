@@ -34,8 +34,10 @@ use tracing::{info, instrument};
 
 pub mod config;
 pub mod constants;
+pub mod explain;
 pub mod extension;
 pub mod server;
+pub mod upgrade;
 
 #[cfg(feature = "ext-redis")]
 pub use spacegate_ext_redis;
@@ -76,7 +78,10 @@ where
 {
     info!("Starting spacegate...");
     info!("Spacegate Meta Info: {:?}", Meta::new());
-    Ok(config::init_with_config(config, ctrl_c_cancel_token()))
+    let cancel_token = ctrl_c_cancel_token();
+    #[cfg(unix)]
+    upgrade::upgrade_on_signal(cancel_token.clone())?;
+    Ok(config::init_with_config(config, cancel_token))
 }
 
 #[derive(Debug, Clone, Copy)]
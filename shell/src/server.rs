@@ -1,18 +1,32 @@
+mod route_cache;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     net::SocketAddr,
     sync::{Mutex, OnceLock},
 };
 
-use crate::config::{matches_convert::convert_config_to_kernel, plugin_filter_dto::FilterInstallExt, SgGateway, SgHttpRoute, SgProtocolConfig, SgRouteFilter, SgTlsMode};
+use crate::config::{
+    plugin_filter_dto::FilterInstallExt, SgBackendRef, SgCanaryKey, SgErrorPage, SgGateway, SgGatewayFallback, SgHttpCanary, SgHttpRoute, SgHttpRouteRule, SgListener,
+    SgListenerLimits, SgProtocolConfig, SgRouteFilter, SgTlsMode,
+};
 
+use hyper::{header::HeaderValue, StatusCode};
 use lazy_static::lazy_static;
+use spacegate_config::convert::{convert_path_normalization, convert_rule_matches};
 use spacegate_kernel::{
-    helper_layers::reload::Reloader,
-    layers::gateway::{builder::default_gateway_route_fallback, create_http_router, SgGatewayRoute},
-    listener::SgListen,
+    extension::{ListenerHostname, ListenerName},
+    helper_layers::{map_request::MapRequestLayer, reload::Reloader},
+    layers::gateway::{
+        builder::default_gateway_route_fallback,
+        create_http_router,
+        error_page::{self, SgErrorPages},
+        fallback, SgGatewayRoute,
+    },
+    layers::http_route::SgHttpRouteRuleLayer,
+    listener::{limit::SgListenLimits, SgListen},
     service::get_http_backend_service,
-    BoxError, BoxHyperService, Layer,
+    BoxError, BoxHyperService, Layer, SgBody, SgBoxLayer,
 };
 use std::sync::Arc;
 use std::time::Duration;
@@ -24,104 +38,189 @@ use tracing::{debug, error, info, instrument, warn};
 use tokio_rustls::rustls::{self, pki_types::PrivateKeyDer};
 use tokio_util::sync::CancellationToken;
 
+use self::route_cache::RouteCache;
+
 lazy_static! {
     static ref SHUTDOWN_TX: Arc<Mutex<HashMap<String, Sender<()>>>> = <_>::default();
     static ref START_JOIN_HANDLE: Arc<Mutex<HashMap<String, JoinHandle<()>>>> = <_>::default();
 }
 
-fn collect_tower_http_route(
-    http_routes: Vec<crate::SgHttpRoute>,
-    builder_ext: hyper::http::Extensions,
-) -> Result<Vec<spacegate_kernel::layers::http_route::SgHttpRoute>, BoxError> {
-    http_routes
+fn convert_rule(route_rule: SgHttpRouteRule, builder_ext: &hyper::http::Extensions) -> Result<SgHttpRouteRuleLayer, BoxError> {
+    let mut builder = convert_rule_matches(SgHttpRouteRuleLayer::builder().ext(builder_ext.clone()), route_rule.matches)?;
+    let backends = route_rule.backends.into_iter().map(|backend| convert_backend(backend, builder_ext)).collect::<Result<Vec<_>, _>>()?;
+    builder = builder.backends(backends);
+    if let Some(timeout) = route_rule.timeout_ms {
+        builder = builder.timeout(Duration::from_millis(timeout as u64));
+    }
+    if let Some(timeout) = route_rule.connect_timeout_ms {
+        builder = builder.connect_timeout(Duration::from_millis(timeout as u64));
+    }
+    if let Some(timeout) = route_rule.response_header_timeout_ms {
+        builder = builder.response_header_timeout(Duration::from_millis(timeout as u64));
+    }
+    if let Some(timeout) = route_rule.idle_stream_timeout_ms {
+        builder = builder.idle_stream_timeout(Duration::from_millis(timeout as u64));
+    }
+    if let Some(deadline) = route_rule.deadline_ms {
+        builder = builder.deadline(Duration::from_millis(deadline as u64));
+    }
+    if let Some(canary) = route_rule.canary {
+        builder = builder.canary(convert_canary(canary)?);
+    }
+    let plugins = route_rule.filters;
+    builder = SgRouteFilter::install_on_rule(plugins, builder);
+    builder.build()
+}
+
+fn convert_backend(backend: SgBackendRef, builder_ext: &hyper::http::Extensions) -> Result<spacegate_kernel::layers::http_route::SgHttpBackendLayer, BoxError> {
+    let host = backend.get_host();
+    let mut builder = spacegate_kernel::layers::http_route::SgHttpBackendLayer::builder().ext(builder_ext.clone());
+    let plugins = backend.filters;
+    #[cfg(feature = "k8s")]
+    {
+        use crate::extension::k8s_service::K8sService;
+        use spacegate_config::model::BackendHost;
+        use spacegate_kernel::helper_layers::map_request::{add_extension::add_extension, MapRequestLayer};
+        use spacegate_kernel::SgBoxLayer;
+        if let BackendHost::K8sService(data) = backend.host {
+            let namespace_ext = K8sService(data.into());
+            builder = builder.plugin(SgBoxLayer::new(MapRequestLayer::new(add_extension(namespace_ext, true))))
+        }
+    }
+    builder = SgRouteFilter::install_on_backend(plugins, builder);
+    builder = builder.host(host).port(backend.port);
+    if let Some(timeout) = backend.timeout_ms.map(|timeout| Duration::from_millis(timeout as u64)) {
+        builder = builder.timeout(timeout)
+    }
+    if let Some(timeout) = backend.connect_timeout_ms.map(|timeout| Duration::from_millis(timeout as u64)) {
+        builder = builder.connect_timeout(timeout)
+    }
+    if let Some(timeout) = backend.response_header_timeout_ms.map(|timeout| Duration::from_millis(timeout as u64)) {
+        builder = builder.response_header_timeout(timeout)
+    }
+    if let Some(timeout) = backend.idle_stream_timeout_ms.map(|timeout| Duration::from_millis(timeout as u64)) {
+        builder = builder.idle_stream_timeout(timeout)
+    }
+    if let Some(deadline) = backend.deadline_ms.map(|deadline| Duration::from_millis(deadline as u64)) {
+        builder = builder.deadline(deadline)
+    }
+    if let Some(protocol) = backend.protocol {
+        builder = builder.protocol(protocol.to_string());
+    }
+    if let Some(version) = backend.version {
+        builder = builder.version(version);
+    }
+    builder.build()
+}
+
+fn convert_fallback(fallback: SgGatewayFallback, builder_ext: &hyper::http::Extensions) -> Result<fallback::SgGatewayFallback, BoxError> {
+    Ok(match fallback {
+        SgGatewayFallback::Static { status, content_type, body } => fallback::SgGatewayFallback::Static {
+            status: StatusCode::from_u16(status)?,
+            content_type: content_type.map(HeaderValue::try_from).transpose()?,
+            body: body.into(),
+        },
+        SgGatewayFallback::Redirect { location, status } => fallback::SgGatewayFallback::Redirect {
+            status: status.map(StatusCode::from_u16).transpose()?.unwrap_or(StatusCode::FOUND),
+            location: HeaderValue::try_from(location)?,
+        },
+        SgGatewayFallback::Backend { backend } => {
+            let rule = SgHttpRouteRuleLayer::builder().ext(builder_ext.clone()).match_all().backend(convert_backend(backend, builder_ext)?).build()?;
+            fallback::SgGatewayFallback::Backend(rule)
+        }
+    })
+}
+
+fn convert_error_pages(pages: Vec<SgErrorPage>, lang: Option<&str>) -> Result<SgErrorPages, BoxError> {
+    let pages = pages
         .into_iter()
-        .map(|route| {
-            let plugins = route.filters;
-            let rules = route.rules;
-            let rules = rules
-                .into_iter()
-                .map(|route_rule| {
-                    let mut builder = spacegate_kernel::layers::http_route::SgHttpRouteRuleLayer::builder().ext(builder_ext.clone());
-                    builder = if let Some(matches) = route_rule.matches {
-                        builder.matches(matches.into_iter().map(convert_config_to_kernel).collect::<Result<Vec<_>, _>>()?)
-                    } else {
-                        builder.match_all()
-                    };
-                    let backends = route_rule
-                        .backends
-                        .into_iter()
-                        .map(|backend| {
-                            let host = backend.get_host();
-                            let mut builder = spacegate_kernel::layers::http_route::SgHttpBackendLayer::builder().ext(builder_ext.clone());
-                            let plugins = backend.filters;
-                            #[cfg(feature = "k8s")]
-                            {
-                                use crate::extension::k8s_service::K8sService;
-                                use spacegate_config::model::BackendHost;
-                                use spacegate_kernel::helper_layers::map_request::{add_extension::add_extension, MapRequestLayer};
-                                use spacegate_kernel::SgBoxLayer;
-                                if let BackendHost::K8sService(data) = backend.host {
-                                    let namespace_ext = K8sService(data.into());
-                                    builder = builder.plugin(SgBoxLayer::new(MapRequestLayer::new(add_extension(namespace_ext, true))))
-                                }
-                            }
-                            builder = SgRouteFilter::install_on_backend(plugins, builder);
-                            builder = builder.host(host).port(backend.port);
-                            if let Some(timeout) = backend.timeout_ms.map(|timeout| Duration::from_millis(timeout as u64)) {
-                                builder = builder.timeout(timeout)
-                            }
-                            if let Some(protocol) = backend.protocol {
-                                builder = builder.protocol(protocol.to_string());
-                            }
-                            builder.build()
-                        })
-                        .collect::<Result<Vec<_>, _>>()?;
-                    builder = builder.backends(backends);
-                    if let Some(timeout) = route_rule.timeout_ms {
-                        builder = builder.timeout(Duration::from_millis(timeout as u64));
-                    }
-                    let plugins = route_rule.filters;
-                    builder = SgRouteFilter::install_on_rule(plugins, builder);
-                    builder.build()
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-            let mut builder = spacegate_kernel::layers::http_route::SgHttpRoute::builder()
-                .hostnames(route.hostnames.unwrap_or_default())
-                .rules(rules)
-                .ext(builder_ext.clone())
-                .priority(route.priority);
-            builder = SgRouteFilter::install_on_route(plugins, builder);
-            builder.build()
+        .map(|page| {
+            Ok(error_page::SgErrorPage {
+                status: error_page::SgErrorPage::parse_status(&page.status)?,
+                content_type: HeaderValue::try_from(page.content_type)?,
+                template: page.template.into(),
+            })
         })
-        .collect::<Result<Vec<_>, _>>()
+        .collect::<Result<Vec<_>, BoxError>>()?;
+    let pages = SgErrorPages::new(pages);
+    Ok(match lang {
+        Some(lang) => pages.lang(lang),
+        None => pages,
+    })
+}
+
+fn convert_canary(canary: SgHttpCanary) -> Result<spacegate_kernel::layers::http_route::canary::SgHttpCanary, BoxError> {
+    use spacegate_kernel::layers::http_route::canary;
+    Ok(canary::SgHttpCanary {
+        version: canary.version.into(),
+        percentage: canary.percentage,
+        key: match canary.key {
+            Some(SgCanaryKey::Header { name }) => Some(canary::SgCanaryKey::Header(name.parse()?)),
+            Some(SgCanaryKey::Cookie { name }) => Some(canary::SgCanaryKey::Cookie(name)),
+            Some(SgCanaryKey::Query { name }) => Some(canary::SgCanaryKey::Query(name)),
+            None => None,
+        },
+        override_header: canary.override_header.map(|name| name.parse()).transpose()?,
+        sticky_cookie: canary.sticky_cookie.map(|cookie| canary::SgCanaryCookie {
+            name: cookie.name,
+            max_age: cookie.max_age_secs.map(|secs| Duration::from_secs(secs as u64)),
+        }),
+    })
+}
+
+fn convert_listen_limits(limits: &SgListenerLimits) -> SgListenLimits {
+    SgListenLimits {
+        max_connections: limits.max_connections.map(|n| n as usize),
+        max_connections_per_ip: limits.max_connections_per_ip.map(|n| n as usize),
+        header_read_timeout: limits.header_read_timeout_ms.map(|ms| Duration::from_millis(ms as u64)),
+        idle_timeout: limits.idle_timeout_ms.map(|ms| Duration::from_millis(ms as u64)),
+        max_header_size: limits.max_header_size.map(|n| n as usize),
+        max_headers: limits.max_headers.map(|n| n as usize),
+        http2_max_concurrent_streams: limits.http2_max_concurrent_streams,
+    }
 }
 
-/// Create a gateway service from plugins and http_routes
+/// Create a gateway service from the gateway config and http_routes
 pub(crate) fn create_service(
-    gateway_name: &str,
+    config: &SgGateway,
     cancel_token: CancellationToken,
-    plugins: Vec<SgRouteFilter>,
-    http_routes: Vec<crate::SgHttpRoute>,
+    routes: Vec<spacegate_kernel::layers::http_route::SgHttpRoute>,
     reloader: Reloader<SgGatewayRoute>,
+    fallback: SgBoxLayer,
     builder_ext: hyper::http::Extensions,
 ) -> Result<BoxHyperService, BoxError> {
-    let routes = collect_tower_http_route(http_routes, builder_ext.clone())?;
-    let builder = spacegate_kernel::layers::gateway::SgGatewayLayer::builder(gateway_name.to_owned(), cancel_token).http_routers(routes).http_route_reloader(reloader);
+    let path_normalizer = convert_path_normalization(config.parameters.path_normalization.as_deref().unwrap_or_default());
+    let builder = spacegate_kernel::layers::gateway::SgGatewayLayer::builder(config.name.clone(), cancel_token)
+        .http_routers(routes)
+        .http_route_reloader(reloader)
+        .http_fallback(fallback)
+        .error_pages(convert_error_pages(config.error_pages.clone(), config.parameters.lang.as_deref())?)
+        .path_normalizer(path_normalizer);
 
-    let builder = SgRouteFilter::install_on_gateway(plugins, builder.ext(builder_ext));
+    let builder = SgRouteFilter::install_on_gateway(config.filters.clone(), builder.ext(builder_ext));
     let gateway_layer = builder.build();
     let backend_service = get_http_backend_service();
     let service = BoxHyperService::new(gateway_layer.layer(backend_service));
     Ok(service)
 }
 
-/// create a new sg gateway route, which can be sent to reloader
-pub(crate) fn create_router_service(http_routes: Vec<crate::SgHttpRoute>, builder_ext: hyper::http::Extensions) -> Result<SgGatewayRoute, BoxError> {
-    let routes = collect_tower_http_route(http_routes, builder_ext)?;
-    let service = create_http_router(&routes, default_gateway_route_fallback(), get_http_backend_service());
-    Ok(service)
+/// Mark requests with the listener, so routes attach to it and its hostname are respected.
+fn create_listener_service(listener: &SgListener, service: BoxHyperService) -> BoxHyperService {
+    let listener_name = ListenerName(listener.name.as_str().into());
+    let listener_hostname = listener.hostname.as_deref().filter(|hostname| *hostname != "*").map(|hostname| ListenerHostname(hostname.into()));
+    let layer = MapRequestLayer::new(move |mut req: hyper::Request<SgBody>| {
+        req.extensions_mut().insert(listener_name.clone());
+        if let Some(hostname) = &listener_hostname {
+            req.extensions_mut().insert(hostname.clone());
+        }
+        req
+    });
+    BoxHyperService::new(layer.layer(service))
 }
 
+/// How long a gateway waits for its connections to finish when shutting down.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// # Gateway
 /// A running spacegate gateway instance
 ///
@@ -136,6 +235,11 @@ pub struct RunningSgGateway {
     // _guard: tokio_util::sync::DropGuard,
     handle: tokio::task::JoinHandle<()>,
     pub reloader: Reloader<SgGatewayRoute>,
+    /// Fallback of the gateway, kept for rebuilding the router on reload.
+    fallback: SgBoxLayer,
+    /// Built routes, unchanged ones are kept on reload.
+    routes: Arc<Mutex<RouteCache>>,
+    builder_ext: hyper::http::Extensions,
     shutdown_timeout: Duration,
 }
 impl std::fmt::Debug for RunningSgGateway {
@@ -161,25 +265,38 @@ impl RunningSgGateway {
         global_store.remove(gateway_name.as_ref())
     }
 
-    pub async fn global_update(gateway_name: impl AsRef<str>, http_routes: Vec<crate::SgHttpRoute>) -> Result<(), BoxError> {
-        let gateway_name = gateway_name.as_ref();
-        let service = create_router_service(http_routes, Default::default())?;
-        let reloader = {
+    /// Update all routes of the gateway, routes, rules and plugins whose config is unchanged are kept.
+    pub fn global_update(gateway_name: impl AsRef<str>, http_routes: BTreeMap<String, SgHttpRoute>) -> Result<(), BoxError> {
+        Self::global_update_with(gateway_name.as_ref(), |routes, builder_ext| routes.replace_all(http_routes, builder_ext))
+    }
+
+    /// Update a route of the gateway, or remove it if `http_route` is `None`, other routes are kept.
+    pub fn global_update_route(gateway_name: impl AsRef<str>, route_name: impl Into<String>, http_route: Option<SgHttpRoute>) -> Result<(), BoxError> {
+        Self::global_update_with(gateway_name.as_ref(), |routes, builder_ext| routes.update(route_name.into(), http_route, builder_ext))
+    }
+
+    /// The router is stored while the route cache is locked, so concurrent updates are published in the order they are applied.
+    fn global_update_with(gateway_name: &str, update: impl FnOnce(&mut RouteCache, &hyper::http::Extensions) -> Result<(), BoxError>) -> Result<(), BoxError> {
+        let (reloader, fallback, routes, builder_ext) = {
             let store = Self::global_store();
             let global_store = store.lock().expect("poisoned lock");
             if let Some(gw) = global_store.get(gateway_name) {
-                gw.reloader.clone()
+                (gw.reloader.clone(), gw.fallback.clone(), gw.routes.clone(), gw.builder_ext.clone())
             } else {
                 warn!("no such gateway in global repository: {gateway_name}");
                 return Ok(());
             }
         };
-        reloader.reload(service).await;
+        let mut routes = routes.lock().expect("poisoned lock");
+        update(&mut routes, &builder_ext)?;
+        let service = create_http_router(&routes.routes(), &fallback, get_http_backend_service());
+        reloader.reload(service);
         Ok(())
     }
+
     /// Start a gateway from plugins and http_routes
     #[instrument(fields(gateway=%config.name), skip_all, err)]
-    pub fn create(config: SgGateway, http_routes: Vec<SgHttpRoute>, cancel_token: CancellationToken) -> Result<Self, BoxError> {
+    pub fn create(config: SgGateway, http_routes: BTreeMap<String, SgHttpRoute>, cancel_token: CancellationToken) -> Result<Self, BoxError> {
         #[allow(unused_mut)]
         let mut builder_ext = hyper::http::Extensions::new();
         #[cfg(feature = "cache")]
@@ -195,7 +312,13 @@ impl RunningSgGateway {
         }
         tracing::info!("[SG.Server] start gateway");
         let reloader = <Reloader<SgGatewayRoute>>::default();
-        let service = create_service(&config.name, cancel_token.clone(), config.filters, http_routes, reloader.clone(), builder_ext)?;
+        let fallback = match config.fallback.clone() {
+            Some(fallback) => convert_fallback(fallback, &builder_ext)?.into_layer(),
+            None => default_gateway_route_fallback().clone(),
+        };
+        let mut routes = RouteCache::default();
+        routes.replace_all(http_routes, &builder_ext)?;
+        let service = create_service(&config, cancel_token.clone(), routes.routes(), reloader.clone(), fallback.clone(), builder_ext.clone())?;
         if config.listeners.is_empty() {
             return Err("[SG.Server] Missing Listeners".into());
         }
@@ -259,31 +382,40 @@ impl RunningSgGateway {
                 }
             }
             let listen_id = format!("{gateway_name}-{name}-{protocol}", name = listener.name, protocol = protocol);
-            let mut listen = SgListen::new(addr, service.clone(), cancel_token.child_token(), listen_id);
+            let service = create_listener_service(listener, service.clone());
+            let mut listen = SgListen::from_std(crate::upgrade::take_or_bind(addr)?, service, cancel_token.child_token(), listen_id)?.drain_timeout(SHUTDOWN_TIMEOUT);
+            if let Some(std_listener) = &listen.std_listener {
+                crate::upgrade::register(std_listener)?;
+            }
             if let Some(tls_cfg) = tls_cfg {
                 listen = listen.with_tls_config(tls_cfg);
             }
+            if listener.limits != SgListenerLimits::default() {
+                listen = listen.with_limits(convert_listen_limits(&listener.limits));
+            }
             listens.push(listen)
         }
 
         let local_set = tokio::task::LocalSet::new();
+        let mut listen_handles = Vec::with_capacity(listens.len());
         for listen in listens {
-            local_set.spawn_local(async move {
+            listen_handles.push(local_set.spawn_local(async move {
                 let id = listen.listener_id.clone();
                 if let Err(e) = listen.listen().await {
                     tracing::error!("[Sg.Server] listen error: {e}")
                 }
                 tracing::info!("[Sg.Server] listener[{id}] quit listening")
-            });
+            }));
         }
 
         // let cancel_guard = cancel_token.clone().drop_guard();
-        let cancel_task = cancel_token.clone().cancelled_owned();
+        // listeners quit after cancelled and all their connections finished
+        let listen_task = futures_util::future::join_all(listen_handles);
         let handle = {
             let gateway_name = gateway_name.clone();
             tokio::task::spawn_local(async move {
                 tracing::info!(gateway = gateway_name.as_ref(), "[Sg.Server] start all listeners");
-                local_set.run_until(cancel_task).await;
+                local_set.run_until(listen_task).await;
                 tracing::info!(gateway = gateway_name.as_ref(), "[Sg.Server] cancelled");
             })
         };
@@ -293,8 +425,11 @@ impl RunningSgGateway {
             token: cancel_token,
             // _guard: cancel_guard,
             handle,
-            shutdown_timeout: Duration::from_secs(10),
+            shutdown_timeout: SHUTDOWN_TIMEOUT,
             reloader,
+            fallback,
+            routes: Arc::new(Mutex::new(routes)),
+            builder_ext,
         })
     }
 
@@ -307,10 +442,14 @@ impl RunningSgGateway {
             tracing::trace!("[SG.Cache] Remove cache client...");
             spacegate_ext_redis::global_repo().remove(name.as_ref());
         }
-        match timeout(self.shutdown_timeout, self.handle).await {
+        let mut handle = self.handle;
+        match timeout(self.shutdown_timeout, &mut handle).await {
             Ok(_) => {}
             Err(e) => {
                 tracing::warn!("[SG.Server] Wait shutdown timeout:{e}");
+                // the listeners and their sockets are dropped with the task, so the addresses can be bound again
+                handle.abort();
+                let _ = handle.await;
             }
         };
         tracing::info!("[SG.Server] Gateway shutdown");
@@ -0,0 +1,216 @@
+//! # Route Cache
+//!
+//! Routes of a gateway built from the config, by name.
+//!
+//! On reload, routes whose config is unchanged are kept as they are, and a changed route keeps its plugins and rules whose config is unchanged,
+//! so plugin instances and their states survive, e.g. rate limit windows and breaker states.
+//! Configs are compared by their json values.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use spacegate_kernel::{
+    layers::http_route::{SgHttpRoute, SgHttpRouteRuleLayer},
+    BoxError, SgBoxLayer,
+};
+
+use crate::config::{plugin_filter_dto::FilterInstallExt, SgRouteFilter};
+
+use super::convert_rule;
+
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    config: Value,
+    filters: Value,
+    plugins: Vec<SgBoxLayer>,
+    rules: Vec<(Value, SgHttpRouteRuleLayer)>,
+    route: SgHttpRoute,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RouteCache {
+    routes: BTreeMap<String, CachedRoute>,
+}
+
+impl RouteCache {
+    /// Build the route, reusing what's unchanged of the previous one of the same name.
+    fn build(&self, name: &str, route: crate::SgHttpRoute, builder_ext: &hyper::http::Extensions) -> Result<CachedRoute, BoxError> {
+        let previous = self.routes.get(name);
+        let config = serde_json::to_value(&route)?;
+        if let Some(previous) = previous.filter(|previous| previous.config == config) {
+            return Ok(previous.clone());
+        }
+        let filters = serde_json::to_value(&route.filters)?;
+        let plugins = match previous {
+            Some(previous) if previous.filters == filters => previous.plugins.clone(),
+            _ => SgRouteFilter::install_on_route(route.filters, SgHttpRoute::builder().ext(builder_ext.clone())).plugins,
+        };
+        // each previous rule is reused once at most, so identical rules don't share an instance
+        let mut unused = previous.map(|previous| previous.rules.clone()).unwrap_or_default();
+        let rules = route
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let config = serde_json::to_value(&rule)?;
+                let layer = match unused.iter().position(|(previous, _)| previous == &config) {
+                    Some(index) => unused.swap_remove(index).1,
+                    None => convert_rule(rule, builder_ext)?,
+                };
+                Ok((config, layer))
+            })
+            .collect::<Result<Vec<_>, BoxError>>()?;
+        let built = SgHttpRoute::builder()
+            .hostnames(route.hostnames.unwrap_or_default())
+            .listeners(route.section_names.unwrap_or_default())
+            .rules(rules.iter().map(|(_, rule)| rule.clone()))
+            .plugins(plugins.clone())
+            .ext(builder_ext.clone())
+            .priority(route.priority)
+            .build()?;
+        Ok(CachedRoute {
+            config,
+            filters,
+            plugins,
+            rules,
+            route: built,
+        })
+    }
+
+    /// Update a route, or remove it if `route` is `None`, the cache is left as is if it fails.
+    pub(crate) fn update(&mut self, name: String, route: Option<crate::SgHttpRoute>, builder_ext: &hyper::http::Extensions) -> Result<(), BoxError> {
+        match route {
+            Some(route) => {
+                let built = self.build(&name, route, builder_ext)?;
+                self.routes.insert(name, built);
+            }
+            None => {
+                self.routes.remove(&name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace all routes, the cache is left as is if it fails.
+    pub(crate) fn replace_all(&mut self, routes: BTreeMap<String, crate::SgHttpRoute>, builder_ext: &hyper::http::Extensions) -> Result<(), BoxError> {
+        let routes = routes.into_iter().map(|(name, route)| Ok((name.clone(), self.build(&name, route, builder_ext)?))).collect::<Result<BTreeMap<_, _>, BoxError>>()?;
+        self.routes = routes;
+        Ok(())
+    }
+
+    /// Built routes in the order of their names, the router sorts them by priority then.
+    pub(crate) fn routes(&self) -> Vec<SgHttpRoute> {
+        self.routes.values().map(|cached| cached.route.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use serde_json::{json, Value};
+    use spacegate_kernel::{helper_layers::map_request::MapRequestLayer, BoxError, SgBody, SgBoxLayer};
+    use spacegate_plugin::{MakeSgLayer, Plugin, SgPluginRepository};
+
+    use super::RouteCache;
+    use crate::config::{SgHttpPathMatch, SgHttpRoute, SgHttpRouteMatch, SgHttpRouteRule, SgRouteFilter};
+
+    /// Plugin instances created so far.
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingPlugin;
+
+    struct Counting;
+
+    impl MakeSgLayer for Counting {
+        fn make_layer(&self) -> Result<SgBoxLayer, BoxError> {
+            Ok(SgBoxLayer::new(MapRequestLayer::new(std::convert::identity::<hyper::Request<SgBody>>)))
+        }
+    }
+
+    impl Plugin for CountingPlugin {
+        const CODE: &'static str = "route-cache-counting";
+        type MakeLayer = Counting;
+        fn create(_id: Option<String>, _value: Value) -> Result<Self::MakeLayer, BoxError> {
+            CREATED.fetch_add(1, Ordering::SeqCst);
+            Ok(Counting)
+        }
+    }
+
+    fn filter(spec: Value) -> SgRouteFilter {
+        SgRouteFilter {
+            code: CountingPlugin::CODE.to_string(),
+            spec,
+            ..Default::default()
+        }
+    }
+
+    fn rule(path: &str) -> SgHttpRouteRule {
+        SgHttpRouteRule {
+            matches: Some(vec![SgHttpRouteMatch {
+                path: Some(SgHttpPathMatch::Prefix(path.to_string())),
+                ..Default::default()
+            }]),
+            filters: vec![filter(json!({ "rule": path }))],
+            ..Default::default()
+        }
+    }
+
+    fn route(paths: &[&str]) -> SgHttpRoute {
+        SgHttpRoute {
+            filters: vec![filter(json!({ "route": true }))],
+            rules: paths.iter().map(|path| rule(path)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn created() -> usize {
+        CREATED.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_route_cache() -> Result<(), BoxError> {
+        SgPluginRepository::global().register::<CountingPlugin>();
+        let ext = hyper::http::Extensions::new();
+        let mut cache = RouteCache::default();
+        cache.replace_all(BTreeMap::from([("a".to_string(), route(&["/a1", "/a2"])), ("b".to_string(), route(&["/b"]))]), &ext)?;
+        assert_eq!(cache.routes().len(), 2);
+        // a route plugin and a plugin of each rule
+        assert_eq!(created(), 5);
+
+        // unchanged, nothing is created again
+        cache.update("a".to_string(), Some(route(&["/a1", "/a2"])), &ext)?;
+        cache.replace_all(BTreeMap::from([("a".to_string(), route(&["/a1", "/a2"])), ("b".to_string(), route(&["/b"]))]), &ext)?;
+        assert_eq!(created(), 5);
+
+        // a changed rule is created again, the other rule and route plugins are kept
+        let mut changed = route(&["/a1", "/a2"]);
+        changed.rules[1].timeout_ms = Some(1000);
+        cache.update("a".to_string(), Some(changed.clone()), &ext)?;
+        assert_eq!(created(), 6);
+        // so are route plugins when only they are changed
+        changed.filters[0].spec = json!({ "route": false });
+        cache.update("a".to_string(), Some(changed.clone()), &ext)?;
+        assert_eq!(created(), 7);
+
+        // a failed build leaves the cache as is
+        let mut broken = changed.clone();
+        broken.rules[0].matches = Some(vec![SgHttpRouteMatch {
+            path: Some(SgHttpPathMatch::Regular("(".to_string())),
+            ..Default::default()
+        }]);
+        assert!(cache.update("a".to_string(), Some(broken.clone()), &ext).is_err());
+        assert!(cache.replace_all(BTreeMap::from([("a".to_string(), broken), ("c".to_string(), route(&["/c"]))]), &ext).is_err());
+        assert_eq!(cache.routes().len(), 2);
+        let before = created();
+        cache.update("a".to_string(), Some(changed), &ext)?;
+        assert_eq!(created(), before);
+
+        // delete
+        cache.update("b".to_string(), None, &ext)?;
+        assert_eq!(cache.routes().len(), 1);
+        Ok(())
+    }
+}
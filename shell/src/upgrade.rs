@@ -0,0 +1,268 @@
+//! Zero-downtime binary upgrade.
+//!
+//! When the running process receives `SIGUSR2` (see [`upgrade_on_signal`]), it will exec a new process from the current
+//! executable with the same arguments, and hand all bound listening sockets over to it. The new process will accept on the inherited
+//! sockets instead of binding again, and notify the old process once all gateways are started. After that, the old process stops accepting
+//! and drains its connections.
+//!
+//! Sockets are passed by environment variable [`LISTEN_FDS_ENV`], formatted as `<socket addr>=<fd>,<socket addr>=<fd>`.
+//! Call [`init`] at the start of `main`, before the runtime starts, to take them.
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, Weak},
+};
+
+use lazy_static::lazy_static;
+
+/// Environment variable which carries the inherited listening sockets.
+pub const LISTEN_FDS_ENV: &str = "SPACEGATE_LISTEN_FDS";
+/// Environment variable which carries the fd to notify the old process that the new process is ready.
+pub const READY_FD_ENV: &str = "SPACEGATE_READY_FD";
+
+lazy_static! {
+    static ref BOUND_LISTENERS: Mutex<HashMap<SocketAddr, Weak<TcpListener>>> = <_>::default();
+}
+
+/// Take the sockets handed over by the parent process from environment variables, and remove the variables.
+///
+/// Environment variables can't be modified safely while other threads may read them,
+/// so this should be called at the start of `main`, before the runtime starts.
+/// If it's not called, the variables are read when they're first needed, and left as they are.
+pub fn init() {
+    #[cfg(unix)]
+    unix::init();
+}
+
+/// Take the listener inherited from the parent process for this address, or bind a new one.
+pub fn take_or_bind(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    #[cfg(unix)]
+    if let Some(listener) = unix::take_inherited(addr) {
+        tracing::info!("[SG.Upgrade] using inherited listener on {addr}");
+        return Ok(listener);
+    }
+    let socket = if addr.is_ipv4() {
+        tokio::net::TcpSocket::new_v4()?
+    } else {
+        tokio::net::TcpSocket::new_v6()?
+    };
+    #[cfg(not(windows))]
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    socket.listen(1024)?.into_std()
+}
+
+/// Register a listener, so it can be handed over to the new process while upgrading.
+///
+/// Only a weak reference is kept, the socket will be closed once the listener is dropped.
+pub fn register(listener: &Arc<TcpListener>) -> std::io::Result<()> {
+    let addr = listener.local_addr()?;
+    let mut bound = BOUND_LISTENERS.lock().expect("poisoned lock");
+    bound.retain(|_, listener| listener.strong_count() > 0);
+    bound.insert(addr, Arc::downgrade(listener));
+    Ok(())
+}
+
+fn bound_listeners() -> Vec<(SocketAddr, Arc<TcpListener>)> {
+    let bound = BOUND_LISTENERS.lock().expect("poisoned lock");
+    bound.iter().filter_map(|(addr, listener)| listener.upgrade().map(|listener| (*addr, listener))).collect()
+}
+
+#[cfg(unix)]
+pub use unix::{notify_ready, upgrade, upgrade_on_signal};
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        collections::HashMap,
+        io::Write,
+        net::{SocketAddr, TcpListener},
+        os::{
+            fd::{AsRawFd, FromRawFd, RawFd},
+            unix::{net::UnixStream, process::CommandExt},
+        },
+        sync::{Mutex, OnceLock},
+        time::Duration,
+    };
+
+    use spacegate_kernel::BoxError;
+    use tokio::io::AsyncReadExt;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{bound_listeners, LISTEN_FDS_ENV, READY_FD_ENV};
+    use crate::server::RunningSgGateway;
+
+    /// How long to wait for the new process to be ready, it's killed after that.
+    const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// What's handed over by the parent process.
+    struct Inherited {
+        listeners: Mutex<HashMap<SocketAddr, RawFd>>,
+        ready_fd: Mutex<Option<RawFd>>,
+    }
+
+    impl Inherited {
+        fn from_env(remove: bool) -> Self {
+            let listeners = std::env::var(LISTEN_FDS_ENV).map(|value| parse_inherited(&value)).unwrap_or_default();
+            let ready_fd = std::env::var(READY_FD_ENV).ok().and_then(|fd| fd.parse::<RawFd>().ok());
+            if remove {
+                std::env::remove_var(LISTEN_FDS_ENV);
+                std::env::remove_var(READY_FD_ENV);
+            }
+            Self {
+                listeners: Mutex::new(listeners),
+                ready_fd: Mutex::new(ready_fd),
+            }
+        }
+    }
+
+    static INHERITED: OnceLock<Inherited> = OnceLock::new();
+
+    pub(super) fn init() {
+        INHERITED.get_or_init(|| Inherited::from_env(true));
+    }
+
+    fn inherited() -> &'static Inherited {
+        INHERITED.get_or_init(|| Inherited::from_env(false))
+    }
+
+    fn format_listen_fds(listeners: impl IntoIterator<Item = (SocketAddr, RawFd)>) -> String {
+        listeners.into_iter().map(|(addr, fd)| format!("{addr}={fd}")).collect::<Vec<_>>().join(",")
+    }
+
+    fn parse_inherited(value: &str) -> HashMap<SocketAddr, RawFd> {
+        value
+            .split(',')
+            .filter(|item| !item.is_empty())
+            .filter_map(|item| {
+                let parsed = item.rsplit_once('=').and_then(|(addr, fd)| Some((addr.parse::<SocketAddr>().ok()?, fd.parse::<RawFd>().ok()?)));
+                if parsed.is_none() {
+                    tracing::warn!("[SG.Upgrade] invalid inherited listener: {item}");
+                }
+                parsed
+            })
+            .collect()
+    }
+
+    pub(super) fn take_inherited(addr: SocketAddr) -> Option<TcpListener> {
+        let fd = inherited().listeners.lock().expect("poisoned lock").remove(&addr)?;
+        // SAFETY: the fd is a listening socket handed over by the parent process, and it's taken only once.
+        Some(unsafe { TcpListener::from_raw_fd(fd) })
+    }
+
+    fn clear_cloexec(fd: RawFd) -> std::io::Result<()> {
+        // SAFETY: fcntl on a valid fd is safe, and it's async-signal-safe so it can be used in pre_exec.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags < 0 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Notify the old process that this process is ready to serve.
+    ///
+    /// Inherited listeners that are not taken until now will be closed.
+    pub fn notify_ready() {
+        for (addr, fd) in inherited().listeners.lock().expect("poisoned lock").drain() {
+            tracing::info!("[SG.Upgrade] close unused inherited listener on {addr}");
+            // SAFETY: see `take_inherited`
+            drop(unsafe { TcpListener::from_raw_fd(fd) });
+        }
+        let Some(fd) = inherited().ready_fd.lock().expect("poisoned lock").take() else {
+            return;
+        };
+        // SAFETY: the fd is one end of a socket pair created by the parent process
+        let mut ready = unsafe { UnixStream::from_raw_fd(fd) };
+        if let Err(e) = ready.write_all(&[1]) {
+            tracing::warn!("[SG.Upgrade] fail to notify parent process: {e}");
+        }
+    }
+
+    /// Exec a new process from current executable, and hand over all bound listeners.
+    ///
+    /// Returns the pid of the new process once it's ready to serve, if it's not ready in time, it will be killed.
+    pub async fn upgrade() -> Result<u32, BoxError> {
+        let listeners = bound_listeners();
+        let listen_fds = format_listen_fds(listeners.iter().map(|(addr, listener)| (*addr, listener.as_raw_fd())));
+        let (ready_rx, ready_tx) = UnixStream::pair()?;
+        let mut inherit_fds = listeners.iter().map(|(_, listener)| listener.as_raw_fd()).collect::<Vec<_>>();
+        inherit_fds.push(ready_tx.as_raw_fd());
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        command.args(std::env::args_os().skip(1)).env(LISTEN_FDS_ENV, listen_fds).env(READY_FD_ENV, ready_tx.as_raw_fd().to_string());
+        // SAFETY: only async-signal-safe calls are made in the closure
+        unsafe {
+            command.pre_exec(move || inherit_fds.iter().try_for_each(|fd| clear_cloexec(*fd)));
+        }
+        let mut child = command.spawn()?;
+        drop(ready_tx);
+        tracing::info!("[SG.Upgrade] new process [{pid}] spawned, waiting for it to be ready", pid = child.id());
+        ready_rx.set_nonblocking(true)?;
+        let mut ready_rx = tokio::net::UnixStream::from_std(ready_rx)?;
+        let mut buf = [0u8; 1];
+        let result = match tokio::time::timeout(READY_TIMEOUT, ready_rx.read(&mut buf)).await {
+            Ok(Ok(1)) => return Ok(child.id()),
+            Ok(result) => format!("[SG.Upgrade] new process exited before ready: {result:?}"),
+            Err(_) => format!("[SG.Upgrade] new process is not ready in {READY_TIMEOUT:?}"),
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        Err(result.into())
+    }
+
+    /// Upgrade when receiving `SIGUSR2`.
+    ///
+    /// Once the new process is ready, all gateways will be shutdown, and the `cancel_token` will be cancelled.
+    pub fn upgrade_on_signal(cancel_token: CancellationToken) -> std::io::Result<()> {
+        let mut signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())?;
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => return,
+                    received = signal.recv() => if received.is_none() { return }
+                }
+                tracing::info!("[SG.Upgrade] received SIGUSR2, start upgrading...");
+                match upgrade().await {
+                    Ok(pid) => {
+                        tracing::info!("[SG.Upgrade] new process [{pid}] is ready, draining connections...");
+                        let instances = RunningSgGateway::global_store().lock().expect("fail to lock").drain().collect::<Vec<_>>();
+                        for (_, inst) in instances {
+                            inst.shutdown().await;
+                        }
+                        cancel_token.cancel();
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::error!("[SG.Upgrade] upgrade failed: {e}");
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+    #[cfg(test)]
+    mod test {
+        use std::{
+            net::TcpListener,
+            os::fd::{FromRawFd, IntoRawFd},
+        };
+
+        use super::{format_listen_fds, parse_inherited};
+
+        #[test]
+        fn test_inherited_round_trip() -> std::io::Result<()> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let addr = listener.local_addr()?;
+            let value = format_listen_fds([(addr, listener.into_raw_fd())]);
+            // invalid items are skipped
+            let mut inherited = parse_inherited(&format!("{value},invalid,127.0.0.1:1=x"));
+            assert_eq!(inherited.len(), 1);
+            let fd = inherited.remove(&addr).expect("missing listener");
+            // SAFETY: the fd is released by the listener above
+            let listener = unsafe { TcpListener::from_raw_fd(fd) };
+            assert_eq!(listener.local_addr()?, addr);
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{env, time::Duration, vec};
+use std::{collections::BTreeMap, env, time::Duration, vec};
 
 use hyper::header::AUTHORIZATION;
 use hyper::{Response, StatusCode};
@@ -49,23 +49,26 @@ async fn test_custom_plugin() -> BoxResult<()> {
                 listeners: vec![SgListener { port: 8888, ..Default::default() }],
                 ..Default::default()
             },
-            vec![SgHttpRoute {
-                gateway_name: "test_gw".to_string(),
-                filters: vec![SgRouteFilter {
-                    code: "auth".to_string(),
-                    spec: json!({}),
-                    ..Default::default()
-                }],
-                rules: vec![SgHttpRouteRule {
-                    backends: vec![SgBackendRef {
-                        host: BackendHost::Host { host: "postman-echo.com".into() },
-                        protocol: Some(SgBackendProtocol::Https),
+            BTreeMap::from([(
+                "route".to_string(),
+                SgHttpRoute {
+                    gateway_name: "test_gw".to_string(),
+                    filters: vec![SgRouteFilter {
+                        code: "auth".to_string(),
+                        spec: json!({}),
+                        ..Default::default()
+                    }],
+                    rules: vec![SgHttpRouteRule {
+                        backends: vec![SgBackendRef {
+                            host: BackendHost::Host { host: "postman-echo.com".into() },
+                            protocol: Some(SgBackendProtocol::Https),
+                            ..Default::default()
+                        }],
                         ..Default::default()
                     }],
                     ..Default::default()
-                }],
-                ..Default::default()
-            }],
+                },
+            )]),
             token.clone(),
         )
         .expect("fail to start up server");
@@ -1,4 +1,4 @@
-use std::{env, time::Duration, vec};
+use std::{collections::BTreeMap, env, time::Duration, vec};
 
 use serde_json::{json, Value};
 use spacegate_config::model::{BackendHost, SgBackendProtocol, SgBackendRef, SgGateway, SgHttpRoute, SgHttpRouteRule, SgListener, SgProtocolConfig, SgTlsConfig, SgTlsMode};
@@ -142,20 +142,23 @@ async fn test_https() -> Result<(), BoxError> {
                 ],
                 ..Default::default()
             },
-            vec![SgHttpRoute {
-                hostnames: Some(vec!["localhost".to_string()]),
-                gateway_name: "test_gw".to_string(),
-                rules: vec![SgHttpRouteRule {
-                    backends: vec![SgBackendRef {
-                        host: BackendHost::Host { host: "postman-echo.com".into() },
-                        port: 443,
-                        protocol: Some(SgBackendProtocol::Https),
+            BTreeMap::from([(
+                "route".to_string(),
+                SgHttpRoute {
+                    hostnames: Some(vec!["localhost".to_string()]),
+                    gateway_name: "test_gw".to_string(),
+                    rules: vec![SgHttpRouteRule {
+                        backends: vec![SgBackendRef {
+                            host: BackendHost::Host { host: "postman-echo.com".into() },
+                            port: 443,
+                            protocol: Some(SgBackendProtocol::Https),
+                            ..Default::default()
+                        }],
                         ..Default::default()
                     }],
                     ..Default::default()
-                }],
-                ..Default::default()
-            }],
+                },
+            )]),
             token.clone(),
         )
         .expect("fail to start up server");
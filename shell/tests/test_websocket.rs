@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use lazy_static::lazy_static;
 use serde_json::json;
 use spacegate_config::model::{BackendHost, SgBackendRef, SgGateway, SgHttpPathMatch, SgHttpRoute, SgHttpRouteMatch, SgHttpRouteRule, SgListener, SgRouteFilter};
@@ -60,33 +62,36 @@ async fn test_webscoket() -> Result<(), BoxError> {
                 listeners: vec![SgListener { port: 8080, ..Default::default() }],
                 ..Default::default()
             },
-            vec![SgHttpRoute {
-                gateway_name: "test_gw".to_string(),
-                rules: vec![SgHttpRouteRule {
-                    backends: vec![SgBackendRef {
-                        host: BackendHost::Host { host: "postman-echo.com".into() },
-                        port: 8081,
-                        ..Default::default()
-                    }],
-                    matches: Some(vec![SgHttpRouteMatch {
-                        path: Some(SgHttpPathMatch::Prefix("/".into())),
+            BTreeMap::from([(
+                "route".to_string(),
+                SgHttpRoute {
+                    gateway_name: "test_gw".to_string(),
+                    rules: vec![SgHttpRouteRule {
+                        backends: vec![SgBackendRef {
+                            host: BackendHost::Host { host: "postman-echo.com".into() },
+                            port: 8081,
+                            ..Default::default()
+                        }],
+                        matches: Some(vec![SgHttpRouteMatch {
+                            path: Some(SgHttpPathMatch::Prefix("/".into())),
+                            ..Default::default()
+                        }]),
+                        filters: vec![SgRouteFilter {
+                            code: "rewrite".to_string(),
+                            name: None,
+                            spec: TardisFuns::json.obj_to_json(&rewrite::SgFilterRewriteConfig {
+                                hostname: None,
+                                path: Some(spacegate_plugin::model::SgHttpPathModifier {
+                                    kind: spacegate_plugin::model::SgHttpPathModifierType::ReplacePrefixMatch,
+                                    value: "/".to_string(),
+                                }),
+                            })?,
+                        }],
                         ..Default::default()
-                    }]),
-                    filters: vec![SgRouteFilter {
-                        code: "rewrite".to_string(),
-                        name: None,
-                        spec: TardisFuns::json.obj_to_json(&rewrite::SgFilterRewriteConfig {
-                            hostname: None,
-                            path: Some(spacegate_plugin::model::SgHttpPathModifier {
-                                kind: spacegate_plugin::model::SgHttpPathModifierType::ReplacePrefixMatch,
-                                value: "/".to_string(),
-                            }),
-                        })?,
                     }],
                     ..Default::default()
-                }],
-                ..Default::default()
-            }],
+                },
+            )]),
             token.clone(),
         )
         .expect("fail to start up server");